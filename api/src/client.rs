@@ -0,0 +1,151 @@
+use reqwest::StatusCode;
+
+use crate::{
+    BuildHistory, BuildInfo, BuildMode, HostFilter, NightlyHealth, Overview, Regressions,
+    ServiceStatus,
+};
+
+/// A client for the API of a does-it-build instance.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    /// Without a trailing slash.
+    url: String,
+}
+
+impl Client {
+    /// Uses the instance at `url`, like `https://does-it-build.noratrieb.dev`.
+    pub fn new(url: &str) -> Self {
+        Self::with_http_client(reqwest::Client::new(), url)
+    }
+
+    pub fn with_http_client(http: reqwest::Client, url: &str) -> Self {
+        Self {
+            http,
+            url: url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// The build of the target on the newest nightly it was built on, `None` if it has no builds.
+    /// `host` is like in [`HostFilter`].
+    pub async fn latest_build(
+        &self,
+        target: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> reqwest::Result<Option<BuildInfo>> {
+        self.get_optional(self.get(&format!("/api/v1/targets/{target}/latest"), mode, host))
+            .await
+    }
+
+    /// The build of the target on the nightly with its superseded attempts, `None` if it wasn't built.
+    pub async fn build(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> reqwest::Result<Option<BuildHistory>> {
+        self.get_optional(self.get(&format!("/api/v1/builds/{nightly}/{target}"), mode, host))
+            .await
+    }
+
+    /// Like [`Client::build`], but as the instance showed it at the Unix timestamp in seconds.
+    /// `None` if there was no result yet.
+    pub async fn build_as_of(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+        as_of: i64,
+    ) -> reqwest::Result<Option<BuildHistory>> {
+        self.get_optional(
+            self.get(&format!("/api/v1/builds/{nightly}/{target}"), mode, host)
+                .query(&[("as_of", as_of)]),
+        )
+        .await
+    }
+
+    /// The targets that failed on the nightly but passed on the previous one.
+    /// Without a host, the host of the instance is used.
+    pub async fn regressions(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> reqwest::Result<Regressions> {
+        self.get(
+            &format!("/api/v1/nightlies/{nightly}/regressions"),
+            mode,
+            host,
+        )
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+    }
+
+    /// Whether the nightly is fit to be promoted to beta, `None` if it wasn't built.
+    /// Without a host, the host of the instance is used.
+    pub async fn health(
+        &self,
+        nightly: &str,
+        host: Option<&str>,
+    ) -> reqwest::Result<Option<NightlyHealth>> {
+        let mut request = self
+            .http
+            .get(format!("{}/api/v1/health/{nightly}", self.url));
+        if let Some(host) = host {
+            request = request.query(&[("host", host)]);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        response.error_for_status()?.json().await.map(Some)
+    }
+
+    /// The totals of the instance. `host` is like in [`HostFilter`].
+    pub async fn overview(&self, host: Option<&str>) -> reqwest::Result<Overview> {
+        self.http
+            .get(format!("{}/api/v1/overview", self.url))
+            .query(&HostFilter::new(host))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// The version of the instance and its latest database maintenance runs.
+    pub async fn status(&self) -> reqwest::Result<ServiceStatus> {
+        self.http
+            .get(format!("{}/api/v1/status", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    fn get(&self, path: &str, mode: BuildMode, host: Option<&str>) -> reqwest::RequestBuilder {
+        self.http
+            .get(format!("{}{path}", self.url))
+            .query(&[("mode", mode.to_string())])
+            .query(&HostFilter::new(host))
+    }
+
+    /// Sends the request, `None` if it returns 404.
+    async fn get_optional<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<Option<T>> {
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        response.error_for_status()?.json().await.map(Some)
+    }
+}
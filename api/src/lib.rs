@@ -0,0 +1,550 @@
+//! The JSON types of the does-it-build API under `/api/v1`, and a client for it.
+
+#[cfg(feature = "client")]
+mod client;
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client")]
+pub use client::Client;
+
+/// Stores a fieldless enum as text through the `Any` driver, which `#[derive(sqlx::Type)]`
+/// doesn't support. The stored text is the `Display` output of the variant.
+#[cfg(feature = "sqlx")]
+macro_rules! any_text_enum {
+    ($ty:ident [$($variant:ident),* $(,)?]) => {
+        impl sqlx::Type<sqlx::Any> for $ty {
+            fn type_info() -> sqlx::any::AnyTypeInfo {
+                <&str as sqlx::Type<sqlx::Any>>::type_info()
+            }
+
+            fn compatible(ty: &sqlx::any::AnyTypeInfo) -> bool {
+                <&str as sqlx::Type<sqlx::Any>>::compatible(ty)
+            }
+        }
+
+        impl<'q> sqlx::Encode<'q, sqlx::Any> for $ty {
+            fn encode_by_ref(
+                &self,
+                buf: &mut <sqlx::Any as sqlx::Database>::ArgumentBuffer<'q>,
+            ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+                <String as sqlx::Encode<'q, sqlx::Any>>::encode(self.to_string(), buf)
+            }
+        }
+
+        impl<'r> sqlx::Decode<'r, sqlx::Any> for $ty {
+            fn decode(value: sqlx::any::AnyValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+                let value = <&str as sqlx::Decode<'r, sqlx::Any>>::decode(value)?;
+                [$(Self::$variant),*]
+                    .into_iter()
+                    .find(|variant| variant.to_string() == value)
+                    .ok_or_else(|| format!("invalid {}: {value}", stringify!($ty)).into())
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildMode {
+    /// `-Zbuild-std=core`
+    Core,
+    /// `cargo miri setup`
+    MiriStd,
+}
+
+impl Display for BuildMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Core => f.write_str("core"),
+            Self::MiriStd => f.write_str("miri-std"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownBuildMode(String);
+
+impl Display for UnknownBuildMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown build mode {:?}, expected core or miri-std",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownBuildMode {}
+
+impl FromStr for BuildMode {
+    type Err = UnknownBuildMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "core" => Ok(Self::Core),
+            "miri-std" => Ok(Self::MiriStd),
+            _ => Err(UnknownBuildMode(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+any_text_enum!(BuildMode[Core, MiriStd]);
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// The build failed.
+    Error,
+    Pass,
+    /// The target was left out on purpose, for example because it doesn't support the mode.
+    Skipped,
+    /// The toolchain or a component the build needs isn't available for the nightly.
+    Unavailable,
+    /// The build was killed for taking too long.
+    Timeout,
+    /// The build couldn't run because of a problem of the builder, like a full disk.
+    InfraError,
+    /// Miri said that it doesn't support the target.
+    Unsupported,
+}
+
+impl Status {
+    /// Whether the build says something about the target. Only these count for streaks,
+    /// regressions and uptime.
+    pub fn is_verdict(self) -> bool {
+        matches!(self, Self::Error | Self::Pass)
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => f.write_str("error"),
+            Self::Pass => f.write_str("pass"),
+            Self::Skipped => f.write_str("skipped"),
+            Self::Unavailable => f.write_str("unavailable"),
+            Self::Timeout => f.write_str("timeout"),
+            Self::InfraError => f.write_str("infra-error"),
+            Self::Unsupported => f.write_str("unsupported"),
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+any_text_enum!(Status[Error, Pass, Skipped, Unavailable, Timeout, InfraError, Unsupported]);
+
+/// The current result of building a target on a nightly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BuildInfo {
+    pub nightly: String,
+    pub target: String,
+    pub status: Status,
+    pub mode: BuildMode,
+    #[serde(flatten)]
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub timing: BuildTiming,
+    #[serde(flatten)]
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub host: BuildHost,
+    /// Starts at 1 and is incremented every time the build is re-run.
+    pub attempt: i64,
+    /// The support tier of the target, if rustc knows it.
+    pub tier: Option<i32>,
+    /// The first error message in the log of a failed build.
+    /// Unknown for builds from before this was recorded.
+    #[serde(default)]
+    pub error_excerpt: Option<String>,
+}
+
+/// A result of a build that has been superseded by re-running it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BuildAttempt {
+    pub attempt: i64,
+    pub status: Status,
+    #[serde(flatten)]
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub timing: BuildTiming,
+    #[serde(flatten)]
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub host: BuildHost,
+    /// Unix timestamp in seconds of when the next attempt replaced this one.
+    pub superseded_at: i64,
+    /// The first error message in the log of a failed build.
+    /// Unknown for builds from before this was recorded.
+    #[serde(default)]
+    pub error_excerpt: Option<String>,
+}
+
+/// Where a build ran. Results are kept separately for every host triple.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BuildHost {
+    /// Target triple of the host, like `x86_64-unknown-linux-gnu`.
+    pub host: String,
+    /// Unknown for builds from before this was recorded.
+    pub hostname: Option<String>,
+}
+
+/// The `host` parameter of the endpoints that can show the builds of one host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostFilter {
+    /// Without a host, the builds of all hosts are merged. Empty is the same as none, like forms
+    /// submit it.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub host: Option<String>,
+}
+
+impl HostFilter {
+    pub fn new(host: Option<&str>) -> Self {
+        Self {
+            host: host.map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn as_deref(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+}
+
+fn empty_as_none<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|value| !value.is_empty()))
+}
+
+/// When and how a build process ran. Builds from before this was recorded have none of it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BuildTiming {
+    /// Unix timestamp in seconds.
+    pub started_at: Option<i64>,
+    /// Unix timestamp in seconds.
+    pub finished_at: Option<i64>,
+    /// `None` if the process was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The peak resident memory of the largest process of the build, usually rustc, in KiB.
+    pub peak_rss_kib: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ToolchainInfo {
+    pub nightly: String,
+    /// The first line of `rustc -vV`, like `rustc 1.83.0-nightly (1bc403daa 2024-09-06)`.
+    pub rustc_version: String,
+    pub commit_hash: Option<String>,
+    pub llvm_version: Option<String>,
+    pub host: String,
+    pub cargo_version: String,
+    pub miri_version: Option<String>,
+    /// Like `rustup 1.27.1 (54dd3d00f 2024-04-24)`. Not known for toolchains installed before this
+    /// was recorded.
+    #[serde(default)]
+    pub rustup_version: Option<String>,
+    /// `None` if the channel manifest couldn't be fetched, or for toolchains installed before this
+    /// was checked.
+    #[serde(default)]
+    pub verification: Option<Verification>,
+}
+
+/// Whether the installed toolchain is the one the channel manifest of its nightly describes,
+/// judging by the commit of rustc.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verification {
+    Verified,
+    /// Nothing is built with such a toolchain.
+    Mismatch,
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Verified => f.write_str("verified"),
+            Self::Mismatch => f.write_str("mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+any_text_enum!(Verification[Verified, Mismatch]);
+
+/// A build with its superseded attempts, from `/api/v1/builds/<nightly>/<target>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildHistory {
+    #[serde(flatten)]
+    pub current: BuildInfo,
+    pub superseded: Vec<BuildAttempt>,
+}
+
+impl BuildHistory {
+    /// The history as the service showed it at the Unix timestamp in seconds, with the attempt that
+    /// was current then and the ones it had superseded. `None` if there was no result yet.
+    pub fn as_of(mut self, timestamp: i64) -> Option<Self> {
+        let Some(index) = self
+            .superseded
+            .iter()
+            .position(|attempt| attempt.superseded_at > timestamp)
+        else {
+            // Builds from before the finish time was recorded are assumed to have always been there.
+            let shown_at = match self.superseded.last() {
+                Some(previous) => Some(previous.superseded_at),
+                None => self.current.timing.finished_at,
+            };
+            return shown_at
+                .is_none_or(|shown_at| shown_at <= timestamp)
+                .then_some(self);
+        };
+        let attempt = self.superseded.swap_remove(index);
+        self.superseded.truncate(index);
+        if index == 0
+            && attempt
+                .timing
+                .finished_at
+                .is_some_and(|finished_at| finished_at > timestamp)
+        {
+            return None;
+        }
+        self.current = BuildInfo {
+            status: attempt.status,
+            timing: attempt.timing,
+            host: attempt.host,
+            attempt: attempt.attempt,
+            error_excerpt: attempt.error_excerpt,
+            ..self.current
+        };
+        Some(self)
+    }
+}
+
+/// The targets that regressed on a nightly, from `/api/v1/nightlies/<nightly>/regressions`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Regressions {
+    pub nightly: String,
+    /// The nightly the builds are compared to, `None` if there is no earlier one.
+    pub previous: Option<String>,
+    pub mode: BuildMode,
+    pub host: String,
+    pub regressions: Vec<Regression>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Regression {
+    pub target: String,
+    pub tier: Option<i32>,
+    /// The status of the target changes without compiler changes, so the regression might not be real.
+    pub flaky: bool,
+    /// Whether the target also fails with the beta of the same day, so the fix needs a backport.
+    /// Unknown until it was built with beta, and for modes that can't be built with beta.
+    #[serde(default)]
+    pub beta_affected: Option<bool>,
+}
+
+/// Whether a nightly is fit to be promoted to beta, from `/api/v1/health/<nightly>`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NightlyHealth {
+    pub nightly: String,
+    pub host: String,
+    /// Every mode was built with the whole toolchain, no tier 1 or 2 target regressed (unless it is
+    /// flaky), the compiler didn't crash and no target lost its prebuilt std.
+    pub fit_to_promote: bool,
+    pub modes: Vec<ModeHealth>,
+    /// Targets with a `rust-std` component in the channel manifest of the previous nightly, but not this one.
+    pub lost_prebuilt_std: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeHealth {
+    pub mode: BuildMode,
+    /// Whether all targets have been built, so the rest of this is final.
+    pub finished: bool,
+    /// Why the toolchain couldn't be installed, usually a missing component.
+    pub unavailable: Option<String>,
+    /// The nightly the builds are compared to, `None` if there is no earlier one.
+    pub previous: Option<String>,
+    /// Ordered by tier, targets without one last.
+    pub regressions_by_tier: Vec<TierRegressions>,
+    /// Targets with an internal compiler error in their log.
+    pub ices: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TierRegressions {
+    pub tier: Option<i32>,
+    pub regressions: Vec<Regression>,
+}
+
+/// Totals for the index page and status pages, from `/api/v1/overview`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Overview {
+    /// The number of targets that have been built at least once.
+    pub targets: i64,
+    /// The newest nightly with builds, `None` before the first build.
+    pub latest_nightly: Option<String>,
+    /// The modes that have builds.
+    pub modes: Vec<ModeOverview>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeOverview {
+    pub mode: BuildMode,
+    /// The newest nightly built in this mode, which the counts are about.
+    pub nightly: String,
+    pub pass: i64,
+    pub error: i64,
+    /// Regressions and fixes on the nightlies of the last 7 days.
+    pub regressions_this_week: i64,
+    pub fixes_this_week: i64,
+    /// The build jobs of the nightly.
+    pub progress: BuilderProgress,
+}
+
+/// How many targets of a nightly are waiting to be built, being built and done.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BuilderProgress {
+    pub pending: i64,
+    pub running: i64,
+    pub done: i64,
+}
+
+/// The state of the instance itself, from `/api/v1/status`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    /// The commit the instance was built from.
+    pub version: String,
+    /// The latest runs of the database maintenance, newest first.
+    pub maintenance: Vec<MaintenanceRun>,
+}
+
+/// A run of the maintenance that updates the statistics of the query planner and gives the space
+/// of removed logs back to the file system.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct MaintenanceRun {
+    /// Unix timestamps.
+    pub started_at: i64,
+    /// `None` while it runs.
+    pub finished_at: Option<i64>,
+    /// How much the database file shrank. Only SQLite gives space back.
+    pub freed_bytes: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildAttempt, BuildHistory, BuildHost, BuildInfo, BuildMode, BuildTiming, Status};
+
+    #[test]
+    fn build_history() {
+        let history = serde_json::from_str::<BuildHistory>(
+            r#"{
+                "nightly": "2026-10-10",
+                "target": "aarch64-unknown-none",
+                "status": "pass",
+                "mode": "miri-std",
+                "started_at": 1792182305,
+                "finished_at": 1792182310,
+                "exit_code": 0,
+                "peak_rss_kib": null,
+                "host": "x86_64-unknown-linux-gnu",
+                "hostname": "vm",
+                "attempt": 2,
+                "tier": 2,
+                "superseded": [{
+                    "attempt": 1,
+                    "status": "error",
+                    "started_at": null,
+                    "finished_at": null,
+                    "exit_code": null,
+                    "peak_rss_kib": null,
+                    "host": "x86_64-unknown-linux-gnu",
+                    "hostname": null,
+                    "superseded_at": 1792182300
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(history.current.mode, BuildMode::MiriStd);
+        assert_eq!(history.current.status, Status::Pass);
+        assert_eq!(history.current.timing.exit_code, Some(0));
+        assert_eq!(history.current.host.host, "x86_64-unknown-linux-gnu");
+        assert_eq!(history.superseded[0].status, Status::Error);
+    }
+
+    #[test]
+    fn build_history_as_of() {
+        let attempt = |attempt, status, finished_at, superseded_at| BuildAttempt {
+            attempt,
+            status,
+            timing: BuildTiming {
+                finished_at: Some(finished_at),
+                ..BuildTiming::default()
+            },
+            host: BuildHost::default(),
+            superseded_at,
+            error_excerpt: None,
+        };
+        let history = BuildHistory {
+            current: BuildInfo {
+                nightly: "2026-10-10".to_owned(),
+                target: "aarch64-unknown-none".to_owned(),
+                status: Status::Pass,
+                mode: BuildMode::Core,
+                timing: BuildTiming {
+                    finished_at: Some(300),
+                    ..BuildTiming::default()
+                },
+                host: BuildHost::default(),
+                attempt: 3,
+                tier: Some(2),
+                error_excerpt: None,
+            },
+            superseded: vec![
+                attempt(1, Status::Error, 100, 150),
+                attempt(2, Status::InfraError, 200, 250),
+            ],
+        };
+        let status_as_of = |timestamp| {
+            history.clone().as_of(timestamp).map(|history| {
+                (
+                    history.current.attempt,
+                    history.current.status,
+                    history.superseded.len(),
+                )
+            })
+        };
+        assert_eq!(status_as_of(99), None);
+        assert_eq!(status_as_of(100), Some((1, Status::Error, 0)));
+        assert_eq!(status_as_of(150), Some((2, Status::InfraError, 1)));
+        assert_eq!(status_as_of(249), Some((2, Status::InfraError, 1)));
+        assert_eq!(status_as_of(250), Some((3, Status::Pass, 2)));
+        assert_eq!(status_as_of(1000), Some((3, Status::Pass, 2)));
+    }
+
+    #[test]
+    fn status_names() {
+        // The database stores the `Display` name, so it has to match the serialized one.
+        for status in [
+            Status::Error,
+            Status::Pass,
+            Status::Skipped,
+            Status::Unavailable,
+            Status::Timeout,
+            Status::InfraError,
+            Status::Unsupported,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&status).unwrap(),
+                format!("\"{status}\"")
+            );
+        }
+    }
+}
@@ -12,6 +12,10 @@ fn main() {
     };
 
     println!("cargo:rustc-env=GIT_COMMIT={version}");
+    println!(
+        "cargo:rustc-env=HOST_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
 }
 
 fn try_get_commit() -> color_eyre::Result<String> {
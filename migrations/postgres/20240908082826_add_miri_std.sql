@@ -0,0 +1,13 @@
+-- Migrate build_info
+
+ALTER TABLE build_info ADD COLUMN "mode" VARCHAR NOT NULL DEFAULT 'core';
+ALTER TABLE build_info ALTER COLUMN "mode" DROP DEFAULT;
+ALTER TABLE build_info DROP CONSTRAINT build_info_pkey;
+ALTER TABLE build_info ADD PRIMARY KEY ("nightly", "target", "mode");
+
+-- Migrate finished_nightly
+
+ALTER TABLE finished_nightly ADD COLUMN "mode" VARCHAR NOT NULL DEFAULT 'core';
+ALTER TABLE finished_nightly ALTER COLUMN "mode" DROP DEFAULT;
+ALTER TABLE finished_nightly DROP CONSTRAINT finished_nightly_pkey;
+ALTER TABLE finished_nightly ADD PRIMARY KEY ("nightly", "mode");
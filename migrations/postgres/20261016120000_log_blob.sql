@@ -0,0 +1,19 @@
+-- Build logs are stored once per distinct content, keyed by their SHA-256 hash.
+
+CREATE TABLE log_blob (
+    "hash" VARCHAR NOT NULL PRIMARY KEY,
+    "content" VARCHAR NOT NULL
+);
+
+-- Existing logs get a placeholder key, which is replaced by their hash on startup.
+
+INSERT INTO log_blob (hash, content)
+SELECT 'legacy:' || mode || ':' || nightly || ':' || target, stderr FROM build_info;
+
+ALTER TABLE build_info ADD COLUMN "stderr_hash" VARCHAR;
+UPDATE build_info SET stderr_hash = 'legacy:' || mode || ':' || nightly || ':' || target;
+ALTER TABLE build_info ALTER COLUMN "stderr_hash" SET NOT NULL;
+ALTER TABLE build_info ADD FOREIGN KEY ("stderr_hash") REFERENCES log_blob ("hash");
+ALTER TABLE build_info DROP COLUMN "stderr";
+
+CREATE INDEX build_info_stderr_hash ON build_info ("stderr_hash");
@@ -0,0 +1,4 @@
+-- The primary key already covers lookups by (nightly, target, mode).
+-- This one is for going through the history of a single target.
+
+CREATE INDEX build_info_target_mode_nightly ON build_info ("target", "mode", "nightly");
@@ -0,0 +1,12 @@
+-- Facts about the toolchain of a nightly, captured when it is installed.
+
+CREATE TABLE toolchain_info (
+    "nightly" VARCHAR NOT NULL PRIMARY KEY,
+    "rustc_version" VARCHAR NOT NULL,
+    "commit_hash" VARCHAR,
+    "llvm_version" VARCHAR,
+    "host" VARCHAR NOT NULL,
+    "cargo_version" VARCHAR NOT NULL,
+    -- Only known once the nightly has been installed for a miri build.
+    "miri_version" VARCHAR
+);
@@ -0,0 +1,7 @@
+-- Where a build ran: the host target triple and the hostname of the machine.
+-- Unknown for builds from before this was recorded.
+
+ALTER TABLE build_info ADD COLUMN "host" VARCHAR;
+ALTER TABLE build_info ADD COLUMN "hostname" VARCHAR;
+ALTER TABLE build_attempt ADD COLUMN "host" VARCHAR;
+ALTER TABLE build_attempt ADD COLUMN "hostname" VARCHAR;
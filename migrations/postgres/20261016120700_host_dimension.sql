@@ -0,0 +1,22 @@
+-- Results, jobs and finished nightlies are kept per host triple, so that the same matrix
+-- can be built on several hosts. Everything from before ran on x86_64 Linux.
+
+UPDATE build_info SET host = 'x86_64-unknown-linux-gnu' WHERE host IS NULL;
+ALTER TABLE build_info ALTER COLUMN "host" SET NOT NULL;
+ALTER TABLE build_info DROP CONSTRAINT build_info_pkey;
+ALTER TABLE build_info ADD PRIMARY KEY ("nightly", "target", "mode", "host");
+
+UPDATE build_attempt SET host = 'x86_64-unknown-linux-gnu' WHERE host IS NULL;
+ALTER TABLE build_attempt ALTER COLUMN "host" SET NOT NULL;
+ALTER TABLE build_attempt DROP CONSTRAINT build_attempt_pkey;
+ALTER TABLE build_attempt ADD PRIMARY KEY ("nightly", "target", "mode", "host", "attempt");
+
+ALTER TABLE finished_nightly ADD COLUMN "host" VARCHAR NOT NULL DEFAULT 'x86_64-unknown-linux-gnu';
+ALTER TABLE finished_nightly ALTER COLUMN "host" DROP DEFAULT;
+ALTER TABLE finished_nightly DROP CONSTRAINT finished_nightly_pkey;
+ALTER TABLE finished_nightly ADD PRIMARY KEY ("nightly", "mode", "host");
+
+ALTER TABLE job ADD COLUMN "host" VARCHAR NOT NULL DEFAULT 'x86_64-unknown-linux-gnu';
+ALTER TABLE job ALTER COLUMN "host" DROP DEFAULT;
+ALTER TABLE job DROP CONSTRAINT job_pkey;
+ALTER TABLE job ADD PRIMARY KEY ("nightly", "mode", "host", "target");
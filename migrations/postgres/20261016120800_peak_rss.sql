@@ -0,0 +1,4 @@
+-- Peak resident memory of the largest process of a build in KiB, unknown for builds from before it was recorded.
+
+ALTER TABLE build_info ADD COLUMN "peak_rss_kib" BIGINT;
+ALTER TABLE build_attempt ADD COLUMN "peak_rss_kib" BIGINT;
@@ -0,0 +1,10 @@
+-- The platform support metadata rustc has about every target, from the newest nightly that was installed.
+
+CREATE TABLE target_meta (
+    "target" VARCHAR NOT NULL PRIMARY KEY,
+    "nightly" VARCHAR NOT NULL,
+    "tier" INTEGER,
+    "host_tools" BOOLEAN,
+    "std" BOOLEAN,
+    "description" VARCHAR
+);
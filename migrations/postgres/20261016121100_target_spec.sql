@@ -0,0 +1,16 @@
+-- The target spec JSON of every target on every nightly.
+-- Specs rarely change between nightlies, so they are stored once per distinct content like logs.
+
+CREATE TABLE target_spec_blob (
+    "hash" VARCHAR NOT NULL PRIMARY KEY,
+    "spec" VARCHAR NOT NULL
+);
+
+CREATE TABLE target_spec (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "spec_hash" VARCHAR NOT NULL REFERENCES target_spec_blob ("hash"),
+    PRIMARY KEY ("nightly", "target")
+);
+
+CREATE INDEX target_spec_target ON target_spec ("target", "nightly");
@@ -0,0 +1,7 @@
+-- Nightlies whose regressions have been posted to the configured channels.
+
+CREATE TABLE escalated_nightly (
+    "nightly" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    PRIMARY KEY ("nightly", "mode")
+);
@@ -0,0 +1,7 @@
+-- Nightlies whose static JSON snapshot has been written.
+
+CREATE TABLE published_snapshot (
+    "nightly" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    PRIMARY KEY ("nightly", "mode")
+);
@@ -0,0 +1,4 @@
+-- The error of rustup for nightlies that were finished without builds because the toolchain or
+-- a component of it couldn't be installed.
+
+ALTER TABLE finished_nightly ADD COLUMN "toolchain_error" VARCHAR;
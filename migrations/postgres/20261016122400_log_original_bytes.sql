@@ -0,0 +1,4 @@
+-- The size of a log that was larger than `build.max_log_bytes`, of which only the start and end
+-- are stored. NULL for logs that are stored completely.
+
+ALTER TABLE log_blob ADD COLUMN "original_bytes" BIGINT;
@@ -0,0 +1,8 @@
+-- Runs of the database maintenance. `finished_at` is NULL while it runs, `error` is set if it failed.
+
+CREATE TABLE maintenance_run (
+    "started_at" BIGINT NOT NULL PRIMARY KEY,
+    "finished_at" BIGINT,
+    "freed_bytes" BIGINT,
+    "error" VARCHAR
+);
@@ -0,0 +1,12 @@
+-- Builds of regressed targets with the beta of the same day, to see whether the regression
+-- already reached beta. `toolchain` is the dated beta toolchain, `status` the status of its build.
+
+CREATE TABLE beta_check (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "host" VARCHAR NOT NULL,
+    "toolchain" VARCHAR NOT NULL,
+    "status" VARCHAR NOT NULL,
+    PRIMARY KEY ("nightly", "target", "mode", "host")
+);
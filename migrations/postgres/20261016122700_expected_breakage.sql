@@ -0,0 +1,13 @@
+-- Status changes that are expected, like a target that is removed or demoted on purpose, with the
+-- reason. Regressions of the target on the nightlies from `first_nightly` to `last_nightly` aren't
+-- reported.
+
+CREATE TABLE expected_breakage (
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "first_nightly" VARCHAR NOT NULL,
+    "last_nightly" VARCHAR NOT NULL,
+    "reason" VARCHAR NOT NULL,
+    "created_at" BIGINT NOT NULL,
+    PRIMARY KEY ("target", "mode", "first_nightly")
+);
@@ -0,0 +1,16 @@
+-- The latest result of every target, mode and host on the peers of this instance, replaced with
+-- every sync. Kept apart from `build_info` so they never count as builds of this instance.
+
+CREATE TABLE federated_build (
+    "peer" VARCHAR NOT NULL,
+    "peer_url" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "host" VARCHAR NOT NULL,
+    "nightly" VARCHAR NOT NULL,
+    "status" VARCHAR NOT NULL,
+    "tier" INTEGER,
+    "error_excerpt" VARCHAR,
+    "fetched_at" BIGINT NOT NULL,
+    PRIMARY KEY ("peer", "target", "mode", "host")
+);
@@ -0,0 +1,11 @@
+-- Every change made through an authenticated endpoint, like a requested rebuild, with who made it.
+-- `payload` is the JSON body of the request.
+
+CREATE TABLE audit_log (
+    "created_at" BIGINT NOT NULL,
+    "actor" VARCHAR NOT NULL,
+    "action" VARCHAR NOT NULL,
+    "payload" VARCHAR NOT NULL
+);
+
+CREATE INDEX audit_log_created_at ON audit_log ("created_at");
@@ -0,0 +1,9 @@
+-- The destinations, like `zulip`, that the escalation of a nightly was posted to. A nightly is only
+-- in `escalated_nightly` once it reached all of them.
+
+CREATE TABLE escalated_destination (
+    "nightly" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "destination" VARCHAR NOT NULL,
+    PRIMARY KEY ("nightly", "mode", "destination")
+);
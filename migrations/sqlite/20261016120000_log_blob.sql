@@ -0,0 +1,30 @@
+-- Build logs are stored once per distinct content, keyed by their SHA-256 hash.
+
+CREATE TABLE log_blob (
+    "hash" VARCHAR NOT NULL PRIMARY KEY,
+    "content" VARCHAR NOT NULL
+);
+
+-- Existing logs get a placeholder key, which is replaced by their hash on startup.
+
+INSERT INTO log_blob (hash, content)
+SELECT 'legacy:' || mode || ':' || nightly || ':' || target, stderr FROM build_info;
+
+CREATE TABLE new_build_info (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "status" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "stderr_hash" VARCHAR NOT NULL REFERENCES log_blob ("hash"),
+
+    PRIMARY KEY ("nightly", "target", "mode")
+);
+
+INSERT INTO new_build_info (nightly, target, status, mode, stderr_hash)
+SELECT nightly, target, status, mode, 'legacy:' || mode || ':' || nightly || ':' || target FROM build_info;
+
+DROP TABLE build_info;
+
+ALTER TABLE new_build_info RENAME TO build_info;
+
+CREATE INDEX build_info_stderr_hash ON build_info ("stderr_hash");
@@ -0,0 +1,6 @@
+-- Unix timestamps in seconds. The exit code is NULL if the build was killed by a signal.
+-- All of them are NULL for builds from before they were recorded.
+
+ALTER TABLE build_info ADD COLUMN "started_at" INTEGER;
+ALTER TABLE build_info ADD COLUMN "finished_at" INTEGER;
+ALTER TABLE build_info ADD COLUMN "exit_code" INTEGER;
@@ -0,0 +1,21 @@
+-- Builds that are re-run keep their previous results here. build_info has the current attempt.
+
+ALTER TABLE build_info ADD COLUMN "attempt" INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE build_attempt (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "attempt" INTEGER NOT NULL,
+    "status" VARCHAR NOT NULL,
+    "stderr_hash" VARCHAR NOT NULL REFERENCES log_blob ("hash"),
+    "started_at" INTEGER,
+    "finished_at" INTEGER,
+    "exit_code" INTEGER,
+    -- Unix timestamp in seconds of when the next attempt replaced this one.
+    "superseded_at" INTEGER NOT NULL,
+
+    PRIMARY KEY ("nightly", "target", "mode", "attempt")
+);
+
+CREATE INDEX build_attempt_stderr_hash ON build_attempt ("stderr_hash");
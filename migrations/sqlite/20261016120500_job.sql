@@ -0,0 +1,15 @@
+-- Targets of a nightly that still need to be built. Builders lease jobs so that
+-- several of them can share the work and a restart continues where it stopped.
+
+CREATE TABLE job (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    -- pending, running or done
+    "state" VARCHAR NOT NULL,
+    "leased_by" VARCHAR,
+    -- Unix timestamp in seconds after which a running job can be taken over.
+    "lease_expiry" INTEGER,
+
+    PRIMARY KEY ("nightly", "mode", "target")
+);
@@ -0,0 +1,97 @@
+-- Results, jobs and finished nightlies are kept per host triple, so that the same matrix
+-- can be built on several hosts. Everything from before ran on x86_64 Linux.
+
+CREATE TABLE new_build_info (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "status" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "stderr_hash" VARCHAR NOT NULL REFERENCES log_blob ("hash"),
+    "started_at" INTEGER,
+    "finished_at" INTEGER,
+    "exit_code" INTEGER,
+    "attempt" INTEGER NOT NULL DEFAULT 1,
+    "host" VARCHAR NOT NULL,
+    "hostname" VARCHAR,
+
+    PRIMARY KEY ("nightly", "target", "mode", "host")
+);
+
+INSERT INTO new_build_info
+    (nightly, target, status, mode, stderr_hash, started_at, finished_at, exit_code, attempt, host, hostname)
+SELECT nightly, target, status, mode, stderr_hash, started_at, finished_at, exit_code, attempt,
+    COALESCE(host, 'x86_64-unknown-linux-gnu'), hostname
+FROM build_info;
+
+DROP TABLE build_info;
+
+ALTER TABLE new_build_info RENAME TO build_info;
+
+CREATE INDEX build_info_stderr_hash ON build_info ("stderr_hash");
+CREATE INDEX build_info_target_mode_nightly ON build_info ("target", "mode", "nightly");
+
+CREATE TABLE new_build_attempt (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "attempt" INTEGER NOT NULL,
+    "status" VARCHAR NOT NULL,
+    "stderr_hash" VARCHAR NOT NULL REFERENCES log_blob ("hash"),
+    "started_at" INTEGER,
+    "finished_at" INTEGER,
+    "exit_code" INTEGER,
+    "superseded_at" INTEGER NOT NULL,
+    "host" VARCHAR NOT NULL,
+    "hostname" VARCHAR,
+
+    PRIMARY KEY ("nightly", "target", "mode", "host", "attempt")
+);
+
+INSERT INTO new_build_attempt
+    (nightly, target, mode, attempt, status, stderr_hash, started_at, finished_at, exit_code, superseded_at, host, hostname)
+SELECT nightly, target, mode, attempt, status, stderr_hash, started_at, finished_at, exit_code, superseded_at,
+    COALESCE(host, 'x86_64-unknown-linux-gnu'), hostname
+FROM build_attempt;
+
+DROP TABLE build_attempt;
+
+ALTER TABLE new_build_attempt RENAME TO build_attempt;
+
+CREATE INDEX build_attempt_stderr_hash ON build_attempt ("stderr_hash");
+
+CREATE TABLE new_finished_nightly (
+    "nightly" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "is_broken" BOOLEAN NOT NULL DEFAULT FALSE,
+    "host" VARCHAR NOT NULL,
+
+    PRIMARY KEY ("nightly", "mode", "host")
+);
+
+INSERT INTO new_finished_nightly (nightly, mode, is_broken, host)
+SELECT nightly, mode, is_broken, 'x86_64-unknown-linux-gnu' FROM finished_nightly;
+
+DROP TABLE finished_nightly;
+
+ALTER TABLE new_finished_nightly RENAME TO finished_nightly;
+
+CREATE TABLE new_job (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "host" VARCHAR NOT NULL,
+    -- pending, running or done
+    "state" VARCHAR NOT NULL,
+    "leased_by" VARCHAR,
+    -- Unix timestamp in seconds after which a running job can be taken over.
+    "lease_expiry" INTEGER,
+
+    PRIMARY KEY ("nightly", "mode", "host", "target")
+);
+
+INSERT INTO new_job (nightly, target, mode, host, state, leased_by, lease_expiry)
+SELECT nightly, target, mode, 'x86_64-unknown-linux-gnu', state, leased_by, lease_expiry FROM job;
+
+DROP TABLE job;
+
+ALTER TABLE new_job RENAME TO job;
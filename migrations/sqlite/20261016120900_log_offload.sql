@@ -0,0 +1,4 @@
+-- Logs that are too large are kept in object storage under this key. The content is then only
+-- the head and tail of the log, while the hash is still that of the full log.
+
+ALTER TABLE log_blob ADD COLUMN "offloaded_key" VARCHAR;
@@ -0,0 +1,9 @@
+-- The components of every target triple, parsed from the name. Existing targets are added on startup.
+
+CREATE TABLE target (
+    "target" VARCHAR NOT NULL PRIMARY KEY,
+    "arch" VARCHAR NOT NULL,
+    "vendor" VARCHAR NOT NULL,
+    "os" VARCHAR NOT NULL,
+    "env" VARCHAR NOT NULL
+);
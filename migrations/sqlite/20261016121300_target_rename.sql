@@ -0,0 +1,8 @@
+-- Targets that disappeared while a target with an almost identical spec appeared on the same nightly.
+
+CREATE TABLE target_rename (
+    "old_target" VARCHAR NOT NULL PRIMARY KEY,
+    "new_target" VARCHAR NOT NULL,
+    -- The first nightly with the new name.
+    "nightly" VARCHAR NOT NULL
+);
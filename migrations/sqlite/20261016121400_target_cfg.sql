@@ -0,0 +1,15 @@
+-- The output of `rustc --print cfg` for every target on every nightly, stored once per distinct content.
+
+CREATE TABLE target_cfg_blob (
+    "hash" VARCHAR NOT NULL PRIMARY KEY,
+    "cfg" VARCHAR NOT NULL
+);
+
+CREATE TABLE target_cfg (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "cfg_hash" VARCHAR NOT NULL REFERENCES target_cfg_blob ("hash"),
+    PRIMARY KEY ("nightly", "target")
+);
+
+CREATE INDEX target_cfg_target ON target_cfg ("target", "nightly");
@@ -0,0 +1,6 @@
+-- The targets that have a prebuilt rust-std component on a nightly, sorted and separated by newlines.
+
+CREATE TABLE prebuilt_std (
+    "nightly" VARCHAR NOT NULL PRIMARY KEY,
+    "targets" VARCHAR NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Maintainers of targets according to the platform support docs, replaced on every sync.
+
+CREATE TABLE target_maintainer (
+    "target" VARCHAR NOT NULL,
+    "github" VARCHAR NOT NULL,
+    PRIMARY KEY ("target", "github")
+);
@@ -0,0 +1,9 @@
+-- Builds that should be done again even though they exist, requested through the rebuild hook.
+
+CREATE TABLE rebuild_request (
+    "nightly" VARCHAR NOT NULL,
+    "target" VARCHAR NOT NULL,
+    "mode" VARCHAR NOT NULL,
+    "host" VARCHAR NOT NULL,
+    PRIMARY KEY ("nightly", "target", "mode", "host")
+);
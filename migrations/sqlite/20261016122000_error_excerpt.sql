@@ -0,0 +1,4 @@
+-- The first error message in the log of a failed build. Builds from before this was added have none.
+
+ALTER TABLE build_info ADD COLUMN "error_excerpt" VARCHAR;
+ALTER TABLE build_attempt ADD COLUMN "error_excerpt" VARCHAR;
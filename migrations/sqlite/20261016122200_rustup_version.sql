@@ -0,0 +1,4 @@
+-- The output of `rustup --version` when the toolchain was installed, as changes of rustup have
+-- changed results before.
+
+ALTER TABLE toolchain_info ADD COLUMN "rustup_version" VARCHAR;
@@ -0,0 +1,4 @@
+-- Whether the installed toolchain of a nightly is the one its channel manifest describes:
+-- verified or mismatch, NULL if it was not checked.
+
+ALTER TABLE toolchain_info ADD COLUMN "verification" VARCHAR;
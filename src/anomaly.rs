@@ -0,0 +1,173 @@
+//! Builds that took much longer or produced a much larger or smaller log than the earlier builds of
+//! the target, even if the status stayed the same. A flood of new warnings or a compiler that got
+//! slower for a target often comes before it breaks.
+
+use std::collections::BTreeMap;
+
+use color_eyre::Result;
+use serde::Serialize;
+
+use crate::{
+    db::{log_hash, BuildMetrics, BuildMode, Db, Status},
+    retention,
+};
+
+/// How many nightlies, including the one that is checked, are looked at.
+const WINDOW: i64 = 15;
+/// How many earlier builds with the same status a target needs for a baseline.
+const MIN_BASELINE: usize = 5;
+/// How many times larger or smaller than the baseline a value must be.
+const FACTOR: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    DurationSecs,
+    StderrBytes,
+}
+
+impl Metric {
+    fn of(self, build: &BuildMetrics) -> Option<i64> {
+        match self {
+            Self::DurationSecs => build.duration_secs,
+            Self::StderrBytes => build.stderr_bytes,
+        }
+    }
+
+    /// Changes smaller than this are noise, like a log that grew from 200 bytes to 1 KiB.
+    fn min_change(self) -> i64 {
+        match self {
+            Self::DurationSecs => 60,
+            Self::StderrBytes => 16 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub target: String,
+    pub status: Status,
+    pub metric: Metric,
+    pub value: i64,
+    /// The median of the earlier builds with the same status.
+    pub baseline: i64,
+}
+
+/// The builds of the nightly in the mode that deviate from the earlier builds of their target.
+pub async fn anomalies(
+    db: &Db,
+    nightly: &str,
+    mode: BuildMode,
+    host: Option<&str>,
+) -> Result<Vec<Anomaly>> {
+    let pruned_hash = log_hash(retention::PRUNED_LOG_MARKER);
+    let metrics = db
+        .build_metrics(mode, host, nightly, WINDOW, &pruned_hash)
+        .await?;
+    let mut histories = BTreeMap::<_, Vec<_>>::new();
+    for build in metrics {
+        histories
+            .entry(build.target.clone())
+            .or_default()
+            .push(build);
+    }
+    Ok(histories
+        .into_values()
+        .filter_map(|mut history| {
+            let build = history.pop().filter(|build| build.nightly == nightly)?;
+            Some(find_anomalies(&build, &history))
+        })
+        .flatten()
+        .collect())
+}
+
+/// Compares the build to the earlier ones of its target with the same status.
+fn find_anomalies(build: &BuildMetrics, earlier: &[BuildMetrics]) -> Vec<Anomaly> {
+    [Metric::DurationSecs, Metric::StderrBytes]
+        .into_iter()
+        .filter_map(|metric| {
+            let value = metric.of(build)?;
+            let mut baseline = earlier
+                .iter()
+                .filter(|earlier| earlier.status == build.status)
+                .filter_map(|earlier| metric.of(earlier))
+                .collect::<Vec<_>>();
+            if baseline.len() < MIN_BASELINE {
+                return None;
+            }
+            baseline.sort_unstable();
+            let baseline = baseline[baseline.len() / 2];
+            let deviates = value as f64 >= baseline as f64 * FACTOR
+                || (value as f64) * FACTOR <= baseline as f64;
+            (deviates && (value - baseline).abs() >= metric.min_change()).then(|| Anomaly {
+                target: build.target.clone(),
+                status: build.status,
+                metric,
+                value,
+                baseline,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Anomaly, Metric};
+    use crate::db::{BuildMetrics, Status};
+
+    #[test]
+    fn find_anomalies() {
+        let build = |nightly: &str, status, duration_secs, stderr_bytes| BuildMetrics {
+            target: "riscv64gc-unknown-linux-gnu".to_owned(),
+            nightly: nightly.to_owned(),
+            status,
+            duration_secs: Some(duration_secs),
+            stderr_bytes,
+        };
+        let earlier = (1..=6)
+            .map(|day| build(&format!("2026-10-0{day}"), Status::Pass, 100, Some(2000)))
+            .chain([build("2026-10-07", Status::Error, 1000, Some(500_000))])
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            super::find_anomalies(
+                &build("2026-10-08", Status::Pass, 110, Some(3000)),
+                &earlier
+            ),
+            []
+        );
+        assert_eq!(
+            super::find_anomalies(&build("2026-10-08", Status::Pass, 400, None), &earlier),
+            [Anomaly {
+                target: "riscv64gc-unknown-linux-gnu".to_owned(),
+                status: Status::Pass,
+                metric: Metric::DurationSecs,
+                value: 400,
+                baseline: 100,
+            }]
+        );
+        assert_eq!(
+            super::find_anomalies(
+                &build("2026-10-08", Status::Pass, 100, Some(80_000)),
+                &earlier
+            )
+            .iter()
+            .map(|anomaly| anomaly.metric)
+            .collect::<Vec<_>>(),
+            [Metric::StderrBytes]
+        );
+        // Too small a change to matter, even though it is large relative to the baseline.
+        assert_eq!(
+            super::find_anomalies(
+                &build("2026-10-08", Status::Pass, 100, Some(8000)),
+                &earlier
+            ),
+            []
+        );
+        // The one earlier failure is not enough of a baseline.
+        assert_eq!(
+            super::find_anomalies(&build("2026-10-08", Status::Error, 10, Some(10)), &earlier),
+            []
+        );
+    }
+}
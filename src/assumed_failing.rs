@@ -0,0 +1,175 @@
+//! Targets that have failed with the same error for a long time are assumed to keep failing.
+//! They're only built on every few nightlies and recorded as skipped on the others, unless a
+//! target of the same architecture changes its status, as whatever fixed or broke that one
+//! likely affects them too.
+
+use std::collections::{BTreeMap, HashMap};
+
+use color_eyre::Result;
+
+use crate::{
+    config::AssumeFailingConfig,
+    db::{BuildMode, Db, Status},
+    nightlies,
+};
+
+/// The targets that are skipped on the nightly, with the note that is stored as their log.
+pub async fn assumed_failing(
+    db: &Db,
+    nightly: &str,
+    mode: BuildMode,
+    host: &str,
+    config: &AssumeFailingConfig,
+) -> Result<BTreeMap<String, String>> {
+    let Some(after_days) = config.after_days else {
+        return Ok(BTreeMap::new());
+    };
+    let since = nightlies::days_before(nightly, after_days)?;
+    let history = db.recent_history(&since, nightly, mode, Some(host)).await?;
+    let arches = db
+        .targets()
+        .await?
+        .into_iter()
+        .map(|(target, triple)| (target, triple.arch))
+        .collect();
+    Ok(select(&history, &since, &arches, config.recheck_every))
+}
+
+/// Picks the targets to skip from their builds, oldest first, starting with the last one with
+/// a verdict before `since`.
+fn select(
+    history: &[(String, String, Status, Option<String>)],
+    since: &str,
+    arches: &HashMap<String, String>,
+    recheck_every: u32,
+) -> BTreeMap<String, String> {
+    let mut by_target = BTreeMap::<_, Vec<_>>::new();
+    for (target, nightly, status, excerpt) in history {
+        by_target.entry(target.as_str()).or_default().push((
+            nightly.as_str(),
+            *status,
+            excerpt.as_deref(),
+        ));
+    }
+
+    // The last nightly on which each target's status changed.
+    let mut last_change = HashMap::new();
+    for (&target, builds) in &by_target {
+        let verdicts = builds.iter().filter(|(_, status, _)| status.is_verdict());
+        let changes = verdicts
+            .clone()
+            .zip(verdicts.skip(1))
+            .filter(|((_, before, _), (_, after, _))| before != after);
+        if let Some((_, (nightly, _, _))) = changes.last() {
+            last_change.insert(target, *nightly);
+        }
+    }
+
+    let mut skipped = BTreeMap::new();
+    for (&target, builds) in &by_target {
+        let mut verdicts = builds.iter().filter(|(_, status, _)| status.is_verdict());
+        let Some(&(first_nightly, Status::Error, Some(excerpt))) = verdicts.next() else {
+            continue;
+        };
+        // Without a build before `since`, the error may be more recent than that.
+        if first_nightly >= since {
+            continue;
+        }
+        let mut last_nightly = first_nightly;
+        if !verdicts.all(|&(nightly, status, other)| {
+            last_nightly = nightly;
+            status == Status::Error && other == Some(excerpt)
+        }) {
+            continue;
+        }
+
+        let skips = builds
+            .iter()
+            .filter(|(nightly, status, _)| *nightly > last_nightly && *status == Status::Skipped)
+            .count();
+        if skips + 1 >= recheck_every as usize {
+            continue;
+        }
+        let Some(arch) = arches.get(target) else {
+            continue;
+        };
+        let related_changed = last_change.iter().any(|(&other, &nightly)| {
+            other != target && arches.get(other) == Some(arch) && nightly > last_nightly
+        });
+        if related_changed {
+            continue;
+        }
+
+        skipped.insert(
+            target.to_owned(),
+            format!(
+                "skipped (assumed failing): the target failed with `{excerpt}` on every build since {first_nightly} or earlier.\n\
+                It is built again once every {recheck_every} nightlies, or once a target with the same architecture changes its status.\n"
+            ),
+        );
+    }
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::db::Status::{self, Error, Pass, Skipped};
+
+    #[test]
+    fn select() {
+        let build = |target: &str, nightly: &str, status: Status, excerpt: Option<&str>| {
+            (
+                target.to_owned(),
+                nightly.to_owned(),
+                status,
+                excerpt.map(ToOwned::to_owned),
+            )
+        };
+        let arches = HashMap::from([
+            ("armv7a-none-eabi".to_owned(), "armv7a".to_owned()),
+            ("armv7a-none-eabihf".to_owned(), "armv7a".to_owned()),
+            ("avr-none".to_owned(), "avr".to_owned()),
+            ("bpfel-unknown-none".to_owned(), "bpfel".to_owned()),
+        ]);
+        let broken = Some("error: linker `rust-lld` not found");
+        let history = vec![
+            // Fails since before the window, skipped since.
+            build("avr-none", "2026-06-30", Error, broken),
+            build("avr-none", "2026-09-01", Error, broken),
+            build("avr-none", "2026-10-01", Error, broken),
+            build("avr-none", "2026-10-02", Skipped, None),
+            // Fails with another error.
+            build("bpfel-unknown-none", "2026-06-30", Error, broken),
+            build(
+                "bpfel-unknown-none",
+                "2026-09-01",
+                Error,
+                Some("error: unknown target"),
+            ),
+        ];
+        let since = "2026-07-01";
+        let skipped = super::select(&history, since, &arches, 3);
+        assert_eq!(skipped.keys().collect::<Vec<_>>(), ["avr-none"]);
+        assert!(skipped["avr-none"].contains("since 2026-06-30 or earlier"));
+
+        // Rechecked after two skips.
+        let mut rechecked = history.clone();
+        rechecked.push(build("avr-none", "2026-10-03", Skipped, None));
+        assert!(super::select(&rechecked, since, &arches, 3).is_empty());
+
+        // Rechecked once a target of the same architecture changes.
+        let history = vec![
+            build("armv7a-none-eabi", "2026-06-30", Pass, None),
+            build("armv7a-none-eabi", "2026-10-01", Pass, None),
+            build("armv7a-none-eabihf", "2026-06-30", Error, broken),
+            build("armv7a-none-eabihf", "2026-10-01", Error, broken),
+            build("armv7a-none-eabihf", "2026-10-02", Skipped, None),
+        ];
+        assert_eq!(super::select(&history, since, &arches, 7).len(), 1);
+        let mut changed = history.clone();
+        changed.push(build("armv7a-none-eabi", "2026-10-03", Error, broken));
+        assert!(super::select(&changed, since, &arches, 7).is_empty());
+    }
+}
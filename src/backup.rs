@@ -0,0 +1,189 @@
+use std::{sync::Arc, time::Duration};
+
+use color_eyre::{
+    eyre::{bail, Context, OptionExt},
+    Result,
+};
+use futures::TryStreamExt;
+use object_store::{aws::AmazonS3Builder, buffered::BufWriter, path::Path, ObjectStore};
+use tracing::{error, info};
+
+use crate::db::{Backend, Db};
+
+const BACKUP_FILE_PREFIX: &str = "does-it-build-";
+const BACKUP_FILE_SUFFIX: &str = ".sqlite";
+
+/// Where backups are stored.
+#[derive(Clone)]
+pub struct BackupStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl BackupStore {
+    /// Uses the bucket from an `s3://bucket/prefix` URL, credentials come from the usual `AWS_*` variables.
+    pub fn new(url: &str) -> Result<Self> {
+        let (store, prefix) = s3_store(url).wrap_err("configuring backup bucket")?;
+        Ok(Self { store, prefix })
+    }
+
+    /// Names of all backups, oldest first.
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut names = self
+            .store
+            .list(Some(&self.prefix))
+            .try_filter_map(|meta| async move {
+                Ok(meta
+                    .location
+                    .filename()
+                    .filter(|name| {
+                        name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(BACKUP_FILE_SUFFIX)
+                    })
+                    .map(ToOwned::to_owned))
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .wrap_err("listing backups")?;
+        // The names contain the timestamp, so sorting them sorts by age.
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// The bucket of an `s3://bucket/prefix` URL and the prefix in it.
+/// Credentials come from the usual `AWS_*` variables.
+pub fn s3_store(url: &str) -> Result<(Arc<dyn ObjectStore>, Path)> {
+    let (bucket, prefix) = parse_s3_url(url)?;
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .build()?;
+    Ok((Arc::new(store), Path::from(prefix)))
+}
+
+/// Splits `s3://bucket/some/prefix` into the bucket and the prefix.
+fn parse_s3_url(url: &str) -> Result<(&str, &str)> {
+    let rest = url
+        .strip_prefix("s3://")
+        .ok_or_eyre("the URL must start with s3://")?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        bail!("the URL is missing the bucket name");
+    }
+    Ok((bucket, prefix.trim_matches('/')))
+}
+
+/// Periodically uploads a snapshot of the database, keeping the newest `keep` backups.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_backup(db: Db, store: BackupStore, interval: Duration, keep: usize) {
+    loop {
+        if let Err(err) = backup(&db, &store, keep).await {
+            error!(?err, "Failed to back up database");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn backup(db: &Db, store: &BackupStore, keep: usize) -> Result<()> {
+    if db.backend != Backend::Sqlite {
+        bail!("backups are only supported for SQLite, use pg_dump for PostgreSQL");
+    }
+
+    let dir = tempfile::tempdir().wrap_err("creating temporary directory")?;
+    let snapshot = dir.path().join("snapshot.sqlite");
+    db.snapshot_into(
+        snapshot
+            .to_str()
+            .ok_or_eyre("temporary directory is not UTF-8")?,
+    )
+    .await?;
+
+    let now = time::OffsetDateTime::now_utc()
+        .format(time::macros::format_description!(
+            "[year]-[month]-[day]T[hour]-[minute]-[second]Z"
+        ))
+        .wrap_err("formatting backup timestamp")?;
+    let name = format!("{BACKUP_FILE_PREFIX}{now}{BACKUP_FILE_SUFFIX}");
+
+    let mut file = tokio::fs::File::open(&snapshot)
+        .await
+        .wrap_err("opening snapshot")?;
+    let mut writer = BufWriter::new(store.store.clone(), store.prefix.child(name.as_str()));
+    tokio::io::copy(&mut file, &mut writer)
+        .await
+        .wrap_err("uploading backup")?;
+    tokio::io::AsyncWriteExt::shutdown(&mut writer)
+        .await
+        .wrap_err("finishing backup upload")?;
+    info!(%name, "Uploaded database backup");
+
+    let backups = store.list().await?;
+    for old in &backups[..backups.len().saturating_sub(keep)] {
+        store
+            .store
+            .delete(&store.prefix.child(old.as_str()))
+            .await
+            .wrap_err_with(|| format!("deleting old backup {old}"))?;
+        info!(name = %old, "Deleted old database backup");
+    }
+
+    Ok(())
+}
+
+/// Downloads a backup (the newest one by default) to `db_path`.
+pub async fn restore(store: &BackupStore, name: Option<String>, db_path: &str) -> Result<()> {
+    if tokio::fs::try_exists(db_path)
+        .await
+        .wrap_err("checking for existing database")?
+    {
+        bail!("{db_path} already exists, move it away before restoring");
+    }
+
+    let name = match name {
+        Some(name) => name,
+        None => store
+            .list()
+            .await?
+            .pop()
+            .ok_or_eyre("there are no backups")?,
+    };
+
+    let mut stream = store
+        .store
+        .get(&store.prefix.child(name.as_str()))
+        .await
+        .wrap_err_with(|| format!("fetching backup {name}"))?
+        .into_stream();
+    // Download next to the database first so a failed download doesn't leave a broken database behind.
+    let partial_path = format!("{db_path}.partial");
+    let mut file = tokio::fs::File::create(&partial_path)
+        .await
+        .wrap_err("creating database file")?;
+    while let Some(chunk) = stream.try_next().await.wrap_err("downloading backup")? {
+        tokio::io::AsyncWriteExt::write_all(&mut file, &chunk)
+            .await
+            .wrap_err("writing database file")?;
+    }
+    tokio::io::AsyncWriteExt::flush(&mut file)
+        .await
+        .wrap_err("writing database file")?;
+    tokio::fs::rename(&partial_path, db_path)
+        .await
+        .wrap_err("moving restored database into place")?;
+
+    info!(%name, %db_path, "Restored database backup");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn s3_url() {
+        assert_eq!(super::parse_s3_url("s3://bucket").unwrap(), ("bucket", ""));
+        assert_eq!(
+            super::parse_s3_url("s3://bucket/backups/dib/").unwrap(),
+            ("bucket", "backups/dib")
+        );
+        assert!(super::parse_s3_url("https://bucket").is_err());
+        assert!(super::parse_s3_url("s3:///prefix").is_err());
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
-    fmt::{Debug, Display},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    io::Read,
     num::NonZeroUsize,
     path::Path,
+    process::{ExitStatus, Output, Stdio},
     time::Duration,
 };
 
@@ -9,77 +11,153 @@ use color_eyre::{
     eyre::{bail, Context},
     Result,
 };
-use futures::StreamExt;
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde::Deserialize;
 use tokio::process::Command;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    db::{BuildMode, Db, FullBuildInfo, Status},
+    build_env::BuildEnv,
+    config::BuildConfig,
+    db::{
+        BetaCheck, BuildHost, BuildMode, BuildTiming, FullBuildInfo, Status, TargetCfg, TargetMeta,
+        TargetSpec, ToolchainInfo, Verification,
+    },
+    host, log_offload,
     nightlies::{Nightlies, NightlyCache},
+    queue::BuildQueue,
+    telemetry,
+    toolchain::{Toolchain, ToolchainLease, ToolchainManager},
 };
 
-pub struct Toolchain(String);
-impl Toolchain {
-    pub fn from_nightly(nightly: &str) -> Self {
-        Self(format!("nightly-{nightly}"))
-    }
-}
-impl Debug for Toolchain {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
-    }
-}
-impl Display for Toolchain {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
-    }
-}
-
-pub async fn background_builder(db: Db) -> Result<()> {
+pub async fn background_builder(db: impl BuildQueue, config: BuildConfig) -> Result<()> {
     let mut nightly_cache = NightlyCache::default();
+    let mut slots = Vec::new();
+    for slot in 0..config.concurrent_nightlies {
+        let env = BuildEnv::for_slot(&config, slot)?;
+        slots.push(ToolchainManager::new(env, config.toolchain_cache.clone()).await?);
+    }
+    let (db, config, slots) = (&db, &config, &slots);
+    let mut free = (0..slots.len()).collect::<BTreeSet<_>>();
+    let mut building = HashSet::new();
+    let mut running = FuturesUnordered::new();
     loop {
-        let nightlies = Nightlies::fetch(&mut nightly_cache)
-            .await
-            .wrap_err("fetching nightlies")?;
+        let dist = slots[0].env().dist();
+        let nightlies = if dist.is_offline() && dist.manifests_file().is_none() {
+            Nightlies::installed(slots[0].installed_nightlies(), config)
+        } else {
+            Nightlies::fetch(dist, &mut nightly_cache, config)
+                .await
+                .wrap_err("fetching nightlies")?
+        };
         let already_finished = db
             .finished_nightlies()
             .await
             .wrap_err("fetching finished nightlies")?;
 
-        let next = nightlies.select_latest_to_build(&already_finished);
-        match next {
-            Some((nightly, mode)) => {
-                info!(%nightly, %mode, "Building next nightly");
-                let result = build_every_target_for_toolchain(&db, &nightly, mode)
-                    .await
-                    .wrap_err_with(|| format!("building targets for toolchain {nightly}"));
-                if let Err(err) = result {
-                    error!(%nightly, %mode, ?err, "Failed to build nightly");
-                    db.finish_nightly_as_broken(&nightly, mode)
-                        .await
-                        .wrap_err("marking nightly as broken")?;
-                }
+        let mut unfinished = nightlies
+            .unfinished(&already_finished, &config.modes, &config.schedule)
+            .filter(|next| !building.contains(next))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        while let Some(&slot) = free.first() {
+            if !running.is_empty() && !has_spare_capacity(slots[slot].env()) {
+                debug!("Not starting another nightly, the builder is busy");
+                break;
             }
-            None => {
-                info!("No new nightly, waiting for an hour to try again");
-                tokio::time::sleep(Duration::from_secs(1 * 60 * 60)).await;
+            let Some((nightly, mode)) = unfinished.next() else {
+                break;
+            };
+            // The one after it, whose toolchain is installed while the last targets are built.
+            // Other slots would install it again, so it's only done with a single one.
+            let upcoming = unfinished.peek().cloned().filter(|_| slots.len() == 1);
+            info!(%nightly, %mode, slot, "Building next nightly");
+            free.remove(&slot);
+            building.insert((nightly.clone(), mode));
+            running.push(async move {
+                let upcoming = upcoming
+                    .as_ref()
+                    .map(|(nightly, mode)| (nightly.as_str(), *mode));
+                let result = build_every_target_for_toolchain(
+                    db,
+                    config,
+                    &slots[slot],
+                    &nightly,
+                    mode,
+                    upcoming,
+                )
+                .await
+                .wrap_err_with(|| format!("building targets for toolchain {nightly}"));
+                (slot, nightly, mode, result)
+            });
+        }
+
+        if running.is_empty() {
+            info!(
+                minutes = config.poll_interval_minutes,
+                "No new nightly, waiting to try again"
+            );
+            tokio::time::sleep(Duration::from_secs(config.poll_interval_minutes * 60)).await;
+            continue;
+        }
+        // With a free slot, look again once the load may have gone down.
+        let finished = if free.is_empty() {
+            running.next().await
+        } else {
+            match tokio::time::timeout(CAPACITY_POLL, running.next()).await {
+                Ok(finished) => finished,
+                Err(_) => continue,
             }
+        };
+        let Some((slot, nightly, mode, result)) = finished else {
+            continue;
+        };
+        free.insert(slot);
+        building.remove(&(nightly.clone(), mode));
+        if let Err(err) = result {
+            error!(%nightly, %mode, ?err, "Failed to build nightly");
+            db.finish_nightly(&nightly, mode, true)
+                .await
+                .wrap_err("marking nightly as broken")?;
         }
     }
 }
 
-async fn targets_for_toolchain(toolchain: &Toolchain) -> Result<Vec<String>> {
-    let output = Command::new("rustc")
-        .arg(format!("+{toolchain}"))
-        .arg("--print")
-        .arg("target-list")
-        .output()
+/// How often the builder checks whether it can start another nightly while one is building.
+const CAPACITY_POLL: Duration = Duration::from_secs(10 * 60);
+/// The disk space a nightly needs for its toolchain and builds.
+const MIN_FREE_BYTES_PER_NIGHTLY: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Whether another nightly can be built next to the running ones: the load has to be below the
+/// number of cores, which is the case while they install toolchains or finish their last targets,
+/// and the disk must have room for another toolchain.
+fn has_spare_capacity(env: &BuildEnv) -> bool {
+    // The load of a remote machine isn't known, only `build.concurrent_nightlies` limits it.
+    if !env.executor().is_local() {
+        return true;
+    }
+    let cores = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    host::load_average().is_some_and(|load| load < cores as f64)
+        && host::available_bytes(env.home())
+            .is_some_and(|available| available >= MIN_FREE_BYTES_PER_NIGHTLY)
+}
+
+async fn targets_for_toolchain(env: &BuildEnv, toolchain: &Toolchain) -> Result<Vec<String>> {
+    let output = env
+        .executor()
+        .output(
+            env.command("rustc")
+                .arg(format!("+{toolchain}"))
+                .arg("--print")
+                .arg("target-list"),
+        )
         .await
         .wrap_err("failed to spawn rustc")?;
     if !output.status.success() {
         bail!(
             "failed to get target-list from rustc: {:?}",
-            String::from_utf8(output.stderr)
+            String::from_utf8_lossy(&output.stderr)
         );
     }
 
@@ -90,216 +168,947 @@ async fn targets_for_toolchain(toolchain: &Toolchain) -> Result<Vec<String>> {
         .collect())
 }
 
-#[tracing::instrument]
-async fn install_toolchain(toolchain: &Toolchain, mode: BuildMode) -> Result<()> {
-    info!(%toolchain, "Installing toolchain");
-
-    let result = Command::new("rustup")
-        .arg("toolchain")
-        .arg("install")
-        .arg(&toolchain.0)
-        .arg("--profile")
-        .arg("minimal")
-        .output()
+/// The target specs of all targets of the toolchain, and the platform support metadata in them.
+async fn target_specs_for_toolchain(
+    env: &BuildEnv,
+    nightly: &str,
+    toolchain: &Toolchain,
+) -> Result<(Vec<TargetMeta>, Vec<TargetSpec>)> {
+    let specs = command_stdout(
+        env,
+        env.command("rustc").arg(format!("+{toolchain}")).args([
+            "-Zunstable-options",
+            "--print",
+            "all-target-specs-json",
+        ]),
+    )
+    .await
+    .wrap_err("getting target specs")?;
+    parse_target_specs(nightly, &specs)
+}
+
+/// The cfgs of the targets. Targets rustc fails to print them for are left out.
+async fn target_cfgs_for_toolchain(
+    env: &BuildEnv,
+    nightly: &str,
+    toolchain: &Toolchain,
+    targets: &[String],
+) -> Vec<TargetCfg> {
+    futures::stream::iter(targets)
+        .map(|target| async move {
+            let output = command_stdout(
+                env,
+                env.command("rustc")
+                    .arg(format!("+{toolchain}"))
+                    .args(["--print", "cfg", "--target", target]),
+            )
+            .await;
+            match output {
+                Ok(output) => Some(TargetCfg {
+                    target: target.clone(),
+                    nightly: nightly.to_owned(),
+                    cfg: normalize_cfg(&output),
+                }),
+                Err(err) => {
+                    warn!(%target, ?err, "Failed to get target cfg");
+                    None
+                }
+            }
+        })
+        .buffer_unordered(8)
+        .filter_map(|cfg| async move { cfg })
+        .collect()
         .await
-        .wrap_err("failed to spawn rustup")?;
-    if !result.status.success() {
-        bail!("rustup failed: {:?}", String::from_utf8(result.stderr));
-    }
-    let result = Command::new("rustup")
-        .arg("component")
-        .arg("add")
-        .arg("rust-src")
-        .arg("--toolchain")
-        .arg(&toolchain.0)
-        .output()
+}
+
+/// Sorts the lines of `--print cfg`, so its output can be compared between nightlies.
+fn normalize_cfg(output: &str) -> String {
+    let mut lines = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    lines.sort_unstable();
+    lines.join("\n")
+}
+
+#[derive(Default, Deserialize)]
+struct TargetSpecMetadata {
+    description: Option<String>,
+    tier: Option<i32>,
+    host_tools: Option<bool>,
+    std: Option<bool>,
+}
+
+/// Parses the output of `--print all-target-specs-json`.
+fn parse_target_specs(nightly: &str, specs: &str) -> Result<(Vec<TargetMeta>, Vec<TargetSpec>)> {
+    let specs = serde_json::from_str::<BTreeMap<String, serde_json::Value>>(specs)
+        .wrap_err("target specs are invalid")?;
+    let mut metas = Vec::new();
+    let mut result = Vec::new();
+    for (target, spec) in specs {
+        // Older nightlies don't have it.
+        let metadata = match spec.get("metadata") {
+            Some(metadata) => serde_json::from_value::<TargetSpecMetadata>(metadata.clone())
+                .wrap_err_with(|| format!("metadata of {target} is invalid"))?,
+            None => TargetSpecMetadata::default(),
+        };
+        metas.push(TargetMeta {
+            target: target.clone(),
+            nightly: nightly.to_owned(),
+            tier: metadata.tier,
+            host_tools: metadata.host_tools,
+            std: metadata.std,
+            description: metadata.description,
+        });
+        result.push(TargetSpec {
+            spec: serde_json::to_string_pretty(&spec)?,
+            target,
+            nightly: nightly.to_owned(),
+        });
+    }
+    Ok((metas, result))
+}
+
+/// Asks the installed toolchain about itself.
+async fn toolchain_info(
+    env: &BuildEnv,
+    nightly: &str,
+    lease: &ToolchainLease,
+    mode: BuildMode,
+) -> Result<ToolchainInfo> {
+    let toolchain = lease.toolchain();
+    let rustc = command_stdout(
+        env,
+        env.command("rustc").arg(format!("+{toolchain}")).arg("-vV"),
+    )
+    .await
+    .wrap_err("getting rustc version")?;
+    let cargo = command_stdout(
+        env,
+        env.command("cargo")
+            .arg(format!("+{toolchain}"))
+            .arg("--version"),
+    )
+    .await
+    .wrap_err("getting cargo version")?;
+    let miri = if mode == BuildMode::MiriStd {
+        let miri = command_stdout(
+            env,
+            env.command("cargo")
+                .arg(format!("+{toolchain}"))
+                .args(["miri", "--version"]),
+        )
         .await
-        .wrap_err("failed to spawn rustup")?;
-    if !result.status.success() {
-        bail!("rustup failed: {:?}", String::from_utf8(result.stderr));
-    }
-    if mode == BuildMode::MiriStd {
-        let result = Command::new("rustup")
-            .arg("component")
-            .arg("add")
-            .arg("miri")
-            .arg("--toolchain")
-            .arg(&toolchain.0)
-            .output()
-            .await
-            .wrap_err("failed to spawn rustup")?;
-        if !result.status.success() {
-            bail!("rustup failed: {:?}", String::from_utf8(result.stderr));
-        }
+        .wrap_err("getting miri version")?;
+        Some(miri.trim().to_owned())
+    } else {
+        None
+    };
+
+    let rustc = parse_rustc_verbose_version(&rustc);
+    Ok(ToolchainInfo {
+        nightly: nightly.to_owned(),
+        rustc_version: rustc.version.unwrap_or_default(),
+        commit_hash: rustc.commit_hash,
+        llvm_version: rustc.llvm_version,
+        host: rustc.host.unwrap_or_default(),
+        cargo_version: cargo.trim().to_owned(),
+        miri_version: miri,
+        rustup_version: Some(lease.rustup_version().to_owned()),
+        verification: None,
+    })
+}
+
+/// Downloads the crates.io dependencies of the standard library into the `CARGO_HOME` all builds
+/// share. Without this, the first builds of a nightly all download them at the same time and wait
+/// for each other on the lock of cargo's package cache.
+async fn fetch_std_dependencies(env: &BuildEnv, toolchain: &Toolchain) -> Result<()> {
+    let sysroot = command_stdout(
+        env,
+        env.command("rustc")
+            .arg(format!("+{toolchain}"))
+            .args(["--print", "sysroot"]),
+    )
+    .await
+    .wrap_err("getting sysroot")?;
+    let manifest = Path::new(sysroot.trim()).join("lib/rustlib/src/rust/library/Cargo.toml");
+    // Before the standard library had its own workspace, build-std made one up on the fly.
+    if !env.executor().exists(&manifest).await? {
+        debug!(%toolchain, "The standard library has no workspace to fetch");
+        return Ok(());
     }
+    command_stdout(
+        env,
+        env.command("cargo")
+            .arg(format!("+{toolchain}"))
+            .args(["fetch", "--locked", "--manifest-path"])
+            .arg(&manifest),
+    )
+    .await
+    .wrap_err("fetching dependencies of the standard library")?;
     Ok(())
 }
 
-#[tracing::instrument]
-async fn uninstall_toolchain(toolchain: &Toolchain) -> Result<()> {
-    info!(%toolchain, "Uninstalling toolchain");
-
-    let result = Command::new("rustup")
-        .arg("toolchain")
-        .arg("remove")
-        .arg(&toolchain.0)
-        .output()
+async fn command_stdout(env: &BuildEnv, command: &mut Command) -> Result<String> {
+    let output = env
+        .executor()
+        .output(command)
         .await
-        .wrap_err("failed to spawn rustup")?;
-    if !result.status.success() {
+        .wrap_err("failed to spawn")?;
+    if !output.status.success() {
         bail!(
-            "rustup toolchain remove failed: {:?}",
-            String::from_utf8(result.stderr)
+            "command failed: {:?}",
+            String::from_utf8_lossy(&output.stderr)
         );
     }
-    Ok(())
+    String::from_utf8(output.stdout).wrap_err("output is invalid UTF-8")
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct RustcVersion {
+    version: Option<String>,
+    commit_hash: Option<String>,
+    host: Option<String>,
+    llvm_version: Option<String>,
 }
 
+/// Parses the output of `rustc -vV`.
+fn parse_rustc_verbose_version(output: &str) -> RustcVersion {
+    let mut lines = output.lines();
+    let mut version = RustcVersion {
+        version: lines.next().map(|line| line.trim().to_owned()),
+        ..Default::default()
+    };
+    for line in lines {
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let value = Some(value.trim().to_owned());
+        match key {
+            "commit-hash" => version.commit_hash = value,
+            "host" => version.host = value,
+            "LLVM version" => version.llvm_version = value,
+            _ => {}
+        }
+    }
+    version
+}
+
+/// Builds every target of the nightly in the mode with the toolchains of the manager, storing the
+/// results in the queue, unless the nightly is finished already. `upcoming` is the nightly after
+/// it, whose toolchain is installed while the last targets build.
 pub async fn build_every_target_for_toolchain(
-    db: &Db,
+    db: &impl BuildQueue,
+    config: &BuildConfig,
+    toolchains: &ToolchainManager,
     nightly: &str,
     mode: BuildMode,
+    upcoming: Option<(&str, BuildMode)>,
 ) -> Result<()> {
     if db.is_nightly_finished(nightly, mode).await? {
         debug!("Nightly is already finished, not trying again");
         return Ok(());
     }
 
-    let toolchain = Toolchain::from_nightly(nightly);
-    install_toolchain(&toolchain, mode).await?;
+    let lease = match toolchains
+        .acquire(&Toolchain::from_nightly(nightly), mode)
+        .await?
+    {
+        Ok(lease) => lease,
+        Err(error) => {
+            warn!(%nightly, %mode, %error, "Toolchain is not available");
+            db.finish_nightly_unavailable(nightly, mode, &error).await?;
+            return Ok(());
+        }
+    };
+    let toolchain = lease.toolchain();
+    let env = toolchains.env();
+
+    let mut info = toolchain_info(env, nightly, &lease, mode)
+        .await
+        .wrap_err("getting toolchain info")?;
+    // Offline, rustup's copy of the manifest at least shows whether the toolchain was damaged.
+    let manifest = if env.dist().is_offline() {
+        let path = env
+            .rustup_home()
+            .join("toolchains")
+            .join(format!("{toolchain}-{}", env.host()))
+            .join("lib/rustlib/multirust-channel-manifest.toml");
+        env.executor().read_file(&path).await
+    } else {
+        crate::manifest::fetch(env.dist(), nightly).await
+    };
+    info.verification = match &manifest {
+        Ok(manifest) => crate::manifest::verify(info.commit_hash.as_deref(), manifest)
+            .unwrap_or_else(|err| {
+                warn!(?err, "Failed to verify the toolchain");
+                None
+            }),
+        Err(err) => {
+            warn!(?err, "Failed to fetch the channel manifest");
+            None
+        }
+    };
+    db.insert_toolchain_info(&info).await?;
+    // Every build would fail with a toolchain that got corrupted on the way.
+    if info.verification == Some(Verification::Mismatch) {
+        bail!(
+            "the installed rustc of {toolchain} is from commit {:?}, which is not the one in the channel manifest",
+            info.commit_hash
+        );
+    }
+    // Nothing depends on the specs, so don't give up on the nightly without them.
+    match target_specs_for_toolchain(env, nightly, toolchain).await {
+        Ok((metas, specs)) => {
+            db.insert_target_meta(&metas).await?;
+            db.insert_target_specs(&specs).await?;
+        }
+        Err(err) => warn!(?err, "Failed to get target specs"),
+    }
+    match manifest.and_then(|manifest| crate::prebuilt_std::targets_from_manifest(&manifest)) {
+        Ok(targets) => db.insert_prebuilt_std(nightly, &targets).await?,
+        Err(err) => warn!(?err, "Failed to get targets with a prebuilt std"),
+    }
+    let host = BuildHost {
+        host: env.host().to_owned(),
+        hostname: Some(
+            env.executor()
+                .remote_hostname()
+                .map_or_else(hostname, ToOwned::to_owned),
+        ),
+    };
+
+    // The builds download whatever is missing themselves.
+    if let Err(err) = fetch_std_dependencies(env, toolchain).await {
+        warn!(
+            ?err,
+            "Failed to fetch the dependencies of the standard library"
+        );
+    }
 
-    let targets = targets_for_toolchain(&toolchain)
+    let targets = targets_for_toolchain(env, toolchain)
         .await
         .wrap_err("failed to get targets")?;
 
-    let concurrent = std::env::var("DOES_IT_BUILD_PARALLEL_JOBS")
-        .map(|jobs| jobs.parse().unwrap())
-        .unwrap_or_else(|_| {
+    let cfgs = target_cfgs_for_toolchain(env, nightly, toolchain, &targets).await;
+    db.insert_target_cfgs(&cfgs).await?;
+
+    let targets = targets
+        .into_iter()
+        .filter(|target| config.targets.matches(target))
+        .collect::<Vec<_>>();
+
+    let concurrent = config
+        .parallel_jobs
+        .unwrap_or_else(|| {
             std::thread::available_parallelism()
                 .unwrap_or(NonZeroUsize::new(2).unwrap())
                 .get()
                 / 2
-        });
+        })
+        // With no parallelism at all, nothing would ever get built.
+        .max(1);
 
-    let results = futures::stream::iter(
-        targets
-            .iter()
-            .map(|target| build_single_target(&db, nightly, target, mode)),
+    db.enqueue_jobs(nightly, mode, &targets).await?;
+
+    let settings = JobSettings {
+        max_log_bytes: config.max_log_bytes,
+        assumed_failing: db
+            .assumed_failing(nightly, mode, &config.assume_failing)
+            .await
+            .wrap_err("getting targets that are assumed to fail")?,
+    };
+    if !settings.assumed_failing.is_empty() {
+        info!(
+            count = settings.assumed_failing.len(),
+            "Skipping targets that are assumed to fail"
+        );
+    }
+
+    let worker = worker_id();
+    let mut builders = futures::stream::iter(
+        (0..concurrent).map(|_| build_jobs(db, env, nightly, mode, &host, &worker, &settings)),
     )
-    .buffer_unordered(concurrent)
-    .collect::<Vec<Result<()>>>()
-    .await;
+    .buffer_unordered(concurrent);
+    let mut results = Vec::new();
+    while let Some(result) = builders.next().await {
+        // Once one of them runs out of jobs, the others are on the last targets, so the next
+        // toolchain is installed while the pool drains instead of after it.
+        if results.is_empty() {
+            if let Some((next_nightly, next_mode)) = upcoming {
+                toolchains.prefetch(Toolchain::from_nightly(next_nightly), next_mode);
+            }
+        }
+        results.push(result);
+    }
     for result in results {
-        result?;
+        result.wrap_err_with(|| format!("building targets for toolchain {toolchain}"))?;
     }
 
+    // Other builders may still be working on the jobs they leased. If they die, their
+    // jobs are picked up again once the lease expires.
+    while db.has_unfinished_jobs(nightly, mode).await? {
+        debug!("Waiting for other builders to finish");
+        tokio::time::sleep(JOB_LEASE_RENEWAL).await;
+        build_jobs(db, env, nightly, mode, &host, &worker, &settings).await?;
+    }
+
+    // Before finishing it, so the regressions are escalated with what is known about beta.
+    // Miri isn't shipped on beta.
+    if config.check_beta && mode == BuildMode::Core {
+        if let Err(err) = check_beta(db, toolchains, nightly, mode).await {
+            warn!(?err, "Failed to check whether the regressions reached beta");
+        }
+    }
+
+    // Mark it as finished, so we never have to build it again.
+    db.finish_nightly(nightly, mode, false).await?;
+
+    lease.release().await?;
+
+    Ok(())
+}
+
+/// Builds the targets that regressed on the nightly with the beta of the same day, to see whether
+/// the regression already reached beta and needs a backport.
+async fn check_beta(
+    db: &impl BuildQueue,
+    toolchains: &ToolchainManager,
+    nightly: &str,
+    mode: BuildMode,
+) -> Result<()> {
+    let targets = db.unchecked_beta_regressions(nightly, mode).await?;
+    if targets.is_empty() {
+        return Ok(());
+    }
+    let toolchain = Toolchain::beta_of(nightly);
+    let lease = match toolchains.acquire(&toolchain, mode).await? {
+        Ok(lease) => lease,
+        Err(error) => {
+            warn!(%toolchain, %error, "Beta is not available");
+            return Ok(());
+        }
+    };
+    info!(%toolchain, count = targets.len(), "Building regressions with beta");
     for target in targets {
-        build_single_target(db, nightly, &target, mode)
+        let tmpdir = toolchains.env().tempdir()?;
+        let result = build_target(toolchains.env(), tmpdir.path(), &toolchain, &target, mode)
             .await
-            .wrap_err_with(|| format!("building target {target} for toolchain {toolchain}"))?;
+            .wrap_err_with(|| format!("building target {target} with {toolchain}"))?;
+        info!(%target, status = %result.status, "Finished building regression with beta");
+        db.insert_beta_check(&BetaCheck {
+            nightly: nightly.to_owned(),
+            target,
+            mode,
+            toolchain: toolchain.to_string(),
+            status: result.status,
+        })
+        .await?;
     }
+    lease.release().await
+}
 
-    // Mark it as finished, so we never have to build it again.
-    db.finish_nightly(nightly, mode).await?;
+/// How long a builder may work on a job before others can take it over, unless it renews the lease.
+const JOB_LEASE: Duration = Duration::from_secs(10 * 60);
+const JOB_LEASE_RENEWAL: Duration = Duration::from_secs(2 * 60);
 
-    uninstall_toolchain(&toolchain).await?;
+fn hostname() -> String {
+    host::hostname().unwrap_or_else(|| "unknown".into())
+}
 
+/// Identifies this process in job leases.
+fn worker_id() -> String {
+    format!("{}:{}", hostname(), std::process::id())
+}
+
+/// What the jobs of a nightly are built with, besides the toolchain.
+struct JobSettings {
+    max_log_bytes: usize,
+    /// Targets that are recorded as skipped instead of built, with the note to store as their log.
+    assumed_failing: BTreeMap<String, String>,
+}
+
+/// Claims and builds jobs of the nightly until there are none left to claim.
+async fn build_jobs(
+    db: &impl BuildQueue,
+    env: &BuildEnv,
+    nightly: &str,
+    mode: BuildMode,
+    host: &BuildHost,
+    worker: &str,
+    settings: &JobSettings,
+) -> Result<()> {
+    while let Some(target) = db.claim_job(nightly, mode, worker, JOB_LEASE).await? {
+        let build = build_single_target(db, env, nightly, &target, mode, host, settings);
+        tokio::pin!(build);
+        loop {
+            tokio::select! {
+                result = &mut build => {
+                    result.wrap_err_with(|| format!("building target {target}"))?;
+                    break;
+                }
+                _ = tokio::time::sleep(JOB_LEASE_RENEWAL) => {
+                    db.renew_job_lease(nightly, &target, mode, worker, JOB_LEASE).await?;
+                }
+            }
+        }
+        db.complete_job(nightly, &target, mode).await?;
+    }
     Ok(())
 }
 
-#[tracing::instrument(skip(db))]
-async fn build_single_target(db: &Db, nightly: &str, target: &str, mode: BuildMode) -> Result<()> {
+#[tracing::instrument(skip(db, env, mode, host, settings), fields(%mode))]
+async fn build_single_target(
+    db: &impl BuildQueue,
+    env: &BuildEnv,
+    nightly: &str,
+    target: &str,
+    mode: BuildMode,
+    host: &BuildHost,
+    settings: &JobSettings,
+) -> Result<()> {
     let existing = db
-        .build_status_full(nightly, target, mode)
+        .build_exists(nightly, target, mode)
         .await
         .wrap_err("getting existing build")?;
-    if existing.is_some() {
+    if existing {
         debug!("Build already exists");
         return Ok(());
     }
 
-    info!("Building target");
+    if let Some(note) = settings.assumed_failing.get(target) {
+        info!("Skipping target that is assumed to fail");
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        return db
+            .insert_build(FullBuildInfo {
+                nightly: nightly.into(),
+                target: target.into(),
+                status: Status::Skipped,
+                stderr: note.clone(),
+                original_stderr_bytes: None,
+                mode,
+                timing: BuildTiming {
+                    started_at: Some(now),
+                    finished_at: Some(now),
+                    exit_code: None,
+                    peak_rss_kib: None,
+                },
+                host: host.clone(),
+            })
+            .await;
+    }
 
-    let tmpdir = tempfile::tempdir().wrap_err("creating temporary directory")?;
+    // Every attempt is stored, so the ones that failed because of the builder show up as
+    // superseded attempts of the build.
+    for attempt in 1..=INFRA_ERROR_ATTEMPTS {
+        info!(attempt, "Building target");
 
-    let result = build_target(
-        tmpdir.path(),
-        &Toolchain::from_nightly(nightly),
-        target,
-        mode,
-    )
-    .await
-    .wrap_err("running build")?;
-
-    db.insert(FullBuildInfo {
-        nightly: nightly.into(),
-        target: target.into(),
-        status: result.status,
-        stderr: result.stderr,
-        mode,
-    })
-    .await?;
+        let tmpdir = env.tempdir()?;
+
+        let started_at = time::OffsetDateTime::now_utc().unix_timestamp();
+        let start = std::time::Instant::now();
+        let result = build_target(
+            env,
+            tmpdir.path(),
+            &Toolchain::from_nightly(nightly),
+            target,
+            mode,
+        )
+        .await
+        .wrap_err("running build")?;
+        drop(tmpdir);
+        let duration = start.elapsed();
+        info!(
+            status = %result.status,
+            duration_secs = duration.as_secs_f64(),
+            "Finished building target"
+        );
+        telemetry::record_build(mode, result.status, duration);
+
+        let (stderr, original_stderr_bytes) =
+            log_offload::cap(result.stderr, settings.max_log_bytes);
+        db.insert_build(FullBuildInfo {
+            nightly: nightly.into(),
+            target: target.into(),
+            status: result.status,
+            stderr,
+            original_stderr_bytes,
+            mode,
+            timing: BuildTiming {
+                started_at: Some(started_at),
+                finished_at: Some(time::OffsetDateTime::now_utc().unix_timestamp()),
+                exit_code: result.exit_code,
+                peak_rss_kib: result.peak_rss_kib,
+            },
+            host: host.clone(),
+        })
+        .await?;
+
+        if result.status != Status::InfraError {
+            break;
+        }
+        if attempt < INFRA_ERROR_ATTEMPTS {
+            warn!(
+                attempt,
+                "Build failed because of the builder, trying again later"
+            );
+            tokio::time::sleep(INFRA_ERROR_RETRY_DELAY).await;
+        }
+    }
 
     Ok(())
 }
 
+/// How often a build is tried before its infra error is kept as the result.
+const INFRA_ERROR_ATTEMPTS: u32 = 3;
+/// Gives other builds time to finish and free up memory or disk space.
+const INFRA_ERROR_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+/// A failed build with less free disk space than this left probably failed because of it.
+const MIN_FREE_BYTES: u64 = 512 * 1024 * 1024;
+
 struct BuildResult {
     status: Status,
     stderr: String,
+    exit_code: Option<i32>,
+    peak_rss_kib: Option<i64>,
 }
 
 /// Build a target core in a temporary directory and see whether it passes or not.
 async fn build_target(
+    env: &BuildEnv,
     tmpdir: &Path,
     toolchain: &Toolchain,
     target: &str,
     mode: BuildMode,
 ) -> Result<BuildResult> {
-    let output = match mode {
+    // Over SSH, the project lives in a directory of the same name on the remote machine.
+    env.executor().create_remote_dir(tmpdir).await?;
+    let result = build_target_in(env, tmpdir, toolchain, target, mode).await;
+    if let Err(err) = env.executor().remove_remote_dir(tmpdir).await {
+        warn!(?err, "Failed to remove the remote build directory");
+    }
+    result
+}
+
+async fn build_target_in(
+    env: &BuildEnv,
+    tmpdir: &Path,
+    toolchain: &Toolchain,
+    target: &str,
+    mode: BuildMode,
+) -> Result<BuildResult> {
+    let (output, peak_rss_kib) = match mode {
         BuildMode::Core => {
-            let init = Command::new("cargo")
-                .args(["init", "--lib", "--name", "target-test"])
-                .current_dir(&tmpdir)
-                .output()
+            let init = env
+                .executor()
+                .output(
+                    env.command("cargo")
+                        .args(["init", "--lib", "--name", "target-test"])
+                        .current_dir(tmpdir),
+                )
                 .await
                 .wrap_err("spawning cargo init")?;
             if !init.status.success() {
-                bail!("init failed: {}", String::from_utf8(init.stderr)?);
+                bail!("init failed: {}", String::from_utf8_lossy(&init.stderr));
             }
 
-            let librs = tmpdir.join("src").join("lib.rs");
-            std::fs::write(&librs, "#![no_std]\n")
-                .wrap_err_with(|| format!("writing to {}", librs.display()))?;
+            env.executor()
+                .write_file(&tmpdir.join("src").join("lib.rs"), "#![no_std]\n")
+                .await?;
 
-            Command::new("cargo")
+            let mut build = env.std_command("cargo");
+            build
                 .arg(format!("+{toolchain}"))
                 .args(["build", "-Zbuild-std=core", "--release"])
                 .args(["--target", target])
-                .current_dir(&tmpdir)
-                .output()
+                .current_dir(tmpdir);
+            // Beta only allows the unstable `-Zbuild-std` when pretending to be the compiler's own build.
+            if !toolchain.is_nightly() {
+                build.env("RUSTC_BOOTSTRAP", "1");
+            }
+            output_with_peak_rss(env.executor().prepare(build))
                 .await
-                .wrap_err("spawning cargo build")?
+                .wrap_err("running cargo build")?
+        }
+        BuildMode::MiriStd => {
+            let mut setup = env.std_command("cargo");
+            setup
+                .arg(format!("+{toolchain}"))
+                .args(["miri", "setup"])
+                .args(["--target", target])
+                .current_dir(tmpdir)
+                .env("MIRI_SYSROOT", tmpdir);
+            output_with_peak_rss(env.executor().prepare(setup))
+                .await
+                .wrap_err("running cargo miri setup")?
         }
-        BuildMode::MiriStd => Command::new("cargo")
-            .arg(format!("+{toolchain}"))
-            .args(["miri", "setup"])
-            .args(["--target", target])
-            .current_dir(&tmpdir)
-            .env("MIRI_SYSROOT", tmpdir)
-            .output()
-            .await
-            .wrap_err("spawning cargo build")?,
     };
 
-    let stderr = String::from_utf8(output.stderr).wrap_err("cargo stderr utf8")?;
+    let mut stderr = env.sanitize_log(&build_output_to_string(output.stderr), tmpdir);
 
+    // The free space of the local disk says nothing about a remote machine.
+    let available = env
+        .executor()
+        .is_local()
+        .then(|| host::available_bytes(tmpdir))
+        .flatten();
     let status = if output.status.success() {
         Status::Pass
+    } else if let Some(reason) = env
+        .executor()
+        .connection_error(output.status)
+        .or_else(|| infra_error(output.status, &stderr, available).map(ToOwned::to_owned))
+    {
+        warn!(%reason, "Build failed because of the builder");
+        stderr.push_str(&format!(
+            "note: does-it-build recorded this as an infra error: {reason}\n"
+        ));
+        Status::InfraError
+    } else if mode == BuildMode::MiriStd && miri_unsupported(&stderr) {
+        Status::Unsupported
     } else {
         Status::Error
     };
 
     info!("Finished build");
 
-    Ok(BuildResult { status, stderr })
+    Ok(BuildResult {
+        status,
+        stderr,
+        exit_code: output.status.code(),
+        // Only that of `ssh` itself is measured for remote builds.
+        peak_rss_kib: peak_rss_kib.filter(|_| env.executor().is_local()),
+    })
+}
+
+/// Why a failed build failed because of the builder instead of the target, if it did: the kernel
+/// killed it or rustc (which cargo reports as killed by `SIGKILL`) for running out of memory,
+/// or the disk is full.
+fn infra_error(
+    status: ExitStatus,
+    stderr: &str,
+    available_bytes: Option<u64>,
+) -> Option<&'static str> {
+    if host::killed(status) || stderr.contains("(signal: 9, SIGKILL: kill)") {
+        return Some("the build was killed, probably because the builder ran out of memory");
+    }
+    // The first is the message of Unix, the second the one of Windows.
+    if stderr.contains("No space left on device")
+        || stderr.contains("There is not enough space on the disk")
+        || available_bytes.is_some_and(|available| available < MIN_FREE_BYTES)
+    {
+        return Some("the builder ran out of disk space");
+    }
+    None
+}
+
+/// Whether Miri refused to set up the target because it doesn't support it, as opposed to the
+/// standard library failing to build. Only error messages that name Miri itself count, as those of
+/// rustc can mention the `unsupported` platform of the standard library.
+fn miri_unsupported(stderr: &str) -> bool {
+    stderr.lines().any(|line| {
+        let Some(message) = line
+            .strip_prefix("error: ")
+            .or_else(|| line.strip_prefix("fatal error: "))
+        else {
+            return false;
+        };
+        let message = message.to_lowercase();
+        message.contains("miri")
+            && (message.contains("not support")
+                || message.contains("n't support")
+                || message.contains("unsupported"))
+    })
+}
+
+/// Linkers and C compilers can print bytes that are not UTF-8, which must not lose the result of the
+/// build. They are replaced, with a note at the end of the log that this happened.
+fn build_output_to_string(output: Vec<u8>) -> String {
+    match String::from_utf8(output) {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Build output is not valid UTF-8, replacing the invalid bytes");
+            let mut output = String::from_utf8_lossy(err.as_bytes()).into_owned();
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str("note: does-it-build replaced bytes that are not valid UTF-8 in this output with U+FFFD\n");
+            output
+        }
+    }
+}
+
+/// Runs the command like `Command::output`, also returning the peak resident memory in KiB
+/// of its largest process, including the compilers cargo spawned, if the OS reports it.
+async fn output_with_peak_rss(mut command: std::process::Command) -> Result<(Output, Option<i64>)> {
+    tokio::task::spawn_blocking(move || {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err("failed to spawn")?;
+        let mut stdout_pipe = child.stdout.take().unwrap();
+        let mut stderr_pipe = child.stderr.take().unwrap();
+        let stdout = std::thread::spawn(move || {
+            let mut stdout = Vec::new();
+            stdout_pipe.read_to_end(&mut stdout).map(|_| stdout)
+        });
+        let stderr = std::thread::spawn(move || {
+            let mut stderr = Vec::new();
+            stderr_pipe.read_to_end(&mut stderr).map(|_| stderr)
+        });
+
+        // Waiting right away lets Windows track the processes cargo starts from the beginning.
+        let (status, peak_rss_kib) =
+            host::wait_with_peak_rss(child).wrap_err("waiting for process")?;
+        let stdout = stdout
+            .join()
+            .expect("reading stdout panicked")
+            .wrap_err("reading stdout")?;
+        let stderr = stderr
+            .join()
+            .expect("reading stderr panicked")
+            .wrap_err("reading stderr")?;
+        let output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+        Ok((output, peak_rss_kib))
+    })
+    .await
+    .wrap_err("waiting for build thread")?
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn target_specs() {
+        let specs = r#"{
+            "aarch64-unknown-linux-gnu": {
+                "arch": "aarch64",
+                "metadata": {
+                    "description": "ARM64 Linux (kernel 4.1, glibc 2.17+)",
+                    "host_tools": true,
+                    "std": true,
+                    "tier": 1
+                }
+            },
+            "avr-none": { "arch": "avr" }
+        }"#;
+        let (metas, specs) = super::parse_target_specs("2024-09-07", specs).unwrap();
+        assert_eq!(
+            metas,
+            [
+                crate::db::TargetMeta {
+                    target: "aarch64-unknown-linux-gnu".to_owned(),
+                    nightly: "2024-09-07".to_owned(),
+                    tier: Some(1),
+                    host_tools: Some(true),
+                    std: Some(true),
+                    description: Some("ARM64 Linux (kernel 4.1, glibc 2.17+)".to_owned()),
+                },
+                crate::db::TargetMeta {
+                    target: "avr-none".to_owned(),
+                    nightly: "2024-09-07".to_owned(),
+                    tier: None,
+                    host_tools: None,
+                    std: None,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(specs[1].target, "avr-none");
+        assert_eq!(specs[1].spec, "{\n  \"arch\": \"avr\"\n}");
+    }
+
+    #[test]
+    fn build_output_to_string() {
+        assert_eq!(
+            super::build_output_to_string(b"error: linking failed\n".to_vec()),
+            "error: linking failed\n"
+        );
+        assert_eq!(
+            super::build_output_to_string(b"ld: bad byte \xff".to_vec()),
+            "ld: bad byte \u{FFFD}\nnote: does-it-build replaced bytes that are not valid UTF-8 in this output with U+FFFD\n"
+        );
+    }
+
+    #[test]
+    fn miri_unsupported() {
+        assert!(super::miri_unsupported(
+            "error: Miri does not support the target `avr-none`\n"
+        ));
+        assert!(super::miri_unsupported(
+            "fatal error: the target `x86_64-unknown-uefi` is not supported by Miri\n"
+        ));
+        assert!(!super::miri_unsupported(
+            "error[E0425]: cannot find function `abort_internal` in module `crate::sys::pal::unsupported`\n"
+        ));
+        assert!(!super::miri_unsupported(
+            "error: could not compile `std` (lib) due to 1 previous error\nnote: miri setup failed\n"
+        ));
+    }
+
+    // Exit statuses can only be made up on Unix.
+    #[cfg(unix)]
+    #[test]
+    fn infra_error() {
+        use std::{os::unix::process::ExitStatusExt, process::ExitStatus};
+
+        let failed = ExitStatus::from_raw(101 << 8);
+        let killed = ExitStatus::from_raw(libc::SIGKILL);
+        let plenty = Some(100 * 1024 * 1024 * 1024);
+        assert_eq!(
+            super::infra_error(failed, "error[E0463]: can't find crate for `std`", plenty),
+            None
+        );
+        assert!(super::infra_error(killed, "", plenty).is_some());
+        assert!(super::infra_error(
+            failed,
+            "error: could not compile `core` (lib)\n\nCaused by:\n  process didn't exit successfully: `rustc` (signal: 9, SIGKILL: kill)",
+            plenty
+        )
+        .is_some());
+        assert!(super::infra_error(
+            failed,
+            "error: failed to write: No space left on device (os error 28)",
+            plenty
+        )
+        .is_some());
+        assert!(super::infra_error(
+            failed,
+            "error: failed to write: There is not enough space on the disk. (os error 112)",
+            plenty
+        )
+        .is_some());
+        assert!(super::infra_error(failed, "error: linking failed", Some(1024)).is_some());
+        assert_eq!(
+            super::infra_error(failed, "error: linking failed", None),
+            None
+        );
+    }
+
+    #[test]
+    fn rustc_verbose_version() {
+        let output = "rustc 1.83.0-nightly (1bc403daa 2024-09-06)
+binary: rustc
+commit-hash: 1bc403daadbebb553ccc211a0a8eebb73989665f
+commit-date: 2024-09-06
+host: x86_64-unknown-linux-gnu
+release: 1.83.0-nightly
+LLVM version: 19.1.0
+";
+        assert_eq!(
+            super::parse_rustc_verbose_version(output),
+            super::RustcVersion {
+                version: Some("rustc 1.83.0-nightly (1bc403daa 2024-09-06)".to_owned()),
+                commit_hash: Some("1bc403daadbebb553ccc211a0a8eebb73989665f".to_owned()),
+                host: Some("x86_64-unknown-linux-gnu".to_owned()),
+                llvm_version: Some("19.1.0".to_owned()),
+            }
+        );
+    }
 }
@@ -0,0 +1,102 @@
+//! Building a single nightly and reporting the result through the exit code,
+//! for gating on it in CI.
+
+use color_eyre::{eyre::bail, Result};
+
+use crate::{
+    build,
+    build_env::BuildEnv,
+    config::BuildConfig,
+    db::{BuildMode, NightlyRecord},
+    flaky,
+    queue::DbQueue,
+    toolchain::ToolchainManager,
+};
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum FailOn {
+    /// A target that built on the previous nightly fails.
+    Regression,
+    /// Any target fails.
+    Error,
+}
+
+/// Builds the nightly unless it was built already and returns whether it passes.
+pub async fn run(
+    queue: &DbQueue,
+    config: &BuildConfig,
+    nightly: &str,
+    mode: BuildMode,
+    fail_on: FailOn,
+    build: bool,
+) -> Result<bool> {
+    if build {
+        build::build_every_target_for_toolchain(
+            queue,
+            config,
+            &ToolchainManager::new(BuildEnv::new(config)?, config.toolchain_cache.clone()).await?,
+            nightly,
+            mode,
+            None,
+        )
+        .await?;
+    }
+
+    let db = &queue.db;
+    let finish_state = db
+        .nightly_finish_states(nightly)
+        .await?
+        .into_iter()
+        .find(|state| state.mode == mode && state.host.as_deref() == Some(crate::HOST));
+    match finish_state {
+        None => bail!(
+            "nightly-{nightly} has not been built for {mode} on {}",
+            crate::HOST
+        ),
+        Some(NightlyRecord {
+            toolchain_error: Some(error),
+            ..
+        }) => {
+            println!("nightly-{nightly} {mode} is not available: {error}");
+            return Ok(false);
+        }
+        Some(state) if state.broken => {
+            println!("nightly-{nightly} {mode} is broken, no targets could be built");
+            return Ok(false);
+        }
+        Some(_) => {}
+    }
+
+    let failed = match fail_on {
+        FailOn::Error => db.failed_targets(nightly, mode, crate::HOST).await?,
+        FailOn::Regression => match db.previous_nightly(nightly, mode, crate::HOST).await? {
+            Some(previous) => {
+                println!("Comparing nightly-{nightly} {mode} to nightly-{previous}");
+                let flaky = flaky::flaky_targets(db, mode, Some(crate::HOST)).await?;
+                let (flaky, regressions) = db
+                    .regressions(nightly, &previous, mode, crate::HOST)
+                    .await?
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|target| flaky.contains(target));
+                for target in flaky {
+                    println!("ignoring regression of flaky target {target}");
+                }
+                regressions
+            }
+            None => {
+                println!("No nightly before nightly-{nightly} to compare {mode} to");
+                Vec::new()
+            }
+        },
+    };
+
+    if failed.is_empty() {
+        println!("ok: no failing targets");
+    } else {
+        println!("{} failing targets:", failed.len());
+        for target in &failed {
+            println!("  {target}");
+        }
+    }
+    Ok(failed.is_empty())
+}
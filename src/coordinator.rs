@@ -0,0 +1,446 @@
+//! Distributed building: workers on other machines run the builds and talk to the
+//! coordinator, the process that owns the database, over an authenticated HTTP API.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::AssumeFailingConfig,
+    db::{
+        BetaCheck, BuildMode, Db, FinishedNightly, FullBuildInfo, TargetCfg, TargetMeta,
+        TargetSpec, ToolchainInfo,
+    },
+    log_offload::LogOffload,
+    queue::{BuildQueue, DbQueue},
+};
+
+/// A request of a worker, which works on the jobs and results of its host triple.
+#[derive(Serialize, Deserialize)]
+pub struct WorkerCall {
+    pub host: String,
+    #[serde(flatten)]
+    pub request: WorkerRequest,
+}
+
+/// A database operation a worker asks the coordinator to do.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum WorkerRequest {
+    FinishedNightlies,
+    IsNightlyFinished {
+        nightly: String,
+        mode: BuildMode,
+    },
+    FinishNightly {
+        nightly: String,
+        mode: BuildMode,
+        broken: bool,
+    },
+    FinishNightlyUnavailable {
+        nightly: String,
+        mode: BuildMode,
+        error: String,
+    },
+    InsertToolchainInfo {
+        info: ToolchainInfo,
+    },
+    InsertTargetMeta {
+        metas: Vec<TargetMeta>,
+    },
+    InsertTargetSpecs {
+        specs: Vec<TargetSpec>,
+    },
+    InsertTargetCfgs {
+        cfgs: Vec<TargetCfg>,
+    },
+    InsertPrebuiltStd {
+        nightly: String,
+        targets: Vec<String>,
+    },
+    EnqueueJobs {
+        nightly: String,
+        mode: BuildMode,
+        targets: Vec<String>,
+    },
+    ClaimJob {
+        nightly: String,
+        mode: BuildMode,
+        worker: String,
+        lease: Duration,
+    },
+    RenewJobLease {
+        nightly: String,
+        target: String,
+        mode: BuildMode,
+        worker: String,
+        lease: Duration,
+    },
+    CompleteJob {
+        nightly: String,
+        target: String,
+        mode: BuildMode,
+    },
+    HasUnfinishedJobs {
+        nightly: String,
+        mode: BuildMode,
+    },
+    BuildExists {
+        nightly: String,
+        target: String,
+        mode: BuildMode,
+    },
+    AssumedFailing {
+        nightly: String,
+        mode: BuildMode,
+        config: AssumeFailingConfig,
+    },
+    InsertBuild {
+        info: FullBuildInfo,
+    },
+    UncheckedBetaRegressions {
+        nightly: String,
+        mode: BuildMode,
+    },
+    InsertBetaCheck {
+        check: BetaCheck,
+    },
+}
+
+/// Runs a worker's request against the database on the coordinator.
+pub async fn handle(db: &Db, log_offload: LogOffload, call: WorkerCall) -> Result<Value> {
+    let queue = DbQueue::new(db.clone(), call.host, log_offload);
+    let value = match call.request {
+        WorkerRequest::FinishedNightlies => {
+            serde_json::to_value(queue.finished_nightlies().await?)?
+        }
+        WorkerRequest::IsNightlyFinished { nightly, mode } => {
+            queue.is_nightly_finished(&nightly, mode).await?.into()
+        }
+        WorkerRequest::FinishNightly {
+            nightly,
+            mode,
+            broken,
+        } => {
+            queue.finish_nightly(&nightly, mode, broken).await?;
+            Value::Null
+        }
+        WorkerRequest::FinishNightlyUnavailable {
+            nightly,
+            mode,
+            error,
+        } => {
+            queue
+                .finish_nightly_unavailable(&nightly, mode, &error)
+                .await?;
+            Value::Null
+        }
+        WorkerRequest::InsertToolchainInfo { info } => {
+            queue.insert_toolchain_info(&info).await?;
+            Value::Null
+        }
+        WorkerRequest::InsertTargetMeta { metas } => {
+            queue.insert_target_meta(&metas).await?;
+            Value::Null
+        }
+        WorkerRequest::InsertTargetSpecs { specs } => {
+            queue.insert_target_specs(&specs).await?;
+            Value::Null
+        }
+        WorkerRequest::InsertTargetCfgs { cfgs } => {
+            queue.insert_target_cfgs(&cfgs).await?;
+            Value::Null
+        }
+        WorkerRequest::InsertPrebuiltStd { nightly, targets } => {
+            queue.insert_prebuilt_std(&nightly, &targets).await?;
+            Value::Null
+        }
+        WorkerRequest::EnqueueJobs {
+            nightly,
+            mode,
+            targets,
+        } => {
+            queue.enqueue_jobs(&nightly, mode, &targets).await?;
+            Value::Null
+        }
+        WorkerRequest::ClaimJob {
+            nightly,
+            mode,
+            worker,
+            lease,
+        } => queue
+            .claim_job(&nightly, mode, &worker, lease)
+            .await?
+            .into(),
+        WorkerRequest::RenewJobLease {
+            nightly,
+            target,
+            mode,
+            worker,
+            lease,
+        } => {
+            queue
+                .renew_job_lease(&nightly, &target, mode, &worker, lease)
+                .await?;
+            Value::Null
+        }
+        WorkerRequest::CompleteJob {
+            nightly,
+            target,
+            mode,
+        } => {
+            queue.complete_job(&nightly, &target, mode).await?;
+            Value::Null
+        }
+        WorkerRequest::HasUnfinishedJobs { nightly, mode } => {
+            queue.has_unfinished_jobs(&nightly, mode).await?.into()
+        }
+        WorkerRequest::BuildExists {
+            nightly,
+            target,
+            mode,
+        } => queue.build_exists(&nightly, &target, mode).await?.into(),
+        WorkerRequest::AssumedFailing {
+            nightly,
+            mode,
+            config,
+        } => serde_json::to_value(queue.assumed_failing(&nightly, mode, &config).await?)?,
+        WorkerRequest::InsertBuild { info } => {
+            queue.insert_build(info).await?;
+            Value::Null
+        }
+        WorkerRequest::UncheckedBetaRegressions { nightly, mode } => {
+            serde_json::to_value(queue.unchecked_beta_regressions(&nightly, mode).await?)?
+        }
+        WorkerRequest::InsertBetaCheck { check } => {
+            queue.insert_beta_check(&check).await?;
+            Value::Null
+        }
+    };
+    // The queue only lives for this request, so nothing can be left in its batch.
+    queue.write_batch(true).await?;
+    Ok(value)
+}
+
+/// Connection of a worker to the coordinator.
+pub struct Client {
+    url: String,
+    token: String,
+    /// The target triple the builds of this worker run on.
+    host: String,
+}
+
+impl Client {
+    /// `coordinator` is the base URL of the coordinator's website.
+    pub fn new(coordinator: &str, token: String, host: String) -> Self {
+        Self {
+            url: format!("{}/api/v1/worker", coordinator.trim_end_matches('/')),
+            token,
+            host,
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, request: WorkerRequest) -> Result<T> {
+        let call = crate::http::client()
+            .post(&self.url)
+            .bearer_auth(&self.token)
+            .json(&WorkerCall {
+                host: self.host.clone(),
+                request,
+            });
+        let response = crate::http::send(call)
+            .await
+            .wrap_err("sending request to coordinator")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("coordinator responded with {status}: {body}");
+        }
+        response
+            .json()
+            .await
+            .wrap_err("invalid response from coordinator")
+    }
+}
+
+impl BuildQueue for Client {
+    async fn finished_nightlies(&self) -> Result<Vec<FinishedNightly>> {
+        self.call(WorkerRequest::FinishedNightlies).await
+    }
+
+    async fn is_nightly_finished(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
+        self.call(WorkerRequest::IsNightlyFinished {
+            nightly: nightly.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn finish_nightly(&self, nightly: &str, mode: BuildMode, broken: bool) -> Result<()> {
+        self.call(WorkerRequest::FinishNightly {
+            nightly: nightly.to_owned(),
+            mode,
+            broken,
+        })
+        .await
+    }
+
+    async fn finish_nightly_unavailable(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        error: &str,
+    ) -> Result<()> {
+        self.call(WorkerRequest::FinishNightlyUnavailable {
+            nightly: nightly.to_owned(),
+            mode,
+            error: error.to_owned(),
+        })
+        .await
+    }
+
+    async fn insert_toolchain_info(&self, info: &ToolchainInfo) -> Result<()> {
+        self.call(WorkerRequest::InsertToolchainInfo { info: info.clone() })
+            .await
+    }
+
+    async fn insert_target_meta(&self, metas: &[TargetMeta]) -> Result<()> {
+        self.call(WorkerRequest::InsertTargetMeta {
+            metas: metas.to_vec(),
+        })
+        .await
+    }
+
+    async fn insert_target_specs(&self, specs: &[TargetSpec]) -> Result<()> {
+        self.call(WorkerRequest::InsertTargetSpecs {
+            specs: specs.to_vec(),
+        })
+        .await
+    }
+
+    async fn insert_target_cfgs(&self, cfgs: &[TargetCfg]) -> Result<()> {
+        self.call(WorkerRequest::InsertTargetCfgs {
+            cfgs: cfgs.to_vec(),
+        })
+        .await
+    }
+
+    async fn insert_prebuilt_std(&self, nightly: &str, targets: &[String]) -> Result<()> {
+        self.call(WorkerRequest::InsertPrebuiltStd {
+            nightly: nightly.to_owned(),
+            targets: targets.to_vec(),
+        })
+        .await
+    }
+
+    async fn enqueue_jobs(&self, nightly: &str, mode: BuildMode, targets: &[String]) -> Result<()> {
+        self.call(WorkerRequest::EnqueueJobs {
+            nightly: nightly.to_owned(),
+            mode,
+            targets: targets.to_vec(),
+        })
+        .await
+    }
+
+    async fn claim_job(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<Option<String>> {
+        self.call(WorkerRequest::ClaimJob {
+            nightly: nightly.to_owned(),
+            mode,
+            worker: worker.to_owned(),
+            lease,
+        })
+        .await
+    }
+
+    async fn renew_job_lease(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<()> {
+        self.call(WorkerRequest::RenewJobLease {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            mode,
+            worker: worker.to_owned(),
+            lease,
+        })
+        .await
+    }
+
+    async fn complete_job(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<()> {
+        self.call(WorkerRequest::CompleteJob {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn has_unfinished_jobs(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
+        self.call(WorkerRequest::HasUnfinishedJobs {
+            nightly: nightly.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn build_exists(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<bool> {
+        self.call(WorkerRequest::BuildExists {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn assumed_failing(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        config: &AssumeFailingConfig,
+    ) -> Result<BTreeMap<String, String>> {
+        self.call(WorkerRequest::AssumedFailing {
+            nightly: nightly.to_owned(),
+            mode,
+            config: config.clone(),
+        })
+        .await
+    }
+
+    async fn insert_build(&self, info: FullBuildInfo) -> Result<()> {
+        self.call(WorkerRequest::InsertBuild { info }).await
+    }
+
+    async fn unchecked_beta_regressions(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+    ) -> Result<Vec<String>> {
+        self.call(WorkerRequest::UncheckedBetaRegressions {
+            nightly: nightly.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn insert_beta_check(&self, check: &BetaCheck) -> Result<()> {
+        self.call(WorkerRequest::InsertBetaCheck {
+            check: check.clone(),
+        })
+        .await
+    }
+}
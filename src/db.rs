@@ -1,44 +1,101 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::HashMap, str::FromStr, sync::LazyLock, time::Duration};
 
-use color_eyre::{
-    eyre::{bail, Context},
-    Result,
-};
+use color_eyre::{eyre::Context, Result};
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
-use sqlx::{migrate::Migrator, sqlite::SqliteConnectOptions, Pool, Sqlite};
+use sha2::{Digest, Sha256};
+use sqlx::{
+    any::{AnyConnectOptions, AnyPoolOptions},
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
+    Any, AnyPool, ConnectOptions, Executor, Transaction,
+};
+use tracing::info;
+
+pub use does_it_build_api::{
+    BuildAttempt, BuildHost, BuildInfo, BuildMode, BuildTiming, BuilderProgress, MaintenanceRun,
+    Status, ToolchainInfo, Verification,
+};
 
+use crate::{config::DbConfig, excerpt, log_offload::OffloadedLog, triple::TargetTriple};
+
+/// The database, which is either SQLite or PostgreSQL.
+///
+/// All queries go through sqlx's `Any` driver, so they must be written in SQL that
+/// both databases understand, using `$1`-style placeholders.
 #[derive(Clone)]
 pub struct Db {
-    pub conn: Pool<Sqlite>,
+    pub conn: AnyPool,
+    pub backend: Backend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
 }
 
-pub static MIGRATOR: Migrator = sqlx::migrate!();
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("migrations/sqlite");
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("migrations/postgres");
 
-#[derive(Debug, Clone, Copy, sqlx::Type, Serialize, Deserialize, PartialEq, Eq, Hash)]
-#[sqlx(rename_all = "kebab-case")]
-#[serde(rename_all = "kebab-case")]
-pub enum BuildMode {
-    /// `-Zbuild-std=core`
-    Core,
-    /// `cargo miri setup`
-    MiriStd,
+/// A target without a single passing build in a mode.
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
+pub struct NeverPassed {
+    pub target: String,
+    pub mode: BuildMode,
+    pub tier: Option<i32>,
+    /// How many builds of the target failed.
+    pub builds: i64,
+    pub first_nightly: String,
+    pub last_nightly: String,
 }
 
-impl Display for BuildMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Core => f.write_str("core"),
-            Self::MiriStd => f.write_str("miri-std"),
-        }
-    }
+/// A target that has failed on every nightly since `first_bad`, up to the latest one.
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
+pub struct StaleFailure {
+    pub target: String,
+    pub mode: BuildMode,
+    pub tier: Option<i32>,
+    /// The first nightly after the last passing build, or the first build if it never passed.
+    pub first_bad: String,
+    pub failed_builds: i64,
+}
+
+/// The number of passing and failing targets of a nightly in a mode.
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
+pub struct PassCount {
+    pub nightly: String,
+    pub mode: BuildMode,
+    pub pass: i64,
+    pub error: i64,
 }
 
+/// How many builds of a target passed and failed on a nightly, for a calendar of its history.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize)]
+pub struct CalendarDay {
+    pub nightly: String,
+    pub pass: i64,
+    pub error: i64,
+}
+
+/// How many targets of a nightly failed after passing on their previous build, and the other way around.
 #[derive(sqlx::FromRow, Serialize, Deserialize)]
-pub struct BuildInfo {
+pub struct RegressionCount {
     pub nightly: String,
+    pub mode: BuildMode,
+    pub regressions: i64,
+    pub fixes: i64,
+}
+
+/// Consecutive builds of a target with the same status.
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
+pub struct Streak {
     pub target: String,
-    pub status: Status,
     pub mode: BuildMode,
+    pub status: Status,
+    pub first_nightly: String,
+    pub last_nightly: String,
+    pub length: i64,
 }
 
 #[derive(Clone, sqlx::FromRow, Serialize, Deserialize)]
@@ -47,129 +104,3095 @@ pub struct FullBuildInfo {
     pub target: String,
     pub status: Status,
     pub stderr: String,
+    /// The size of the log if it was too large to be stored completely.
+    #[serde(default)]
+    #[sqlx(default)]
+    pub original_stderr_bytes: Option<i64>,
+    pub mode: BuildMode,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub timing: BuildTiming,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub host: BuildHost,
+}
+
+/// A status change of a target that is expected, like when it is removed or demoted on purpose.
+/// Its regressions on the nightlies from `first_nightly` to `last_nightly` aren't reported.
+#[derive(Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct ExpectedBreakage {
+    pub target: String,
+    pub mode: BuildMode,
+    pub first_nightly: String,
+    pub last_nightly: String,
+    /// Like "demoted to tier 3 in rust-lang/rust#12345".
+    pub reason: String,
+    pub created_at: i64,
+}
+
+/// How long a build took and how large its log was, for comparing it to earlier builds.
+#[derive(Clone, sqlx::FromRow)]
+pub struct BuildMetrics {
+    pub target: String,
+    pub nightly: String,
+    pub status: Status,
+    pub duration_secs: Option<i64>,
+    pub stderr_bytes: Option<i64>,
+}
+
+/// A change made through an authenticated endpoint.
+#[derive(Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub created_at: i64,
+    /// Who made the change, like `github:<login>`.
+    pub actor: String,
+    /// What was changed, like `rebuild`.
+    pub action: String,
+    /// The request as JSON.
+    pub payload: String,
+}
+
+/// The latest result of a target on a peer instance.
+#[derive(Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct FederatedBuild {
+    /// The name of the peer in the config.
+    pub peer: String,
+    pub peer_url: String,
+    pub nightly: String,
+    pub target: String,
+    pub mode: BuildMode,
+    pub host: String,
+    pub status: Status,
+    pub tier: Option<i32>,
+    pub error_excerpt: Option<String>,
+    /// When it was pulled from the peer.
+    pub fetched_at: i64,
+}
+
+/// A build of a target that regressed on a nightly with the beta of the same day.
+#[derive(Clone, sqlx::FromRow, Serialize, Deserialize)]
+pub struct BetaCheck {
+    pub nightly: String,
+    pub target: String,
+    pub mode: BuildMode,
+    /// The dated beta toolchain, like `beta-2026-10-16`.
+    pub toolchain: String,
+    pub status: Status,
+}
+
+/// A write of the builder that is stored together with others in one transaction.
+pub enum BatchedWrite {
+    /// The result of a build. If the log was offloaded, only its truncated version is stored.
+    Build(FullBuildInfo, Option<OffloadedLog>),
+    CompleteJob {
+        nightly: String,
+        target: String,
+        mode: BuildMode,
+    },
+}
+
+/// What rustc says about the platform support of a target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetMeta {
+    pub target: String,
+    /// The nightly this was taken from.
+    pub nightly: String,
+    pub tier: Option<i32>,
+    /// Whether rustc and cargo are shipped for the target.
+    pub host_tools: Option<bool>,
+    /// Whether the target supports std.
+    pub std: Option<bool>,
+    pub description: Option<String>,
+}
+
+/// The target spec JSON of a target on a nightly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetSpec {
+    pub target: String,
+    pub nightly: String,
+    pub spec: String,
+}
+
+/// The output of `rustc --print cfg` for a target on a nightly, with the lines sorted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetCfg {
+    pub target: String,
+    pub nightly: String,
+    pub cfg: String,
+}
+
+/// A target that was renamed, so its history continues under the new name.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct TargetRename {
+    pub old_target: String,
+    pub new_target: String,
+    /// The first nightly with the new name.
+    pub nightly: String,
+}
+
+/// The number of builds of a nightly with a certain mode and status on a host.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize)]
+pub struct StatusCount {
+    pub mode: BuildMode,
+    pub host: String,
+    pub status: Status,
+    pub count: i64,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FinishedNightly {
+    pub nightly: String,
     pub mode: BuildMode,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy, sqlx::Type, Serialize, Deserialize)]
-#[sqlx(rename_all = "snake_case")]
-#[serde(rename_all = "lowercase")]
-pub enum Status {
-    Error,
-    Pass,
+/// A current or superseded build result, as exported for analysis.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub nightly: String,
+    pub target: String,
+    pub mode: BuildMode,
+    pub status: Status,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub peak_rss_kib: Option<i64>,
+    /// `None` in exports from before builds were kept per host.
+    pub host: Option<String>,
+    pub hostname: Option<String>,
+    pub attempt: i64,
+    /// `None` for the current result of a build.
+    pub superseded_at: Option<i64>,
+    /// `None` if logs were left out of the export.
+    pub stderr: Option<String>,
+    /// `None` in exports from before this was recorded.
+    #[serde(default)]
+    pub error_excerpt: Option<String>,
 }
 
-impl Display for Status {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Error => f.write_str("error"),
-            Self::Pass => f.write_str("pass"),
+impl BuildRecord {
+    /// The stored excerpt, or the one from the log for exports from before excerpts were recorded.
+    fn error_excerpt(&self) -> Option<String> {
+        match (&self.error_excerpt, self.status, &self.stderr) {
+            (Some(excerpt), _, _) => Some(excerpt.clone()),
+            (None, Status::Error, Some(stderr)) => excerpt::error_excerpt(stderr),
+            _ => None,
         }
     }
 }
 
-#[derive(sqlx::FromRow, Debug, PartialEq, Eq, Hash)]
-pub struct FinishedNightly {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NightlyRecord {
     pub nightly: String,
     pub mode: BuildMode,
+    /// `None` in exports from before nightlies were finished per host.
+    #[serde(default)]
+    pub host: Option<String>,
+    pub broken: bool,
+    /// The error of rustup if the toolchain or a component couldn't be installed, so nothing was built.
+    #[serde(default)]
+    pub toolchain_error: Option<String>,
+}
+
+/// A part of a build log. Offsets count characters, like `SUBSTR` does.
+#[derive(Debug)]
+pub struct LogRange {
+    /// The length of the whole log.
+    pub total: i64,
+    /// The size in bytes of the log before only its start and end were stored.
+    pub original_bytes: Option<i64>,
+    /// Whether the full log is in the log storage, instead of only its start and end being kept.
+    pub offloaded: bool,
+    /// Where `content` starts in the log.
+    pub start: i64,
+    pub content: String,
+}
+
+/// The builds that say whether the target works. Builds that were skipped or couldn't run are left
+/// out of histories like streaks and regressions, so they don't interrupt them.
+const VERDICT: &str = "status IN ('pass', 'error')";
+
+/// The tier of the target of a `build_info` row.
+const TIER: &str =
+    "(SELECT tier FROM target_meta WHERE target_meta.target = build_info.target) AS tier";
+
+/// The value of `PRAGMA auto_vacuum` when free pages are only given back by `PRAGMA incremental_vacuum`.
+const SQLITE_INCREMENTAL_VACUUM: i64 = 2;
+
+/// The host of everything that was built before results were kept per host.
+pub const LEGACY_HOST: &str = "x86_64-unknown-linux-gnu";
+
+/// `build_info` as seen from the host bound to `host_param`, or merged over all hosts if it is NULL.
+/// A merged build has the result of a host it failed on, if there is one.
+fn build_info_for_host(host_param: &str) -> String {
+    format!(
+        "(SELECT * FROM (
+            SELECT *, ROW_NUMBER() OVER (
+                PARTITION BY nightly, target, mode
+                ORDER BY CASE WHEN status = 'error' THEN 0 WHEN status = 'pass' THEN 1 ELSE 2 END, host
+            ) AS host_rank
+            FROM build_info WHERE {host_param} IS NULL OR host = {host_param}
+        ) AS ranked WHERE host_rank = 1) AS build_info"
+    )
+}
+
+/// Whether the build `build` is covered by an expected breakage, so its regression isn't one.
+fn is_expected(build: &str) -> String {
+    format!(
+        "EXISTS (
+            SELECT 1 FROM expected_breakage
+            WHERE expected_breakage.target = {build}.target AND expected_breakage.mode = {build}.mode
+                AND {build}.nightly BETWEEN expected_breakage.first_nightly AND expected_breakage.last_nightly
+        )"
+    )
+}
+
+pub fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
 }
 
 impl Db {
-    pub async fn open(path: &str) -> Result<Self> {
-        let db_opts = SqliteConnectOptions::from_str(path)
-            .wrap_err("parsing database URL")?
-            .create_if_missing(true);
+    /// Opens a `postgres://` URL or an SQLite database path, which is created if it doesn't exist.
+    pub async fn open(config: &DbConfig) -> Result<Self> {
+        let url = config.url.as_str();
+        sqlx::any::install_default_drivers();
 
-        let conn = Pool::connect_with(db_opts)
+        let (backend, db_opts) = if is_postgres_url(url) {
+            let db_opts = AnyConnectOptions::from_str(url).wrap_err("parsing database URL")?;
+            (Backend::Postgres, db_opts)
+        } else {
+            let sqlite_opts = SqliteConnectOptions::from_str(url)
+                .wrap_err("parsing database URL")?
+                .create_if_missing(true);
+            let db_opts = AnyConnectOptions::from_url(&sqlite_opts.to_url_lossy())
+                .wrap_err("parsing database URL")?;
+            (Backend::Sqlite, db_opts)
+        };
+
+        let mut pool_opts = AnyPoolOptions::new().max_connections(config.pool_size);
+        if backend == Backend::Sqlite {
+            SqliteJournalMode::from_str(&config.sqlite_journal_mode)
+                .wrap_err("invalid SQLite journal mode")?;
+            SqliteSynchronous::from_str(&config.sqlite_synchronous)
+                .wrap_err("invalid SQLite synchronous level")?;
+            // The SQLite connect options are lost when going through `AnyConnectOptions`,
+            // so the pragmas are set on every new connection instead.
+            let pragmas = format!(
+                "PRAGMA journal_mode = {}; PRAGMA synchronous = {}; PRAGMA busy_timeout = {};",
+                config.sqlite_journal_mode.to_uppercase(),
+                config.sqlite_synchronous.to_uppercase(),
+                config.sqlite_busy_timeout_ms
+            );
+            pool_opts = pool_opts.after_connect(move |conn, _| {
+                let pragmas = pragmas.clone();
+                Box::pin(async move {
+                    conn.execute(&*pragmas).await?;
+                    Ok(())
+                })
+            });
+        }
+
+        let conn = pool_opts
+            .connect_with(db_opts)
             .await
-            .wrap_err_with(|| format!("opening db from `{}`", path))?;
-        Ok(Self { conn })
+            .wrap_err_with(|| format!("opening db from `{}`", url))?;
+        Ok(Self { conn, backend })
     }
 
-    pub async fn insert(&self, info: FullBuildInfo) -> Result<()> {
-        sqlx::query(
-            "INSERT INTO build_info (nightly, target, status, stderr, mode) VALUES (?, ?, ?, ?, ?);",
-        )
-        .bind(info.nightly)
-        .bind(info.target)
-        .bind(info.status)
-        .bind(info.stderr)
-        .bind(info.mode)
-        .execute(&self.conn)
-        .await
-        .wrap_err("inserting build info into database")?;
+    pub async fn migrate(&self) -> Result<()> {
+        let migrator = match self.backend {
+            Backend::Sqlite => &SQLITE_MIGRATOR,
+            Backend::Postgres => &POSTGRES_MIGRATOR,
+        };
+        migrator
+            .run(&self.conn)
+            .await
+            .wrap_err("running migrations")?;
+
+        self.rehash_legacy_logs()
+            .await
+            .wrap_err("moving logs to content-addressed storage")?;
+        self.add_missing_targets()
+            .await
+            .wrap_err("adding targets of existing builds")
+    }
+
+    /// Writes a consistent copy of the SQLite database to `path` without blocking writers for long.
+    pub async fn snapshot_into(&self, path: &str) -> Result<()> {
+        sqlx::query("VACUUM INTO $1")
+            .bind(path)
+            .execute(&self.conn)
+            .await
+            .wrap_err("writing database snapshot")?;
         Ok(())
     }
 
-    pub async fn build_status(&self) -> Result<Vec<BuildInfo>> {
-        sqlx::query_as::<_, BuildInfo>("SELECT nightly, target, status, mode FROM build_info")
+    /// Logs from before content-addressed storage got a placeholder key in the migration,
+    /// as SQLite can't compute hashes. Replace it with the real hash, merging identical logs.
+    async fn rehash_legacy_logs(&self) -> Result<()> {
+        loop {
+            let legacy = sqlx::query_as::<_, (String, String)>(
+                "SELECT hash, content FROM log_blob WHERE hash LIKE 'legacy:%' LIMIT 1000",
+            )
             .fetch_all(&self.conn)
             .await
-            .wrap_err("getting build status from DB")
+            .wrap_err("fetching legacy logs")?;
+            if legacy.is_empty() {
+                return Ok(());
+            }
+            info!("Hashing {} legacy build logs", legacy.len());
+
+            let mut tx = self.conn.begin().await?;
+            for (legacy_hash, content) in legacy {
+                let hash = insert_log_blob(&mut tx, &content).await?;
+                sqlx::query("UPDATE build_info SET stderr_hash = $1 WHERE stderr_hash = $2")
+                    .bind(&hash)
+                    .bind(&legacy_hash)
+                    .execute(&mut *tx)
+                    .await
+                    .wrap_err("updating log reference")?;
+                sqlx::query("DELETE FROM log_blob WHERE hash = $1")
+                    .bind(&legacy_hash)
+                    .execute(&mut *tx)
+                    .await
+                    .wrap_err("deleting legacy log")?;
+            }
+            tx.commit().await?;
+        }
+    }
+
+    /// Builds from before the `target` table, or imported ones, may have targets that aren't in it.
+    async fn add_missing_targets(&self) -> Result<()> {
+        let missing = sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT target FROM build_info
+            WHERE target NOT IN (SELECT target FROM target)",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("fetching missing targets")?;
+        let mut tx = self.conn.begin().await?;
+        for target in missing {
+            insert_target(&mut tx, &target).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Stores the writes of the builder on `host` in one transaction, so a nightly doesn't pay for
+    /// a commit with every one of its hundreds of builds. The transaction keeps to one connection,
+    /// which prepares each statement once and reuses it for the rest of the batch.
+    #[tracing::instrument(skip_all, fields(writes = writes.len()))]
+    pub async fn insert_batch(&self, host: &str, writes: &[BatchedWrite]) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for write in writes {
+            match write {
+                BatchedWrite::Build(info, offloaded) => {
+                    insert_build(&mut tx, info, offloaded.as_ref()).await?;
+                    // The build replaces the existing one a rebuild was requested for.
+                    sqlx::query(
+                        "DELETE FROM rebuild_request
+                        WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4",
+                    )
+                    .bind(&info.nightly)
+                    .bind(&info.target)
+                    .bind(info.mode)
+                    .bind(host)
+                    .execute(&mut *tx)
+                    .await
+                    .wrap_err("removing rebuild request")?;
+                }
+                BatchedWrite::CompleteJob {
+                    nightly,
+                    target,
+                    mode,
+                } => {
+                    sqlx::query(
+                        "UPDATE job SET state = 'done', leased_by = NULL, lease_expiry = NULL
+                        WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4",
+                    )
+                    .bind(nightly)
+                    .bind(target)
+                    .bind(mode)
+                    .bind(host)
+                    .execute(&mut *tx)
+                    .await
+                    .wrap_err("completing job")?;
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(())
     }
 
-    pub async fn build_status_full(
+    /// The builds of one host, or of all hosts merged if `host` is `None`, read as they are needed.
+    pub fn build_status<'a>(
+        &'a self,
+        host: Option<&'a str>,
+    ) -> BoxStream<'a, sqlx::Result<BuildInfo>> {
+        // The stream borrows the query, so it is only formatted once.
+        static QUERY: LazyLock<String> = LazyLock::new(|| {
+            format!(
+                "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                    {TIER}
+                FROM {}",
+                build_info_for_host("$1")
+            )
+        });
+        sqlx::query_as::<_, BuildInfo>(&QUERY)
+            .bind(host)
+            .fetch(&self.conn)
+    }
+
+    /// The newest build of every target, or only `target`, and mode on every host, or only on `host`.
+    pub async fn latest_builds(
+        &self,
+        target: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<Vec<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM (
+                SELECT *, ROW_NUMBER() OVER (PARTITION BY target, mode, host ORDER BY nightly DESC) AS latest_rank
+                FROM build_info WHERE ($1 IS NULL OR target = $1) AND ($2 IS NULL OR host = $2)
+            ) AS build_info
+            WHERE latest_rank = 1
+            ORDER BY target, mode, host"
+        ))
+        .bind(target)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting latest builds")
+    }
+
+    pub async fn build_status_single(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Option<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM {} WHERE nightly = $1 AND target = $2 AND mode = $3",
+            build_info_for_host("$4")
+        ))
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting build status from DB")
+    }
+
+    /// The build of the target on the newest nightly it was built on.
+    pub async fn latest_build(
+        &self,
+        target: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Option<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM {} WHERE target = $1 AND mode = $2
+            ORDER BY nightly DESC LIMIT 1",
+            build_info_for_host("$3")
+        ))
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting latest build")
+    }
+
+    /// The log of a build, which is truncated if it has been offloaded, and the key of the full log.
+    pub async fn build_log(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Option<(String, Option<String>)>> {
+        sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT log_blob.content, log_blob.offloaded_key FROM build_info
+            JOIN log_blob ON log_blob.hash = build_info.stderr_hash
+            WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4",
+        )
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting build log")
+    }
+
+    /// The `len` characters of the log of a build before `end`, or before the end of the log.
+    /// Only that part is loaded from the database, as logs can be megabytes large.
+    pub async fn build_log_range(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: &str,
+        end: Option<i64>,
+        len: i64,
+    ) -> Result<Option<LogRange>> {
+        const LOG: &str = "FROM build_info
+            JOIN log_blob ON log_blob.hash = build_info.stderr_hash
+            WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4";
+        let total = sqlx::query_as::<_, (i64, Option<i64>, Option<String>)>(&format!(
+            "SELECT LENGTH(log_blob.content), log_blob.original_bytes, log_blob.offloaded_key {LOG}"
+        ))
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting build log length")?;
+        let Some((total, original_bytes, offloaded_key)) = total else {
+            return Ok(None);
+        };
+        let end = end.unwrap_or(total).clamp(0, total);
+        let start = (end - len).max(0);
+        let content = sqlx::query_scalar::<_, String>(&format!(
+            "SELECT SUBSTR(log_blob.content, CAST($5 AS INTEGER), CAST($6 AS INTEGER)) {LOG}"
+        ))
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .bind(start + 1)
+        .bind(end - start)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting build log range")?;
+        Ok(Some(LogRange {
+            total,
+            original_bytes,
+            offloaded: offloaded_key.is_some(),
+            start,
+            content,
+        }))
+    }
+
+    /// The superseded attempts of a build on a host, oldest first.
+    pub async fn build_attempts(
         &self,
         nightly: &str,
         target: &str,
         mode: BuildMode,
-    ) -> Result<Option<FullBuildInfo>> {
-        let result = sqlx::query_as::<_, FullBuildInfo>(
-            "SELECT nightly, target, status, stderr, mode FROM build_info
-            WHERE nightly = ? AND target = ? AND mode = ?",
+        host: &str,
+    ) -> Result<Vec<BuildAttempt>> {
+        sqlx::query_as::<_, BuildAttempt>(
+            "SELECT attempt, status, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, superseded_at, error_excerpt
+            FROM build_attempt WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4
+            ORDER BY attempt",
         )
         .bind(nightly)
         .bind(target)
         .bind(mode)
+        .bind(host)
         .fetch_all(&self.conn)
         .await
-        .wrap_err("getting build status from DB")?;
-        Ok(result.first().cloned())
+        .wrap_err("getting build attempts")
     }
 
-    pub async fn finished_nightlies(&self) -> Result<Vec<FinishedNightly>> {
-        let result =
-            sqlx::query_as::<_, FinishedNightly>("SELECT nightly, mode from finished_nightly")
-                .fetch_all(&self.conn)
-                .await
-                .wrap_err("fetching finished nightlies")?;
+    /// Other targets of the same nightly, mode and host whose build produced exactly the same output.
+    pub async fn targets_with_same_stderr(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Vec<String>> {
+        let result = sqlx::query_as::<_, (String,)>(
+            "SELECT other.target FROM build_info
+            JOIN build_info AS other
+                ON other.stderr_hash = build_info.stderr_hash
+                AND other.nightly = build_info.nightly
+                AND other.mode = build_info.mode
+                AND other.host = build_info.host
+            WHERE build_info.nightly = $1 AND build_info.target = $2 AND build_info.mode = $3
+                AND build_info.host = $4
+                AND other.target != build_info.target
+            ORDER BY other.target",
+        )
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting builds with the same stderr")?;
+        Ok(result.into_iter().map(|(target,)| target).collect())
+    }
+
+    /// The nightlies finished on a host, or on any host if `host` is `None`.
+    pub async fn finished_nightlies(&self, host: Option<&str>) -> Result<Vec<FinishedNightly>> {
+        let result = sqlx::query_as::<_, FinishedNightly>(
+            "SELECT DISTINCT nightly, mode FROM finished_nightly WHERE $1 IS NULL OR host = $1",
+        )
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("fetching finished nightlies")?;
 
         Ok(result)
     }
 
-    pub async fn is_nightly_finished(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
-        let result = sqlx::query_as::<_, FinishedNightly>(
-            "SELECT nightly, mode from finished_nightly WHERE nightly = ? AND mode = ?",
+    pub async fn is_nightly_finished(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "SELECT 1 FROM finished_nightly WHERE nightly = $1 AND mode = $2 AND host = $3",
         )
         .bind(nightly)
         .bind(mode)
-        .fetch_all(&self.conn)
+        .bind(host)
+        .fetch_optional(&self.conn)
         .await
         .wrap_err("checking whether a nightly is finished")?;
 
-        if result.len() > 1 {
-            bail!("found more than one result for {nightly} {mode}");
-        }
+        Ok(result.is_some())
+    }
+
+    /// Marks a nightly as done on a host and removes its jobs. Another builder may have finished it already.
+    #[tracing::instrument(skip(self))]
+    pub async fn finish_nightly(&self, nightly: &str, mode: BuildMode, host: &str) -> Result<()> {
+        self.finish_nightly_inner(nightly, mode, host, false, None)
+            .await
+    }
+
+    pub async fn finish_nightly_as_broken(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<()> {
+        self.finish_nightly_inner(nightly, mode, host, true, None)
+            .await
+    }
 
-        Ok(result.len() == 1)
+    /// Marks a nightly as done without builds, because rustup failed to install it with `error`.
+    pub async fn finish_nightly_as_unavailable(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+        error: &str,
+    ) -> Result<()> {
+        self.finish_nightly_inner(nightly, mode, host, false, Some(error))
+            .await
     }
 
-    pub async fn finish_nightly(&self, nightly: &str, mode: BuildMode) -> Result<()> {
-        sqlx::query("INSERT INTO finished_nightly (nightly, mode) VALUES (?, ?)")
+    async fn finish_nightly_inner(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+        broken: bool,
+        toolchain_error: Option<&str>,
+    ) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO finished_nightly (nightly, mode, host, is_broken, toolchain_error) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (nightly, mode, host) DO NOTHING",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .bind(broken)
+        .bind(toolchain_error)
+        .execute(&mut *tx)
+        .await
+        .wrap_err("inserting finished nightly")?;
+        sqlx::query("DELETE FROM job WHERE nightly = $1 AND mode = $2 AND host = $3")
             .bind(nightly)
             .bind(mode)
-            .execute(&self.conn)
+            .bind(host)
+            .execute(&mut *tx)
             .await
-            .wrap_err("inserting finished nightly")?;
+            .wrap_err("deleting jobs of finished nightly")?;
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn finish_nightly_as_broken(&self, nightly: &str, mode: BuildMode) -> Result<()> {
-        sqlx::query("INSERT INTO finished_nightly (nightly, mode, is_broken) VALUES (?, ?, TRUE)")
+    /// Makes the builders build the targets of a finished nightly again, or every target it has
+    /// builds for if `targets` is empty. Returns the modes the nightly was reopened in, which are
+    /// the ones it was finished in, or only `mode` if it is set. The audit entry is recorded with the
+    /// requests if the nightly was reopened.
+    pub async fn request_rebuild(
+        &self,
+        nightly: &str,
+        mode: Option<BuildMode>,
+        host: &str,
+        targets: &[String],
+        audit: &AuditEntry,
+    ) -> Result<Vec<BuildMode>> {
+        let mut tx = self.conn.begin().await?;
+        let modes = sqlx::query_scalar::<_, BuildMode>(
+            "SELECT mode FROM finished_nightly
+            WHERE nightly = $1 AND ($2 IS NULL OR mode = $2) AND host = $3
+            ORDER BY mode",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&mut *tx)
+        .await
+        .wrap_err("getting finished modes of nightly")?;
+        for &mode in &modes {
+            let targets = if targets.is_empty() {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT target FROM build_info WHERE nightly = $1 AND mode = $2 AND host = $3",
+                )
+                .bind(nightly)
+                .bind(mode)
+                .bind(host)
+                .fetch_all(&mut *tx)
+                .await
+                .wrap_err("getting built targets of nightly")?
+            } else {
+                targets.to_vec()
+            };
+            for target in targets {
+                sqlx::query(
+                    "INSERT INTO rebuild_request (nightly, target, mode, host) VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (nightly, target, mode, host) DO NOTHING",
+                )
+                .bind(nightly)
+                .bind(target)
+                .bind(mode)
+                .bind(host)
+                .execute(&mut *tx)
+                .await
+                .wrap_err("inserting rebuild request")?;
+            }
+            sqlx::query(
+                "DELETE FROM finished_nightly WHERE nightly = $1 AND mode = $2 AND host = $3",
+            )
             .bind(nightly)
             .bind(mode)
-            .execute(&self.conn)
+            .bind(host)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("reopening nightly")?;
+        }
+        if !modes.is_empty() {
+            insert_audit_entry(&mut tx, audit).await?;
+        }
+        tx.commit().await?;
+        Ok(modes)
+    }
+
+    pub async fn is_rebuild_requested(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "SELECT 1 FROM rebuild_request
+            WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4",
+        )
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("checking for a rebuild request")?;
+        Ok(result.is_some())
+    }
+
+    /// Adds a pending job for every target that doesn't have one yet.
+    #[tracing::instrument(skip(self, targets))]
+    pub async fn enqueue_jobs(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+        targets: &[String],
+    ) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for target in targets {
+            sqlx::query(
+                "INSERT INTO job (nightly, target, mode, host, state) VALUES ($1, $2, $3, $4, 'pending')
+                ON CONFLICT (nightly, mode, host, target) DO NOTHING",
+            )
+            .bind(nightly)
+            .bind(target)
+            .bind(mode)
+            .bind(host)
+            .execute(&mut *tx)
             .await
-            .wrap_err("inserting finished broken nightly")?;
+            .wrap_err("inserting job")?;
+        }
+        tx.commit().await?;
         Ok(())
     }
+
+    /// Leases the next pending job, or a running one whose lease has expired, and returns its target.
+    #[tracing::instrument(skip(self))]
+    pub async fn claim_job(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<Option<String>> {
+        const CLAIMABLE: &str = "nightly = $1 AND mode = $2 AND host = $3
+            AND (state = 'pending' OR (state = 'running' AND lease_expiry < $4))";
+        loop {
+            let now = time::OffsetDateTime::now_utc().unix_timestamp();
+            // The condition is repeated outside the subquery so that a concurrent claim
+            // of the same job in PostgreSQL makes this update match nothing instead of stealing it.
+            let claimed = sqlx::query_as::<_, (String,)>(&format!(
+                "UPDATE job SET state = 'running', leased_by = $5, lease_expiry = $6
+                WHERE {CLAIMABLE}
+                AND target = (SELECT target FROM job WHERE {CLAIMABLE} ORDER BY target LIMIT 1)
+                RETURNING target"
+            ))
+            .bind(nightly)
+            .bind(mode)
+            .bind(host)
+            .bind(now)
+            .bind(worker)
+            .bind(now + lease.as_secs() as i64)
+            .fetch_optional(&self.conn)
+            .await
+            .wrap_err("claiming job")?;
+            if let Some((target,)) = claimed {
+                return Ok(Some(target));
+            }
+
+            let remaining = sqlx::query(&format!("SELECT 1 FROM job WHERE {CLAIMABLE} LIMIT 1"))
+                .bind(nightly)
+                .bind(mode)
+                .bind(host)
+                .bind(now)
+                .fetch_optional(&self.conn)
+                .await
+                .wrap_err("checking for claimable jobs")?;
+            if remaining.is_none() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Extends the lease of a job that is still being built.
+    pub async fn renew_job_lease(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        host: &str,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE job SET lease_expiry = $6
+            WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4 AND leased_by = $5
+                AND state = 'running'",
+        )
+        .bind(nightly)
+        .bind(target)
+        .bind(mode)
+        .bind(host)
+        .bind(worker)
+        .bind(time::OffsetDateTime::now_utc().unix_timestamp() + lease.as_secs() as i64)
+        .execute(&self.conn)
+        .await
+        .wrap_err("renewing job lease")?;
+        Ok(())
+    }
+
+    /// Whether any job of the nightly on the host is not done yet, including ones leased by other builders.
+    pub async fn has_unfinished_jobs(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT 1 FROM job WHERE nightly = $1 AND mode = $2 AND host = $3 AND state <> 'done' LIMIT 1",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("checking for unfinished jobs")?;
+        Ok(row.is_some())
+    }
+
+    /// Whether any builder on any host has jobs that aren't done.
+    pub async fn is_building(&self) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM job WHERE state <> 'done' LIMIT 1")
+            .fetch_optional(&self.conn)
+            .await
+            .wrap_err("checking for unfinished jobs")?;
+        Ok(row.is_some())
+    }
+
+    /// How many jobs of the nightly are in every state, on all hosts if `host` is `None`.
+    pub async fn job_progress(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<BuilderProgress> {
+        sqlx::query_as::<_, BuilderProgress>(
+            "SELECT
+                COALESCE(SUM(CASE WHEN state = 'pending' THEN 1 ELSE 0 END), 0) AS pending,
+                COALESCE(SUM(CASE WHEN state = 'running' THEN 1 ELSE 0 END), 0) AS running,
+                COALESCE(SUM(CASE WHEN state = 'done' THEN 1 ELSE 0 END), 0) AS done
+            FROM job WHERE nightly = $1 AND mode = $2 AND ($3 IS NULL OR host = $3)",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting job progress")
+    }
+
+    /// Stores the toolchain info of a nightly. The miri and rustup versions are kept if the new info
+    /// doesn't have them.
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_toolchain_info(&self, info: &ToolchainInfo) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO toolchain_info
+                (nightly, rustc_version, commit_hash, llvm_version, host, cargo_version, miri_version, rustup_version, verification)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (nightly) DO UPDATE SET
+                rustc_version = excluded.rustc_version,
+                commit_hash = excluded.commit_hash,
+                llvm_version = excluded.llvm_version,
+                host = excluded.host,
+                cargo_version = excluded.cargo_version,
+                miri_version = COALESCE(excluded.miri_version, toolchain_info.miri_version),
+                rustup_version = COALESCE(excluded.rustup_version, toolchain_info.rustup_version),
+                verification = COALESCE(excluded.verification, toolchain_info.verification)",
+        )
+        .bind(&info.nightly)
+        .bind(&info.rustc_version)
+        .bind(&info.commit_hash)
+        .bind(&info.llvm_version)
+        .bind(&info.host)
+        .bind(&info.cargo_version)
+        .bind(&info.miri_version)
+        .bind(&info.rustup_version)
+        .bind(info.verification)
+        .execute(&self.conn)
+        .await
+        .wrap_err("inserting toolchain info")?;
+        Ok(())
+    }
+
+    pub async fn toolchain_info(&self, nightly: &str) -> Result<Option<ToolchainInfo>> {
+        sqlx::query_as::<_, ToolchainInfo>(
+            "SELECT nightly, rustc_version, commit_hash, llvm_version, host, cargo_version, miri_version,
+                rustup_version, verification
+            FROM toolchain_info WHERE nightly = $1",
+        )
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting toolchain info")
+    }
+
+    /// Stores target metadata, unless there already is some from a newer nightly.
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_target_meta(&self, metas: &[TargetMeta]) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for meta in metas {
+            sqlx::query(
+                "INSERT INTO target_meta (target, nightly, tier, host_tools, std, description)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (target) DO UPDATE SET
+                    nightly = excluded.nightly,
+                    tier = excluded.tier,
+                    host_tools = excluded.host_tools,
+                    std = excluded.std,
+                    description = excluded.description
+                WHERE excluded.nightly >= target_meta.nightly",
+            )
+            .bind(&meta.target)
+            .bind(&meta.nightly)
+            .bind(meta.tier)
+            .bind(meta.host_tools)
+            .bind(meta.std)
+            .bind(&meta.description)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target metadata")?;
+            insert_target(&mut tx, &meta.target).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn target_tier(&self, target: &str) -> Result<Option<i32>> {
+        sqlx::query_scalar::<_, Option<i32>>("SELECT tier FROM target_meta WHERE target = $1")
+            .bind(target)
+            .fetch_optional(&self.conn)
+            .await
+            .map(Option::flatten)
+            .wrap_err("getting target tier")
+    }
+
+    /// All targets and the components of their triples.
+    pub async fn targets(&self) -> Result<Vec<(String, TargetTriple)>> {
+        let rows = sqlx::query_as::<_, (String, String, String, String, String)>(
+            "SELECT target, arch, vendor, os, env FROM target ORDER BY target",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting targets")?;
+        Ok(rows
+            .into_iter()
+            .map(|(target, arch, vendor, os, env)| {
+                (
+                    target,
+                    TargetTriple {
+                        arch,
+                        vendor,
+                        os,
+                        env,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    pub async fn target_meta(&self) -> Result<Vec<TargetMeta>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                Option<i32>,
+                Option<i32>,
+                Option<i32>,
+                Option<String>,
+            ),
+        >(
+            "SELECT target, nightly, tier,
+                CASE WHEN host_tools IS NULL THEN NULL WHEN host_tools THEN 1 ELSE 0 END,
+                CASE WHEN std IS NULL THEN NULL WHEN std THEN 1 ELSE 0 END,
+                description
+            FROM target_meta ORDER BY target",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting target metadata")?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(target, nightly, tier, host_tools, std, description)| TargetMeta {
+                    target,
+                    nightly,
+                    tier,
+                    host_tools: host_tools.map(|value| value != 0),
+                    std: std.map(|value| value != 0),
+                    description,
+                },
+            )
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_target_specs(&self, specs: &[TargetSpec]) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for spec in specs {
+            let hash = format!("{:x}", Sha256::digest(&spec.spec));
+            sqlx::query(
+                "INSERT INTO target_spec_blob (hash, spec) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+            )
+            .bind(&hash)
+            .bind(&spec.spec)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target spec")?;
+            sqlx::query(
+                "INSERT INTO target_spec (nightly, target, spec_hash) VALUES ($1, $2, $3)
+                ON CONFLICT (nightly, target) DO UPDATE SET spec_hash = excluded.spec_hash",
+            )
+            .bind(&spec.nightly)
+            .bind(&spec.target)
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target spec")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The spec of the target on the nightly, or on the newest nightly that has one.
+    pub async fn target_spec(
+        &self,
+        target: &str,
+        nightly: Option<&str>,
+    ) -> Result<Option<TargetSpec>> {
+        sqlx::query_as::<_, (String, String)>(
+            "SELECT nightly, target_spec_blob.spec FROM target_spec
+            JOIN target_spec_blob ON target_spec_blob.hash = target_spec.spec_hash
+            WHERE target = $1 AND ($2 IS NULL OR nightly = $2)
+            ORDER BY nightly DESC LIMIT 1",
+        )
+        .bind(target)
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting target spec")
+        .map(|row| {
+            row.map(|(nightly, spec)| TargetSpec {
+                target: target.to_owned(),
+                nightly,
+                spec,
+            })
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn insert_target_cfgs(&self, cfgs: &[TargetCfg]) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for cfg in cfgs {
+            let hash = format!("{:x}", Sha256::digest(&cfg.cfg));
+            sqlx::query(
+                "INSERT INTO target_cfg_blob (hash, cfg) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+            )
+            .bind(&hash)
+            .bind(&cfg.cfg)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target cfg")?;
+            sqlx::query(
+                "INSERT INTO target_cfg (nightly, target, cfg_hash) VALUES ($1, $2, $3)
+                ON CONFLICT (nightly, target) DO UPDATE SET cfg_hash = excluded.cfg_hash",
+            )
+            .bind(&cfg.nightly)
+            .bind(&cfg.target)
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target cfg")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Stores the targets that have a prebuilt std on the nightly.
+    #[tracing::instrument(skip(self, targets))]
+    pub async fn insert_prebuilt_std(&self, nightly: &str, targets: &[String]) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO prebuilt_std (nightly, targets) VALUES ($1, $2)
+            ON CONFLICT (nightly) DO UPDATE SET targets = excluded.targets",
+        )
+        .bind(nightly)
+        .bind(targets.join("\n"))
+        .execute(&self.conn)
+        .await
+        .wrap_err("inserting prebuilt std targets")?;
+        Ok(())
+    }
+
+    /// The targets with a prebuilt std of every nightly, oldest first.
+    pub async fn prebuilt_std(&self) -> Result<Vec<(String, Vec<String>)>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            "SELECT nightly, targets FROM prebuilt_std ORDER BY nightly",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting prebuilt std targets")?;
+        Ok(rows
+            .into_iter()
+            .map(|(nightly, targets)| {
+                let targets = targets.lines().map(ToOwned::to_owned).collect();
+                (nightly, targets)
+            })
+            .collect())
+    }
+
+    /// The miri-std builds that tell whether Miri supports their target, ordered by target and then nightly.
+    pub async fn miri_setup_results(
+        &self,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, String, Status)>> {
+        sqlx::query_as::<_, (String, String, Status)>(&format!(
+            "SELECT target, nightly, status FROM {}
+            WHERE mode = $2 AND status IN ('pass', 'error', 'unsupported')
+            ORDER BY target, nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(BuildMode::MiriStd)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting miri setup results")
+    }
+
+    /// Replaces the maintainers from the platform support docs with the given `(target, github)` pairs.
+    pub async fn replace_target_maintainers(&self, maintainers: &[(String, String)]) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("DELETE FROM target_maintainer")
+            .execute(&mut *tx)
+            .await
+            .wrap_err("removing target maintainers")?;
+        for (target, github) in maintainers {
+            sqlx::query(
+                "INSERT INTO target_maintainer (target, github) VALUES ($1, $2)
+                ON CONFLICT (target, github) DO NOTHING",
+            )
+            .bind(target)
+            .bind(github)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting target maintainer")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The GitHub handles of the maintainers of the target from the platform support docs.
+    pub async fn target_maintainers(&self, target: &str) -> Result<Vec<String>> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT github FROM target_maintainer WHERE target = $1 ORDER BY github",
+        )
+        .bind(target)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting target maintainers")
+    }
+
+    /// The cfg of the target on the nightly, or on the newest nightly that has one.
+    pub async fn target_cfg(
+        &self,
+        target: &str,
+        nightly: Option<&str>,
+    ) -> Result<Option<TargetCfg>> {
+        sqlx::query_as::<_, (String, String)>(
+            "SELECT nightly, target_cfg_blob.cfg FROM target_cfg
+            JOIN target_cfg_blob ON target_cfg_blob.hash = target_cfg.cfg_hash
+            WHERE target = $1 AND ($2 IS NULL OR nightly = $2)
+            ORDER BY nightly DESC LIMIT 1",
+        )
+        .bind(target)
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting target cfg")
+        .map(|row| {
+            row.map(|(nightly, cfg)| TargetCfg {
+                target: target.to_owned(),
+                nightly,
+                cfg,
+            })
+        })
+    }
+
+    /// The newest nightly before `nightly` that has a cfg for the target.
+    pub async fn previous_target_cfg_nightly(
+        &self,
+        target: &str,
+        nightly: &str,
+    ) -> Result<Option<String>> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT nightly FROM target_cfg WHERE target = $1 AND nightly < $2
+            ORDER BY nightly DESC LIMIT 1",
+        )
+        .bind(target)
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting previous target cfg")
+    }
+
+    /// The specs of all targets on the nightly.
+    pub async fn target_specs_of_nightly(&self, nightly: &str) -> Result<Vec<TargetSpec>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            "SELECT target, target_spec_blob.spec FROM target_spec
+            JOIN target_spec_blob ON target_spec_blob.hash = target_spec.spec_hash
+            WHERE nightly = $1 ORDER BY target",
+        )
+        .bind(nightly)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting target specs")?;
+        Ok(rows
+            .into_iter()
+            .map(|(target, spec)| TargetSpec {
+                target,
+                nightly: nightly.to_owned(),
+                spec,
+            })
+            .collect())
+    }
+
+    /// The nightlies with specs right before and after `nightly`.
+    pub async fn adjacent_spec_nightlies(
+        &self,
+        nightly: &str,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let before = sqlx::query_scalar::<_, String>(
+            "SELECT nightly FROM target_spec WHERE nightly < $1 ORDER BY nightly DESC LIMIT 1",
+        )
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting previous nightly with specs")?;
+        let after = sqlx::query_scalar::<_, String>(
+            "SELECT nightly FROM target_spec WHERE nightly > $1 ORDER BY nightly LIMIT 1",
+        )
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting next nightly with specs")?;
+        Ok((before, after))
+    }
+
+    /// Records a rename, keeping the earliest nightly it was seen on.
+    pub async fn insert_target_rename(&self, rename: &TargetRename) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO target_rename (old_target, new_target, nightly) VALUES ($1, $2, $3)
+            ON CONFLICT (old_target) DO UPDATE SET
+                new_target = excluded.new_target,
+                nightly = excluded.nightly
+            WHERE excluded.nightly < target_rename.nightly",
+        )
+        .bind(&rename.old_target)
+        .bind(&rename.new_target)
+        .bind(&rename.nightly)
+        .execute(&self.conn)
+        .await
+        .wrap_err("inserting target rename")?;
+        Ok(())
+    }
+
+    pub async fn target_renames(&self) -> Result<Vec<TargetRename>> {
+        sqlx::query_as::<_, TargetRename>(
+            "SELECT old_target, new_target, nightly FROM target_rename ORDER BY nightly, old_target",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting target renames")
+    }
+
+    /// The newest nightly before `nightly` that has a spec for the target.
+    pub async fn previous_target_spec_nightly(
+        &self,
+        target: &str,
+        nightly: &str,
+    ) -> Result<Option<String>> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT nightly FROM target_spec WHERE target = $1 AND nightly < $2
+            ORDER BY nightly DESC LIMIT 1",
+        )
+        .bind(target)
+        .bind(nightly)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("getting previous target spec")
+    }
+
+    pub async fn status_counts(&self, nightly: &str) -> Result<Vec<StatusCount>> {
+        sqlx::query_as::<_, StatusCount>(
+            "SELECT mode, host, status, COUNT(*) AS count FROM build_info
+            WHERE nightly = $1 GROUP BY mode, host, status ORDER BY mode, host, status",
+        )
+        .bind(nightly)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("counting build statuses")
+    }
+
+    /// The most recent nightly before `nightly` that has builds for the mode on the host.
+    pub async fn previous_nightly(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Option<String>> {
+        let row = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT MAX(nightly) FROM build_info WHERE nightly < $1 AND mode = $2 AND host = $3",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting previous nightly")?;
+        Ok(row.0)
+    }
+
+    /// The newest nightly that was finished without being broken or unavailable in the mode on the host.
+    pub async fn latest_finished_nightly(
+        &self,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Option<String>> {
+        sqlx::query_scalar::<_, Option<String>>(
+            "SELECT MAX(nightly) FROM finished_nightly
+            WHERE mode = $1 AND host = $2 AND CASE WHEN is_broken THEN 1 ELSE 0 END = 0
+                AND toolchain_error IS NULL",
+        )
+        .bind(mode)
+        .bind(host)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting latest finished nightly")
+    }
+
+    /// The newest nightly with builds, or the newest one that finished building, in the mode and on
+    /// the host if they are given.
+    pub async fn newest_nightly(
+        &self,
+        finished: bool,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Option<String>> {
+        let table = if finished {
+            "finished_nightly"
+        } else {
+            "build_info"
+        };
+        sqlx::query_scalar::<_, Option<String>>(&format!(
+            "SELECT MAX(nightly) FROM {table}
+            WHERE ($1 IS NULL OR mode = $1) AND ($2 IS NULL OR host = $2)"
+        ))
+        .bind(mode)
+        .bind(host)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting newest nightly")
+    }
+
+    pub async fn is_escalated(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM escalated_nightly WHERE nightly = $1 AND mode = $2")
+            .bind(nightly)
+            .bind(mode)
+            .fetch_optional(&self.conn)
+            .await
+            .wrap_err("checking whether a nightly was escalated")?;
+        Ok(row.is_some())
+    }
+
+    pub async fn mark_escalated(&self, nightly: &str, mode: BuildMode) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO escalated_nightly (nightly, mode) VALUES ($1, $2)
+            ON CONFLICT (nightly, mode) DO NOTHING",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .execute(&self.conn)
+        .await
+        .wrap_err("marking nightly as escalated")?;
+        Ok(())
+    }
+
+    /// Whether the escalation of the nightly was posted to the destination, like `zulip`.
+    pub async fn is_escalated_to(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        destination: &str,
+    ) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT 1 FROM escalated_destination
+            WHERE nightly = $1 AND mode = $2 AND destination = $3",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(destination)
+        .fetch_optional(&self.conn)
+        .await
+        .wrap_err("checking whether a nightly was escalated to a destination")?;
+        Ok(row.is_some())
+    }
+
+    pub async fn mark_escalated_to(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        destination: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO escalated_destination (nightly, mode, destination) VALUES ($1, $2, $3)
+            ON CONFLICT (nightly, mode, destination) DO NOTHING",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(destination)
+        .execute(&self.conn)
+        .await
+        .wrap_err("marking nightly as escalated to a destination")?;
+        Ok(())
+    }
+
+    /// Nightlies finished on the host that don't have a snapshot yet, oldest first.
+    pub async fn unpublished_snapshots(&self, host: &str) -> Result<Vec<FinishedNightly>> {
+        sqlx::query_as::<_, FinishedNightly>(
+            "SELECT nightly, mode FROM finished_nightly
+            WHERE host = $1 AND NOT EXISTS (
+                SELECT 1 FROM published_snapshot
+                WHERE published_snapshot.nightly = finished_nightly.nightly
+                    AND published_snapshot.mode = finished_nightly.mode
+            )
+            ORDER BY nightly, mode",
+        )
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting nightlies without a snapshot")
+    }
+
+    pub async fn mark_snapshot_published(&self, nightly: &str, mode: BuildMode) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO published_snapshot (nightly, mode) VALUES ($1, $2)
+            ON CONFLICT (nightly, mode) DO NOTHING",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .execute(&self.conn)
+        .await
+        .wrap_err("marking snapshot as published")?;
+        Ok(())
+    }
+
+    /// The builds of the nightly on every host, unmerged.
+    pub async fn nightly_builds(&self, nightly: &str, mode: BuildMode) -> Result<Vec<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM build_info WHERE nightly = $1 AND mode = $2
+            ORDER BY target, host"
+        ))
+        .bind(nightly)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting builds of nightly")
+    }
+
+    /// Targets that failed on the nightly.
+    pub async fn failed_targets(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT target FROM build_info
+            WHERE nightly = $1 AND mode = $2 AND host = $3 AND status = 'error'
+            ORDER BY target",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting failed targets")?;
+        Ok(rows.into_iter().map(|(target,)| target).collect())
+    }
+
+    /// Targets that failed on `nightly` but passed on `previous`.
+    pub async fn regressions(
+        &self,
+        nightly: &str,
+        previous: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(&format!(
+            "SELECT current.target FROM build_info AS current
+            JOIN build_info AS previous
+                ON previous.target = current.target
+                AND previous.mode = current.mode
+                AND previous.host = current.host
+            WHERE current.nightly = $1 AND previous.nightly = $2
+                AND current.mode = $3 AND current.host = $4
+                AND current.status = 'error' AND previous.status = 'pass'
+                AND NOT {}
+            ORDER BY current.target",
+            is_expected("current")
+        ))
+        .bind(nightly)
+        .bind(previous)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting regressions")?;
+        Ok(rows.into_iter().map(|(target,)| target).collect())
+    }
+
+    /// Stores the breakages and records the audit entry for them.
+    pub async fn insert_expected_breakages(
+        &self,
+        breakages: &[ExpectedBreakage],
+        audit: &AuditEntry,
+    ) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        for breakage in breakages {
+            sqlx::query(
+                "INSERT INTO expected_breakage (target, mode, first_nightly, last_nightly, reason, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (target, mode, first_nightly) DO UPDATE SET
+                    last_nightly = excluded.last_nightly,
+                    reason = excluded.reason,
+                    created_at = excluded.created_at",
+            )
+            .bind(&breakage.target)
+            .bind(breakage.mode)
+            .bind(&breakage.first_nightly)
+            .bind(&breakage.last_nightly)
+            .bind(&breakage.reason)
+            .bind(breakage.created_at)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting expected breakage")?;
+        }
+        insert_audit_entry(&mut tx, audit).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The expected breakages of the target, or of all targets, newest first.
+    pub async fn expected_breakages(&self, target: Option<&str>) -> Result<Vec<ExpectedBreakage>> {
+        sqlx::query_as::<_, ExpectedBreakage>(
+            "SELECT target, mode, first_nightly, last_nightly, reason, created_at
+            FROM expected_breakage
+            WHERE $1 IS NULL OR target = $1
+            ORDER BY first_nightly DESC, target, mode",
+        )
+        .bind(target)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting expected breakages")
+    }
+
+    /// The expected breakages that cover the nightly.
+    pub async fn expected_breakages_on(&self, nightly: &str) -> Result<Vec<ExpectedBreakage>> {
+        sqlx::query_as::<_, ExpectedBreakage>(
+            "SELECT target, mode, first_nightly, last_nightly, reason, created_at
+            FROM expected_breakage
+            WHERE $1 BETWEEN first_nightly AND last_nightly
+            ORDER BY target, mode",
+        )
+        .bind(nightly)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting expected breakages of nightly")
+    }
+
+    /// Changes record their entry in their own transaction, this is for setting up tests.
+    #[cfg(test)]
+    pub async fn insert_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        insert_audit_entry(&mut tx, entry).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The latest entries of the audit log, or of one actor, newest first.
+    pub async fn audit_log(&self, actor: Option<&str>, limit: i64) -> Result<Vec<AuditEntry>> {
+        sqlx::query_as::<_, AuditEntry>(
+            "SELECT created_at, actor, action, payload FROM audit_log
+            WHERE $1 IS NULL OR actor = $1
+            ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(actor)
+        .bind(limit)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting audit log")
+    }
+
+    /// Replaces the results of the peer with `builds`.
+    pub async fn replace_federated_builds(
+        &self,
+        peer: &str,
+        builds: &[FederatedBuild],
+    ) -> Result<()> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("DELETE FROM federated_build WHERE peer = $1")
+            .bind(peer)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("deleting federated builds")?;
+        for build in builds {
+            sqlx::query(
+                "INSERT INTO federated_build (peer, peer_url, target, mode, host, nightly, status, tier, error_excerpt, fetched_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            )
+            .bind(peer)
+            .bind(&build.peer_url)
+            .bind(&build.target)
+            .bind(build.mode)
+            .bind(&build.host)
+            .bind(&build.nightly)
+            .bind(build.status)
+            .bind(build.tier)
+            .bind(&build.error_excerpt)
+            .bind(build.fetched_at)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting federated build")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Removes the results of peers that are no longer configured.
+    pub async fn retain_federated_peers(&self, peers: &[String]) -> Result<()> {
+        let known = sqlx::query_scalar::<_, String>("SELECT DISTINCT peer FROM federated_build")
+            .fetch_all(&self.conn)
+            .await
+            .wrap_err("getting federated peers")?;
+        for peer in known.into_iter().filter(|peer| !peers.contains(peer)) {
+            sqlx::query("DELETE FROM federated_build WHERE peer = $1")
+                .bind(&peer)
+                .execute(&self.conn)
+                .await
+                .wrap_err("deleting federated builds of removed peer")?;
+        }
+        Ok(())
+    }
+
+    /// The results of all peers, of the target if there is one.
+    pub async fn federated_builds(&self, target: Option<&str>) -> Result<Vec<FederatedBuild>> {
+        sqlx::query_as::<_, FederatedBuild>(
+            "SELECT peer, peer_url, nightly, target, mode, host, status, tier, error_excerpt, fetched_at
+            FROM federated_build
+            WHERE $1 IS NULL OR target = $1
+            ORDER BY target, mode, host, peer",
+        )
+        .bind(target)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting federated builds")
+    }
+
+    pub async fn insert_beta_check(&self, check: &BetaCheck, host: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO beta_check (nightly, target, mode, host, toolchain, status)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (nightly, target, mode, host) DO UPDATE SET
+                toolchain = excluded.toolchain,
+                status = excluded.status",
+        )
+        .bind(&check.nightly)
+        .bind(&check.target)
+        .bind(check.mode)
+        .bind(host)
+        .bind(&check.toolchain)
+        .bind(check.status)
+        .execute(&self.conn)
+        .await
+        .wrap_err("inserting beta check")?;
+        Ok(())
+    }
+
+    /// The beta checks of the regressions of a nightly.
+    pub async fn beta_checks(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Vec<BetaCheck>> {
+        sqlx::query_as::<_, BetaCheck>(
+            "SELECT nightly, target, mode, toolchain, status FROM beta_check
+            WHERE nightly = $1 AND mode = $2 AND host = $3
+            ORDER BY target",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting beta checks")
+    }
+
+    /// Targets that never passed in a mode, on the host or on any host if it is `None`.
+    pub async fn never_passed(
+        &self,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<NeverPassed>> {
+        sqlx::query_as::<_, NeverPassed>(&format!(
+            "SELECT target, mode, COUNT(*) AS builds,
+                MIN(nightly) AS first_nightly, MAX(nightly) AS last_nightly, {TIER}
+            FROM build_info
+            WHERE ($1 IS NULL OR mode = $1) AND ($2 IS NULL OR host = $2) AND {VERDICT}
+            GROUP BY target, mode
+            HAVING SUM(CASE WHEN status = 'pass' THEN 1 ELSE 0 END) = 0
+            ORDER BY mode, target"
+        ))
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting targets that never passed")
+    }
+
+    /// Targets that are still failing and have been failing since `cutoff` or earlier,
+    /// on the host or on any host if it is `None`.
+    pub async fn stale_failures(
+        &self,
+        cutoff: &str,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<StaleFailure>> {
+        sqlx::query_as::<_, StaleFailure>(&format!(
+            "SELECT target, mode, MIN(nightly) AS first_bad, COUNT(*) AS failed_builds, {TIER}
+            FROM {}
+            WHERE status = 'error' AND ($2 IS NULL OR mode = $2) AND nightly > COALESCE((
+                SELECT MAX(passed.nightly) FROM (SELECT * FROM {}) AS passed
+                WHERE passed.target = build_info.target AND passed.mode = build_info.mode
+                    AND passed.status = 'pass'
+            ), '')
+            GROUP BY target, mode
+            HAVING MIN(nightly) <= $3 AND MAX(nightly) = (
+                SELECT MAX(latest.nightly) FROM build_info AS latest
+                WHERE latest.mode = build_info.mode AND ($1 IS NULL OR latest.host = $1)
+            )
+            ORDER BY first_bad, mode, target",
+            build_info_for_host("$1"),
+            build_info_for_host("$1"),
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(cutoff)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting stale failures")
+    }
+
+    /// The number of passing and failing targets of every nightly since `since`, oldest first.
+    pub async fn pass_counts(
+        &self,
+        since: Option<&str>,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<PassCount>> {
+        sqlx::query_as::<_, PassCount>(&format!(
+            "SELECT nightly, mode,
+                SUM(CASE WHEN status = 'pass' THEN 1 ELSE 0 END) AS pass,
+                SUM(CASE WHEN status = 'error' THEN 1 ELSE 0 END) AS error
+            FROM {}
+            WHERE ($2 IS NULL OR nightly >= $2) AND ($3 IS NULL OR mode = $3)
+            GROUP BY nightly, mode
+            ORDER BY nightly, mode",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(since)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting pass counts")
+    }
+
+    /// The number of passing and failing builds of a target on every nightly since `since`, over all
+    /// modes if `mode` is `None`, oldest first.
+    pub async fn calendar(
+        &self,
+        target: &str,
+        since: &str,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<CalendarDay>> {
+        sqlx::query_as::<_, CalendarDay>(&format!(
+            "SELECT nightly,
+                SUM(CASE WHEN status = 'pass' THEN 1 ELSE 0 END) AS pass,
+                SUM(CASE WHEN status = 'error' THEN 1 ELSE 0 END) AS error
+            FROM {}
+            WHERE target = $2 AND nightly >= $3 AND ($4 IS NULL OR mode = $4)
+            GROUP BY nightly
+            ORDER BY nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(target)
+        .bind(since)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting calendar")
+    }
+
+    /// The number of regressions and fixes of every nightly since `since`, oldest first.
+    pub async fn regression_counts(
+        &self,
+        since: Option<&str>,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<RegressionCount>> {
+        sqlx::query_as::<_, RegressionCount>(&format!(
+            "SELECT nightly, mode,
+                SUM(CASE WHEN status = 'error' AND previous = 'pass' AND NOT {} THEN 1 ELSE 0 END) AS regressions,
+                SUM(CASE WHEN status = 'pass' AND previous = 'error' THEN 1 ELSE 0 END) AS fixes
+            FROM (
+                SELECT nightly, target, mode, status,
+                    LAG(status) OVER (PARTITION BY target, mode ORDER BY nightly) AS previous
+                FROM {}
+                WHERE ($3 IS NULL OR mode = $3) AND {VERDICT}
+            ) AS with_previous
+            WHERE $2 IS NULL OR nightly >= $2
+            GROUP BY nightly, mode
+            ORDER BY nightly, mode",
+            is_expected("with_previous"),
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(since)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting regression counts")
+    }
+
+    /// The builds from `since` on with a different status than the previous build of their target,
+    /// ordered by target and then nightly.
+    pub async fn status_changes(
+        &self,
+        since: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, String, Status)>> {
+        sqlx::query_as::<_, (String, String, Status)>(&format!(
+            "SELECT target, nightly, status FROM (
+                SELECT target, nightly, status,
+                    LAG(status) OVER (PARTITION BY target ORDER BY nightly) AS previous
+                FROM {}
+                WHERE mode = $2 AND {VERDICT}
+            ) AS with_previous
+            WHERE previous IS NOT NULL AND status <> previous AND nightly >= $3
+            ORDER BY target, nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(since)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting status changes")
+    }
+
+    /// The builds of every target from `since` until before `before` and the last build before
+    /// `since` with a verdict, ordered by target and then nightly.
+    pub async fn recent_history(
+        &self,
+        since: &str,
+        before: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, String, Status, Option<String>)>> {
+        sqlx::query_as::<_, (String, String, Status, Option<String>)>(&format!(
+            "SELECT target, nightly, status, error_excerpt FROM (
+                SELECT target, nightly, status, error_excerpt,
+                    ROW_NUMBER() OVER (PARTITION BY target, nightly >= $3 ORDER BY nightly DESC) AS newest
+                FROM {}
+                WHERE mode = $2 AND nightly < $4 AND (nightly >= $3 OR {VERDICT})
+            ) AS numbered
+            WHERE nightly >= $3 OR newest = 1
+            ORDER BY target, nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(since)
+        .bind(before)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting recent build history")
+    }
+
+    /// The builds with a verdict of tier 1 and 2 targets in every mode after `after` until `until`,
+    /// with their tier and error excerpt, ordered by target, mode and then nightly.
+    pub async fn tier_1_and_2_verdicts(
+        &self,
+        after: &str,
+        until: &str,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, BuildMode, String, Status, i32, Option<String>)>> {
+        sqlx::query_as::<_, (String, BuildMode, String, Status, i32, Option<String>)>(&format!(
+            "SELECT target, mode, nightly, status, tier, error_excerpt FROM (
+                SELECT target, mode, nightly, status, error_excerpt, {TIER}
+                FROM {}
+                WHERE nightly > $2 AND nightly <= $3 AND {VERDICT}
+            ) AS with_tier
+            WHERE tier IN (1, 2)
+            ORDER BY target, mode, nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(after)
+        .bind(until)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting builds of tier 1 and 2 targets")
+    }
+
+    /// The targets with an internal compiler error in their log from `since` on, with the first nightly it happened on.
+    pub async fn ices_since(
+        &self,
+        since: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, String)>> {
+        sqlx::query_as::<_, (String, String)>(&format!(
+            "SELECT target, MIN(nightly) FROM {}
+            JOIN log_blob ON log_blob.hash = build_info.stderr_hash
+            WHERE mode = $2 AND nightly >= $3 AND status = 'error'
+                AND log_blob.content LIKE '%internal compiler error%'
+            GROUP BY target
+            ORDER BY target",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(since)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting internal compiler errors")
+    }
+
+    /// The targets with an internal compiler error in their log on the nightly.
+    pub async fn nightly_ices(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: &str,
+    ) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT target FROM build_info
+            JOIN log_blob ON log_blob.hash = build_info.stderr_hash
+            WHERE nightly = $1 AND mode = $2 AND host = $3 AND status = 'error'
+                AND log_blob.content LIKE '%internal compiler error%'
+            ORDER BY target",
+        )
+        .bind(nightly)
+        .bind(mode)
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting internal compiler errors of a nightly")?;
+        Ok(rows.into_iter().map(|(target,)| target).collect())
+    }
+
+    /// The streaks of consecutive builds with the same status of every target, oldest first.
+    pub async fn streaks(
+        &self,
+        target: Option<&str>,
+        mode: Option<BuildMode>,
+        host: Option<&str>,
+    ) -> Result<Vec<Streak>> {
+        // Within a streak, the position among all builds of the target and the position among
+        // its builds with the same status both increase by one, so their difference is constant.
+        sqlx::query_as::<_, Streak>(&format!(
+            "SELECT target, mode, status, MIN(nightly) AS first_nightly, MAX(nightly) AS last_nightly,
+                COUNT(*) AS length
+            FROM (
+                SELECT target, mode, status, nightly,
+                    ROW_NUMBER() OVER (PARTITION BY target, mode ORDER BY nightly)
+                    - ROW_NUMBER() OVER (PARTITION BY target, mode, status ORDER BY nightly) AS streak
+                FROM {}
+                WHERE ($2 IS NULL OR target = $2) AND ($3 IS NULL OR mode = $3) AND {VERDICT}
+            ) AS numbered
+            GROUP BY target, mode, status, streak
+            ORDER BY target, mode, first_nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(target)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting streaks")
+    }
+
+    /// The builds of the last `nightlies` nightlies in the mode with the commit of their compiler,
+    /// ordered by target and then nightly.
+    pub async fn recent_builds(
+        &self,
+        mode: BuildMode,
+        host: Option<&str>,
+        nightlies: i64,
+    ) -> Result<Vec<(String, Status, Option<String>)>> {
+        sqlx::query_as::<_, (String, Status, Option<String>)>(&format!(
+            "SELECT build_info.target, build_info.status, toolchain_info.commit_hash
+            FROM {}
+            LEFT JOIN toolchain_info ON toolchain_info.nightly = build_info.nightly
+            WHERE build_info.mode = $2 AND build_info.{VERDICT} AND build_info.nightly IN (
+                SELECT DISTINCT nightly FROM build_info WHERE mode = $2 ORDER BY nightly DESC LIMIT $3
+            )
+            ORDER BY build_info.target, build_info.nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(nightlies)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting recent builds")
+    }
+
+    /// The duration and log size of the builds with a verdict on the last `nightlies` nightlies of
+    /// the mode up to `until`, oldest first for every target. The size is in bytes and unknown for
+    /// logs that were replaced by `pruned_hash`.
+    pub async fn build_metrics(
+        &self,
+        mode: BuildMode,
+        host: Option<&str>,
+        until: &str,
+        nightlies: i64,
+        pruned_hash: &str,
+    ) -> Result<Vec<BuildMetrics>> {
+        sqlx::query_as::<_, BuildMetrics>(&format!(
+            "SELECT build_info.target, build_info.nightly, build_info.status,
+                CAST(build_info.finished_at - build_info.started_at AS BIGINT) AS duration_secs,
+                CASE WHEN log_blob.hash <> $5
+                    THEN CAST(COALESCE(log_blob.original_bytes, OCTET_LENGTH(log_blob.content)) AS BIGINT)
+                END AS stderr_bytes
+            FROM {}
+            JOIN log_blob ON log_blob.hash = build_info.stderr_hash
+            WHERE build_info.mode = $2 AND build_info.{VERDICT} AND build_info.nightly IN (
+                SELECT DISTINCT nightly FROM build_info WHERE mode = $2 AND nightly <= $3
+                ORDER BY nightly DESC LIMIT $4
+            )
+            ORDER BY build_info.target, build_info.nightly",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(until)
+        .bind(nightlies)
+        .bind(pruned_hash)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting build metrics")
+    }
+
+    /// The builds of the last `nightlies` nightlies of the mode, for the index page.
+    pub async fn recent_build_status(
+        &self,
+        mode: BuildMode,
+        host: Option<&str>,
+        nightlies: i64,
+    ) -> Result<Vec<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM {}
+            WHERE build_info.mode = $2 AND build_info.nightly IN (
+                SELECT DISTINCT nightly FROM build_info WHERE mode = $2 ORDER BY nightly DESC LIMIT $3
+            )",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(mode)
+        .bind(nightlies)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting recent build status")
+    }
+
+    /// The builds of a nightly in the mode, of one host or merged over all hosts if `host` is `None`.
+    pub async fn nightly_build_status(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Vec<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM {}
+            WHERE nightly = $2 AND mode = $3
+            ORDER BY target",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(nightly)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting build status of nightly")
+    }
+
+    /// The builds of the target on the last `nightlies` nightlies it was built on, newest first.
+    pub async fn target_build_status(
+        &self,
+        target: &str,
+        host: Option<&str>,
+        nightlies: i64,
+    ) -> Result<Vec<BuildInfo>> {
+        sqlx::query_as::<_, BuildInfo>(&format!(
+            "SELECT nightly, target, status, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, error_excerpt,
+                {TIER}
+            FROM {}
+            WHERE target = $2 AND nightly IN (
+                SELECT DISTINCT nightly FROM build_info WHERE target = $2 ORDER BY nightly DESC LIMIT $3
+            )
+            ORDER BY nightly DESC, mode",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(target)
+        .bind(nightlies)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting build status of target")
+    }
+
+    /// The nightlies before and after the nightly that have builds in the mode, on the host or any host.
+    pub async fn adjacent_nightlies(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<(Option<String>, Option<String>)> {
+        sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            "SELECT
+                (SELECT MAX(nightly) FROM build_info
+                    WHERE nightly < $2 AND mode = $3 AND ($1 IS NULL OR host = $1)),
+                (SELECT MIN(nightly) FROM build_info
+                    WHERE nightly > $2 AND mode = $3 AND ($1 IS NULL OR host = $1))",
+        )
+        .bind(host)
+        .bind(nightly)
+        .bind(mode)
+        .fetch_one(&self.conn)
+        .await
+        .wrap_err("getting adjacent nightlies")
+    }
+
+    /// Targets where re-running a build of one of the last `nightlies` nightlies changed its status.
+    pub async fn rerun_status_changes(
+        &self,
+        mode: BuildMode,
+        host: Option<&str>,
+        nightlies: i64,
+    ) -> Result<Vec<String>> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT build_attempt.target FROM build_attempt
+            JOIN build_info
+                ON build_info.nightly = build_attempt.nightly
+                AND build_info.target = build_attempt.target
+                AND build_info.mode = build_attempt.mode
+                AND build_info.host = build_attempt.host
+            WHERE build_attempt.status <> build_info.status
+                AND build_attempt.status IN ('pass', 'error') AND build_info.status IN ('pass', 'error')
+                AND build_attempt.mode = $1 AND ($2 IS NULL OR build_attempt.host = $2)
+                AND build_attempt.nightly IN (
+                    SELECT DISTINCT nightly FROM build_info WHERE mode = $1 ORDER BY nightly DESC LIMIT $3
+                )
+            ORDER BY build_attempt.target",
+        )
+        .bind(mode)
+        .bind(host)
+        .bind(nightlies)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting targets with changed re-runs")
+    }
+
+    /// The number of passing builds and all builds of every target and mode from `since` on.
+    pub async fn pass_totals(
+        &self,
+        since: &str,
+        target: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, BuildMode, i64, i64)>> {
+        sqlx::query_as::<_, (String, BuildMode, i64, i64)>(&format!(
+            "SELECT target, mode, SUM(CASE WHEN status = 'pass' THEN 1 ELSE 0 END), COUNT(*)
+            FROM {}
+            WHERE nightly >= $2 AND ($3 IS NULL OR target = $3) AND {VERDICT}
+            GROUP BY target, mode",
+            build_info_for_host("$1")
+        ))
+        .bind(host)
+        .bind(since)
+        .bind(target)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting pass totals")
+    }
+
+    /// The status of two targets on every nightly that both were built on, oldest first.
+    pub async fn compare_targets(
+        &self,
+        a: &str,
+        b: &str,
+        mode: BuildMode,
+        host: Option<&str>,
+    ) -> Result<Vec<(String, Status, Status)>> {
+        sqlx::query_as::<_, (String, Status, Status)>(&format!(
+            "SELECT a.nightly, a.status, b.status
+            FROM (SELECT * FROM {}) AS a
+            JOIN (SELECT * FROM {}) AS b ON b.nightly = a.nightly AND b.mode = a.mode
+            WHERE a.target = $2 AND b.target = $3 AND a.mode = $4
+            ORDER BY a.nightly",
+            build_info_for_host("$1"),
+            build_info_for_host("$1"),
+        ))
+        .bind(host)
+        .bind(a)
+        .bind(b)
+        .bind(mode)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("comparing targets")
+    }
+
+    /// The targets of the most recent nightly built on the host.
+    pub async fn latest_targets(&self, host: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT DISTINCT target FROM build_info
+            WHERE host = $1 AND nightly = (SELECT MAX(nightly) FROM build_info WHERE host = $1)
+            ORDER BY target",
+        )
+        .bind(host)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting targets of the latest nightly")?;
+        Ok(rows.into_iter().map(|(target,)| target).collect())
+    }
+
+    /// All build results including superseded ones, or the ones of a single nightly, ordered by nightly.
+    pub fn build_records<'a>(
+        &'a self,
+        with_logs: bool,
+        nightly: Option<&'a str>,
+    ) -> BoxStream<'a, sqlx::Result<BuildRecord>> {
+        let query = if with_logs {
+            "SELECT b.nightly, b.target, b.mode, b.status, b.started_at, b.finished_at, b.exit_code, b.peak_rss_kib,
+                b.host, b.hostname, b.attempt, CAST(NULL AS BIGINT) AS superseded_at, l.content AS stderr, b.error_excerpt
+            FROM build_info b JOIN log_blob l ON l.hash = b.stderr_hash
+            WHERE $1 IS NULL OR b.nightly = $1
+            UNION ALL
+            SELECT a.nightly, a.target, a.mode, a.status, a.started_at, a.finished_at, a.exit_code, a.peak_rss_kib,
+                a.host, a.hostname, a.attempt, a.superseded_at, l.content AS stderr, a.error_excerpt
+            FROM build_attempt a JOIN log_blob l ON l.hash = a.stderr_hash
+            WHERE $1 IS NULL OR a.nightly = $1
+            ORDER BY nightly, mode, target, host, attempt"
+        } else {
+            "SELECT nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib,
+                host, hostname, attempt, CAST(NULL AS BIGINT) AS superseded_at, CAST(NULL AS VARCHAR) AS stderr, error_excerpt
+            FROM build_info
+            WHERE $1 IS NULL OR nightly = $1
+            UNION ALL
+            SELECT nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib,
+                host, hostname, attempt, superseded_at, CAST(NULL AS VARCHAR) AS stderr, error_excerpt
+            FROM build_attempt
+            WHERE $1 IS NULL OR nightly = $1
+            ORDER BY nightly, mode, target, host, attempt"
+        };
+        sqlx::query_as::<_, BuildRecord>(query)
+            .bind(nightly)
+            .fetch(&self.conn)
+    }
+
+    pub async fn nightly_records(&self) -> Result<Vec<NightlyRecord>> {
+        let rows = sqlx::query_as::<_, (String, BuildMode, String, i32, Option<String>)>(
+            "SELECT nightly, mode, host, CASE WHEN is_broken THEN 1 ELSE 0 END, toolchain_error
+            FROM finished_nightly ORDER BY nightly, mode, host",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting finished nightlies")?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(nightly, mode, host, broken, toolchain_error)| NightlyRecord {
+                    nightly,
+                    mode,
+                    host: Some(host),
+                    broken: broken != 0,
+                    toolchain_error,
+                },
+            )
+            .collect())
+    }
+
+    pub async fn all_toolchain_info(&self) -> Result<Vec<ToolchainInfo>> {
+        sqlx::query_as::<_, ToolchainInfo>(
+            "SELECT nightly, rustc_version, commit_hash, llvm_version, host, cargo_version, miri_version,
+                rustup_version, verification
+            FROM toolchain_info ORDER BY nightly",
+        )
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting toolchain info")
+    }
+
+    /// Adds builds from another instance's export. Builds that exist here are left alone, including
+    /// their history. `decided` remembers for each build seen in earlier calls whether it is imported,
+    /// so a build's attempts can be spread over several calls.
+    pub async fn import_builds(
+        &self,
+        records: Vec<BuildRecord>,
+        missing_log: &str,
+        decided: &mut HashMap<(String, String, BuildMode, String), bool>,
+    ) -> Result<u64> {
+        let mut tx = self.conn.begin().await?;
+        let mut imported = 0;
+        for record in records {
+            let host = record.host.as_deref().unwrap_or(LEGACY_HOST).to_owned();
+            let key = (
+                record.nightly.clone(),
+                record.target.clone(),
+                record.mode,
+                host.clone(),
+            );
+            let import = match decided.get(&key) {
+                Some(&import) => import,
+                None => {
+                    let existing = sqlx::query(
+                        "SELECT 1 FROM build_info
+                        WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $4",
+                    )
+                    .bind(&record.nightly)
+                    .bind(&record.target)
+                    .bind(record.mode)
+                    .bind(&host)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .wrap_err("checking for existing build")?;
+                    decided.insert(key, existing.is_none());
+                    existing.is_none()
+                }
+            };
+            if !import {
+                continue;
+            }
+
+            insert_target(&mut tx, &record.target).await?;
+            let stderr_hash =
+                insert_log_blob(&mut tx, record.stderr.as_deref().unwrap_or(missing_log)).await?;
+            let query = match record.superseded_at {
+                None => {
+                    "INSERT INTO build_info
+                        (nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, stderr_hash, error_excerpt)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                    ON CONFLICT (nightly, target, mode, host) DO NOTHING"
+                }
+                Some(_) => {
+                    "INSERT INTO build_attempt
+                        (nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, stderr_hash, error_excerpt, superseded_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                    ON CONFLICT (nightly, target, mode, host, attempt) DO NOTHING"
+                }
+            };
+            let error_excerpt = record.error_excerpt();
+            let mut query = sqlx::query(query)
+                .bind(record.nightly)
+                .bind(record.target)
+                .bind(record.mode)
+                .bind(record.status)
+                .bind(record.started_at)
+                .bind(record.finished_at)
+                .bind(record.exit_code)
+                .bind(record.peak_rss_kib)
+                .bind(host)
+                .bind(record.hostname)
+                .bind(record.attempt)
+                .bind(stderr_hash)
+                .bind(error_excerpt);
+            if let Some(superseded_at) = record.superseded_at {
+                query = query.bind(superseded_at);
+            }
+            imported += query
+                .execute(&mut *tx)
+                .await
+                .wrap_err("inserting imported build")?
+                .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    /// Stores builds from the primary instance of a mirror. Unlike `import_builds`, a build is
+    /// replaced if the primary has a newer attempt of it.
+    pub async fn mirror_builds(&self, records: Vec<BuildRecord>, missing_log: &str) -> Result<u64> {
+        let mut tx = self.conn.begin().await?;
+        let mut mirrored = 0;
+        for record in records {
+            insert_target(&mut tx, &record.target).await?;
+            let stderr_hash =
+                insert_log_blob(&mut tx, record.stderr.as_deref().unwrap_or(missing_log)).await?;
+            let query = match record.superseded_at {
+                None => {
+                    "INSERT INTO build_info
+                        (nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, stderr_hash, error_excerpt)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                    ON CONFLICT (nightly, target, mode, host) DO UPDATE SET
+                        status = excluded.status,
+                        started_at = excluded.started_at,
+                        finished_at = excluded.finished_at,
+                        exit_code = excluded.exit_code,
+                        peak_rss_kib = excluded.peak_rss_kib,
+                        hostname = excluded.hostname,
+                        attempt = excluded.attempt,
+                        stderr_hash = excluded.stderr_hash,
+                        error_excerpt = excluded.error_excerpt
+                    WHERE excluded.attempt > build_info.attempt"
+                }
+                Some(_) => {
+                    "INSERT INTO build_attempt
+                        (nightly, target, mode, status, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, attempt, stderr_hash, error_excerpt, superseded_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                    ON CONFLICT (nightly, target, mode, host, attempt) DO NOTHING"
+                }
+            };
+            let error_excerpt = record.error_excerpt();
+            let mut query = sqlx::query(query)
+                .bind(record.nightly)
+                .bind(record.target)
+                .bind(record.mode)
+                .bind(record.status)
+                .bind(record.started_at)
+                .bind(record.finished_at)
+                .bind(record.exit_code)
+                .bind(record.peak_rss_kib)
+                .bind(record.host.as_deref().unwrap_or(LEGACY_HOST).to_owned())
+                .bind(record.hostname)
+                .bind(record.attempt)
+                .bind(stderr_hash)
+                .bind(error_excerpt);
+            if let Some(superseded_at) = record.superseded_at {
+                query = query.bind(superseded_at);
+            }
+            mirrored += query
+                .execute(&mut *tx)
+                .await
+                .wrap_err("inserting mirrored build")?
+                .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(mirrored)
+    }
+
+    /// Adds finished nightlies from another instance's export, keeping existing ones.
+    pub async fn import_nightlies(&self, records: Vec<NightlyRecord>) -> Result<u64> {
+        let mut tx = self.conn.begin().await?;
+        let mut imported = 0;
+        for record in records {
+            imported += sqlx::query(
+                "INSERT INTO finished_nightly (nightly, mode, host, is_broken, toolchain_error) VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (nightly, mode, host) DO NOTHING",
+            )
+            .bind(record.nightly)
+            .bind(record.mode)
+            .bind(record.host.as_deref().unwrap_or(LEGACY_HOST))
+            .bind(record.broken)
+            .bind(record.toolchain_error)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting imported nightly")?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    /// Adds toolchain info from another instance's export, keeping existing ones.
+    pub async fn import_toolchain_info(&self, records: Vec<ToolchainInfo>) -> Result<u64> {
+        let mut tx = self.conn.begin().await?;
+        let mut imported = 0;
+        for info in records {
+            imported += sqlx::query(
+                "INSERT INTO toolchain_info
+                    (nightly, rustc_version, commit_hash, llvm_version, host, cargo_version, miri_version, rustup_version, verification)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (nightly) DO NOTHING",
+            )
+            .bind(info.nightly)
+            .bind(info.rustc_version)
+            .bind(info.commit_hash)
+            .bind(info.llvm_version)
+            .bind(info.host)
+            .bind(info.cargo_version)
+            .bind(info.miri_version)
+            .bind(info.rustup_version)
+            .bind(info.verification)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("inserting imported toolchain info")?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    /// The modes and hosts the nightly is finished for, and whether it was broken.
+    pub async fn nightly_finish_states(&self, nightly: &str) -> Result<Vec<NightlyRecord>> {
+        let rows = sqlx::query_as::<_, (BuildMode, String, i32, Option<String>)>(
+            "SELECT mode, host, CASE WHEN is_broken THEN 1 ELSE 0 END, toolchain_error FROM finished_nightly
+            WHERE nightly = $1 ORDER BY mode, host",
+        )
+        .bind(nightly)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("checking whether a nightly is finished")?;
+        Ok(rows
+            .into_iter()
+            .map(|(mode, host, broken, toolchain_error)| NightlyRecord {
+                nightly: nightly.to_owned(),
+                mode,
+                host: Some(host),
+                broken: broken != 0,
+                toolchain_error,
+            })
+            .collect())
+    }
+
+    /// Replaces the logs of all builds of nightlies before `cutoff` with `marker`, keeping their status.
+    /// The first failure of every regression keeps its log, as that is the interesting one.
+    /// Returns the number of pruned logs.
+    pub async fn prune_logs_before(&self, cutoff: &str, marker: &str) -> Result<u64> {
+        let mut tx = self.conn.begin().await?;
+        let marker_hash = insert_log_blob(&mut tx, marker).await?;
+
+        let pruned = sqlx::query(&format!(
+            "WITH history AS (
+                SELECT nightly, target, mode, host, status,
+                    LAG(status) OVER (PARTITION BY target, mode, host ORDER BY nightly) AS previous_status
+                FROM build_info
+                WHERE {VERDICT}
+            )
+            UPDATE build_info SET stderr_hash = $1
+            WHERE nightly < $2 AND stderr_hash != $1
+            AND NOT EXISTS (
+                SELECT 1 FROM history
+                WHERE history.nightly = build_info.nightly
+                    AND history.target = build_info.target
+                    AND history.mode = build_info.mode
+                    AND history.host = build_info.host
+                    AND history.status = 'error'
+                    AND history.previous_status = 'pass'
+            )"
+        ))
+        .bind(&marker_hash)
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .wrap_err("pruning logs")?
+        .rows_affected();
+
+        sqlx::query("UPDATE build_attempt SET stderr_hash = $1 WHERE nightly < $2")
+            .bind(&marker_hash)
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await
+            .wrap_err("pruning logs of superseded attempts")?;
+
+        sqlx::query(
+            "DELETE FROM log_blob
+            WHERE NOT EXISTS (SELECT 1 FROM build_info WHERE stderr_hash = log_blob.hash)
+            AND NOT EXISTS (SELECT 1 FROM build_attempt WHERE stderr_hash = log_blob.hash)",
+        )
+        .execute(&mut *tx)
+        .await
+        .wrap_err("deleting unreferenced logs")?;
+
+        tx.commit().await?;
+        Ok(pruned)
+    }
+
+    /// Updates the statistics of the query planner and, on SQLite, gives free pages, like the ones
+    /// of pruned logs, back to the file system. Returns by how many bytes the database shrank.
+    pub async fn maintain(&self) -> Result<Option<i64>> {
+        if self.backend == Backend::Postgres {
+            // Autovacuum takes care of the space.
+            sqlx::query("ANALYZE")
+                .execute(&self.conn)
+                .await
+                .wrap_err("analyzing database")?;
+            return Ok(None);
+        }
+
+        // Pragmas only apply to their connection, so everything runs on the same one.
+        let mut conn = self.conn.acquire().await?;
+        let pages_before = sqlx::query_scalar::<_, i64>("PRAGMA page_count")
+            .fetch_one(&mut *conn)
+            .await
+            .wrap_err("getting page count")?;
+
+        // An incremental vacuum does nothing unless it was enabled before the tables were created,
+        // or the database was rewritten after enabling it. That is done once here.
+        let auto_vacuum = sqlx::query_scalar::<_, i64>("PRAGMA auto_vacuum")
+            .fetch_one(&mut *conn)
+            .await
+            .wrap_err("getting auto vacuum mode")?;
+        if auto_vacuum != SQLITE_INCREMENTAL_VACUUM {
+            info!("Enabling incremental vacuum, which rewrites the database once");
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+                .execute(&mut *conn)
+                .await
+                .wrap_err("enabling incremental vacuum")?;
+            sqlx::query("VACUUM")
+                .execute(&mut *conn)
+                .await
+                .wrap_err("vacuuming database")?;
+        }
+
+        sqlx::query("ANALYZE")
+            .execute(&mut *conn)
+            .await
+            .wrap_err("analyzing database")?;
+        sqlx::query("PRAGMA optimize")
+            .execute(&mut *conn)
+            .await
+            .wrap_err("optimizing database")?;
+        sqlx::query("PRAGMA incremental_vacuum")
+            .execute(&mut *conn)
+            .await
+            .wrap_err("vacuuming database")?;
+
+        let pages_after = sqlx::query_scalar::<_, i64>("PRAGMA page_count")
+            .fetch_one(&mut *conn)
+            .await
+            .wrap_err("getting page count")?;
+        let page_size = sqlx::query_scalar::<_, i64>("PRAGMA page_size")
+            .fetch_one(&mut *conn)
+            .await
+            .wrap_err("getting page size")?;
+        // Enabling incremental vacuum adds pages to track the free ones, which is not a loss.
+        Ok(Some((pages_before - pages_after).max(0) * page_size))
+    }
+
+    pub async fn start_maintenance_run(&self, started_at: i64) -> Result<()> {
+        sqlx::query("INSERT INTO maintenance_run (started_at) VALUES ($1)")
+            .bind(started_at)
+            .execute(&self.conn)
+            .await
+            .wrap_err("recording maintenance run")?;
+        Ok(())
+    }
+
+    pub async fn finish_maintenance_run(
+        &self,
+        started_at: i64,
+        freed_bytes: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE maintenance_run SET finished_at = $2, freed_bytes = $3, error = $4
+            WHERE started_at = $1",
+        )
+        .bind(started_at)
+        .bind(time::OffsetDateTime::now_utc().unix_timestamp())
+        .bind(freed_bytes)
+        .bind(error)
+        .execute(&self.conn)
+        .await
+        .wrap_err("recording end of maintenance run")?;
+        Ok(())
+    }
+
+    /// The latest maintenance runs, newest first.
+    pub async fn maintenance_runs(&self, limit: i64) -> Result<Vec<MaintenanceRun>> {
+        sqlx::query_as::<_, MaintenanceRun>(
+            "SELECT started_at, finished_at, freed_bytes, error FROM maintenance_run
+            ORDER BY started_at DESC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.conn)
+        .await
+        .wrap_err("getting maintenance runs")
+    }
+}
+
+pub fn log_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Stores the result of a build. If the build already has a result, it is kept as a superseded attempt.
+/// If the log was offloaded, only its truncated version is stored. The first error in the log is
+/// stored separately for failed builds.
+#[tracing::instrument(skip_all, fields(nightly = %info.nightly, target = %info.target, mode = %info.mode))]
+async fn insert_build(
+    tx: &mut Transaction<'_, Any>,
+    info: &FullBuildInfo,
+    offloaded: Option<&OffloadedLog>,
+) -> Result<()> {
+    // Taken from the full log, the error might be in the part that is cut off when offloading.
+    let error_excerpt = match info.status {
+        Status::Error => excerpt::error_excerpt(&info.stderr),
+        _ => None,
+    };
+    let stderr_hash = match offloaded {
+        Some(offloaded) => insert_offloaded_log_blob(tx, &info.stderr, offloaded).await?,
+        None => insert_log_blob(tx, &info.stderr).await?,
+    };
+    if let Some(original_bytes) = info.original_stderr_bytes {
+        sqlx::query("UPDATE log_blob SET original_bytes = $1 WHERE hash = $2")
+            .bind(original_bytes)
+            .bind(&stderr_hash)
+            .execute(&mut **tx)
+            .await
+            .wrap_err("recording log size")?;
+    }
+
+    insert_target(tx, &info.target).await?;
+
+    // Keep the previous result around, this does nothing for the first attempt.
+    sqlx::query(
+        "INSERT INTO build_attempt
+            (nightly, target, mode, attempt, status, stderr_hash, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, error_excerpt, superseded_at)
+        SELECT nightly, target, mode, attempt, status, stderr_hash, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, error_excerpt, $4
+        FROM build_info WHERE nightly = $1 AND target = $2 AND mode = $3 AND host = $5",
+    )
+    .bind(&info.nightly)
+    .bind(&info.target)
+    .bind(info.mode)
+    .bind(time::OffsetDateTime::now_utc().unix_timestamp())
+    .bind(&info.host.host)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("archiving previous attempt")?;
+
+    sqlx::query(
+        "INSERT INTO build_info (nightly, target, status, stderr_hash, mode, started_at, finished_at, exit_code, peak_rss_kib, host, hostname, error_excerpt)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        ON CONFLICT (nightly, target, mode, host) DO UPDATE SET
+            status = excluded.status,
+            stderr_hash = excluded.stderr_hash,
+            error_excerpt = excluded.error_excerpt,
+            started_at = excluded.started_at,
+            finished_at = excluded.finished_at,
+            exit_code = excluded.exit_code,
+            peak_rss_kib = excluded.peak_rss_kib,
+            hostname = excluded.hostname,
+            attempt = build_info.attempt + 1",
+    )
+    .bind(&info.nightly)
+    .bind(&info.target)
+    .bind(info.status)
+    .bind(stderr_hash)
+    .bind(info.mode)
+    .bind(info.timing.started_at)
+    .bind(info.timing.finished_at)
+    .bind(info.timing.exit_code)
+    .bind(info.timing.peak_rss_kib)
+    .bind(&info.host.host)
+    .bind(&info.host.hostname)
+    .bind(error_excerpt)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("inserting build info into database")?;
+    Ok(())
+}
+
+/// Adds the components of the target triple, if they aren't stored yet.
+async fn insert_target(tx: &mut Transaction<'_, Any>, target: &str) -> Result<()> {
+    let triple = TargetTriple::parse(target);
+    sqlx::query(
+        "INSERT INTO target (target, arch, vendor, os, env) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (target) DO NOTHING",
+    )
+    .bind(target)
+    .bind(triple.arch)
+    .bind(triple.vendor)
+    .bind(triple.os)
+    .bind(triple.env)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("inserting target")?;
+    Ok(())
+}
+
+/// Stores a log if it isn't already stored and returns its hash.
+async fn insert_log_blob(tx: &mut Transaction<'_, Any>, content: &str) -> Result<String> {
+    let hash = log_hash(content);
+    sqlx::query(
+        "INSERT INTO log_blob (hash, content) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+    )
+    .bind(&hash)
+    .bind(content)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("inserting log")?;
+    Ok(hash)
+}
+
+/// Audit entries are written in the transaction of the change they record, so there is never one
+/// without the other.
+async fn insert_audit_entry(tx: &mut Transaction<'_, Any>, entry: &AuditEntry) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (created_at, actor, action, payload) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(entry.created_at)
+    .bind(&entry.actor)
+    .bind(&entry.action)
+    .bind(&entry.payload)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("inserting audit log entry")?;
+    Ok(())
+}
+
+/// Like `insert_log_blob`, but only the truncated log and the size of the full one are stored.
+async fn insert_offloaded_log_blob(
+    tx: &mut Transaction<'_, Any>,
+    content: &str,
+    offloaded: &OffloadedLog,
+) -> Result<String> {
+    let hash = log_hash(content);
+    sqlx::query(
+        "INSERT INTO log_blob (hash, content, offloaded_key, original_bytes) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (hash) DO NOTHING",
+    )
+    .bind(&hash)
+    .bind(&offloaded.truncated)
+    .bind(&offloaded.key)
+    .bind(content.len() as i64)
+    .execute(&mut **tx)
+    .await
+    .wrap_err("inserting log")?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{config::DbConfig, log_offload::OffloadedLog};
+
+    use super::{
+        AuditEntry, BatchedWrite, BuildHost, BuildMode, Db, ExpectedBreakage, FullBuildInfo, Status,
+    };
+
+    /// A migrated SQLite database that is deleted together with the directory.
+    pub(crate) async fn temp_db() -> (tempfile::TempDir, Db) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DbConfig {
+            url: dir.path().join("db.sqlite").to_str().unwrap().to_owned(),
+            ..DbConfig::default()
+        };
+        let db = Db::open(&config).await.unwrap();
+        db.migrate().await.unwrap();
+        (dir, db)
+    }
+
+    fn audit_entry(created_at: i64, actor: &str) -> AuditEntry {
+        AuditEntry {
+            created_at,
+            actor: actor.to_owned(),
+            action: "rebuild".to_owned(),
+            payload: format!("{{\"at\":{created_at}}}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn audited_changes() {
+        let (_dir, db) = temp_db().await;
+        let breakage = ExpectedBreakage {
+            target: "i686-apple-darwin".to_owned(),
+            mode: BuildMode::Core,
+            first_nightly: "2026-10-10".to_owned(),
+            last_nightly: "2026-10-10".to_owned(),
+            reason: "removed".to_owned(),
+            created_at: 1,
+        };
+        db.insert_expected_breakages(&[breakage], &audit_entry(1, "token"))
+            .await
+            .unwrap();
+        assert_eq!(db.expected_breakages(None).await.unwrap().len(), 1);
+        assert_eq!(db.audit_log(None, 10).await.unwrap().len(), 1);
+
+        // Nothing is reopened for a nightly that didn't finish, so nothing is recorded.
+        let modes = db
+            .request_rebuild(
+                "2026-10-10",
+                None,
+                crate::HOST,
+                &[],
+                &audit_entry(2, "token"),
+            )
+            .await
+            .unwrap();
+        assert!(modes.is_empty());
+        assert_eq!(db.audit_log(None, 10).await.unwrap().len(), 1);
+
+        db.finish_nightly("2026-10-10", BuildMode::Core, crate::HOST)
+            .await
+            .unwrap();
+        let modes = db
+            .request_rebuild(
+                "2026-10-10",
+                None,
+                crate::HOST,
+                &[],
+                &audit_entry(3, "token"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(modes, [BuildMode::Core]);
+        assert_eq!(db.audit_log(None, 10).await.unwrap()[0].created_at, 3);
+    }
+
+    #[tokio::test]
+    async fn build_metrics_count_bytes() {
+        let (_dir, db) = temp_db().await;
+        let build = |target: &str, stderr: &str| FullBuildInfo {
+            nightly: "2026-10-10".to_owned(),
+            target: target.to_owned(),
+            status: Status::Pass,
+            stderr: stderr.to_owned(),
+            original_stderr_bytes: None,
+            mode: BuildMode::Core,
+            timing: Default::default(),
+            host: BuildHost {
+                host: crate::HOST.to_owned(),
+                hostname: None,
+            },
+        };
+        let offloaded = OffloadedLog {
+            key: "logs/aarch64".to_owned(),
+            truncated: "start...end".to_owned(),
+        };
+        db.insert_batch(
+            crate::HOST,
+            &[
+                BatchedWrite::Build(
+                    build("aarch64-apple-darwin", &"x".repeat(5000)),
+                    Some(offloaded),
+                ),
+                BatchedWrite::Build(build("x86_64-unknown-linux-gnu", "ä\n"), None),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let metrics = db
+            .build_metrics(BuildMode::Core, None, "2026-10-10", 10, "pruned")
+            .await
+            .unwrap();
+        let sizes = metrics
+            .iter()
+            .map(|build| (build.target.as_str(), build.stderr_bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            [
+                ("aarch64-apple-darwin", Some(5000)),
+                ("x86_64-unknown-linux-gnu", Some(3))
+            ]
+        );
+    }
 }
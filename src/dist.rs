@@ -0,0 +1,117 @@
+//! Where nightlies are looked up and downloaded from: static.rust-lang.org or a mirror of it, like
+//! an internal one, optionally through a proxy. rustup and cargo get the same settings as variables.
+//! Offline, nothing is looked up or downloaded at all.
+
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use reqwest::Response;
+
+use crate::config::DistConfig;
+
+#[derive(Debug, Clone)]
+pub struct Dist {
+    /// Without a trailing slash.
+    server: String,
+    proxy: Option<String>,
+    client: reqwest::Client,
+    offline: bool,
+    manifests_file: Option<String>,
+}
+
+impl Dist {
+    pub fn new(config: &DistConfig) -> Result<Self> {
+        let client = match &config.proxy {
+            Some(proxy) => crate::http::builder()
+                .proxy(reqwest::Proxy::all(proxy).wrap_err("invalid dist proxy")?)
+                .build()
+                .wrap_err("building the HTTP client for the dist server")?,
+            None => crate::http::client().clone(),
+        };
+        Ok(Self {
+            server: config.server.trim_end_matches('/').to_owned(),
+            proxy: config.proxy.clone(),
+            client,
+            offline: config.offline,
+            manifests_file: config.manifests_file.clone(),
+        })
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn manifests_file(&self) -> Option<&Path> {
+        self.manifests_file.as_deref().map(Path::new)
+    }
+
+    /// The URL of the file on the dist server, like `dist/2026-10-10/channel-rust-nightly.toml`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.server)
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Response> {
+        if self.offline {
+            bail!("not fetching {path}, the builder is offline");
+        }
+        Ok(crate::http::send(self.client.get(self.url(path))).await?)
+    }
+
+    /// The variables that point rustup and cargo to the same server and proxy. Unset with the
+    /// defaults, so logs don't change for everyone else.
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        if self.server != DistConfig::DEFAULT_SERVER {
+            env.push(("RUSTUP_DIST_SERVER".to_owned(), self.server.clone()));
+        }
+        if let Some(proxy) = &self.proxy {
+            // rustup reads the lowercase ones, cargo its own.
+            for name in ["http_proxy", "https_proxy", "CARGO_HTTP_PROXY"] {
+                env.push((name.to_owned(), proxy.clone()));
+            }
+        }
+        if self.offline {
+            env.push(("CARGO_NET_OFFLINE".to_owned(), "true".to_owned()));
+        }
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dist;
+    use crate::config::DistConfig;
+
+    #[test]
+    fn env() {
+        let default = Dist::new(&DistConfig::default()).unwrap();
+        assert_eq!(
+            default.url("manifests.txt"),
+            "https://static.rust-lang.org/manifests.txt"
+        );
+        assert!(default.env().is_empty());
+
+        let mirror = Dist::new(&DistConfig {
+            server: "https://rust-mirror.internal/".to_owned(),
+            proxy: Some("http://proxy.internal:3128".to_owned()),
+            ..DistConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            mirror.url("manifests.txt"),
+            "https://rust-mirror.internal/manifests.txt"
+        );
+        let env = mirror.env();
+        assert!(env.contains(&(
+            "RUSTUP_DIST_SERVER".to_owned(),
+            "https://rust-mirror.internal".to_owned()
+        )));
+        assert!(env.contains(&(
+            "https_proxy".to_owned(),
+            "http://proxy.internal:3128".to_owned()
+        )));
+    }
+}
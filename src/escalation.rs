@@ -0,0 +1,592 @@
+//! Posting regressions of important targets to Zulip or a GitHub issue, mentioning the team
+//! responsible for them, so breakage doesn't rely on someone looking at the website. Important
+//! changes to their target specs are posted the same way, even if they still build.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use color_eyre::{eyre::Context, Result};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::{
+    config::{wildcard_match, EscalationConfig},
+    db::{BuildMode, Db, Status},
+    flaky,
+    target_spec::{self, SemanticChange},
+};
+
+/// Checks for newly finished nightlies and posts their regressions.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_escalator(db: Db, config: EscalationConfig) {
+    let client = crate::http::client();
+    loop {
+        for mode in [BuildMode::Core, BuildMode::MiriStd] {
+            if let Err(err) = escalate_latest(&db, &config, client, mode).await {
+                error!(?err, %mode, "Failed to escalate regressions");
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(config.interval_minutes * 60)).await;
+    }
+}
+
+/// Posts the regressions and spec changes of the latest finished nightly of the mode, once.
+/// Older nightlies are never posted, so enabling this doesn't post the whole history.
+async fn escalate_latest(
+    db: &Db,
+    config: &EscalationConfig,
+    client: &reqwest::Client,
+    mode: BuildMode,
+) -> Result<()> {
+    let Some(nightly) = db.latest_finished_nightly(mode, crate::HOST).await? else {
+        return Ok(());
+    };
+    if db.is_escalated(&nightly, mode).await? {
+        return Ok(());
+    }
+
+    let tiers = db
+        .target_meta()
+        .await?
+        .into_iter()
+        .filter_map(|meta| Some((meta.target, meta.tier?)))
+        .filter(|(_, tier)| config.tiers.contains(tier))
+        .collect::<HashMap<_, _>>();
+
+    let mut regressions = None;
+    if let Some(previous) = db.previous_nightly(&nightly, mode, crate::HOST).await? {
+        let flaky = flaky::flaky_targets(db, mode, Some(crate::HOST)).await?;
+        let targets = db
+            .regressions(&nightly, &previous, mode, crate::HOST)
+            .await?
+            .into_iter()
+            .filter(|target| !flaky.contains(target))
+            .filter_map(|target| {
+                let tier = *tiers.get(&target)?;
+                Some((target, tier))
+            })
+            .collect::<Vec<_>>();
+        let on_beta = db
+            .beta_checks(&nightly, mode, crate::HOST)
+            .await?
+            .into_iter()
+            .filter(|check| check.status == Status::Error)
+            .map(|check| check.target)
+            .collect::<HashSet<_>>();
+        if !targets.is_empty() {
+            regressions = Some((previous, targets, on_beta));
+        }
+    }
+
+    let mut spec_changes = None;
+    // Specs don't depend on the mode.
+    if mode == BuildMode::Core {
+        if let Some((previous, changes)) = target_spec::semantic_changes(db, &nightly).await? {
+            let changes = changes
+                .into_iter()
+                .filter_map(|change| Some((*tiers.get(&change.target)?, change)))
+                .collect::<Vec<_>>();
+            if !changes.is_empty() {
+                spec_changes = Some((previous, changes));
+            }
+        }
+    }
+
+    let regressions = regressions
+        .as_ref()
+        .map(|(previous, targets, on_beta)| Regressions {
+            nightly: &nightly,
+            previous,
+            mode,
+            targets,
+            on_beta,
+        });
+    let spec_changes = spec_changes
+        .as_ref()
+        .map(|(previous, changes)| SpecChanges {
+            nightly: &nightly,
+            previous,
+            changes,
+        });
+    if regressions.is_some() || spec_changes.is_some() {
+        // Everything is posted in one message per destination, so a failed post is retried on the
+        // next run without posting it again where it went through.
+        post(db, &nightly, mode, client, config, |mention| {
+            regressions
+                .iter()
+                .map(|regressions| regressions.message(config, mention))
+                .chain(
+                    spec_changes
+                        .iter()
+                        .map(|changes| changes.message(config, mention)),
+                )
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .await?;
+        info!(
+            %nightly,
+            %mode,
+            regressions = %regressions.map_or(0, |regressions| regressions.targets.len()),
+            spec_changes = %spec_changes.map_or(0, |changes| changes.changes.len()),
+            "Escalated regressions"
+        );
+    }
+
+    db.mark_escalated(&nightly, mode).await
+}
+
+struct Regressions<'a> {
+    nightly: &'a str,
+    previous: &'a str,
+    mode: BuildMode,
+    /// The targets with their tier.
+    targets: &'a [(String, i32)],
+    /// The targets that also fail with the beta of the same day.
+    on_beta: &'a HashSet<String>,
+}
+
+impl Regressions<'_> {
+    /// A Markdown message listing the targets, mentioning their team with `mention`.
+    fn message(&self, config: &EscalationConfig, mention: fn(&str) -> String) -> String {
+        let mut message = format!(
+            "Targets that passed on nightly-{} regressed in {} on nightly-{}:\n",
+            self.previous, self.mode, self.nightly
+        );
+        for (target, tier) in self.targets {
+            let name = match &config.site_url {
+                Some(url) => format!(
+                    "[`{target}`]({}/build?nightly={}&target={target}&mode={})",
+                    url.trim_end_matches('/'),
+                    self.nightly,
+                    self.mode
+                ),
+                None => format!("`{target}`"),
+            };
+            message.push_str(&format!("- {name} (tier {tier})"));
+            if self.on_beta.contains(target) {
+                message.push_str(", already on beta");
+            }
+            if let Some(team) = team_for(target, config) {
+                message.push_str(&format!(", cc {}", mention(team)));
+            }
+            message.push('\n');
+        }
+        message
+    }
+}
+
+struct SpecChanges<'a> {
+    nightly: &'a str,
+    previous: &'a str,
+    /// The changes with the tier of their target.
+    changes: &'a [(i32, SemanticChange)],
+}
+
+impl SpecChanges<'_> {
+    /// A Markdown message listing the changes, mentioning the team of the target with `mention`.
+    fn message(&self, config: &EscalationConfig, mention: fn(&str) -> String) -> String {
+        let mut message = format!(
+            "Target specs changed on nightly-{} since nightly-{}:\n",
+            self.nightly, self.previous
+        );
+        for (tier, SemanticChange { target, change }) in self.changes {
+            let name = match &config.site_url {
+                Some(url) => format!(
+                    "[`{target}`]({}/api/v1/targets/{target}/spec-diff?from={}&to={})",
+                    url.trim_end_matches('/'),
+                    self.previous,
+                    self.nightly
+                ),
+                None => format!("`{target}`"),
+            };
+            let value = |value: &Option<serde_json::Value>| match value {
+                Some(serde_json::Value::String(value)) => format!("`{value}`"),
+                Some(value) => format!("`{value}`"),
+                None => "unset".to_owned(),
+            };
+            message.push_str(&format!(
+                "- {name} (tier {tier}): `{}` changed from {} to {}",
+                change.key,
+                value(&change.before),
+                value(&change.after)
+            ));
+            if let Some(team) = team_for(target, config) {
+                message.push_str(&format!(", cc {}", mention(team)));
+            }
+            message.push('\n');
+        }
+        message
+    }
+}
+
+/// Posts the message to the configured Zulip stream and GitHub issue, built with their way of mentioning a team.
+/// Destinations that already got the message for the nightly are skipped.
+async fn post(
+    db: &Db,
+    nightly: &str,
+    mode: BuildMode,
+    client: &reqwest::Client,
+    config: &EscalationConfig,
+    message: impl Fn(fn(&str) -> String) -> String,
+) -> Result<()> {
+    if config.zulip_url.is_some() && !db.is_escalated_to(nightly, mode, ZULIP).await? {
+        post_to_zulip(client, config, &message(zulip_mention)).await?;
+        db.mark_escalated_to(nightly, mode, ZULIP).await?;
+    }
+    if config.github_issue.is_some() && !db.is_escalated_to(nightly, mode, GITHUB).await? {
+        comment_on_github(client, config, &message(github_mention)).await?;
+        db.mark_escalated_to(nightly, mode, GITHUB).await?;
+    }
+    Ok(())
+}
+
+/// The destinations in `escalated_destination`.
+const ZULIP: &str = "zulip";
+const GITHUB: &str = "github";
+
+/// The team of the longest pattern that matches the target.
+fn team_for<'a>(target: &str, config: &'a EscalationConfig) -> Option<&'a str> {
+    config
+        .teams
+        .iter()
+        .filter(|(pattern, _)| wildcard_match(pattern, target))
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, team)| team.as_str())
+}
+
+fn zulip_mention(team: &str) -> String {
+    format!("@*T-{team}*")
+}
+
+fn github_mention(team: &str) -> String {
+    format!("@rust-lang/{team}")
+}
+
+#[derive(Serialize)]
+struct ZulipMessage<'a> {
+    r#type: &'static str,
+    to: &'a str,
+    topic: &'a str,
+    content: &'a str,
+}
+
+async fn post_to_zulip(
+    client: &reqwest::Client,
+    config: &EscalationConfig,
+    content: &str,
+) -> Result<()> {
+    let (Some(url), Some(email), Some(api_key), Some(stream)) = (
+        &config.zulip_url,
+        &config.zulip_email,
+        &config.zulip_api_key,
+        &config.zulip_stream,
+    ) else {
+        unreachable!("the config is validated on startup");
+    };
+    let request = client
+        .post(format!("{}/api/v1/messages", url.trim_end_matches('/')))
+        .basic_auth(email, Some(api_key))
+        .form(&ZulipMessage {
+            r#type: "stream",
+            to: stream,
+            topic: &config.zulip_topic,
+            content,
+        });
+    crate::http::send(request)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err("posting to Zulip")?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct GithubComment<'a> {
+    body: &'a str,
+}
+
+async fn comment_on_github(
+    client: &reqwest::Client,
+    config: &EscalationConfig,
+    body: &str,
+) -> Result<()> {
+    let (Some(repo), Some(issue), Some(token)) = (
+        &config.github_repo,
+        config.github_issue,
+        &config.github_token,
+    ) else {
+        unreachable!("the config is validated on startup");
+    };
+    let request = client
+        .post(format!(
+            "https://api.github.com/repos/{repo}/issues/{issue}/comments"
+        ))
+        .bearer_auth(token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .json(&GithubComment { body });
+    crate::http::send(request)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err("commenting on GitHub")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use crate::{
+        config::EscalationConfig,
+        db::{
+            tests::temp_db, BatchedWrite, BuildHost, BuildMode, Db, FullBuildInfo, Status,
+            TargetMeta, TargetSpec,
+        },
+        target_spec::{SemanticChange, SpecChange},
+    };
+
+    use super::{Regressions, SpecChanges};
+
+    /// A Zulip server that answers requests with the statuses in order and returns their bodies.
+    async fn zulip(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..read]);
+                    let request = String::from_utf8_lossy(&request);
+                    let Some((head, body)) = request.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break body.to_owned();
+                    }
+                };
+                bodies.push(body);
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {status} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+            }
+            bodies
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn message() {
+        let config = EscalationConfig {
+            teams: [
+                ("*".to_owned(), "compiler".to_owned()),
+                ("*-apple-*".to_owned(), "apple".to_owned()),
+            ]
+            .into(),
+            site_url: Some("https://example.com/".to_owned()),
+            ..EscalationConfig::default()
+        };
+        let regressions = Regressions {
+            nightly: "2026-10-10",
+            previous: "2026-10-09",
+            mode: BuildMode::Core,
+            targets: &[
+                ("aarch64-apple-ios".to_owned(), 2),
+                ("wasm32-wasip1".to_owned(), 2),
+            ],
+            on_beta: &HashSet::from(["wasm32-wasip1".to_owned()]),
+        };
+        assert_eq!(
+            regressions.message(&config, super::zulip_mention),
+            "Targets that passed on nightly-2026-10-09 regressed in core on nightly-2026-10-10:
+- [`aarch64-apple-ios`](https://example.com/build?nightly=2026-10-10&target=aarch64-apple-ios&mode=core) (tier 2), cc @*T-apple*
+- [`wasm32-wasip1`](https://example.com/build?nightly=2026-10-10&target=wasm32-wasip1&mode=core) (tier 2), already on beta, cc @*T-compiler*
+"
+        );
+    }
+
+    #[test]
+    fn spec_changes_message() {
+        let config = EscalationConfig {
+            teams: [("thumb*".to_owned(), "embedded".to_owned())].into(),
+            ..EscalationConfig::default()
+        };
+        let changes = SpecChanges {
+            nightly: "2026-10-10",
+            previous: "2026-10-09",
+            changes: &[
+                (
+                    2,
+                    SemanticChange {
+                        target: "thumbv6m-none-eabi".to_owned(),
+                        change: SpecChange {
+                            key: "max-atomic-width".to_owned(),
+                            before: Some(json!(32)),
+                            after: Some(json!(0)),
+                        },
+                    },
+                ),
+                (
+                    2,
+                    SemanticChange {
+                        target: "x86_64-unknown-none".to_owned(),
+                        change: SpecChange {
+                            key: "panic-strategy".to_owned(),
+                            before: Some(json!("abort")),
+                            after: None,
+                        },
+                    },
+                ),
+            ],
+        };
+        assert_eq!(
+            changes.message(&config, super::github_mention),
+            "Target specs changed on nightly-2026-10-10 since nightly-2026-10-09:
+- `thumbv6m-none-eabi` (tier 2): `max-atomic-width` changed from `32` to `0`, cc @rust-lang/embedded
+- `x86_64-unknown-none` (tier 2): `panic-strategy` changed from `abort` to unset
+"
+        );
+    }
+
+    /// A regression and a spec change of a tier 2 target on 2026-10-10.
+    async fn seed_regression(db: &Db) {
+        let target = "thumbv6m-none-eabi";
+        for (nightly, status, max_atomic_width) in [
+            ("2026-10-09", Status::Pass, 32),
+            ("2026-10-10", Status::Error, 0),
+        ] {
+            let build = FullBuildInfo {
+                nightly: nightly.to_owned(),
+                target: target.to_owned(),
+                status,
+                stderr: String::new(),
+                original_stderr_bytes: None,
+                mode: BuildMode::Core,
+                timing: Default::default(),
+                host: BuildHost {
+                    host: crate::HOST.to_owned(),
+                    hostname: None,
+                },
+            };
+            db.insert_batch(crate::HOST, &[BatchedWrite::Build(build, None)])
+                .await
+                .unwrap();
+            db.finish_nightly(nightly, BuildMode::Core, crate::HOST)
+                .await
+                .unwrap();
+            db.insert_target_specs(&[TargetSpec {
+                target: target.to_owned(),
+                nightly: nightly.to_owned(),
+                spec: json!({ "max-atomic-width": max_atomic_width }).to_string(),
+            }])
+            .await
+            .unwrap();
+        }
+        db.insert_target_meta(&[TargetMeta {
+            target: target.to_owned(),
+            nightly: "2026-10-10".to_owned(),
+            tier: Some(2),
+            host_tools: None,
+            std: None,
+            description: None,
+        }])
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_post_is_retried_as_a_whole() {
+        let (_dir, db) = temp_db().await;
+        seed_regression(&db).await;
+
+        let (url, zulip) = zulip(vec![500, 200]).await;
+        let config = EscalationConfig {
+            zulip_url: Some(url),
+            zulip_email: Some("bot@example.com".to_owned()),
+            zulip_api_key: Some("key".to_owned()),
+            zulip_stream: Some("t-infra".to_owned()),
+            ..EscalationConfig::default()
+        };
+        let client = crate::http::client();
+
+        assert!(
+            super::escalate_latest(&db, &config, client, BuildMode::Core)
+                .await
+                .is_err()
+        );
+        assert!(!db
+            .is_escalated("2026-10-10", BuildMode::Core)
+            .await
+            .unwrap());
+        super::escalate_latest(&db, &config, client, BuildMode::Core)
+            .await
+            .unwrap();
+        assert!(db
+            .is_escalated("2026-10-10", BuildMode::Core)
+            .await
+            .unwrap());
+        // Nothing is posted once it went through.
+        super::escalate_latest(&db, &config, client, BuildMode::Core)
+            .await
+            .unwrap();
+
+        let bodies = zulip.await.unwrap();
+        assert_eq!(bodies.len(), 2);
+        for body in bodies {
+            assert!(body.contains("regressed"), "{body}");
+            assert!(body.contains("max-atomic-width"), "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn reached_destinations_are_skipped() {
+        let (_dir, db) = temp_db().await;
+        seed_regression(&db).await;
+        db.mark_escalated_to("2026-10-10", BuildMode::Core, super::ZULIP)
+            .await
+            .unwrap();
+
+        // The server is gone, so posting to it again would fail.
+        let (url, zulip) = zulip(vec![]).await;
+        zulip.await.unwrap();
+        let config = EscalationConfig {
+            zulip_url: Some(url),
+            zulip_email: Some("bot@example.com".to_owned()),
+            zulip_api_key: Some("key".to_owned()),
+            zulip_stream: Some("t-infra".to_owned()),
+            ..EscalationConfig::default()
+        };
+        super::escalate_latest(&db, &config, crate::http::client(), BuildMode::Core)
+            .await
+            .unwrap();
+        assert!(db
+            .is_escalated("2026-10-10", BuildMode::Core)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,72 @@
+//! Finding the error that made a build fail in its log, so it can be shown without opening the log.
+
+/// Longer messages are cut off.
+const MAX_CHARS: usize = 200;
+
+/// The first error message in the output of a failed build, like ``error[E0463]: can't find crate for `std` ``.
+/// Internal compiler errors win over other errors, and the summaries at the end of the output are
+/// only used if there is nothing else.
+pub fn error_excerpt(stderr: &str) -> Option<String> {
+    let lines = || stderr.lines().map(str::trim);
+    let errors =
+        || lines().filter(|line| line.starts_with("error[E") || line.starts_with("error:"));
+    let is_summary = |line: &str| {
+        line.starts_with("error: could not compile") || line.starts_with("error: aborting due to")
+    };
+
+    let line = lines()
+        .find(|line| {
+            line.contains("internal compiler error:") || line.starts_with("thread 'rustc' panicked")
+        })
+        .or_else(|| errors().find(|line| !is_summary(line)))
+        .or_else(|| errors().next())?;
+
+    Some(match line.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::error_excerpt;
+
+    #[test]
+    fn first_error() {
+        let stderr = "   Compiling core v0.0.0
+warning: unused variable: `x`
+error[E0463]: can't find crate for `std`
+  |
+error: could not compile `core` (lib) due to 1 previous error
+";
+        assert_eq!(
+            error_excerpt(stderr).as_deref(),
+            Some("error[E0463]: can't find crate for `std`")
+        );
+        assert_eq!(
+            error_excerpt("error: could not compile `core`\n").as_deref(),
+            Some("error: could not compile `core`")
+        );
+        assert_eq!(error_excerpt("warning: nothing failed\n"), None);
+    }
+
+    #[test]
+    fn ice() {
+        let stderr = "error: linking with `cc` failed
+thread 'rustc' panicked at compiler/rustc_codegen_llvm/src/back/write.rs:12:5:
+error: the compiler unexpectedly panicked. this is a bug.
+";
+        assert_eq!(
+            error_excerpt(stderr).as_deref(),
+            Some("thread 'rustc' panicked at compiler/rustc_codegen_llvm/src/back/write.rs:12:5:")
+        );
+    }
+
+    #[test]
+    fn long_message() {
+        let stderr = format!("error: {}", "ä".repeat(300));
+        let excerpt = error_excerpt(&stderr).unwrap();
+        assert_eq!(excerpt.chars().count(), 201);
+        assert!(excerpt.ends_with('…'));
+    }
+}
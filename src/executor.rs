@@ -0,0 +1,289 @@
+//! Where the rustup, cargo and rustc processes of the builder run: on this machine, or on another
+//! one over SSH, like a big remote box or one with an exotic host architecture that shouldn't run
+//! the whole service. Remote builds use the same paths as local ones would, so the commands only
+//! have to be wrapped, and the projects in the temporary directories are written there as well.
+
+use std::{
+    ffi::OsStr,
+    path::Path,
+    process::{ExitStatus, Output, Stdio},
+};
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::SshConfig;
+
+#[derive(Debug, Clone)]
+pub enum Executor {
+    Local,
+    Ssh(Ssh),
+}
+
+#[derive(Debug, Clone)]
+pub struct Ssh {
+    /// Like `builder@arm-box.example.com`.
+    destination: String,
+    options: Vec<String>,
+}
+
+/// The exit code of `ssh` itself when the connection fails, instead of the one of the command.
+const SSH_CONNECTION_FAILED: i32 = 255;
+
+impl Executor {
+    pub fn new(config: &SshConfig) -> Self {
+        match &config.destination {
+            Some(destination) => Self::Ssh(Ssh {
+                destination: destination.clone(),
+                options: config.options.clone(),
+            }),
+            None => Self::Local,
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Local)
+    }
+
+    /// The name of the remote machine, which builds should be recorded with.
+    pub fn remote_hostname(&self) -> Option<&str> {
+        match self {
+            Self::Local => None,
+            Self::Ssh(ssh) => Some(
+                ssh.destination
+                    .rsplit_once('@')
+                    .map_or(ssh.destination.as_str(), |(_, host)| host),
+            ),
+        }
+    }
+
+    /// The command that runs `command` where builds run. Over SSH, it runs in the same directory
+    /// with the same variables, except for `PATH`, which is the one of the remote machine.
+    pub fn prepare(&self, command: std::process::Command) -> std::process::Command {
+        match self {
+            Self::Local => command,
+            Self::Ssh(ssh) => ssh.command(&remote_script(&command)),
+        }
+    }
+
+    /// Like `tokio::process::Command::output`, where builds run.
+    pub async fn output(&self, command: &mut tokio::process::Command) -> std::io::Result<Output> {
+        match self {
+            Self::Local => command.output().await,
+            Self::Ssh(ssh) => {
+                tokio::process::Command::from(ssh.command(&remote_script(command.as_std())))
+                    .output()
+                    .await
+            }
+        }
+    }
+
+    /// Why the command failed because of the connection to the remote machine, if it did.
+    pub fn connection_error(&self, status: ExitStatus) -> Option<String> {
+        match self {
+            Self::Ssh(ssh) if status.code() == Some(SSH_CONNECTION_FAILED) => {
+                Some(format!("the SSH connection to {} failed", ssh.destination))
+            }
+            _ => None,
+        }
+    }
+
+    /// Creates the directory where builds run, if it isn't this machine.
+    pub async fn create_remote_dir(&self, dir: &Path) -> Result<()> {
+        match self {
+            Self::Local => Ok(()),
+            Self::Ssh(ssh) => ssh
+                .run(&format!("mkdir -p {}", quote(dir.as_os_str())), None)
+                .await
+                .wrap_err_with(|| format!("creating {} on the remote machine", dir.display())),
+        }
+    }
+
+    /// Removes the directory where builds run, if it isn't this machine. Local temporary
+    /// directories remove themselves.
+    pub async fn remove_remote_dir(&self, dir: &Path) -> Result<()> {
+        match self {
+            Self::Local => Ok(()),
+            Self::Ssh(ssh) => ssh
+                .run(&format!("rm -rf {}", quote(dir.as_os_str())), None)
+                .await
+                .wrap_err_with(|| format!("removing {} on the remote machine", dir.display())),
+        }
+    }
+
+    pub async fn write_file(&self, path: &Path, contents: &str) -> Result<()> {
+        match self {
+            Self::Local => std::fs::write(path, contents)
+                .wrap_err_with(|| format!("writing to {}", path.display())),
+            Self::Ssh(ssh) => ssh
+                .run(
+                    &format!("cat > {}", quote(path.as_os_str())),
+                    Some(contents.as_bytes()),
+                )
+                .await
+                .wrap_err_with(|| format!("writing to {} on the remote machine", path.display())),
+        }
+    }
+
+    pub async fn read_file(&self, path: &Path) -> Result<String> {
+        match self {
+            Self::Local => std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("reading {}", path.display())),
+            Self::Ssh(ssh) => {
+                let output = ssh
+                    .output(&format!("cat {}", quote(path.as_os_str())), None)
+                    .await?;
+                if !output.status.success() {
+                    bail!(
+                        "reading {} on the remote machine: {}",
+                        path.display(),
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                String::from_utf8(output.stdout)
+                    .wrap_err_with(|| format!("{} is invalid UTF-8", path.display()))
+            }
+        }
+    }
+
+    pub async fn exists(&self, path: &Path) -> Result<bool> {
+        match self {
+            Self::Local => Ok(path.exists()),
+            Self::Ssh(ssh) => {
+                let output = ssh
+                    .output(&format!("test -e {}", quote(path.as_os_str())), None)
+                    .await?;
+                if output.status.code() == Some(SSH_CONNECTION_FAILED) {
+                    bail!("connecting to {}", ssh.destination);
+                }
+                Ok(output.status.success())
+            }
+        }
+    }
+
+    /// The disk space the directory takes up where builds run, in bytes.
+    pub async fn remote_dir_size(&self, dir: &Path) -> Result<Option<u64>> {
+        let Self::Ssh(ssh) = self else {
+            return Ok(None);
+        };
+        let output = ssh
+            .output(&format!("du -sk {}", quote(dir.as_os_str())), None)
+            .await?;
+        if !output.status.success() {
+            return Ok(Some(0));
+        }
+        let kib = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .next()
+            .and_then(|kib| kib.parse::<u64>().ok())
+            .unwrap_or_default();
+        Ok(Some(kib * 1024))
+    }
+}
+
+impl Ssh {
+    fn command(&self, script: &str) -> std::process::Command {
+        let mut command = std::process::Command::new("ssh");
+        // Never wait for a password or host key prompt nobody answers.
+        command
+            .args(["-o", "BatchMode=yes"])
+            .args(&self.options)
+            .arg(&self.destination)
+            .arg(script);
+        command
+    }
+
+    async fn output(&self, script: &str, stdin: Option<&[u8]>) -> Result<Output> {
+        let mut child = tokio::process::Command::from(self.command(script))
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err("spawning ssh")?;
+        if let Some(stdin) = stdin {
+            let mut pipe = child.stdin.take().unwrap();
+            pipe.write_all(stdin).await.wrap_err("writing to ssh")?;
+        }
+        child.wait_with_output().await.wrap_err("waiting for ssh")
+    }
+
+    async fn run(&self, script: &str, stdin: Option<&[u8]>) -> Result<()> {
+        let output = self.output(script, stdin).await?;
+        if !output.status.success() {
+            bail!(
+                "`{script}` failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A shell command that runs `command` like it would run locally, for the shell `ssh` starts.
+fn remote_script(command: &std::process::Command) -> String {
+    let mut script = String::new();
+    if let Some(dir) = command.get_current_dir() {
+        script.push_str(&format!("cd {} && ", quote(dir.as_os_str())));
+    }
+    // The variables of the builds are all set explicitly, so the remote ones are cleared as well.
+    script.push_str(r#"exec env -i PATH="$PATH""#);
+    for (name, value) in command.get_envs() {
+        let Some(value) = value.filter(|_| name != "PATH") else {
+            continue;
+        };
+        let mut assignment = name.to_owned();
+        assignment.push("=");
+        assignment.push(value);
+        script.push(' ');
+        script.push_str(&quote(&assignment));
+    }
+    for part in std::iter::once(command.get_program()).chain(command.get_args()) {
+        script.push(' ');
+        script.push_str(&quote(part));
+    }
+    script
+}
+
+/// Quotes the value for a POSIX shell.
+fn quote(value: &OsStr) -> String {
+    format!("'{}'", value.to_string_lossy().replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    #[test]
+    fn remote_script() {
+        let mut command = std::process::Command::new("cargo");
+        command
+            .env_clear()
+            .env("PATH", "/usr/bin")
+            .env("HOME", "/srv/build home")
+            .env("RUSTFLAGS", "--cfg 'quoted'")
+            .args([
+                "+nightly-2026-10-10",
+                "build",
+                "--target",
+                "x86_64-unknown-none",
+            ])
+            .current_dir("/tmp/.tmpAbC");
+        assert_eq!(
+            super::remote_script(&command),
+            r#"cd '/tmp/.tmpAbC' && exec env -i PATH="$PATH" 'HOME=/srv/build home' 'RUSTFLAGS=--cfg '\''quoted'\''' 'cargo' '+nightly-2026-10-10' 'build' '--target' 'x86_64-unknown-none'"#
+        );
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(super::quote(OsStr::new("plain")), "'plain'");
+        assert_eq!(super::quote(OsStr::new("it's")), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,174 @@
+//! Dumping the whole dataset into files that are easier to analyze than the database.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use arrow::{
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    json::reader::{Decoder, ReaderBuilder},
+};
+use color_eyre::{eyre::Context, Result};
+use futures::StreamExt;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use serde::Serialize;
+use tracing::info;
+
+use crate::db::Db;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    Jsonl,
+    Parquet,
+}
+
+/// How many rows are written at once.
+pub const CHUNK_SIZE: usize = 10_000;
+
+/// Writes `builds`, `nightlies` and `toolchains` files into `out`.
+pub async fn export(db: &Db, format: ExportFormat, out: &Path, with_logs: bool) -> Result<()> {
+    std::fs::create_dir_all(out).wrap_err("creating output directory")?;
+
+    let mut builds = TableWriter::create(out, "builds", format, build_schema())?;
+    let mut records = db.build_records(with_logs, None).chunks(CHUNK_SIZE);
+    let mut count = 0;
+    while let Some(chunk) = records.next().await {
+        let chunk = chunk
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("reading builds")?;
+        builds.write(&chunk)?;
+        count += chunk.len();
+    }
+    builds.finish()?;
+    info!(%count, "Exported builds");
+
+    let nightlies = db.nightly_records().await?;
+    let mut writer = TableWriter::create(out, "nightlies", format, nightly_schema())?;
+    for chunk in nightlies.chunks(CHUNK_SIZE) {
+        writer.write(chunk)?;
+    }
+    writer.finish()?;
+    info!(count = %nightlies.len(), "Exported nightlies");
+
+    let toolchains = db.all_toolchain_info().await?;
+    let mut writer = TableWriter::create(out, "toolchains", format, toolchain_schema())?;
+    for chunk in toolchains.chunks(CHUNK_SIZE) {
+        writer.write(chunk)?;
+    }
+    writer.finish()?;
+    info!(count = %toolchains.len(), "Exported toolchains");
+
+    Ok(())
+}
+
+fn build_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("nightly", DataType::Utf8, false),
+        Field::new("target", DataType::Utf8, false),
+        Field::new("mode", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("started_at", DataType::Int64, true),
+        Field::new("finished_at", DataType::Int64, true),
+        Field::new("exit_code", DataType::Int32, true),
+        Field::new("peak_rss_kib", DataType::Int64, true),
+        Field::new("host", DataType::Utf8, false),
+        Field::new("hostname", DataType::Utf8, true),
+        Field::new("attempt", DataType::Int64, false),
+        Field::new("superseded_at", DataType::Int64, true),
+        Field::new("stderr", DataType::Utf8, true),
+        Field::new("error_excerpt", DataType::Utf8, true),
+    ]))
+}
+
+fn nightly_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("nightly", DataType::Utf8, false),
+        Field::new("mode", DataType::Utf8, false),
+        Field::new("host", DataType::Utf8, false),
+        Field::new("broken", DataType::Boolean, false),
+        Field::new("toolchain_error", DataType::Utf8, true),
+    ]))
+}
+
+fn toolchain_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("nightly", DataType::Utf8, false),
+        Field::new("rustc_version", DataType::Utf8, false),
+        Field::new("commit_hash", DataType::Utf8, true),
+        Field::new("llvm_version", DataType::Utf8, true),
+        Field::new("host", DataType::Utf8, false),
+        Field::new("cargo_version", DataType::Utf8, false),
+        Field::new("miri_version", DataType::Utf8, true),
+        Field::new("rustup_version", DataType::Utf8, true),
+        Field::new("verification", DataType::Utf8, true),
+    ]))
+}
+
+enum TableWriter {
+    Jsonl(BufWriter<File>),
+    /// Rows are turned into Arrow batches through their `Serialize` impl, which must match the schema.
+    Parquet(Box<ArrowWriter<File>>, Decoder),
+}
+
+impl TableWriter {
+    fn create(out: &Path, name: &str, format: ExportFormat, schema: SchemaRef) -> Result<Self> {
+        let extension = match format {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        };
+        let path = out.join(format!("{name}.{extension}"));
+        let file = File::create(&path).wrap_err_with(|| format!("creating {}", path.display()))?;
+        Ok(match format {
+            ExportFormat::Jsonl => Self::Jsonl(BufWriter::new(file)),
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                    .build();
+                let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+                    .wrap_err("creating parquet writer")?;
+                let decoder = ReaderBuilder::new(schema)
+                    .with_batch_size(CHUNK_SIZE)
+                    .build_decoder()
+                    .wrap_err("creating arrow decoder")?;
+                Self::Parquet(Box::new(writer), decoder)
+            }
+        })
+    }
+
+    fn write<T: Serialize>(&mut self, rows: &[T]) -> Result<()> {
+        match self {
+            Self::Jsonl(writer) => {
+                for row in rows {
+                    serde_json::to_writer(&mut *writer, row).wrap_err("writing row")?;
+                    writer.write_all(b"\n").wrap_err("writing row")?;
+                }
+            }
+            Self::Parquet(writer, decoder) => {
+                decoder.serialize(rows).wrap_err("converting rows")?;
+                if let Some(batch) = decoder.flush().wrap_err("converting rows")? {
+                    writer.write(&batch).wrap_err("writing rows")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Jsonl(mut writer) => writer.flush().wrap_err("writing file"),
+            Self::Parquet(writer, _) => {
+                writer.close().wrap_err("writing parquet footer")?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Showing the latest results of other instances next to the ones of this instance, so builders
+//! run by others, like on hosts this instance doesn't have, show up on the same site.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use color_eyre::{eyre::Context, Result};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    db::{BuildInfo, BuildMode, Db, FederatedBuild, Status},
+    triple::TargetTriple,
+};
+
+/// The source of the results of this instance in the merged view. Peers can't have this name.
+pub const LOCAL_SOURCE: &str = "local";
+
+/// A result in the merged view, with the instance it came from.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SourcedBuild {
+    /// `local` or the name of the peer.
+    pub source: String,
+    /// The base URL of the peer, none for this instance.
+    pub source_url: Option<String>,
+    pub nightly: String,
+    pub target: String,
+    pub mode: BuildMode,
+    pub host: String,
+    pub status: Status,
+    pub tier: Option<i32>,
+    pub error_excerpt: Option<String>,
+    /// When it was pulled from the peer.
+    pub fetched_at: Option<i64>,
+}
+
+/// Periodically pulls the latest results of the peers, by name.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_sync(db: Db, peers: BTreeMap<String, String>, interval: Duration) {
+    let client = crate::http::client();
+    let names = peers.keys().cloned().collect::<Vec<_>>();
+    if let Err(err) = db.retain_federated_peers(&names).await {
+        error!(?err, "Failed to remove results of removed peers");
+    }
+    loop {
+        for (peer, url) in &peers {
+            if let Err(err) = sync(&db, client, peer, url.trim_end_matches('/')).await {
+                error!(?err, %peer, "Failed to sync from peer");
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn sync(db: &Db, client: &reqwest::Client, peer: &str, url: &str) -> Result<()> {
+    let builds = crate::http::send(client.get(format!("{url}/api/v1/latest")))
+        .await
+        .wrap_err_with(|| format!("fetching {url}/api/v1/latest"))?
+        .json::<Vec<BuildInfo>>()
+        .await
+        .wrap_err_with(|| format!("decoding {url}/api/v1/latest"))?;
+    let fetched_at = time::OffsetDateTime::now_utc().unix_timestamp();
+    let (builds, invalid): (Vec<_>, Vec<_>) = builds
+        .into_iter()
+        .partition(|build| TargetTriple::is_plain(&build.target));
+    if !invalid.is_empty() {
+        warn!(%peer, builds = %invalid.len(), "Ignoring builds of targets with invalid names");
+    }
+    let builds = builds
+        .into_iter()
+        .map(|build| FederatedBuild {
+            peer: peer.to_owned(),
+            peer_url: url.to_owned(),
+            nightly: build.nightly,
+            target: build.target,
+            mode: build.mode,
+            host: build.host.host,
+            status: build.status,
+            tier: build.tier,
+            error_excerpt: build.error_excerpt,
+            fetched_at,
+        })
+        .collect::<Vec<_>>();
+    db.replace_federated_builds(peer, &builds).await?;
+    info!(%peer, builds = %builds.len(), "Synced from peer");
+    Ok(())
+}
+
+/// The results of this instance and of the peers, ordered by target, mode, host and then source.
+pub fn merged(local: Vec<BuildInfo>, federated: Vec<FederatedBuild>) -> Vec<SourcedBuild> {
+    let local = local.into_iter().map(|build| SourcedBuild {
+        source: LOCAL_SOURCE.to_owned(),
+        source_url: None,
+        nightly: build.nightly,
+        target: build.target,
+        mode: build.mode,
+        host: build.host.host,
+        status: build.status,
+        tier: build.tier,
+        error_excerpt: build.error_excerpt,
+        fetched_at: None,
+    });
+    let federated = federated.into_iter().map(|build| SourcedBuild {
+        source: build.peer,
+        source_url: Some(build.peer_url),
+        nightly: build.nightly,
+        target: build.target,
+        mode: build.mode,
+        host: build.host,
+        status: build.status,
+        tier: build.tier,
+        error_excerpt: build.error_excerpt,
+        fetched_at: Some(build.fetched_at),
+    });
+    let mut builds = local.chain(federated).collect::<Vec<_>>();
+    // Sorting is stable, so the results of this instance come first.
+    builds.sort_by(|a, b| (&a.target, a.mode, &a.host).cmp(&(&b.target, b.mode, &b.host)));
+    builds
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{
+        BuildHost, BuildInfo,
+        BuildMode::{Core, MiriStd},
+        BuildTiming, FederatedBuild,
+        Status::{Error, Pass},
+    };
+
+    #[test]
+    fn merged() {
+        let local = BuildInfo {
+            nightly: "2026-10-16".into(),
+            target: "wasm32-wasip1".into(),
+            status: Pass,
+            mode: Core,
+            timing: BuildTiming::default(),
+            host: BuildHost {
+                host: "x86_64-unknown-linux-gnu".into(),
+                hostname: None,
+            },
+            attempt: 1,
+            tier: Some(2),
+            error_excerpt: None,
+        };
+        let federated = |target: &str, mode, host: &str| FederatedBuild {
+            peer: "arm".into(),
+            peer_url: "https://arm.example.com".into(),
+            nightly: "2026-10-15".into(),
+            target: target.into(),
+            mode,
+            host: host.into(),
+            status: Error,
+            tier: Some(2),
+            error_excerpt: Some("error: linking failed".into()),
+            fetched_at: 1_792_000_000,
+        };
+        let merged = super::merged(
+            vec![local],
+            vec![
+                federated("wasm32-wasip1", MiriStd, "aarch64-unknown-linux-gnu"),
+                federated("wasm32-wasip1", Core, "x86_64-unknown-linux-gnu"),
+                federated("aarch64-apple-ios", Core, "aarch64-unknown-linux-gnu"),
+            ],
+        );
+        let order = merged
+            .iter()
+            .map(|build| (build.target.as_str(), build.mode, build.source.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                ("aarch64-apple-ios", Core, "arm"),
+                ("wasm32-wasip1", Core, "local"),
+                ("wasm32-wasip1", Core, "arm"),
+                ("wasm32-wasip1", MiriStd, "arm"),
+            ]
+        );
+        assert_eq!(merged[1].source_url, None);
+        assert_eq!(
+            merged[2].source_url.as_deref(),
+            Some("https://arm.example.com")
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Detecting targets whose status changes without a reason in the compiler, likely because of
+//! the builder or nondeterminism. Their regressions are not worth reporting until they stabilize.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use color_eyre::Result;
+
+use crate::db::{BuildMode, Db, Status};
+
+/// How many of the most recent nightlies are looked at. Targets stop being flaky
+/// once their status stops changing for this long.
+const WINDOW: i64 = 14;
+/// How often the status must change within the window to count as flaky, even with compiler changes.
+const MIN_FLIPS: usize = 3;
+
+/// The targets that are flaky in the mode on the host, or on any host if it is `None`.
+pub async fn flaky_targets(
+    db: &Db,
+    mode: BuildMode,
+    host: Option<&str>,
+) -> Result<BTreeSet<String>> {
+    let mut histories = BTreeMap::<_, Vec<_>>::new();
+    for (target, status, commit_hash) in db.recent_builds(mode, host, WINDOW).await? {
+        histories
+            .entry(target)
+            .or_default()
+            .push((status, commit_hash));
+    }
+    let mut flaky = histories
+        .into_iter()
+        .filter(|(_, history)| is_flaky(history))
+        .map(|(target, _)| target)
+        .collect::<BTreeSet<_>>();
+    flaky.extend(db.rerun_status_changes(mode, host, WINDOW).await?);
+    Ok(flaky)
+}
+
+/// Whether the status, oldest first and with the compiler commit, changes without the compiler
+/// changing, or changes back and forth too often.
+fn is_flaky(history: &[(Status, Option<String>)]) -> bool {
+    let mut flips = 0;
+    for pair in history.windows(2) {
+        let [(before, before_commit), (after, after_commit)] = pair else {
+            unreachable!("windows of two");
+        };
+        if before == after {
+            continue;
+        }
+        if before_commit.is_some() && before_commit == after_commit {
+            return true;
+        }
+        flips += 1;
+    }
+    flips >= MIN_FLIPS
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Status::{Error, Pass};
+
+    #[test]
+    fn is_flaky() {
+        let history = |statuses: &[(crate::db::Status, &str)]| {
+            statuses
+                .iter()
+                .map(|(status, commit)| (*status, Some(commit.to_string())))
+                .collect::<Vec<_>>()
+        };
+        assert!(!super::is_flaky(&history(&[
+            (Pass, "a"),
+            (Error, "b"),
+            (Error, "c")
+        ])));
+        assert!(super::is_flaky(&history(&[(Pass, "a"), (Error, "a")])));
+        assert!(super::is_flaky(&history(&[
+            (Pass, "a"),
+            (Error, "b"),
+            (Pass, "c"),
+            (Error, "d")
+        ])));
+        assert!(!super::is_flaky(&[(Pass, None), (Error, None)]));
+    }
+}
@@ -0,0 +1,222 @@
+//! What differs between the operating systems the builder runs on: Linux, macOS and Windows.
+//! Builds behave differently on them too, like `cargo miri setup` or targets that need a C compiler,
+//! which is recorded since results are kept separately for every host.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus},
+};
+
+/// Variables the processes of builds can't run without on this OS, passed on next to
+/// `build.pass_env`. On Windows, they find system libraries, temporary directories and the MSVC
+/// tools with them, and the temporary directory of macOS is different for every user.
+pub const ESSENTIAL_ENV: &[&str] = if cfg!(windows) {
+    &[
+        "SYSTEMROOT",
+        "SYSTEMDRIVE",
+        "WINDIR",
+        "COMSPEC",
+        "PATHEXT",
+        "TEMP",
+        "TMP",
+        "USERPROFILE",
+        "APPDATA",
+        "LOCALAPPDATA",
+        "PROGRAMDATA",
+        "PROGRAMFILES",
+        "PROGRAMFILES(X86)",
+    ]
+} else if cfg!(target_os = "macos") {
+    &["TMPDIR"]
+} else {
+    &[]
+};
+
+/// Like `std::fs::canonicalize`, but without the `\\?\` prefix it adds on Windows, which cargo and
+/// linkers don't understand in all places.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let path = std::fs::canonicalize(path)?;
+    if cfg!(windows) {
+        if let Some(path) = path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+            return Ok(PathBuf::from(path));
+        }
+    }
+    Ok(path)
+}
+
+/// Whether the process was killed with `SIGKILL`, like by the OOM killer. Windows has no signals.
+pub fn killed(status: ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal() == Some(libc::SIGKILL)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        false
+    }
+}
+
+/// The name of this machine.
+pub fn hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        // SAFETY: The buffer is valid for its length.
+        if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+            return None;
+        }
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        Some(String::from_utf8_lossy(&name[..len]).into_owned())
+    }
+    #[cfg(not(unix))]
+    {
+        std::env::var("COMPUTERNAME").ok()
+    }
+}
+
+/// The load average of the last minute. Windows doesn't have one.
+pub fn load_average() -> Option<f64> {
+    #[cfg(unix)]
+    {
+        let mut load = [0.0];
+        // SAFETY: The buffer has room for the one sample asked for.
+        (unsafe { libc::getloadavg(load.as_mut_ptr(), 1) } == 1).then_some(load[0])
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// The free disk space for unprivileged users on the file system of the path.
+pub fn available_bytes(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: statvfs is a plain C struct.
+        let mut stat = unsafe { std::mem::zeroed::<libc::statvfs>() };
+        // SAFETY: The path is a valid C string and the pointer is valid.
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        #[allow(
+            clippy::unnecessary_cast,
+            reason = "the types differ between platforms"
+        )]
+        Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        let path = path
+            .as_os_str()
+            .encode_wide()
+            .chain([0])
+            .collect::<Vec<_>>();
+        let mut available = 0;
+        // SAFETY: The path is nul-terminated and the other pointers may be null.
+        let ok = unsafe {
+            windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW(
+                path.as_ptr(),
+                &mut available,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        (ok != 0).then_some(available)
+    }
+}
+
+/// Waits for the child like `Child::wait`, also returning the peak resident memory in KiB of its
+/// largest process, including the ones it spawned, if the OS reports it.
+pub fn wait_with_peak_rss(child: Child) -> io::Result<(ExitStatus, Option<i64>)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        // `Child::wait` doesn't report the resource usage, so reap the process ourselves.
+        let pid = child.id() as libc::pid_t;
+        let mut status = 0;
+        // SAFETY: rusage is a plain C struct.
+        let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+        loop {
+            // SAFETY: The pointers are valid and the process is our child that nothing else waits for.
+            if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } != -1 {
+                break;
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+        // Linux reports it in KiB, macOS in bytes.
+        let peak_rss_kib = if cfg!(target_os = "macos") {
+            usage.ru_maxrss / 1024
+        } else {
+            usage.ru_maxrss
+        };
+        #[allow(
+            clippy::unnecessary_cast,
+            reason = "the types differ between platforms"
+        )]
+        Ok((ExitStatus::from_raw(status), Some(peak_rss_kib as i64)))
+    }
+    #[cfg(windows)]
+    {
+        windows::wait_with_peak_rss(child)
+    }
+}
+
+/// Windows only reports the peak memory of the processes in a job object, which the child is put in
+/// right after it started. The processes it spawns later end up in the same job.
+#[cfg(windows)]
+mod windows {
+    use std::{
+        io,
+        os::windows::io::AsRawHandle,
+        process::{Child, ExitStatus},
+    };
+
+    use windows_sys::Win32::{
+        Foundation::CloseHandle,
+        System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            QueryInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        },
+    };
+
+    pub fn wait_with_peak_rss(mut child: Child) -> io::Result<(ExitStatus, Option<i64>)> {
+        // SAFETY: Both attributes may be null.
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        // SAFETY: The handles are valid, and the child is alive until it is waited for.
+        let assigned =
+            !job.is_null() && unsafe { AssignProcessToJobObject(job, child.as_raw_handle()) } != 0;
+        let status = child.wait();
+        let mut peak_rss_kib = None;
+        if assigned {
+            // SAFETY: The struct is plain data.
+            let mut info = unsafe { std::mem::zeroed::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() };
+            // SAFETY: The buffer has the size of the struct of the information class.
+            let ok = unsafe {
+                QueryInformationJobObject(
+                    job,
+                    JobObjectExtendedLimitInformation,
+                    (&raw mut info).cast(),
+                    size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok != 0 {
+                peak_rss_kib = Some((info.PeakProcessMemoryUsed / 1024) as i64);
+            }
+        }
+        if !job.is_null() {
+            // SAFETY: The job was created above and isn't used anymore.
+            unsafe { CloseHandle(job) };
+        }
+        Ok((status?, peak_rss_kib))
+    }
+}
@@ -0,0 +1,100 @@
+//! The HTTP client for requests to other services, like static.rust-lang.org, chat and the coordinator.
+//! It is shared, so connections are reused, and retries requests that failed on the way with a backoff.
+
+use std::{sync::LazyLock, time::Duration};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use tracing::warn;
+
+const USER_AGENT: &str = concat!("does-it-build/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Mirrors download whole nightlies at once, which takes a while.
+const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// How often a request is sent before its error is returned.
+const ATTEMPTS: u32 = 4;
+/// Doubles after every attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+static CLIENT: LazyLock<reqwest::Client> =
+    LazyLock::new(|| builder().build().expect("building the HTTP client"));
+
+/// The settings of the shared client, for clients that need different ones, like another proxy.
+pub fn builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(TIMEOUT)
+}
+
+pub fn client() -> &'static reqwest::Client {
+    &CLIENT
+}
+
+pub async fn get(url: &str) -> reqwest::Result<Response> {
+    send(client().get(url)).await
+}
+
+/// Sends the request, and again after a growing delay if it failed in a way that may go away.
+/// Responses with an error status are returned like any other once the attempts run out.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 1..ATTEMPTS {
+        // Requests with a streaming body can only be sent once.
+        let Some(retry) = request.try_clone() else {
+            break;
+        };
+        let result = client.execute(retry).await;
+        let error = match &result {
+            Ok(response) if is_retryable_status(request.method(), response.status()) => {
+                response.status().to_string()
+            }
+            Err(err) if is_retryable_error(request.method(), err) => err.to_string(),
+            _ => return result,
+        };
+        warn!(attempt, %error, url = %request.url(), ?backoff, "HTTP request failed, trying again");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+    client.execute(request).await
+}
+
+/// Whether a request may be sent again without doing twice what it does. The server never got
+/// requests that failed to connect, and rejected the ones it's too busy for, but any other
+/// failure may have happened after it processed the request.
+fn is_retryable_error(method: &Method, err: &reqwest::Error) -> bool {
+    err.is_connect() || (method.is_idempotent() && err.is_timeout())
+}
+
+fn is_retryable_status(method: &Method, status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) || (method.is_idempotent() && status.is_server_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Method, StatusCode};
+
+    #[test]
+    fn is_retryable_status() {
+        assert!(super::is_retryable_status(
+            &Method::GET,
+            StatusCode::BAD_GATEWAY
+        ));
+        assert!(!super::is_retryable_status(
+            &Method::POST,
+            StatusCode::BAD_GATEWAY
+        ));
+        assert!(super::is_retryable_status(
+            &Method::POST,
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(!super::is_retryable_status(
+            &Method::GET,
+            StatusCode::NOT_FOUND
+        ));
+    }
+}
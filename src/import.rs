@@ -0,0 +1,115 @@
+//! Merging another instance's export into the database.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    path::Path,
+};
+
+use arrow::json::ArrayWriter;
+use color_eyre::{eyre::Context, Result};
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use serde::de::DeserializeOwned;
+use tracing::{info, warn};
+
+use crate::{db::Db, export::CHUNK_SIZE};
+
+const MISSING_LOG: &str = "The log of this build was not included in the imported data.\n";
+
+/// Imports the `builds`, `nightlies` and `toolchains` files written by `export` from `dir`.
+/// Data that already exists in the database wins over the imported data.
+pub async fn import(db: &Db, dir: &Path) -> Result<()> {
+    if let Some(mut reader) = TableReader::open(dir, "builds")? {
+        let mut decided = HashMap::new();
+        let mut imported = 0;
+        while let Some(chunk) = reader.next_chunk()? {
+            imported += db.import_builds(chunk, MISSING_LOG, &mut decided).await?;
+        }
+        info!(%imported, "Imported builds");
+    }
+
+    if let Some(mut reader) = TableReader::open(dir, "nightlies")? {
+        let mut imported = 0;
+        while let Some(chunk) = reader.next_chunk()? {
+            imported += db.import_nightlies(chunk).await?;
+        }
+        info!(%imported, "Imported nightlies");
+    }
+
+    if let Some(mut reader) = TableReader::open(dir, "toolchains")? {
+        let mut imported = 0;
+        while let Some(chunk) = reader.next_chunk()? {
+            imported += db.import_toolchain_info(chunk).await?;
+        }
+        info!(%imported, "Imported toolchains");
+    }
+
+    Ok(())
+}
+
+enum TableReader {
+    Jsonl(Lines<BufReader<File>>),
+    Parquet(ParquetRecordBatchReader),
+}
+
+impl TableReader {
+    /// Opens `{name}.jsonl` or `{name}.parquet`, whichever exists.
+    fn open(dir: &Path, name: &str) -> Result<Option<Self>> {
+        let jsonl = dir.join(format!("{name}.jsonl"));
+        if jsonl.exists() {
+            let file =
+                File::open(&jsonl).wrap_err_with(|| format!("opening {}", jsonl.display()))?;
+            return Ok(Some(Self::Jsonl(BufReader::new(file).lines())));
+        }
+        let parquet = dir.join(format!("{name}.parquet"));
+        if parquet.exists() {
+            let file =
+                File::open(&parquet).wrap_err_with(|| format!("opening {}", parquet.display()))?;
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .wrap_err_with(|| format!("reading {}", parquet.display()))?
+                .with_batch_size(CHUNK_SIZE)
+                .build()
+                .wrap_err_with(|| format!("reading {}", parquet.display()))?;
+            return Ok(Some(Self::Parquet(reader)));
+        }
+        warn!(%name, "No file for table in import directory, skipping it");
+        Ok(None)
+    }
+
+    fn next_chunk<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>> {
+        match self {
+            Self::Jsonl(lines) => {
+                let mut rows = Vec::new();
+                for line in lines.by_ref() {
+                    let line = line.wrap_err("reading line")?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    rows.push(serde_json::from_str(&line).wrap_err("invalid row")?);
+                    if rows.len() == CHUNK_SIZE {
+                        break;
+                    }
+                }
+                Ok((!rows.is_empty()).then_some(rows))
+            }
+            Self::Parquet(reader) => {
+                let Some(batch) = reader.next() else {
+                    return Ok(None);
+                };
+                let batch = batch.wrap_err("reading parquet")?;
+                // Going through JSON keeps the row types the same as for JSON Lines.
+                let mut writer = ArrayWriter::new(Vec::new());
+                writer.write(&batch).wrap_err("converting rows")?;
+                writer.finish().wrap_err("converting rows")?;
+                let json = writer.into_inner();
+                if json.is_empty() {
+                    return Ok(Some(Vec::new()));
+                }
+                serde_json::from_slice(&json)
+                    .map(Some)
+                    .wrap_err("invalid row")
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Checks which targets the standard library builds for on every nightly. The binary runs the
+//! builder and the website, but the pieces can be embedded too: [`db::Db`] for the results,
+//! [`nightlies::Nightlies`] to find the nightlies to build and
+//! [`build::build_every_target_for_toolchain`] to build them, like for a private target.
+//!
+//! The `web` feature contains the website and `builder` everything that installs toolchains and
+//! runs builds. Both are on by default.
+
+#[cfg(feature = "web")]
+mod anomaly;
+mod assumed_failing;
+pub mod backup;
+#[cfg(feature = "builder")]
+pub mod build;
+#[cfg(feature = "builder")]
+pub mod build_env;
+#[cfg(feature = "builder")]
+pub mod ci;
+pub mod config;
+pub mod coordinator;
+pub mod db;
+pub mod dist;
+pub mod escalation;
+mod excerpt;
+#[cfg(feature = "builder")]
+mod executor;
+pub mod export;
+pub mod federation;
+mod flaky;
+#[cfg(feature = "builder")]
+mod host;
+mod http;
+pub mod import;
+pub mod log_offload;
+pub mod maintainers;
+pub mod maintenance;
+#[cfg(feature = "builder")]
+mod manifest;
+#[cfg(feature = "web")]
+mod miri_support;
+pub mod mirror;
+pub mod nightlies;
+#[cfg(feature = "builder")]
+pub mod plan;
+#[cfg_attr(
+    not(all(feature = "web", feature = "builder")),
+    allow(
+        dead_code,
+        reason = "the builder reads the manifest, the website compares nightlies"
+    )
+)]
+mod prebuilt_std;
+pub mod query;
+pub mod queue;
+#[cfg(feature = "web")]
+mod report;
+pub mod retention;
+#[cfg(feature = "web")]
+mod signoff;
+pub mod snapshot;
+#[cfg_attr(
+    not(feature = "web"),
+    allow(dead_code, reason = "the diffs are only shown on the website")
+)]
+mod target_spec;
+pub mod telemetry;
+#[cfg(feature = "builder")]
+pub mod toolchain;
+#[cfg_attr(
+    not(feature = "web"),
+    allow(dead_code, reason = "targets are only filtered on the website")
+)]
+mod triple;
+#[cfg(feature = "web")]
+mod uptime;
+#[cfg(feature = "web")]
+pub mod web;
+
+/// The version of does-it-build, the commit it was built from.
+pub const VERSION: &str = env!("GIT_COMMIT");
+/// The target triple of the host builds run on. Results are stored separately for every host.
+pub const HOST: &str = env!("HOST_TARGET");
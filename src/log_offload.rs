@@ -0,0 +1,263 @@
+//! Keeping huge build logs in a local directory or object storage, with only their head and tail
+//! in the database.
+//! Some ICE backtraces are tens of megabytes, and a runaway error can produce far more, so logs
+//! are also cut down to `build.max_log_bytes` before they are stored at all.
+
+use std::{path::PathBuf, sync::Arc};
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use futures::{future::BoxFuture, FutureExt};
+use object_store::{path::Path, ObjectStore};
+
+use crate::{config::LogsConfig, db::log_hash};
+
+/// How much of an offloaded log is kept in the database, half of it from the start and half from the end.
+const KEPT_BYTES: usize = 64 * 1024;
+
+/// Where the full logs of builds are kept.
+pub trait LogStore: Send + Sync {
+    /// Stores the log as `name` and returns the key to fetch it with, or nothing if the full log
+    /// stays in the database.
+    fn put<'a>(&'a self, name: &'a str, log: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<String>>;
+}
+
+/// Logs are only stored in the database, which is how it has always been.
+pub struct DatabaseLogs;
+
+impl LogStore for DatabaseLogs {
+    fn put<'a>(&'a self, _name: &'a str, _log: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        async { Ok(None) }.boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move { bail!("log {key} was offloaded, but no log storage is configured") }.boxed()
+    }
+}
+
+/// Files in a local directory, with the file name as the key.
+pub struct FilesystemLogs {
+    dir: PathBuf,
+}
+
+impl FilesystemLogs {
+    /// Creates the directory if needed.
+    pub fn new(dir: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir).wrap_err_with(|| format!("creating log directory {dir}"))?;
+        Ok(Self { dir: dir.into() })
+    }
+}
+
+impl LogStore for FilesystemLogs {
+    fn put<'a>(&'a self, name: &'a str, log: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        async move {
+            // Written under another name first, so a crash never leaves half a log behind.
+            let partial = self.dir.join(format!("{name}.partial"));
+            tokio::fs::write(&partial, log)
+                .await
+                .wrap_err_with(|| format!("writing log to {}", partial.display()))?;
+            tokio::fs::rename(&partial, self.dir.join(name))
+                .await
+                .wrap_err_with(|| format!("moving log {name} into place"))?;
+            Ok(Some(name.to_owned()))
+        }
+        .boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move {
+            // Keys are file names the store made itself, anything else didn't come from here.
+            if key.contains(['/', '\\']) || key.starts_with('.') {
+                bail!("invalid log key {key}");
+            }
+            let bytes = tokio::fs::read(self.dir.join(key))
+                .await
+                .wrap_err_with(|| format!("reading log {key}"))?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        .boxed()
+    }
+}
+
+/// A bucket of S3-compatible object storage, with the path of the object as the key.
+pub struct S3Logs {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl S3Logs {
+    pub fn new(url: &str) -> Result<Self> {
+        let (store, prefix) =
+            crate::backup::s3_store(url).wrap_err("configuring log offload bucket")?;
+        Ok(Self { store, prefix })
+    }
+}
+
+impl LogStore for S3Logs {
+    fn put<'a>(&'a self, name: &'a str, log: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        async move {
+            let key = self.prefix.child(name);
+            self.store
+                .put(&key, log.to_owned().into())
+                .await
+                .wrap_err("uploading log")?;
+            Ok(Some(key.to_string()))
+        }
+        .boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move {
+            let bytes = self
+                .store
+                .get(&Path::from(key))
+                .await
+                .wrap_err_with(|| format!("fetching log {key}"))?
+                .bytes()
+                .await
+                .wrap_err_with(|| format!("fetching log {key}"))?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        .boxed()
+    }
+}
+
+/// Moves logs larger than the threshold out of the database into the configured log storage.
+#[derive(Clone)]
+pub struct LogOffload {
+    store: Arc<dyn LogStore>,
+    threshold: usize,
+}
+
+/// A log that was moved to the log storage.
+pub struct OffloadedLog {
+    pub key: String,
+    /// What is stored in the database instead of the full log.
+    pub truncated: String,
+}
+
+impl LogOffload {
+    /// The storage of `logs.offload_url`: a bucket for `s3://bucket/prefix`, a local directory for
+    /// anything else, and only the database if it isn't set.
+    pub fn new(config: &LogsConfig) -> Result<Self> {
+        let store: Arc<dyn LogStore> = match config.offload_url.as_deref() {
+            None => Arc::new(DatabaseLogs),
+            Some(url) if url.starts_with("s3://") => Arc::new(S3Logs::new(url)?),
+            Some(dir) => Arc::new(FilesystemLogs::new(dir)?),
+        };
+        Ok(Self {
+            store,
+            threshold: config.offload_threshold_bytes,
+        })
+    }
+
+    /// Moves the log to the log storage if it is larger than the threshold.
+    pub async fn offload(&self, log: &str) -> Result<Option<OffloadedLog>> {
+        if log.len() <= self.threshold {
+            return Ok(None);
+        }
+        let Some(key) = self
+            .store
+            .put(&format!("{}.log", log_hash(log)), log)
+            .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(OffloadedLog {
+            key,
+            truncated: truncate_middle(log, KEPT_BYTES, "see the full log"),
+        }))
+    }
+
+    pub async fn fetch(&self, key: &str) -> Result<String> {
+        self.store.get(key).await
+    }
+}
+
+/// Keeps the start and end of a log that is larger than `max` bytes, and returns the original size
+/// if anything was left out.
+pub fn cap(log: String, max: usize) -> (String, Option<i64>) {
+    if log.len() <= max {
+        return (log, None);
+    }
+    let original = log.len() as i64;
+    (
+        truncate_middle(&log, max, "the log was too large to store"),
+        Some(original),
+    )
+}
+
+/// Keeps about `keep` bytes of the start and end of the log, with `note` in the marker of the omitted part.
+fn truncate_middle(log: &str, keep: usize, note: &str) -> String {
+    if log.len() <= keep {
+        return log.to_owned();
+    }
+    let mut head = keep / 2;
+    while !log.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = log.len() - keep / 2;
+    while !log.is_char_boundary(tail) {
+        tail += 1;
+    }
+    format!(
+        "{}\n\n[... {} bytes omitted, {note} ...]\n\n{}",
+        &log[..head],
+        tail - head,
+        &log[tail..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn truncate_middle() {
+        assert_eq!(
+            super::truncate_middle("short", 10, "see the full log"),
+            "short"
+        );
+        assert_eq!(
+            super::truncate_middle("aaaabbbbcccc", 8, "see the full log"),
+            "aaaa\n\n[... 4 bytes omitted, see the full log ...]\n\ncccc"
+        );
+        // Never splits a character.
+        assert_eq!(
+            super::truncate_middle("aäbbbbbbäa", 4, "see the full log"),
+            "a\n\n[... 10 bytes omitted, see the full log ...]\n\na"
+        );
+    }
+
+    #[test]
+    fn cap() {
+        assert_eq!(
+            super::cap("short".to_owned(), 10),
+            ("short".to_owned(), None)
+        );
+        assert_eq!(
+            super::cap("aaaabbbbcccc".to_owned(), 8),
+            (
+                "aaaa\n\n[... 4 bytes omitted, the log was too large to store ...]\n\ncccc"
+                    .to_owned(),
+                Some(12)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn filesystem_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::config::LogsConfig {
+            offload_url: Some(dir.path().to_str().unwrap().to_owned()),
+            offload_threshold_bytes: 4,
+        };
+        let offload = super::LogOffload::new(&config).unwrap();
+        assert!(offload.offload("tiny").await.unwrap().is_none());
+
+        let offloaded = offload.offload("error: huge").await.unwrap().unwrap();
+        assert_eq!(offload.fetch(&offloaded.key).await.unwrap(), "error: huge");
+        assert!(offload.fetch("../secret").await.is_err());
+    }
+}
@@ -1,28 +1,321 @@
-mod build;
-mod db;
-mod nightlies;
-mod web;
+use std::{path::PathBuf, time::Duration};
 
-use color_eyre::{eyre::WrapErr, Result};
-use db::Db;
-use tracing_subscriber::EnvFilter;
+use clap::{Parser, Subcommand};
+use color_eyre::{
+    eyre::{bail, OptionExt},
+    Result,
+};
+use does_it_build::{
+    backup,
+    config::{Config, TelemetryConfig},
+    db,
+    db::Db,
+    escalation, export, federation, import, log_offload, maintainers, maintenance, mirror, query,
+    retention, snapshot,
+    telemetry::Telemetry,
+};
+#[cfg(feature = "builder")]
+use does_it_build::{build, ci, coordinator, plan, queue::DbQueue};
+#[cfg(feature = "web")]
+use does_it_build::{config, web};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-const VERSION: &str = env!("GIT_COMMIT");
+#[derive(Parser)]
+struct Cli {
+    /// TOML config file, defaults to `does-it-build.toml` if it exists.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Format of the log output on stderr.
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: LogFormat,
+    /// Print the config with the defaults and environment variables applied and exit.
+    /// Secrets are left out.
+    #[arg(long, global = true)]
+    print_config: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line, including the fields of the spans the event is in.
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Download a backup to the configured SQLite database path.
+    Restore {
+        /// File name of the backup, defaults to the newest one.
+        backup: Option<String>,
+    },
+    /// Dump all builds, nightlies and toolchains into files in a directory.
+    Export {
+        #[arg(long, value_enum, default_value = "jsonl")]
+        format: export::ExportFormat,
+        /// Directory to write the files to.
+        #[arg(long)]
+        out: PathBuf,
+        /// Leave out the build logs, which make up most of the data.
+        #[arg(long)]
+        without_logs: bool,
+    },
+    /// Merge the files written by `export` from another instance into the database.
+    /// Builds and nightlies that already exist are kept as they are.
+    Import {
+        /// Directory containing the exported files.
+        dir: PathBuf,
+    },
+    /// Print which nightlies and modes would be built next, without building anything.
+    #[cfg(feature = "builder")]
+    Plan {
+        /// How many nightly and mode combinations to show.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Build a single nightly and exit with a non-zero status if it fails.
+    #[cfg(feature = "builder")]
+    Ci {
+        #[arg(long)]
+        nightly: String,
+        #[arg(long, value_enum, default_value = "core")]
+        mode: db::BuildMode,
+        #[arg(long, value_enum, default_value = "regression")]
+        fail_on: ci::FailOn,
+        /// Only check the results of an earlier build.
+        #[arg(long)]
+        no_build: bool,
+    },
+    /// Look up the status of a target on a does-it-build instance.
+    /// Exits with 0 if it passed, 1 if it failed and 2 if the status could not be found.
+    Query {
+        #[arg(long)]
+        target: String,
+        #[arg(long, value_enum, default_value = "core")]
+        mode: db::BuildMode,
+        /// A nightly like `2026-10-10`, or `latest` for the newest one the target was built on.
+        #[arg(long, default_value = "latest")]
+        nightly: String,
+        /// Only look at builds on this host.
+        #[arg(long)]
+        host: Option<String>,
+        /// Base URL of the instance.
+        #[arg(long, default_value = "https://does-it-build.noratrieb.dev")]
+        url: String,
+    },
+    /// Build targets for a coordinator instead of using a local database.
+    /// The coordinator and the worker must share the worker token.
+    #[cfg(feature = "builder")]
+    Worker {
+        /// Base URL of the coordinator, like `https://does-it-build.example.com`.
+        coordinator: String,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info")))
-        .init();
+    let cli = Cli::parse();
+
+    if let Some(Command::Query {
+        target,
+        mode,
+        nightly,
+        host,
+        url,
+    }) = &cli.command
+    {
+        init_tracing(cli.log_format, &TelemetryConfig::default())?;
+        match query::run(url, target, *mode, nightly, host.as_deref()).await {
+            Ok(passed) => std::process::exit(if passed { 0 } else { 1 }),
+            Err(err) => {
+                eprintln!("Error: {err:?}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let config = Config::load(cli.config.as_deref())?;
+    if cli.print_config {
+        print!("{}", config.to_toml()?);
+        return Ok(());
+    }
+    // Kept until the end of `main` so the last spans are exported.
+    #[cfg_attr(not(feature = "builder"), allow(unused_variables))]
+    let telemetry = init_tracing(cli.log_format, &config.telemetry)?;
+
+    #[cfg(feature = "builder")]
+    if let Some(Command::Worker { coordinator }) = cli.command {
+        let token = config
+            .worker
+            .token
+            .ok_or_eyre("a worker token must be configured for workers")?;
+        let client = coordinator::Client::new(&coordinator, token, config.build.host().to_owned());
+        return build::background_builder(client, config.build).await;
+    }
+    if let Some(Command::Restore { backup }) = cli.command {
+        if db::is_postgres_url(&config.db.url) {
+            bail!("restoring backups is only supported for SQLite");
+        }
+        let url = config
+            .backup
+            .url
+            .ok_or_eyre("a backup URL must be configured to restore a backup")?;
+        let store = backup::BackupStore::new(&url)?;
+        return backup::restore(&store, backup, &config.db.url).await;
+    }
+
+    let db = Db::open(&config.db).await?;
+    db.migrate().await?;
+
+    let log_offload = log_offload::LogOffload::new(&config.logs)?;
+    #[cfg(feature = "builder")]
+    let queue = DbQueue::new(
+        db.clone(),
+        config.build.host().to_owned(),
+        log_offload.clone(),
+    );
+
+    if let Some(Command::Export {
+        format,
+        out,
+        without_logs,
+    }) = cli.command
+    {
+        return export::export(&db, format, &out, !without_logs).await;
+    }
+    if let Some(Command::Import { dir }) = cli.command {
+        return import::import(&db, &dir).await;
+    }
+    #[cfg(feature = "builder")]
+    if let Some(Command::Ci {
+        nightly,
+        mode,
+        fail_on,
+        no_build,
+    }) = cli.command
+    {
+        let passed = ci::run(&queue, &config.build, &nightly, mode, fail_on, !no_build).await?;
+        drop(telemetry);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    #[cfg(feature = "builder")]
+    if let Some(Command::Plan { limit }) = cli.command {
+        return plan::print_plan(&db, &config.build, limit).await;
+    }
+
+    if let Some(days) = config.retention.log_days {
+        tokio::spawn(retention::background_pruner(
+            db.clone(),
+            days,
+            Duration::from_secs(config.retention.interval_hours * 60 * 60),
+        ));
+    }
+
+    if config.maintenance.enabled {
+        tokio::spawn(maintenance::background_maintenance(
+            db.clone(),
+            Duration::from_secs(config.maintenance.interval_hours * 60 * 60),
+        ));
+    }
+
+    if let Some(url) = &config.backup.url {
+        let store = backup::BackupStore::new(url)?;
+        tokio::spawn(backup::background_backup(
+            db.clone(),
+            store,
+            Duration::from_secs(config.backup.interval_hours * 60 * 60),
+            config.backup.keep,
+        ));
+    }
+
+    // A mirror is read-only, everything that changes the data comes from the primary.
+    let mirror = config.mirror.primary_url.clone();
+    if let Some(url) = &mirror {
+        tokio::spawn(mirror::background_sync(
+            db.clone(),
+            url.clone(),
+            Duration::from_secs(config.mirror.interval_minutes * 60),
+        ));
+    }
+
+    let offline = config.build.dist.offline;
+    if offline {
+        info!("Offline, not escalating, syncing maintainers or syncing from federation peers");
+    }
 
-    let db = Db::open(&std::env::var("DB_PATH").unwrap_or("db.sqlite".into())).await?;
-    db::MIGRATOR
-        .run(&db.conn)
-        .await
-        .wrap_err("running migrations")?;
+    if config.escalation.is_enabled() && mirror.is_none() && !offline {
+        tokio::spawn(escalation::background_escalator(
+            db.clone(),
+            config.escalation.clone(),
+        ));
+    }
 
-    let builder = build::background_builder(db.clone());
-    let server = web::webserver(db);
+    if let Some(url) = &config.snapshots.url {
+        let store = snapshot::SnapshotStore::new(url)?;
+        tokio::spawn(snapshot::background_publisher(
+            db.clone(),
+            store,
+            Duration::from_secs(config.snapshots.interval_minutes * 60),
+        ));
+    }
+
+    if !config.federation.peers.is_empty() && !offline {
+        tokio::spawn(federation::background_sync(
+            db.clone(),
+            config.federation.peers.clone(),
+            Duration::from_secs(config.federation.interval_minutes * 60),
+        ));
+    }
+
+    if config.maintainers.from_docs && !offline {
+        tokio::spawn(maintainers::background_sync(
+            db.clone(),
+            Duration::from_secs(config.maintainers.interval_hours * 60 * 60),
+        ));
+    }
+
+    #[cfg(feature = "builder")]
+    let builder = async {
+        if config.build.local && mirror.is_none() {
+            build::background_builder(queue, config.build.clone()).await
+        } else {
+            std::future::pending().await
+        }
+    };
+    #[cfg(not(feature = "builder"))]
+    let builder = {
+        if config.build.local {
+            info!("Built without the builder feature, not building");
+        }
+        std::future::pending::<Result<()>>()
+    };
+
+    #[cfg(feature = "web")]
+    let server = {
+        let mut web_config = config.web.clone();
+        let mut auth_config = config.auth.clone();
+        let mut worker_token = config.worker.token.clone();
+        if mirror.is_some() {
+            web_config.hook_token = None;
+            auth_config.provider = config::AuthProvider::Token;
+            worker_token = None;
+        }
+        web::webserver(
+            db.clone(),
+            web_config,
+            auth_config,
+            worker_token,
+            log_offload,
+            config.maintainers.extra.clone(),
+        )
+    };
+    #[cfg(not(feature = "web"))]
+    let server = {
+        drop(log_offload);
+        std::future::pending::<Result<()>>()
+    };
 
     tokio::select! {
         result = builder => {
@@ -33,3 +326,23 @@ async fn main() -> Result<()> {
         }
     }
 }
+
+/// Logs to stderr and exports the spans if telemetry is configured.
+fn init_tracing(format: LogFormat, config: &TelemetryConfig) -> Result<Option<Telemetry>> {
+    let fmt = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let fmt = match format {
+        LogFormat::Text => fmt.boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let (telemetry, otel) = Telemetry::init(config)?.unzip();
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info")))
+        .with(fmt)
+        .with(otel)
+        .init();
+    Ok(telemetry)
+}
@@ -0,0 +1,206 @@
+//! The maintainers of targets, as listed in the platform support docs of the compiler.
+//! `platform-support.md` links every target to a page that has a "Target maintainers" section.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use futures::{StreamExt, TryStreamExt};
+use tracing::{error, info};
+
+use crate::{config::wildcard_match, db::Db};
+
+const DOCS_URL: &str = "https://raw.githubusercontent.com/rust-lang/rust/master/src/doc/rustc/src";
+
+/// Periodically replaces the maintainers with the ones in the current docs.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_sync(db: Db, interval: Duration) {
+    loop {
+        if let Err(err) = sync(&db).await {
+            error!(?err, "Failed to sync target maintainers");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn sync(db: &Db) -> Result<()> {
+    let index = fetch(&format!("{DOCS_URL}/platform-support.md")).await?;
+    let pages = target_pages(&index);
+    let known_targets = db
+        .targets()
+        .await?
+        .into_iter()
+        .map(|(target, _)| target)
+        .collect::<Vec<_>>();
+
+    let files = pages
+        .iter()
+        .map(|(_, file)| file.clone())
+        .collect::<BTreeSet<_>>();
+    let page_maintainers = futures::stream::iter(files)
+        .map(|file| async move {
+            let page = fetch(&format!("{DOCS_URL}/{file}")).await?;
+            Ok::<_, color_eyre::Report>((file, maintainers_of_page(&page)))
+        })
+        .buffer_unordered(8)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut maintainers = Vec::new();
+    for (pattern, file) in &pages {
+        let Some((_, handles)) = page_maintainers.iter().find(|(page, _)| page == file) else {
+            continue;
+        };
+        // Some pages are linked with a pattern like `*-apple-darwin` instead of every target.
+        let targets = if pattern.contains('*') {
+            known_targets
+                .iter()
+                .filter(|target| wildcard_match(pattern, target))
+                .cloned()
+                .collect()
+        } else {
+            vec![pattern.clone()]
+        };
+        for target in targets {
+            maintainers.extend(
+                handles
+                    .iter()
+                    .map(|handle| (target.clone(), handle.clone())),
+            );
+        }
+    }
+    // An empty list means that the docs changed their format, not that every maintainer left.
+    if maintainers.is_empty() {
+        bail!("no target maintainers found in the platform support docs");
+    }
+
+    db.replace_target_maintainers(&maintainers).await?;
+    info!(count = %maintainers.len(), "Synced target maintainers");
+    Ok(())
+}
+
+async fn fetch(url: &str) -> Result<String> {
+    crate::http::get(url)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err_with(|| format!("fetching {url}"))?
+        .text()
+        .await
+        .wrap_err_with(|| format!("fetching body of {url}"))
+}
+
+/// The targets (or target patterns) linked to their page, like ``[`aarch64-apple-ios`](platform-support/apple-ios.md)``.
+fn target_pages(index: &str) -> Vec<(String, String)> {
+    let mut pages = Vec::new();
+    let mut rest = index;
+    while let Some(start) = rest.find("[`") {
+        rest = &rest[start + 2..];
+        let Some((target, after)) = rest.split_once("`](") else {
+            break;
+        };
+        let Some((link, _)) = after.split_once(')') else {
+            break;
+        };
+        let file = link.split('#').next().unwrap_or_default();
+        if !target.contains(['`', '\n', ' '])
+            && file.starts_with("platform-support/")
+            && file.ends_with(".md")
+        {
+            pages.push((target.to_owned(), file.to_owned()));
+        }
+    }
+    pages
+}
+
+/// The GitHub handles mentioned in the maintainers section of a target page, without the `@`.
+fn maintainers_of_page(page: &str) -> Vec<String> {
+    let mut handles = Vec::new();
+    let mut section_level = None;
+    for line in page.lines() {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            if section_level.is_some_and(|section_level| level <= section_level) {
+                section_level = None;
+            }
+            if line.to_lowercase().contains("maintainer") {
+                section_level = Some(level);
+            }
+            continue;
+        }
+        if section_level.is_none() {
+            continue;
+        }
+        for (i, _) in line.match_indices('@') {
+            // Skip email addresses.
+            let before = line[..i].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+                continue;
+            }
+            let handle = line[i + 1..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect::<String>();
+            if !handle.is_empty() && !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+    }
+    handles
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn target_pages() {
+        let index = "
+target | std | notes
+-------|:---:|-------
+[`aarch64-apple-ios`](platform-support/apple-ios.md) | ✓ | ARM64 iOS
+[`*-apple-darwin`](platform-support/apple-darwin.md#targets) | ✓ | macOS
+`i686-pc-windows-msvc` | ✓ | 32-bit MSVC
+[`x86_64-unknown-uefi`](https://example.com/uefi) | * | UEFI
+";
+        assert_eq!(
+            super::target_pages(index),
+            [
+                (
+                    "aarch64-apple-ios".to_owned(),
+                    "platform-support/apple-ios.md".to_owned()
+                ),
+                (
+                    "*-apple-darwin".to_owned(),
+                    "platform-support/apple-darwin.md".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn maintainers_of_page() {
+        let page = "
+# aarch64-apple-ios
+
+Mention @notamaintainer here.
+
+## Target maintainers
+
+[@alice](https://github.com/alice)
+- Bob, `bob@example.com`, @bob-2
+- @alice again
+
+### Contact
+
+Ask @carol.
+
+## Requirements
+
+@dave
+";
+        assert_eq!(
+            super::maintainers_of_page(page),
+            ["alice", "bob-2", "carol"]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use color_eyre::Result;
+use tracing::{debug, error, info};
+
+use crate::db::Db;
+
+/// How long to wait for the builders to finish before checking again.
+const IDLE_POLL: Duration = Duration::from_secs(10 * 60);
+
+/// Periodically maintains the database, but only while no builds run, as it can block writes
+/// for a while. Runs forever, errors are recorded and retried on the next run.
+pub async fn background_maintenance(db: Db, interval: Duration) {
+    loop {
+        // Right after starting, the builder hasn't queued its jobs yet.
+        tokio::time::sleep(IDLE_POLL).await;
+        match db.is_building().await {
+            Ok(false) => {}
+            Ok(true) => {
+                debug!("Postponing database maintenance until the builds are done");
+                continue;
+            }
+            Err(err) => {
+                error!(?err, "Failed to check for running builds");
+                continue;
+            }
+        }
+
+        if let Err(err) = run(&db).await {
+            error!(?err, "Failed to record database maintenance");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn run(db: &Db) -> Result<()> {
+    let started_at = time::OffsetDateTime::now_utc().unix_timestamp();
+    db.start_maintenance_run(started_at).await?;
+    match db.maintain().await {
+        Ok(freed_bytes) => {
+            info!(?freed_bytes, "Maintained the database");
+            db.finish_maintenance_run(started_at, freed_bytes, None)
+                .await
+        }
+        Err(err) => {
+            error!(?err, "Failed to maintain the database");
+            db.finish_maintenance_run(started_at, None, Some(&format!("{err:#}")))
+                .await
+        }
+    }
+}
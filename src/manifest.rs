@@ -0,0 +1,76 @@
+//! The channel manifest of a nightly, which lists its components and where to download them.
+
+use color_eyre::{eyre::Context, Result};
+use serde::Deserialize;
+
+use crate::{db::Verification, dist::Dist};
+
+pub async fn fetch(dist: &Dist, nightly: &str) -> Result<String> {
+    let path = format!("dist/{nightly}/channel-rust-nightly.toml");
+    let url = dist.url(&path);
+    dist.get(&path)
+        .await
+        .and_then(|resp| Ok(resp.error_for_status()?))
+        .wrap_err_with(|| format!("fetching {url}"))?
+        .text()
+        .await
+        .wrap_err_with(|| format!("fetching body of {url}"))
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    pkg: Packages,
+}
+
+#[derive(Deserialize)]
+struct Packages {
+    rustc: Rustc,
+}
+
+#[derive(Deserialize)]
+struct Rustc {
+    git_commit_hash: Option<String>,
+}
+
+/// Compares the commit the installed rustc reports with the one in the manifest. rustup checks the
+/// hashes of the downloads, this makes sure that what ended up installed is really that nightly.
+/// `None` if the manifest doesn't say.
+pub fn verify(installed_commit: Option<&str>, manifest: &str) -> Result<Option<Verification>> {
+    let manifest = toml::from_str::<Manifest>(manifest).wrap_err("invalid channel manifest")?;
+    Ok(manifest.pkg.rustc.git_commit_hash.map(|expected| {
+        if installed_commit == Some(expected.as_str()) {
+            Verification::Verified
+        } else {
+            Verification::Mismatch
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Verification;
+
+    #[test]
+    fn verify() {
+        let manifest = r#"
+manifest-version = "2"
+date = "2024-09-07"
+
+[pkg.rustc]
+version = "1.83.0-nightly (26b5599e4 2024-09-06)"
+git_commit_hash = "26b5599e4d6ed2b45152c60493c1788c0a27533d"
+"#;
+        assert_eq!(
+            super::verify(Some("26b5599e4d6ed2b45152c60493c1788c0a27533d"), manifest).unwrap(),
+            Some(Verification::Verified)
+        );
+        assert_eq!(
+            super::verify(Some("1bc403daadbebb553ccc211a0a8eebb73989665f"), manifest).unwrap(),
+            Some(Verification::Mismatch)
+        );
+        assert_eq!(
+            super::verify(None, manifest).unwrap(),
+            Some(Verification::Mismatch)
+        );
+    }
+}
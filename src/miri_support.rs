@@ -0,0 +1,135 @@
+//! Which targets Miri supports, judging by `cargo miri setup` in the miri-std mode. A target is
+//! supported once Miri gets to building its standard library, whether that passes or not, so this
+//! changes with Miri's own target checks instead of with the health of std.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::db::Status;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Support {
+    Supported,
+    Unsupported,
+}
+
+impl Support {
+    /// Builds that didn't get to run Miri say nothing about its support.
+    pub fn of(status: Status) -> Option<Self> {
+        match status {
+            Status::Pass | Status::Error => Some(Self::Supported),
+            Status::Unsupported => Some(Self::Unsupported),
+            _ => None,
+        }
+    }
+}
+
+/// The support of a target on its latest nightly.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SupportState {
+    pub target: String,
+    pub support: Support,
+    /// The first nightly of the current support.
+    pub since: String,
+    pub latest_nightly: String,
+}
+
+/// A target gaining or losing Miri support.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SupportChange {
+    /// The first nightly with the change.
+    pub nightly: String,
+    pub target: String,
+    pub support: Support,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SupportMatrix {
+    pub targets: Vec<SupportState>,
+    /// Oldest first.
+    pub changes: Vec<SupportChange>,
+}
+
+/// The matrix from the miri-std builds, ordered by target and then nightly.
+pub fn matrix(builds: &[(String, String, Status)]) -> SupportMatrix {
+    let mut states = BTreeMap::<&str, SupportState>::new();
+    let mut changes = Vec::new();
+    for (target, nightly, status) in builds {
+        let Some(support) = Support::of(*status) else {
+            continue;
+        };
+        match states.get_mut(target.as_str()) {
+            Some(state) if state.support == support => {
+                state.latest_nightly.clone_from(nightly);
+            }
+            Some(state) => {
+                changes.push(SupportChange {
+                    nightly: nightly.clone(),
+                    target: target.clone(),
+                    support,
+                });
+                *state = SupportState {
+                    target: target.clone(),
+                    support,
+                    since: nightly.clone(),
+                    latest_nightly: nightly.clone(),
+                };
+            }
+            None => {
+                states.insert(
+                    target,
+                    SupportState {
+                        target: target.clone(),
+                        support,
+                        since: nightly.clone(),
+                        latest_nightly: nightly.clone(),
+                    },
+                );
+            }
+        }
+    }
+    changes.sort_by(|a, b| {
+        a.nightly
+            .cmp(&b.nightly)
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    SupportMatrix {
+        targets: states.into_values().collect(),
+        changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Status::{self, Error, Pass, Timeout, Unsupported};
+
+    use super::{Support, SupportChange};
+
+    #[test]
+    fn matrix() {
+        let build = |target: &str, nightly: &str, status: Status| {
+            (target.to_owned(), nightly.to_owned(), status)
+        };
+        let matrix = super::matrix(&[
+            build("avr-none", "2026-10-01", Unsupported),
+            build("avr-none", "2026-10-02", Timeout),
+            build("avr-none", "2026-10-03", Error),
+            build("x86_64-unknown-linux-gnu", "2026-10-01", Pass),
+            build("x86_64-unknown-linux-gnu", "2026-10-02", Error),
+        ]);
+        assert_eq!(
+            matrix.changes,
+            [SupportChange {
+                nightly: "2026-10-03".to_owned(),
+                target: "avr-none".to_owned(),
+                support: Support::Supported,
+            }]
+        );
+        assert_eq!(matrix.targets[0].since, "2026-10-03");
+        assert_eq!(matrix.targets[1].support, Support::Supported);
+        assert_eq!(matrix.targets[1].since, "2026-10-01");
+        assert_eq!(matrix.targets[1].latest_nightly, "2026-10-02");
+    }
+}
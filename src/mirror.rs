@@ -0,0 +1,86 @@
+//! Running as a read-only mirror of another instance, syncing from its export API.
+//! Mirrors are cheap to run close to their users, and anyone can mirror the public dataset.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    time::Duration,
+};
+
+use color_eyre::{eyre::Context, Result};
+use serde::de::DeserializeOwned;
+use tracing::{error, info};
+
+use crate::{
+    db::{BuildRecord, Db, NightlyRecord, TargetMeta, ToolchainInfo},
+    nightlies,
+};
+
+const MISSING_LOG: &str = "The log of this build was not included in the mirrored data.\n";
+
+/// Nightlies this recent are synced again every time, to pick up builds that were re-run on the primary.
+const RESYNC_DAYS: u32 = 7;
+
+/// Periodically syncs from the primary instance at `url`.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_sync(db: Db, url: String, interval: Duration) {
+    let client = crate::http::client();
+    let url = url.trim_end_matches('/');
+    loop {
+        if let Err(err) = sync(&db, client, url).await {
+            error!(?err, "Failed to sync from primary instance");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn sync(db: &Db, client: &reqwest::Client, url: &str) -> Result<()> {
+    let nightlies =
+        fetch::<Vec<NightlyRecord>>(client, &format!("{url}/api/v1/export/nightlies")).await?;
+    let known = db
+        .nightly_records()
+        .await?
+        .into_iter()
+        .map(|record| (record.nightly, record.mode, record.host))
+        .collect::<HashSet<_>>();
+    let recent = nightlies::days_ago(RESYNC_DAYS)?;
+    let to_sync = nightlies
+        .iter()
+        .filter(|record| {
+            record.nightly >= recent
+                || !known.contains(&(record.nightly.clone(), record.mode, record.host.clone()))
+        })
+        .map(|record| record.nightly.clone())
+        .collect::<BTreeSet<_>>();
+
+    let mut builds = 0;
+    for nightly in &to_sync {
+        let records = fetch::<Vec<BuildRecord>>(
+            client,
+            &format!("{url}/api/v1/export/builds?nightly={nightly}"),
+        )
+        .await?;
+        builds += db.mirror_builds(records, MISSING_LOG).await?;
+    }
+
+    let toolchains =
+        fetch::<Vec<ToolchainInfo>>(client, &format!("{url}/api/v1/export/toolchains")).await?;
+    db.import_toolchain_info(toolchains).await?;
+    let metas =
+        fetch::<Vec<TargetMeta>>(client, &format!("{url}/api/v1/export/target-meta")).await?;
+    db.insert_target_meta(&metas).await?;
+    // Only after the builds, so a nightly never shows up as finished without them.
+    db.import_nightlies(nightlies).await?;
+
+    info!(nightlies = %to_sync.len(), %builds, "Synced from primary instance");
+    Ok(())
+}
+
+async fn fetch<T: DeserializeOwned>(client: &reqwest::Client, url: &str) -> Result<T> {
+    crate::http::send(client.get(url))
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err_with(|| format!("fetching {url}"))?
+        .json()
+        .await
+        .wrap_err_with(|| format!("invalid response from {url}"))
+}
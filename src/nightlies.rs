@@ -7,9 +7,11 @@ use reqwest::StatusCode;
 use time::Duration;
 use tracing::debug;
 
-use crate::db::{BuildMode, FinishedNightly};
-
-const EARLIEST_CUTOFF_DATE: &str = "2023-01-01";
+use crate::{
+    config::{BuildConfig, ModeSchedule, ScheduleConfig},
+    db::{BuildMode, FinishedNightly},
+    dist::Dist,
+};
 
 #[derive(Default)]
 pub struct NightlyCache {
@@ -23,28 +25,47 @@ pub struct Nightlies {
 }
 
 impl Nightlies {
-    pub async fn fetch(cache: &mut NightlyCache) -> Result<Nightlies> {
-        let manifests = reqwest::get("https://static.rust-lang.org/manifests.txt")
-            .await
-            .wrap_err("fetching https://static.rust-lang.org/manifests.txt")?
-            .text()
-            .await
-            .wrap_err("fetching body of https://static.rust-lang.org/manifests.txt")?;
+    /// Fetches the nightlies from `build.earliest_nightly` on.
+    pub async fn fetch(
+        dist: &Dist,
+        cache: &mut NightlyCache,
+        config: &BuildConfig,
+    ) -> Result<Nightlies> {
+        let earliest = config.earliest_nightly.as_str();
+        let manifests = match dist.manifests_file() {
+            Some(path) => std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("reading {}", path.display()))?,
+            None => {
+                let url = dist.url("manifests.txt");
+                dist.get("manifests.txt")
+                    .await
+                    .wrap_err_with(|| format!("fetching {url}"))?
+                    .text()
+                    .await
+                    .wrap_err_with(|| format!("fetching body of {url}"))?
+            }
+        };
         let mut all = nightlies_from_manifest(&manifests)
             .into_iter()
-            .filter(|date| date.as_str() > EARLIEST_CUTOFF_DATE)
+            .filter(|date| date.as_str() >= earliest)
             .collect::<Vec<_>>();
 
         all.sort();
 
+        // Offline, the local file is all there is.
+        if dist.is_offline() {
+            all.reverse();
+            return Ok(Self { all });
+        }
+
         // The manifests is only updated weekly, which means new nightlies won't be contained.
         // We probe for their existence.
         let latest = all
             .last()
             .ok_or_eyre("did not find any nightlies in manifets.txt")?;
 
-        for nightly in guess_more_recent_nightlies(&latest)? {
-            if nightly_exists(&nightly, cache)
+        for nightly in guess_more_recent_nightlies(latest, config.nightly_probe_days)? {
+            if nightly_exists(dist, &nightly, cache)
                 .await
                 .wrap_err_with(|| format!("checking whether {nightly} exists"))?
             {
@@ -54,26 +75,71 @@ impl Nightlies {
 
         all.reverse();
 
-        debug!("Loaded {} nightlies from the manifest and manual additions", all.len());
+        debug!(
+            "Loaded {} nightlies from the manifest and manual additions",
+            all.len()
+        );
         Ok(Self { all })
     }
 
-    pub fn select_latest_to_build(
+    /// The installed nightlies from `build.earliest_nightly` on, for builders that are offline and
+    /// have no manifests file.
+    pub fn installed(installed: Vec<String>, config: &BuildConfig) -> Nightlies {
+        let mut all = installed
+            .into_iter()
+            .filter(|nightly| nightly.as_str() >= config.earliest_nightly.as_str())
+            .collect::<Vec<_>>();
+        all.sort();
+        all.reverse();
+        debug!("Found {} installed nightlies", all.len());
+        Self { all }
+    }
+
+    /// The nightlies and modes that still need to be built, in the order they are built in: newest
+    /// first, with the modes taking turns in proportion to their weight.
+    pub fn unfinished(
         &self,
         already_finished: &[FinishedNightly],
-    ) -> Option<(String, BuildMode)> {
-        let already_finished = HashSet::<_, RandomState>::from_iter(already_finished.iter());
-
-        self.all
+        modes: &[BuildMode],
+        schedule: &ScheduleConfig,
+    ) -> impl Iterator<Item = (String, BuildMode)> {
+        let already_finished = already_finished
             .iter()
-            .flat_map(|nightly| [(nightly, BuildMode::Core), (nightly, BuildMode::MiriStd)])
-            .find(|(nightly, mode)| {
-                !already_finished.contains(&FinishedNightly {
-                    nightly: (*nightly).to_owned(),
-                    mode: *mode,
-                })
-            })
-            .map(|(nightly, mode)| (nightly.clone(), mode))
+            .map(|finished| (finished.nightly.as_str(), finished.mode))
+            .collect::<HashSet<_, RandomState>>();
+
+        // The `turn`th build of a mode with `weight` is due after `turn / weight` rounds.
+        let mut queue = Vec::new();
+        for (order, &mode) in modes.iter().enumerate() {
+            let ModeSchedule { every, weight } = *schedule.mode(mode);
+            let nightlies = self
+                .all
+                .iter()
+                .filter(|nightly| is_scheduled(nightly, every))
+                .filter(|nightly| !already_finished.contains(&(nightly.as_str(), mode)));
+            for (turn, nightly) in nightlies.enumerate() {
+                queue.push((turn as u64, u64::from(weight), order, nightly.clone(), mode));
+            }
+        }
+        queue.sort_by(
+            |(a_turn, a_weight, a_order, ..), (b_turn, b_weight, b_order, ..)| {
+                (a_turn * b_weight)
+                    .cmp(&(b_turn * a_weight))
+                    .then(a_order.cmp(b_order))
+            },
+        );
+        queue
+            .into_iter()
+            .map(|(_, _, _, nightly, mode)| (nightly, mode))
+    }
+}
+
+/// Whether the nightly is one of those built for a mode that is built on every `every` nightlies.
+fn is_scheduled(nightly: &str, every: u32) -> bool {
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    match time::Date::parse(nightly, format) {
+        Ok(date) => date.to_julian_day() % every as i32 == 0,
+        Err(_) => true,
     }
 }
 
@@ -89,23 +155,39 @@ fn nightlies_from_manifest(manifest: &str) -> Vec<String> {
         .collect()
 }
 
-fn guess_more_recent_nightlies(latest: &str) -> Result<Vec<String>> {
+/// The nightly of the day `days` days ago.
+pub fn days_ago(days: u32) -> Result<String> {
+    let date = time::OffsetDateTime::now_utc().date() - Duration::days(days.into());
+    date.format(time::macros::format_description!("[year]-[month]-[day]"))
+        .wrap_err("formatting date")
+}
+
+/// The nightly `days` days before `nightly`.
+pub fn days_before(nightly: &str, days: u32) -> Result<String> {
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    let date = time::Date::parse(nightly, format).wrap_err("nightly has invalid format")?;
+    (date - Duration::days(days.into()))
+        .format(format)
+        .wrap_err("formatting date")
+}
+
+fn guess_more_recent_nightlies(latest: &str, days: u32) -> Result<Vec<String>> {
     let format = time::macros::format_description!("[year]-[month]-[day]");
     let latest = time::Date::parse(latest, format).wrap_err("latest nightly has invalid format")?;
 
-    // manifests.txt is updated weekly, so let's try 8 just in case.
-    Ok((1..=8)
+    Ok((1..=days.into())
         .filter_map(|offset| latest.checked_add(Duration::days(offset)))
         .map(|date| date.format(format).unwrap())
         .collect())
 }
 
-async fn nightly_exists(nightly: &str, cache: &mut NightlyCache) -> Result<bool> {
+async fn nightly_exists(dist: &Dist, nightly: &str, cache: &mut NightlyCache) -> Result<bool> {
     if cache.exists.contains(nightly) {
         return Ok(true);
     }
-    let url = format!("https://static.rust-lang.org/dist/{nightly}/channel-rust-nightly.toml");
-    let resp = reqwest::get(&url).await.wrap_err("fetching channel")?;
+    let path = format!("dist/{nightly}/channel-rust-nightly.toml");
+    let url = dist.url(&path);
+    let resp = dist.get(&path).await.wrap_err("fetching channel")?;
     debug!(%nightly, %url, status = %resp.status(), "Checked whether a recent nightly exists");
     let exists = resp.status() == StatusCode::OK;
     if exists {
@@ -116,6 +198,13 @@ async fn nightly_exists(nightly: &str, cache: &mut NightlyCache) -> Result<bool>
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        config::ScheduleConfig,
+        db::{BuildMode, FinishedNightly},
+    };
+
+    use super::Nightlies;
+
     #[test]
     fn manifest_parse() {
         let test_manifest = "static.rust-lang.org/dist/2024-08-22/channel-rust-nightly.toml
@@ -123,13 +212,13 @@ static.rust-lang.org/dist/2024-08-22/channel-rust-1.81.0-beta.toml
 static.rust-lang.org/dist/2024-08-22/channel-rust-1.81.0-beta.6.toml
 static.rust-lang.org/dist/2024-08-23/channel-rust-nightly.toml";
 
-        let nightlies = super::nightlies_from_manifest(&test_manifest);
+        let nightlies = super::nightlies_from_manifest(test_manifest);
         assert_eq!(nightlies, vec!["2024-08-22", "2024-08-23"]);
     }
 
     #[test]
     fn guess() {
-        let nightlies = super::guess_more_recent_nightlies("2024-08-28").unwrap();
+        let nightlies = super::guess_more_recent_nightlies("2024-08-28", 8).unwrap();
         assert_eq!(
             nightlies,
             [
@@ -144,4 +233,52 @@ static.rust-lang.org/dist/2024-08-23/channel-rust-nightly.toml";
             ]
         );
     }
+
+    #[test]
+    fn unfinished() {
+        let nightlies = Nightlies {
+            all: ["2026-10-06", "2026-10-05", "2026-10-04", "2026-10-03"]
+                .map(ToOwned::to_owned)
+                .to_vec(),
+        };
+        let finished = [FinishedNightly {
+            nightly: "2026-10-06".to_owned(),
+            mode: BuildMode::Core,
+        }];
+        let modes = [BuildMode::Core, BuildMode::MiriStd];
+        let order = |schedule: &ScheduleConfig| {
+            nightlies
+                .unfinished(&finished, &modes, schedule)
+                .map(|(nightly, mode)| format!("{nightly} {mode}"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            order(&ScheduleConfig::default()),
+            [
+                "2026-10-05 core",
+                "2026-10-06 miri-std",
+                "2026-10-04 core",
+                "2026-10-05 miri-std",
+                "2026-10-03 core",
+                "2026-10-04 miri-std",
+                "2026-10-03 miri-std",
+            ]
+        );
+
+        // Miri on every other nightly, and half as often as core while catching up.
+        let mut schedule = ScheduleConfig::default();
+        schedule.core.weight = 2;
+        schedule.miri_std.every = 2;
+        assert_eq!(
+            order(&schedule),
+            [
+                "2026-10-05 core",
+                "2026-10-06 miri-std",
+                "2026-10-04 core",
+                "2026-10-03 core",
+                "2026-10-04 miri-std",
+            ]
+        );
+    }
 }
@@ -0,0 +1,65 @@
+//! Showing what the builder would do next without building anything,
+//! to check the effect of configuration changes.
+
+use color_eyre::{eyre::Context, Result};
+
+use crate::{
+    build_env::BuildEnv,
+    config::BuildConfig,
+    db::Db,
+    dist::Dist,
+    nightlies::{Nightlies, NightlyCache},
+    toolchain::ToolchainManager,
+};
+
+/// Prints the next `limit` nightlies and modes the builder on this host would build.
+pub async fn print_plan(db: &Db, config: &BuildConfig, limit: usize) -> Result<()> {
+    let dist = Dist::new(&config.dist)?;
+    let nightlies = if dist.is_offline() && dist.manifests_file().is_none() {
+        let toolchains =
+            ToolchainManager::new(BuildEnv::new(config)?, config.toolchain_cache.clone()).await?;
+        Nightlies::installed(toolchains.installed_nightlies(), config)
+    } else {
+        Nightlies::fetch(&dist, &mut NightlyCache::default(), config)
+            .await
+            .wrap_err("fetching nightlies")?
+    };
+    let finished = db.finished_nightlies(Some(crate::HOST)).await?;
+
+    // The target list is only known once a toolchain is installed, so assume
+    // it is the same as for the last nightly that was built.
+    let latest_targets = db.latest_targets(crate::HOST).await?;
+    let targets = latest_targets
+        .iter()
+        .filter(|target| config.targets.matches(target))
+        .count();
+
+    println!("Next builds on {}:", crate::HOST);
+    let mut planned = 0;
+    for (nightly, mode) in nightlies
+        .unfinished(&finished, &config.modes, &config.schedule)
+        .take(limit)
+    {
+        let built: i64 = db
+            .status_counts(&nightly)
+            .await?
+            .iter()
+            .filter(|count| count.mode == mode && count.host == crate::HOST)
+            .map(|count| count.count)
+            .sum();
+        let targets = if latest_targets.is_empty() {
+            "unknown number of targets".to_owned()
+        } else {
+            format!("about {targets} targets")
+        };
+        println!("  nightly-{nightly} {mode}: {targets}, {built} already built");
+        planned += 1;
+    }
+    if planned == 0 {
+        println!(
+            "  nothing, every nightly since {} is built",
+            config.earliest_nightly
+        );
+    }
+    Ok(())
+}
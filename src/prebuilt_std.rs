@@ -0,0 +1,131 @@
+//! Which targets ship a prebuilt `rust-std` component on a nightly, according to its channel manifest.
+//! A target losing it is effectively demoted, even if it still builds.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Manifest {
+    pkg: Packages,
+}
+
+#[derive(Deserialize)]
+struct Packages {
+    #[serde(rename = "rust-std")]
+    rust_std: Package,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    target: BTreeMap<String, PackageTarget>,
+}
+
+#[derive(Deserialize)]
+struct PackageTarget {
+    available: bool,
+}
+
+/// The targets with `rust-std` in the channel manifest, sorted.
+pub fn targets_from_manifest(manifest: &str) -> Result<Vec<String>> {
+    let manifest = toml::from_str::<Manifest>(manifest).wrap_err("invalid channel manifest")?;
+    Ok(manifest
+        .pkg
+        .rust_std
+        .target
+        .into_iter()
+        .filter(|(_, target)| target.available)
+        .map(|(target, _)| target)
+        .collect())
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StdChange {
+    Gained,
+    Lost,
+}
+
+/// A target gaining or losing its prebuilt std.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StdTransition {
+    /// The first nightly with the change.
+    pub nightly: String,
+    pub target: String,
+    pub change: StdChange,
+}
+
+/// The changes between consecutive nightlies, given the targets with std of every nightly in order.
+pub fn transitions(nightlies: &[(String, Vec<String>)]) -> Vec<StdTransition> {
+    let mut transitions = Vec::new();
+    for pair in nightlies.windows(2) {
+        let [(_, before), (nightly, after)] = pair else {
+            unreachable!("windows of two");
+        };
+        let before = before.iter().collect::<BTreeSet<_>>();
+        let after = after.iter().collect::<BTreeSet<_>>();
+        transitions.extend(after.difference(&before).map(|target| StdTransition {
+            nightly: nightly.clone(),
+            target: target.to_string(),
+            change: StdChange::Gained,
+        }));
+        transitions.extend(before.difference(&after).map(|target| StdTransition {
+            nightly: nightly.clone(),
+            target: target.to_string(),
+            change: StdChange::Lost,
+        }));
+    }
+    transitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StdChange, StdTransition};
+
+    #[test]
+    fn targets_from_manifest() {
+        let manifest = r#"
+manifest-version = "2"
+date = "2024-09-07"
+
+[pkg.rust-std]
+version = "1.83.0-nightly (26b5599e4 2024-09-06)"
+
+[pkg.rust-std.target.aarch64-unknown-linux-gnu]
+available = true
+url = "https://static.rust-lang.org/dist/2024-09-07/rust-std-nightly-aarch64-unknown-linux-gnu.tar.gz"
+
+[pkg.rust-std.target.mips-unknown-linux-gnu]
+available = false
+"#;
+        assert_eq!(
+            super::targets_from_manifest(manifest).unwrap(),
+            ["aarch64-unknown-linux-gnu"]
+        );
+    }
+
+    #[test]
+    fn transitions() {
+        let nightlies = [
+            ("1".to_owned(), vec!["a".to_owned(), "b".to_owned()]),
+            ("2".to_owned(), vec!["a".to_owned(), "b".to_owned()]),
+            ("3".to_owned(), vec!["a".to_owned(), "c".to_owned()]),
+        ];
+        assert_eq!(
+            super::transitions(&nightlies),
+            [
+                StdTransition {
+                    nightly: "3".to_owned(),
+                    target: "c".to_owned(),
+                    change: StdChange::Gained,
+                },
+                StdTransition {
+                    nightly: "3".to_owned(),
+                    target: "b".to_owned(),
+                    change: StdChange::Lost,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Looking up the status of a target on a does-it-build instance, for downstream projects
+//! to fail their CI early when the target is already broken upstream.
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use does_it_build_api::Client;
+
+use crate::db::{BuildMode, Status};
+
+/// Prints the status of the target and returns whether it passed.
+/// `nightly` can be `latest` for the newest nightly the target was built on.
+pub async fn run(
+    url: &str,
+    target: &str,
+    mode: BuildMode,
+    nightly: &str,
+    host: Option<&str>,
+) -> Result<bool> {
+    let client = Client::new(url);
+    let build = if nightly == "latest" {
+        client.latest_build(target, mode, host).await
+    } else {
+        client
+            .build(nightly, target, mode, host)
+            .await
+            .map(|history| history.map(|history| history.current))
+    }
+    .wrap_err_with(|| format!("fetching the build of {target} from {url}"))?;
+
+    let Some(build) = build else {
+        if nightly == "latest" {
+            bail!("{target} has no {mode} builds");
+        }
+        bail!("{target} has no {mode} build on nightly-{nightly}");
+    };
+
+    println!(
+        "nightly-{} {target} {mode}: {}",
+        build.nightly, build.status
+    );
+    Ok(build.status == Status::Pass)
+}
@@ -0,0 +1,357 @@
+//! Where the builder gets its work from and where its results go. The database side of it is
+//! also needed by the coordinator, so it doesn't depend on the builder itself.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::Context, Result};
+
+use crate::{
+    assumed_failing,
+    config::AssumeFailingConfig,
+    db::{
+        BatchedWrite, BetaCheck, BuildMode, Db, FinishedNightly, FullBuildInfo, TargetCfg,
+        TargetMeta, TargetSpec, ToolchainInfo,
+    },
+    log_offload::LogOffload,
+};
+
+/// Where the builder gets its work from and stores the results: the database,
+/// or the coordinator when running as a worker.
+#[allow(
+    async_fn_in_trait,
+    reason = "the builder runs on the task it was called from, so its futures needn't be `Send`"
+)]
+pub trait BuildQueue {
+    async fn finished_nightlies(&self) -> Result<Vec<FinishedNightly>>;
+    async fn is_nightly_finished(&self, nightly: &str, mode: BuildMode) -> Result<bool>;
+    async fn finish_nightly(&self, nightly: &str, mode: BuildMode, broken: bool) -> Result<()>;
+    /// Finishes a nightly without builds, because rustup couldn't install it or a component.
+    async fn finish_nightly_unavailable(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        error: &str,
+    ) -> Result<()>;
+    async fn insert_toolchain_info(&self, info: &ToolchainInfo) -> Result<()>;
+    async fn insert_target_meta(&self, metas: &[TargetMeta]) -> Result<()>;
+    async fn insert_target_specs(&self, specs: &[TargetSpec]) -> Result<()>;
+    async fn insert_target_cfgs(&self, cfgs: &[TargetCfg]) -> Result<()>;
+    async fn insert_prebuilt_std(&self, nightly: &str, targets: &[String]) -> Result<()>;
+    async fn enqueue_jobs(&self, nightly: &str, mode: BuildMode, targets: &[String]) -> Result<()>;
+    async fn claim_job(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<Option<String>>;
+    async fn renew_job_lease(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<()>;
+    async fn complete_job(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<()>;
+    async fn has_unfinished_jobs(&self, nightly: &str, mode: BuildMode) -> Result<bool>;
+    async fn build_exists(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<bool>;
+    /// The targets that are skipped on the nightly, with the note to store as their log.
+    async fn assumed_failing(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        config: &AssumeFailingConfig,
+    ) -> Result<BTreeMap<String, String>>;
+    async fn insert_build(&self, info: FullBuildInfo) -> Result<()>;
+    /// The targets that regressed on the nightly compared to the previous one and weren't built
+    /// with the beta of the same day yet.
+    async fn unchecked_beta_regressions(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+    ) -> Result<Vec<String>>;
+    async fn insert_beta_check(&self, check: &BetaCheck) -> Result<()>;
+}
+
+/// The database as the queue of the builders on one host. Finished builds and jobs are written
+/// in batches. If the builder dies before writing them, their jobs are built again once the lease
+/// expires.
+pub struct DbQueue {
+    pub db: Db,
+    pub host: String,
+    pub log_offload: LogOffload,
+    batch: tokio::sync::Mutex<Batch>,
+}
+
+#[derive(Default)]
+struct Batch {
+    writes: Vec<BatchedWrite>,
+    /// When the first write of the batch was added.
+    started: Option<Instant>,
+}
+
+/// How many writes are collected before they are written in one transaction, two for every target.
+const BATCH_SIZE: usize = 64;
+/// The jobs of a batch stay leased until it's written, so it must not wait for the lease to expire.
+const BATCH_MAX_AGE: Duration = Duration::from_secs(30);
+
+impl DbQueue {
+    pub fn new(db: Db, host: String, log_offload: LogOffload) -> Self {
+        Self {
+            db,
+            host,
+            log_offload,
+            batch: Default::default(),
+        }
+    }
+
+    async fn push(&self, write: BatchedWrite) -> Result<()> {
+        let mut batch = self.batch.lock().await;
+        batch.started.get_or_insert_with(Instant::now);
+        batch.writes.push(write);
+        drop(batch);
+        self.write_batch(false).await
+    }
+
+    /// Writes the batch if it is full or old enough, or always with `force`.
+    pub async fn write_batch(&self, force: bool) -> Result<()> {
+        let mut batch = self.batch.lock().await;
+        let due = batch.writes.len() >= BATCH_SIZE
+            || batch
+                .started
+                .is_some_and(|started| started.elapsed() >= BATCH_MAX_AGE);
+        if batch.writes.is_empty() || !(force || due) {
+            return Ok(());
+        }
+        // Holding the lock makes readers wait until the writes are visible.
+        // The batch is only cleared once it is committed, a failed write is retried with the next one.
+        self.db
+            .insert_batch(&self.host, &batch.writes)
+            .await
+            .wrap_err("writing batch of builds")?;
+        batch.writes.clear();
+        batch.started = None;
+        Ok(())
+    }
+}
+
+impl BuildQueue for DbQueue {
+    async fn finished_nightlies(&self) -> Result<Vec<FinishedNightly>> {
+        self.db.finished_nightlies(Some(&self.host)).await
+    }
+
+    async fn is_nightly_finished(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
+        self.db.is_nightly_finished(nightly, mode, &self.host).await
+    }
+
+    async fn finish_nightly(&self, nightly: &str, mode: BuildMode, broken: bool) -> Result<()> {
+        self.write_batch(true).await?;
+        if broken {
+            self.db
+                .finish_nightly_as_broken(nightly, mode, &self.host)
+                .await
+        } else {
+            self.db.finish_nightly(nightly, mode, &self.host).await
+        }
+    }
+
+    async fn finish_nightly_unavailable(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        error: &str,
+    ) -> Result<()> {
+        self.db
+            .finish_nightly_as_unavailable(nightly, mode, &self.host, error)
+            .await
+    }
+
+    async fn insert_toolchain_info(&self, info: &ToolchainInfo) -> Result<()> {
+        self.db.insert_toolchain_info(info).await
+    }
+
+    async fn insert_target_meta(&self, metas: &[TargetMeta]) -> Result<()> {
+        self.db.insert_target_meta(metas).await
+    }
+
+    async fn insert_target_specs(&self, specs: &[TargetSpec]) -> Result<()> {
+        self.db.insert_target_specs(specs).await?;
+        if let Some(spec) = specs.first() {
+            crate::target_spec::record_renames(&self.db, &spec.nightly).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_target_cfgs(&self, cfgs: &[TargetCfg]) -> Result<()> {
+        self.db.insert_target_cfgs(cfgs).await
+    }
+
+    async fn insert_prebuilt_std(&self, nightly: &str, targets: &[String]) -> Result<()> {
+        self.db.insert_prebuilt_std(nightly, targets).await
+    }
+
+    async fn enqueue_jobs(&self, nightly: &str, mode: BuildMode, targets: &[String]) -> Result<()> {
+        self.db
+            .enqueue_jobs(nightly, mode, &self.host, targets)
+            .await
+    }
+
+    async fn claim_job(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<Option<String>> {
+        self.db
+            .claim_job(nightly, mode, &self.host, worker, lease)
+            .await
+    }
+
+    async fn renew_job_lease(
+        &self,
+        nightly: &str,
+        target: &str,
+        mode: BuildMode,
+        worker: &str,
+        lease: Duration,
+    ) -> Result<()> {
+        // Builds that take long would otherwise hold back the batch until the end of the nightly.
+        self.write_batch(false).await?;
+        self.db
+            .renew_job_lease(nightly, target, mode, &self.host, worker, lease)
+            .await
+    }
+
+    async fn complete_job(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<()> {
+        self.push(BatchedWrite::CompleteJob {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            mode,
+        })
+        .await
+    }
+
+    async fn has_unfinished_jobs(&self, nightly: &str, mode: BuildMode) -> Result<bool> {
+        self.write_batch(true).await?;
+        self.db.has_unfinished_jobs(nightly, mode, &self.host).await
+    }
+
+    async fn build_exists(&self, nightly: &str, target: &str, mode: BuildMode) -> Result<bool> {
+        // A requested rebuild replaces the existing build.
+        if self
+            .db
+            .is_rebuild_requested(nightly, target, mode, &self.host)
+            .await?
+        {
+            return Ok(false);
+        }
+        Ok(self
+            .db
+            .build_status_single(nightly, target, mode, Some(&self.host))
+            .await?
+            .is_some())
+    }
+
+    async fn assumed_failing(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+        config: &AssumeFailingConfig,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut skipped =
+            assumed_failing::assumed_failing(&self.db, nightly, mode, &self.host, config).await?;
+        // Someone wants to see a new build.
+        for target in skipped.keys().cloned().collect::<Vec<_>>() {
+            if self
+                .db
+                .is_rebuild_requested(nightly, &target, mode, &self.host)
+                .await?
+            {
+                skipped.remove(&target);
+            }
+        }
+        Ok(skipped)
+    }
+
+    async fn insert_build(&self, mut info: FullBuildInfo) -> Result<()> {
+        info.host.host.clone_from(&self.host);
+        let offloaded = self.log_offload.offload(&info.stderr).await?;
+        self.push(BatchedWrite::Build(info, offloaded)).await
+    }
+
+    async fn unchecked_beta_regressions(
+        &self,
+        nightly: &str,
+        mode: BuildMode,
+    ) -> Result<Vec<String>> {
+        self.write_batch(true).await?;
+        let Some(previous) = self.db.previous_nightly(nightly, mode, &self.host).await? else {
+            return Ok(Vec::new());
+        };
+        let checked = self
+            .db
+            .beta_checks(nightly, mode, &self.host)
+            .await?
+            .into_iter()
+            .map(|check| check.target)
+            .collect::<HashSet<_>>();
+        let mut regressions = self
+            .db
+            .regressions(nightly, &previous, mode, &self.host)
+            .await?;
+        regressions.retain(|target| !checked.contains(target));
+        Ok(regressions)
+    }
+
+    async fn insert_beta_check(&self, check: &BetaCheck) -> Result<()> {
+        self.db.insert_beta_check(check, &self.host).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::LogsConfig,
+        db::{tests::temp_db, BatchedWrite, BuildMode},
+        log_offload::LogOffload,
+    };
+
+    use super::DbQueue;
+
+    #[tokio::test]
+    async fn failed_batch_is_kept() {
+        let (_dir, db) = temp_db().await;
+        let queue = DbQueue::new(
+            db.clone(),
+            crate::HOST.to_owned(),
+            LogOffload::new(&LogsConfig::default()).unwrap(),
+        );
+        queue
+            .push(BatchedWrite::CompleteJob {
+                nightly: "2025-01-01".to_owned(),
+                target: "x86_64-unknown-linux-gnu".to_owned(),
+                mode: BuildMode::Core,
+            })
+            .await
+            .unwrap();
+
+        sqlx::query("ALTER TABLE job RENAME TO job_gone")
+            .execute(&db.conn)
+            .await
+            .unwrap();
+        assert!(queue.write_batch(true).await.is_err());
+        assert_eq!(queue.batch.lock().await.writes.len(), 1);
+
+        sqlx::query("ALTER TABLE job_gone RENAME TO job")
+            .execute(&db.conn)
+            .await
+            .unwrap();
+        queue.write_batch(true).await.unwrap();
+        assert!(queue.batch.lock().await.writes.is_empty());
+    }
+}
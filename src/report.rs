@@ -0,0 +1,176 @@
+//! A summary of the changes over the last days, to paste into an internals post or This Week in Rust.
+
+use color_eyre::Result;
+use serde::Serialize;
+
+use crate::{
+    db::{BuildMode, Db, Status},
+    nightlies,
+};
+
+#[derive(Serialize)]
+pub struct Report {
+    /// The first nightly of the report.
+    pub since: String,
+    pub mode: BuildMode,
+    /// Targets that started failing and are still failing.
+    pub regressions: Vec<Entry>,
+    /// Targets that started passing and are still passing.
+    pub fixes: Vec<Entry>,
+    /// Targets that were already failing before the report and still are.
+    pub still_broken: Vec<Entry>,
+    /// Targets that hit an internal compiler error.
+    pub ices: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub target: String,
+    /// The nightly of the change, or the first nightly it failed on for broken targets.
+    pub nightly: String,
+}
+
+impl Report {
+    /// Collects the changes of the last `days` days.
+    pub async fn generate(db: &Db, days: u32, mode: BuildMode, host: Option<&str>) -> Result<Self> {
+        let since = nightlies::days_ago(days)?;
+        let (regressions, fixes) = last_changes(db.status_changes(&since, mode, host).await?);
+        let before = nightlies::days_ago(days + 1)?;
+        let still_broken = db
+            .stale_failures(&before, Some(mode), host)
+            .await?
+            .into_iter()
+            .map(|failure| Entry {
+                target: failure.target,
+                nightly: failure.first_bad,
+            })
+            .collect();
+        let ices = db
+            .ices_since(&since, mode, host)
+            .await?
+            .into_iter()
+            .map(|(target, nightly)| Entry { target, nightly })
+            .collect();
+        Ok(Self {
+            since,
+            mode,
+            regressions,
+            fixes,
+            still_broken,
+            ices,
+        })
+    }
+
+    fn sections(&self) -> [(&'static str, &[Entry]); 4] {
+        [
+            ("New regressions", &self.regressions),
+            ("Fixed", &self.fixes),
+            ("Still broken", &self.still_broken),
+            ("Internal compiler errors", &self.ices),
+        ]
+    }
+
+    /// The report as Markdown, with links to the builds on the website at `url`.
+    pub fn markdown(&self, url: &str) -> String {
+        let mut markdown = format!(
+            "# does-it-build report for {} since nightly-{}\n",
+            self.mode, self.since
+        );
+        for (title, entries) in self.sections() {
+            markdown.push_str(&format!("\n## {title} ({})\n\n", entries.len()));
+            if entries.is_empty() {
+                markdown.push_str("None.\n");
+            }
+            for entry in entries {
+                markdown.push_str(&format!(
+                    "- [`{}`]({}) on nightly-{}\n",
+                    entry.target,
+                    self.build_url(url, entry),
+                    entry.nightly
+                ));
+            }
+        }
+        markdown
+    }
+
+    /// The report as an HTML fragment, like `markdown`.
+    pub fn html(&self, url: &str) -> String {
+        let mut html = format!(
+            "<h1>does-it-build report for {} since nightly-{}</h1>\n",
+            self.mode, self.since
+        );
+        for (title, entries) in self.sections() {
+            html.push_str(&format!("<h2>{title} ({})</h2>\n", entries.len()));
+            if entries.is_empty() {
+                html.push_str("<p>None.</p>\n");
+                continue;
+            }
+            html.push_str("<ul>\n");
+            for entry in entries {
+                html.push_str(&format!(
+                    "<li><a href=\"{}\"><code>{}</code></a> on nightly-{}</li>\n",
+                    self.build_url(url, entry),
+                    entry.target,
+                    entry.nightly
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html
+    }
+
+    fn build_url(&self, url: &str, entry: &Entry) -> String {
+        format!(
+            "{url}/build?nightly={}&target={}&mode={}",
+            entry.nightly, entry.target, self.mode
+        )
+    }
+}
+
+/// Splits the status changes, ordered by target and nightly, into the targets whose last change
+/// was to failing and the ones whose last change was to passing.
+fn last_changes(changes: Vec<(String, String, Status)>) -> (Vec<Entry>, Vec<Entry>) {
+    let mut regressions = Vec::new();
+    let mut fixes = Vec::new();
+    let mut changes = changes.into_iter().peekable();
+    while let Some((target, nightly, status)) = changes.next() {
+        if changes
+            .peek()
+            .is_some_and(|(next_target, _, _)| *next_target == target)
+        {
+            continue;
+        }
+        let entry = Entry { target, nightly };
+        match status {
+            Status::Error => regressions.push(entry),
+            Status::Pass => fixes.push(entry),
+            _ => {}
+        }
+    }
+    (regressions, fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entry;
+    use crate::db::Status::{Error, Pass};
+
+    #[test]
+    fn last_changes() {
+        let change =
+            |target: &str, nightly: &str, status| (target.to_owned(), nightly.to_owned(), status);
+        let entry = |target: &str, nightly: &str| Entry {
+            target: target.to_owned(),
+            nightly: nightly.to_owned(),
+        };
+        let (regressions, fixes) = super::last_changes(vec![
+            change("a", "1", Error),
+            change("b", "1", Error),
+            change("b", "2", Pass),
+            change("c", "2", Pass),
+            change("c", "3", Error),
+        ]);
+        assert_eq!(regressions, [entry("a", "1"), entry("c", "3")]);
+        assert_eq!(fixes, [entry("b", "2")]);
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use color_eyre::{eyre::Context, Result};
+use tracing::{error, info};
+
+use crate::db::Db;
+
+/// What the logs removed by the retention policy are replaced with.
+pub(crate) const PRUNED_LOG_MARKER: &str = "This log was removed by the retention policy.\n";
+
+/// Periodically removes the logs of old builds, keeping their status.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_pruner(db: Db, retention_days: i64, interval: Duration) {
+    loop {
+        if let Err(err) = prune_old_logs(&db, retention_days).await {
+            error!(?err, "Failed to prune old logs");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn prune_old_logs(db: &Db, retention_days: i64) -> Result<()> {
+    let cutoff = time::OffsetDateTime::now_utc().date() - time::Duration::days(retention_days);
+    let cutoff = cutoff
+        .format(time::macros::format_description!("[year]-[month]-[day]"))
+        .wrap_err("formatting cutoff date")?;
+
+    let pruned = db.prune_logs_before(&cutoff, PRUNED_LOG_MARKER).await?;
+    info!(%cutoff, %pruned, "Pruned logs of old builds");
+    Ok(())
+}
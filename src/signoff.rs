@@ -0,0 +1,191 @@
+//! The report for the release team's signoff of a beta: the tier 1 and 2 targets that failed in
+//! any mode on the nightlies that went into it, from the previous beta branch on.
+
+use color_eyre::Result;
+use does_it_build_api::HostFilter;
+use serde::Serialize;
+
+use crate::{
+    db::{BuildMode, Db, Status},
+    nightlies,
+};
+
+/// Beta is branched from master every six weeks.
+const CYCLE_DAYS: u32 = 42;
+
+#[derive(Serialize)]
+pub struct SignoffReport {
+    /// The date the beta was branched on, whose nightly is the last one of the report.
+    pub beta_branch: String,
+    /// The nightlies after this one are part of the report.
+    pub previous_branch: String,
+    #[serde(flatten)]
+    pub host: HostFilter,
+    /// Targets whose last build before the branch failed.
+    pub failing: Vec<Failure>,
+    /// Targets that failed during the cycle, but passed by the branch.
+    pub fixed: Vec<Failure>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Failure {
+    pub target: String,
+    pub tier: i32,
+    pub mode: BuildMode,
+    pub first_failure: String,
+    pub last_failure: String,
+    /// How many nightlies of the cycle it failed on.
+    pub failed_nightlies: usize,
+    /// The error of the last failure.
+    pub error_excerpt: Option<String>,
+}
+
+/// The date of the beta branch before the one on `beta_branch`, an error if that isn't a date.
+pub fn previous_branch(beta_branch: &str) -> Result<String> {
+    nightlies::days_before(beta_branch, CYCLE_DAYS)
+}
+
+impl SignoffReport {
+    pub async fn generate(db: &Db, beta_branch: &str, host: Option<&str>) -> Result<Self> {
+        let previous_branch = previous_branch(beta_branch)?;
+        let builds = db
+            .tier_1_and_2_verdicts(&previous_branch, beta_branch, host)
+            .await?;
+        let (failing, fixed) = failures(builds);
+        Ok(Self {
+            beta_branch: beta_branch.to_owned(),
+            previous_branch,
+            host: HostFilter::new(host),
+            failing,
+            fixed,
+        })
+    }
+
+    /// The report as Markdown, with a checkbox for every failing target and links to the builds
+    /// on the website at `url`.
+    pub fn markdown(&self, url: &str) -> String {
+        let mut markdown = format!(
+            "# Tier 1 and 2 targets for the beta branched on {}\n\nBuilds of nightly-{} until nightly-{}",
+            self.beta_branch, self.previous_branch, self.beta_branch
+        );
+        match self.host.as_deref() {
+            Some(host) => markdown.push_str(&format!(" on `{host}`.\n")),
+            None => markdown.push_str(".\n"),
+        }
+        for (title, failures, checkbox) in [
+            ("Failing at the branch", &self.failing, "- [ ] "),
+            ("Fixed before the branch", &self.fixed, "- "),
+        ] {
+            markdown.push_str(&format!("\n## {title} ({})\n\n", failures.len()));
+            if failures.is_empty() {
+                markdown.push_str("None.\n");
+            }
+            for failure in failures {
+                markdown.push_str(&format!(
+                    "{checkbox}[`{target}`]({url}/build?nightly={last}&target={target}&mode={mode}) (tier {}, {mode}): first failed on nightly-{}, last on nightly-{last} ({} failed nightlies)",
+                    failure.tier,
+                    failure.first_failure,
+                    failure.failed_nightlies,
+                    target = failure.target,
+                    mode = failure.mode,
+                    last = failure.last_failure,
+                ));
+                if let Some(excerpt) = &failure.error_excerpt {
+                    markdown.push_str(&format!(": `{excerpt}`"));
+                }
+                markdown.push('\n');
+            }
+        }
+        markdown
+    }
+}
+
+/// Splits the builds with a verdict, ordered by target, mode and then nightly, into the targets and
+/// modes whose last build failed and the ones that failed before but passed since.
+fn failures(
+    builds: Vec<(String, BuildMode, String, Status, i32, Option<String>)>,
+) -> (Vec<Failure>, Vec<Failure>) {
+    let mut failing = Vec::new();
+    let mut fixed = Vec::new();
+    let mut builds = builds.into_iter().peekable();
+    let mut current: Option<Failure> = None;
+    while let Some((target, mode, nightly, status, tier, excerpt)) = builds.next() {
+        if status == Status::Error {
+            match &mut current {
+                Some(failure) => {
+                    failure.last_failure = nightly;
+                    failure.failed_nightlies += 1;
+                    failure.error_excerpt = excerpt;
+                }
+                None => {
+                    current = Some(Failure {
+                        target: target.clone(),
+                        tier,
+                        mode,
+                        first_failure: nightly.clone(),
+                        last_failure: nightly,
+                        failed_nightlies: 1,
+                        error_excerpt: excerpt,
+                    });
+                }
+            }
+        }
+        let last_of_target = builds.peek().is_none_or(|(next_target, next_mode, ..)| {
+            *next_target != target || *next_mode != mode
+        });
+        if last_of_target {
+            if let Some(failure) = current.take() {
+                if status == Status::Error {
+                    failing.push(failure);
+                } else {
+                    fixed.push(failure);
+                }
+            }
+        }
+    }
+    (failing, fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{
+        BuildMode::{self, Core, MiriStd},
+        Status::{self, Error, Pass},
+    };
+
+    #[test]
+    fn failures() {
+        let build = |target: &str, mode: BuildMode, nightly: &str, status: Status| {
+            let excerpt = (status == Error).then(|| format!("error on {nightly}"));
+            (
+                target.to_owned(),
+                mode,
+                nightly.to_owned(),
+                status,
+                2,
+                excerpt,
+            )
+        };
+        let (failing, fixed) = super::failures(vec![
+            build("aarch64-apple-ios", Core, "2026-09-01", Error),
+            build("aarch64-apple-ios", Core, "2026-09-02", Pass),
+            build("aarch64-apple-ios", MiriStd, "2026-09-01", Pass),
+            build("wasm32-wasip1", Core, "2026-09-01", Pass),
+            build("wasm32-wasip1", Core, "2026-09-02", Error),
+            build("wasm32-wasip1", Core, "2026-09-03", Error),
+            build("x86_64-pc-windows-msvc", Core, "2026-09-03", Pass),
+        ]);
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(fixed[0].target, "aarch64-apple-ios");
+        assert_eq!(fixed[0].mode, Core);
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].target, "wasm32-wasip1");
+        assert_eq!(failing[0].first_failure, "2026-09-02");
+        assert_eq!(failing[0].last_failure, "2026-09-03");
+        assert_eq!(failing[0].failed_nightlies, 2);
+        assert_eq!(
+            failing[0].error_excerpt.as_deref(),
+            Some("error on 2026-09-03")
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Immutable JSON summaries of finished nightlies in a directory or bucket, so bulk consumers can
+//! fetch cheap static files instead of going through the API.
+
+use std::{sync::Arc, time::Duration};
+
+use color_eyre::{eyre::Context, Result};
+use object_store::{local::LocalFileSystem, path::Path, ObjectStore};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::db::{BuildInfo, BuildMode, Db, ToolchainInfo};
+
+/// Where snapshots are written.
+pub struct SnapshotStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl SnapshotStore {
+    /// Uses the bucket of an `s3://bucket/prefix` URL, or a local directory that is created if needed.
+    pub fn new(url: &str) -> Result<Self> {
+        if url.starts_with("s3://") {
+            let (store, prefix) =
+                crate::backup::s3_store(url).wrap_err("configuring snapshot bucket")?;
+            return Ok(Self { store, prefix });
+        }
+        std::fs::create_dir_all(url)
+            .wrap_err_with(|| format!("creating snapshot directory {url}"))?;
+        let store = LocalFileSystem::new_with_prefix(url)
+            .wrap_err_with(|| format!("opening snapshot directory {url}"))?;
+        Ok(Self {
+            store: Arc::new(store),
+            prefix: Path::default(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    nightly: String,
+    mode: BuildMode,
+    toolchain: Option<ToolchainInfo>,
+    /// The builds of every host, ordered by target and host.
+    builds: Vec<BuildInfo>,
+}
+
+/// Writes snapshots of newly finished nightlies.
+/// Runs forever, errors are logged and retried on the next run.
+pub async fn background_publisher(db: Db, store: SnapshotStore, interval: Duration) {
+    loop {
+        if let Err(err) = publish_new(&db, &store).await {
+            error!(?err, "Failed to publish nightly snapshots");
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn publish_new(db: &Db, store: &SnapshotStore) -> Result<()> {
+    for finished in db.unpublished_snapshots(crate::HOST).await? {
+        let (nightly, mode) = (finished.nightly, finished.mode);
+        let snapshot = Snapshot {
+            toolchain: db.toolchain_info(&nightly).await?,
+            builds: db.nightly_builds(&nightly, mode).await?,
+            nightly,
+            mode,
+        };
+        let key = store
+            .prefix
+            .child(mode.to_string())
+            .child(format!("{}.json", snapshot.nightly));
+        let json = serde_json::to_vec(&snapshot).wrap_err("serializing snapshot")?;
+        store
+            .store
+            .put(&key, json.into())
+            .await
+            .wrap_err_with(|| format!("writing snapshot {key}"))?;
+        db.mark_snapshot_published(&snapshot.nightly, mode).await?;
+        info!(nightly = %snapshot.nightly, %mode, "Published snapshot");
+    }
+    Ok(())
+}
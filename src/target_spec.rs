@@ -0,0 +1,415 @@
+//! Comparing target specs between nightlies. Silent changes to a spec, like a different linker flavor
+//! or set of target features, often explain why a target broke.
+//! Targets that disappear while a target with a nearly identical spec appears are recorded as renamed.
+//! The cfgs of targets are compared the same way, new or removed target features show up there.
+//! Changes to fields like the pointer width or the panic strategy are escalated like regressions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use color_eyre::{eyre::Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::info;
+
+use crate::db::{Db, TargetRename, TargetSpec};
+
+/// How many fields of the spec, besides the metadata, may differ for a target to count as renamed.
+const MAX_RENAME_CHANGES: usize = 3;
+
+#[derive(Serialize)]
+pub struct SpecDiff {
+    pub target: String,
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<SpecChange>,
+}
+
+/// A top-level field of the spec that differs. `None` if the field doesn't exist on that side.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SpecChange {
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Compares the specs of the target on two nightlies.
+/// Without `from`, the newest earlier nightly that has a spec is used.
+/// `None` if one of the specs is missing.
+pub async fn diff_nightlies(
+    db: &Db,
+    target: &str,
+    from: Option<&str>,
+    to: &str,
+) -> Result<Option<SpecDiff>> {
+    let from = match from {
+        Some(from) => from.to_owned(),
+        None => match db.previous_target_spec_nightly(target, to).await? {
+            Some(from) => from,
+            None => return Ok(None),
+        },
+    };
+    let (Some(before), Some(after)) = (
+        db.target_spec(target, Some(&from)).await?,
+        db.target_spec(target, Some(to)).await?,
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(SpecDiff {
+        target: target.to_owned(),
+        from,
+        to: to.to_owned(),
+        changes: diff(&before.spec, &after.spec)?,
+    }))
+}
+
+/// Fields of the spec whose changes alter the ABI or the behavior of code built for the target,
+/// which matter even if the target still builds.
+const SEMANTIC_KEYS: &[&str] = &[
+    "target-pointer-width",
+    "target-endian",
+    "max-atomic-width",
+    "panic-strategy",
+    "linker-flavor",
+];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SemanticChange {
+    pub target: String,
+    #[serde(flatten)]
+    pub change: SpecChange,
+}
+
+/// The changes to important fields of the specs between the previous nightly with specs and the
+/// nightly, with the previous nightly. `None` if there is no previous nightly.
+pub async fn semantic_changes(
+    db: &Db,
+    nightly: &str,
+) -> Result<Option<(String, Vec<SemanticChange>)>> {
+    let (Some(previous), _) = db.adjacent_spec_nightlies(nightly).await? else {
+        return Ok(None);
+    };
+    let before = db.target_specs_of_nightly(&previous).await?;
+    let after = db.target_specs_of_nightly(nightly).await?;
+    let changes = find_semantic_changes(&before, &after)?;
+    Ok(Some((previous, changes)))
+}
+
+/// The changes to important fields of targets that exist on both sides.
+fn find_semantic_changes(
+    before: &[TargetSpec],
+    after: &[TargetSpec],
+) -> Result<Vec<SemanticChange>> {
+    let before = before
+        .iter()
+        .map(|spec| (&spec.target, &spec.spec))
+        .collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+    for spec in after {
+        let Some(previous) = before.get(&spec.target) else {
+            continue;
+        };
+        changes.extend(
+            diff(previous, &spec.spec)?
+                .into_iter()
+                .filter(|change| SEMANTIC_KEYS.contains(&change.key.as_str()))
+                .map(|change| SemanticChange {
+                    target: spec.target.clone(),
+                    change,
+                }),
+        );
+    }
+    Ok(changes)
+}
+
+#[derive(Serialize)]
+pub struct CfgDiff {
+    pub target: String,
+    pub from: String,
+    pub to: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Compares the cfgs of the target on two nightlies, like `diff_nightlies` does for specs.
+pub async fn diff_cfg_nightlies(
+    db: &Db,
+    target: &str,
+    from: Option<&str>,
+    to: &str,
+) -> Result<Option<CfgDiff>> {
+    let from = match from {
+        Some(from) => from.to_owned(),
+        None => match db.previous_target_cfg_nightly(target, to).await? {
+            Some(from) => from,
+            None => return Ok(None),
+        },
+    };
+    let (Some(before), Some(after)) = (
+        db.target_cfg(target, Some(&from)).await?,
+        db.target_cfg(target, Some(to)).await?,
+    ) else {
+        return Ok(None);
+    };
+    let (added, removed) = diff_cfg(&before.cfg, &after.cfg);
+    Ok(Some(CfgDiff {
+        target: target.to_owned(),
+        from,
+        to: to.to_owned(),
+        added,
+        removed,
+    }))
+}
+
+/// The lines only in `after` and the ones only in `before`.
+fn diff_cfg(before: &str, after: &str) -> (Vec<String>, Vec<String>) {
+    let before = before.lines().collect::<HashSet<_>>();
+    let after = after.lines().collect::<HashSet<_>>();
+    let mut added = after
+        .difference(&before)
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    let mut removed = before
+        .difference(&after)
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    added.sort();
+    removed.sort();
+    (added, removed)
+}
+
+/// Looks for renames between the nightly and the nightlies with specs right before and after it,
+/// as nightlies aren't necessarily captured in order.
+pub async fn record_renames(db: &Db, nightly: &str) -> Result<()> {
+    let specs = db.target_specs_of_nightly(nightly).await?;
+    let (before, after) = db.adjacent_spec_nightlies(nightly).await?;
+    let mut renames = Vec::new();
+    if let Some(before) = before {
+        let before = db.target_specs_of_nightly(&before).await?;
+        renames.extend(find_renames(&before, &specs)?);
+    }
+    if let Some(after) = after {
+        let after = db.target_specs_of_nightly(&after).await?;
+        renames.extend(find_renames(&specs, &after)?);
+    }
+    for rename in renames {
+        info!(old = %rename.old_target, new = %rename.new_target, nightly = %rename.nightly, "Found target rename");
+        db.insert_target_rename(&rename).await?;
+    }
+    Ok(())
+}
+
+/// Pairs up targets that are gone in `after` with new ones, the most similar first.
+fn find_renames(before: &[TargetSpec], after: &[TargetSpec]) -> Result<Vec<TargetRename>> {
+    let before_names = before
+        .iter()
+        .map(|spec| &spec.target)
+        .collect::<HashSet<_>>();
+    let after_names = after
+        .iter()
+        .map(|spec| &spec.target)
+        .collect::<HashSet<_>>();
+
+    let mut candidates = Vec::new();
+    for old in before
+        .iter()
+        .filter(|spec| !after_names.contains(&spec.target))
+    {
+        for new in after
+            .iter()
+            .filter(|spec| !before_names.contains(&spec.target))
+        {
+            let changes = diff(&old.spec, &new.spec)?
+                .into_iter()
+                .filter(|change| change.key != "metadata")
+                .count();
+            if changes <= MAX_RENAME_CHANGES {
+                candidates.push((changes, old, new));
+            }
+        }
+    }
+    candidates
+        .sort_by(|a, b| (a.0, &a.1.target, &a.2.target).cmp(&(b.0, &b.1.target, &b.2.target)));
+
+    let mut used = HashSet::new();
+    let mut renames = Vec::new();
+    for (_, old, new) in candidates {
+        if used.contains(&old.target) || used.contains(&new.target) {
+            continue;
+        }
+        used.insert(&old.target);
+        used.insert(&new.target);
+        renames.push(TargetRename {
+            old_target: old.target.clone(),
+            new_target: new.target.clone(),
+            nightly: new.nightly.clone(),
+        });
+    }
+    Ok(renames)
+}
+
+fn diff(before: &str, after: &str) -> Result<Vec<SpecChange>> {
+    let mut before = serde_json::from_str::<BTreeMap<String, Value>>(before)
+        .wrap_err("stored target spec is invalid")?;
+    let after = serde_json::from_str::<BTreeMap<String, Value>>(after)
+        .wrap_err("stored target spec is invalid")?;
+
+    let mut changes = Vec::new();
+    for (key, after) in after {
+        let before = before.remove(&key);
+        if before.as_ref() != Some(&after) {
+            changes.push(SpecChange {
+                key,
+                before,
+                after: Some(after),
+            });
+        }
+    }
+    changes.extend(before.into_iter().map(|(key, before)| SpecChange {
+        key,
+        before: Some(before),
+        after: None,
+    }));
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{SemanticChange, SpecChange};
+    use crate::db::{TargetRename, TargetSpec};
+
+    #[test]
+    fn find_renames() {
+        let spec = |target: &str, nightly: &str, spec: &str| TargetSpec {
+            target: target.to_owned(),
+            nightly: nightly.to_owned(),
+            spec: spec.to_owned(),
+        };
+        let before = [
+            spec("kept", "1", r#"{"arch": "x86"}"#),
+            spec(
+                "old",
+                "1",
+                r#"{"arch": "arm", "os": "none", "metadata": {"tier": 3}}"#,
+            ),
+            spec(
+                "removed",
+                "1",
+                r#"{"arch": "a", "os": "b", "env": "c", "abi": "d"}"#,
+            ),
+        ];
+        let after = [
+            spec("kept", "2", r#"{"arch": "x86"}"#),
+            spec(
+                "new",
+                "2",
+                r#"{"arch": "arm", "os": "none", "metadata": {"tier": 2}}"#,
+            ),
+            spec("similar", "2", r#"{"arch": "arm", "os": "linux"}"#),
+        ];
+        assert_eq!(
+            super::find_renames(&before, &after).unwrap(),
+            [TargetRename {
+                old_target: "old".to_owned(),
+                new_target: "new".to_owned(),
+                nightly: "2".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn find_semantic_changes() {
+        let spec = |target: &str, nightly: &str, spec: &str| TargetSpec {
+            target: target.to_owned(),
+            nightly: nightly.to_owned(),
+            spec: spec.to_owned(),
+        };
+        let before = [
+            spec(
+                "thumbv6m-none-eabi",
+                "2026-10-09",
+                r#"{"max-atomic-width": 32, "linker-flavor": "gnu-lld", "cpu": "cortex-m0"}"#,
+            ),
+            spec(
+                "x86_64-unknown-none",
+                "2026-10-09",
+                r#"{"panic-strategy": "abort"}"#,
+            ),
+        ];
+        let after = [
+            spec(
+                "thumbv6m-none-eabi",
+                "2026-10-10",
+                r#"{"max-atomic-width": 0, "linker-flavor": "gnu-lld", "cpu": "cortex-m0plus"}"#,
+            ),
+            spec("x86_64-unknown-none", "2026-10-10", r#"{}"#),
+            spec(
+                "riscv32e-unknown-none-elf",
+                "2026-10-10",
+                r#"{"max-atomic-width": 32}"#,
+            ),
+        ];
+        assert_eq!(
+            super::find_semantic_changes(&before, &after).unwrap(),
+            [
+                SemanticChange {
+                    target: "thumbv6m-none-eabi".to_owned(),
+                    change: SpecChange {
+                        key: "max-atomic-width".to_owned(),
+                        before: Some(json!(32)),
+                        after: Some(json!(0)),
+                    },
+                },
+                SemanticChange {
+                    target: "x86_64-unknown-none".to_owned(),
+                    change: SpecChange {
+                        key: "panic-strategy".to_owned(),
+                        before: Some(json!("abort")),
+                        after: None,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_cfg() {
+        let before = "panic=\"unwind\"\ntarget_feature=\"sse\"\nunix";
+        let after = "panic=\"unwind\"\ntarget_feature=\"sse\"\ntarget_feature=\"sse2\"";
+        assert_eq!(
+            super::diff_cfg(before, after),
+            (
+                vec!["target_feature=\"sse2\"".to_owned()],
+                vec!["unix".to_owned()]
+            )
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let before = r#"{"arch": "x86", "linker-flavor": "gcc", "max-atomic-width": 64}"#;
+        let after = r#"{"arch": "x86", "linker-flavor": "gnu-cc", "features": "+sse2"}"#;
+        assert_eq!(
+            super::diff(before, after).unwrap(),
+            [
+                SpecChange {
+                    key: "features".to_owned(),
+                    before: None,
+                    after: Some(json!("+sse2")),
+                },
+                SpecChange {
+                    key: "linker-flavor".to_owned(),
+                    before: Some(json!("gcc")),
+                    after: Some(json!("gnu-cc")),
+                },
+                SpecChange {
+                    key: "max-atomic-width".to_owned(),
+                    before: Some(json!(64)),
+                    after: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Exporting traces and metrics over OTLP, in addition to the logs on stderr.
+//!
+//! All spans (installing toolchains, building targets, database queries and HTTP requests) are
+//! exported as traces, so it is visible where the time of a nightly goes. The metrics are recorded
+//! explicitly with the functions in here.
+
+use std::{sync::LazyLock, time::Duration};
+
+use color_eyre::{eyre::Context, Result};
+use opentelemetry::{
+    metrics::{Counter, Histogram},
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+use crate::{
+    config::TelemetryConfig,
+    db::{BuildMode, Status},
+};
+
+static BUILDS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    opentelemetry::global::meter("does-it-build")
+        .u64_counter("builds")
+        .with_description("Finished builds of a target")
+        .build()
+});
+static BUILD_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    opentelemetry::global::meter("does-it-build")
+        .f64_histogram("build.duration")
+        .with_unit("s")
+        .with_description("Duration of building a target")
+        .build()
+});
+
+/// Flushes the remaining traces and metrics when dropped.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Sets up the exporters if an endpoint is configured and returns the layer that exports the spans.
+    pub fn init<S>(config: &TelemetryConfig) -> Result<Option<(Self, impl Layer<S>)>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let Some(endpoint) = &config.otlp_endpoint else {
+            return Ok(None);
+        };
+        let endpoint = endpoint.trim_end_matches('/');
+        let resource = Resource::builder()
+            .with_service_name(config.service_name.clone())
+            .with_attribute(KeyValue::new("service.version", crate::VERSION))
+            .with_attribute(KeyValue::new("host.arch", crate::HOST))
+            .build();
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/traces"))
+            .build()
+            .wrap_err("creating OTLP span exporter")?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/metrics"))
+            .build()
+            .wrap_err("creating OTLP metric exporter")?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+        opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+        let layer =
+            tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("does-it-build"));
+        Ok(Some((
+            Self {
+                tracer_provider,
+                meter_provider,
+            },
+            layer,
+        )))
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(err) = self.tracer_provider.shutdown() {
+            eprintln!("Error: failed to flush traces: {err}");
+        }
+        if let Err(err) = self.meter_provider.shutdown() {
+            eprintln!("Error: failed to flush metrics: {err}");
+        }
+    }
+}
+
+/// Records a finished build. Does nothing if no exporter is configured.
+pub fn record_build(mode: BuildMode, status: Status, duration: Duration) {
+    let attributes = [
+        KeyValue::new("mode", mode.to_string()),
+        KeyValue::new("status", status.to_string()),
+    ];
+    BUILDS.add(1, &attributes);
+    BUILD_DURATION.record(duration.as_secs_f64(), &attributes);
+}
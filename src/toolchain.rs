@@ -0,0 +1,659 @@
+//! Installing and removing the nightly toolchains the builds need with rustup.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{Debug, Display},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::{build_env::BuildEnv, config::ToolchainCacheConfig, db::BuildMode};
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Toolchain(String);
+impl Toolchain {
+    pub fn from_nightly(nightly: &str) -> Self {
+        Self(format!("nightly-{nightly}"))
+    }
+
+    /// The beta that was released on the same day as the nightly.
+    pub fn beta_of(nightly: &str) -> Self {
+        Self(format!("beta-{nightly}"))
+    }
+
+    pub fn is_nightly(&self) -> bool {
+        self.0.starts_with("nightly-")
+    }
+
+    /// The date of a dated nightly.
+    pub fn nightly(&self) -> Option<&str> {
+        self.0.strip_prefix("nightly-")
+    }
+}
+impl Debug for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The components the builds of a mode need on top of the minimal profile.
+fn components(mode: BuildMode) -> &'static [&'static str] {
+    match mode {
+        BuildMode::Core => &["rust-src"],
+        BuildMode::MiriStd => &["rust-src", "miri"],
+    }
+}
+
+/// Keeps track of who uses which installed toolchain, so that a toolchain is only removed once
+/// nothing builds with it anymore, like when core and miri-std of the same nightly are built at the
+/// same time. Unused toolchains stay installed until they don't fit into the cache anymore.
+#[derive(Clone)]
+pub struct ToolchainManager {
+    usage: Arc<Mutex<Usage>>,
+    /// Held while running rustup, so an install never races with the removal of the same toolchain.
+    rustup: Arc<tokio::sync::Mutex<()>>,
+    cache: ToolchainCacheConfig,
+    env: BuildEnv,
+    /// The version of rustup seen last.
+    rustup_version: Arc<Mutex<Option<String>>>,
+    prefetch: Arc<Mutex<Option<Prefetch>>>,
+}
+
+/// A toolchain that is installed in the background before a nightly needs it.
+struct Prefetch {
+    toolchain: Toolchain,
+    /// Holds the lease once the toolchain is installed, so it isn't removed before it is used.
+    task: JoinHandle<Result<Result<ToolchainLease, String>>>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    toolchains: HashMap<Toolchain, Installed>,
+    /// Counts the releases, to find the least recently used toolchains.
+    clock: u64,
+}
+
+#[derive(Debug, Default)]
+struct Installed {
+    users: usize,
+    components: BTreeSet<&'static str>,
+    last_used: u64,
+}
+
+impl Usage {
+    /// Adds a user of the toolchain and returns the components that still have to be added.
+    fn acquire(&mut self, toolchain: &Toolchain, mode: BuildMode) -> Vec<&'static str> {
+        let installed = self.toolchains.entry(toolchain.clone()).or_default();
+        installed.users += 1;
+        components(mode)
+            .iter()
+            .copied()
+            .filter(|component| !installed.components.contains(component))
+            .collect()
+    }
+
+    fn added(&mut self, toolchain: &Toolchain, component: &'static str) {
+        if let Some(installed) = self.toolchains.get_mut(toolchain) {
+            installed.components.insert(component);
+        }
+    }
+
+    /// Removes a user of the toolchain. It stays installed even if that was the last one.
+    fn release(&mut self, toolchain: &Toolchain) {
+        self.clock += 1;
+        if let Some(installed) = self.toolchains.get_mut(toolchain) {
+            installed.users -= 1;
+            installed.last_used = self.clock;
+        }
+    }
+
+    /// The toolchains nothing uses, least recently used first.
+    fn unused(&self) -> Vec<Toolchain> {
+        let mut unused = self
+            .toolchains
+            .iter()
+            .filter(|(_, installed)| installed.users == 0)
+            .map(|(toolchain, installed)| (installed.last_used, toolchain.clone()))
+            .collect::<Vec<_>>();
+        unused.sort();
+        unused.into_iter().map(|(_, toolchain)| toolchain).collect()
+    }
+}
+
+/// A toolchain that stays installed while this exists. Pass it to [`ToolchainLease::release`]
+/// when done with it.
+pub struct ToolchainLease {
+    manager: ToolchainManager,
+    toolchain: Toolchain,
+    rustup_version: String,
+    released: bool,
+}
+
+impl ToolchainLease {
+    pub fn toolchain(&self) -> &Toolchain {
+        &self.toolchain
+    }
+
+    /// The version of rustup the toolchain was acquired with.
+    pub fn rustup_version(&self) -> &str {
+        &self.rustup_version
+    }
+
+    /// Marks the toolchain as unused and removes the toolchains that don't fit into the cache anymore.
+    pub async fn release(mut self) -> Result<()> {
+        let _rustup = self.manager.rustup.lock().await;
+        self.released = true;
+        self.manager.usage.lock().unwrap().release(&self.toolchain);
+        self.manager.evict().await
+    }
+}
+
+impl Drop for ToolchainLease {
+    fn drop(&mut self) {
+        if !self.released {
+            // The cache is cleaned up on the next release.
+            self.manager.usage.lock().unwrap().release(&self.toolchain);
+        }
+    }
+}
+
+impl ToolchainManager {
+    /// Takes over the dated nightlies and betas that are already installed, like the ones an earlier
+    /// run kept in the cache, so they are removed once they don't fit anymore.
+    pub async fn new(env: BuildEnv, cache: ToolchainCacheConfig) -> Result<Self> {
+        // The home of the builds has to exist before rustup can use it there.
+        env.executor().create_remote_dir(env.home()).await?;
+        let list = env
+            .executor()
+            .output(env.command("rustup").args(["toolchain", "list"]))
+            .await
+            .wrap_err("failed to spawn rustup")?;
+        let manager = Self {
+            usage: Arc::default(),
+            rustup: Arc::default(),
+            cache,
+            env,
+            rustup_version: Arc::default(),
+            prefetch: Arc::default(),
+        };
+        let rustup_version = manager.check_rustup().await?;
+        info!(%rustup_version, "Found rustup");
+        {
+            let mut usage = manager.usage.lock().unwrap();
+            for toolchain in
+                parse_toolchain_list(&String::from_utf8_lossy(&list.stdout), manager.env.host())
+            {
+                usage.toolchains.insert(toolchain, Installed::default());
+            }
+        }
+        manager.evict().await?;
+        Ok(manager)
+    }
+
+    /// The environment the toolchains are installed in and have to be run with.
+    pub fn env(&self) -> &BuildEnv {
+        &self.env
+    }
+
+    /// The dates of the installed dated nightlies.
+    pub fn installed_nightlies(&self) -> Vec<String> {
+        self.usage
+            .lock()
+            .unwrap()
+            .toolchains
+            .keys()
+            .filter_map(|toolchain| Some(toolchain.nightly()?.to_owned()))
+            .collect()
+    }
+
+    /// Installs the toolchain with the components the mode needs, unless it already is. The inner
+    /// error is the one of rustup if the nightly or one of the components doesn't exist, which no
+    /// retry will fix.
+    #[tracing::instrument(skip(self))]
+    pub async fn acquire(
+        &self,
+        toolchain: &Toolchain,
+        mode: BuildMode,
+    ) -> Result<Result<ToolchainLease, String>> {
+        let prefetch = self.prefetch.lock().unwrap().take();
+        // Keeps the prefetched toolchain in use until this lease has it.
+        let _prefetched = match prefetch {
+            Some(prefetch) if prefetch.toolchain == *toolchain => {
+                // Installing it again below reports the errors.
+                match prefetch.task.await {
+                    Ok(Ok(Ok(lease))) => Some(lease),
+                    Ok(Ok(Err(error))) => {
+                        debug!(%toolchain, %error, "Toolchain of the prefetch is not available");
+                        None
+                    }
+                    Ok(Err(err)) => {
+                        debug!(%toolchain, ?err, "Failed to prefetch toolchain");
+                        None
+                    }
+                    Err(err) => {
+                        debug!(%toolchain, ?err, "Failed to prefetch toolchain");
+                        None
+                    }
+                }
+            }
+            // The other toolchain is released once it is installed, and stays in the cache.
+            _ => None,
+        };
+        self.install(toolchain, mode).await
+    }
+
+    /// Starts installing the toolchain in the background, like the one of the next nightly while
+    /// the last targets of the current one are still building, so `acquire` finds it installed.
+    pub fn prefetch(&self, toolchain: Toolchain, mode: BuildMode) {
+        info!(%toolchain, %mode, "Installing toolchain of the next nightly ahead of time");
+        let manager = self.clone();
+        let task = {
+            let toolchain = toolchain.clone();
+            tokio::spawn(async move { manager.install(&toolchain, mode).await })
+        };
+        *self.prefetch.lock().unwrap() = Some(Prefetch { toolchain, task });
+    }
+
+    async fn install(
+        &self,
+        toolchain: &Toolchain,
+        mode: BuildMode,
+    ) -> Result<Result<ToolchainLease, String>> {
+        let _rustup = self.rustup.lock().await;
+        let rustup_version = self.check_rustup().await?;
+        let missing = self.usage.lock().unwrap().acquire(toolchain, mode);
+        // Releases the toolchain again if installing it fails.
+        let lease = ToolchainLease {
+            manager: self.clone(),
+            toolchain: toolchain.clone(),
+            rustup_version,
+            released: false,
+        };
+        if missing.is_empty() {
+            debug!(%toolchain, "Toolchain is already installed");
+            return Ok(Ok(lease));
+        }
+        if self.env.dist().is_offline() {
+            return Ok(self
+                .check_provisioned(toolchain, &missing)
+                .await?
+                .map(|()| lease));
+        }
+
+        let mut steps = Vec::new();
+        // Every mode needs a component, so the toolchain is installed once one was added.
+        if missing.len() == components(mode).len() {
+            steps.push((
+                None,
+                vec!["toolchain", "install", &toolchain.0, "--profile", "minimal"],
+            ));
+        }
+        for &component in &missing {
+            steps.push((
+                Some(component),
+                vec!["component", "add", component, "--toolchain", &toolchain.0],
+            ));
+        }
+        info!(%toolchain, ?missing, "Installing toolchain");
+        for (component, args) in steps {
+            if let Err(error) = rustup(&self.env, args).await? {
+                return Ok(Err(error));
+            }
+            if let Some(component) = component {
+                self.usage.lock().unwrap().added(toolchain, component);
+            }
+        }
+        Ok(Ok(lease))
+    }
+
+    /// Offline, nothing can be installed, so the components have to be there already. Missing ones
+    /// make the toolchain unavailable, like ones that don't exist.
+    async fn check_provisioned(
+        &self,
+        toolchain: &Toolchain,
+        components: &[&'static str],
+    ) -> Result<Result<(), String>> {
+        let output = self
+            .env
+            .executor()
+            .output(self.env.command("rustup").args([
+                "component",
+                "list",
+                "--installed",
+                "--toolchain",
+                &toolchain.0,
+            ]))
+            .await
+            .wrap_err("failed to spawn rustup")?;
+        if !output.status.success() {
+            return Ok(Err(format!(
+                "{toolchain} is not installed, and the builder is offline"
+            )));
+        }
+        let installed = String::from_utf8_lossy(&output.stdout);
+        for &component in components {
+            if !has_component(&installed, component, self.env.host()) {
+                return Ok(Err(format!(
+                    "{component} of {toolchain} is not installed, and the builder is offline"
+                )));
+            }
+            self.usage.lock().unwrap().added(toolchain, component);
+        }
+        Ok(Ok(()))
+    }
+
+    /// The version of rustup, warning if it changed since it was last checked. Updates of rustup on
+    /// the host have changed results before, so they shouldn't go unnoticed.
+    async fn check_rustup(&self) -> Result<String> {
+        let output = self
+            .env
+            .executor()
+            .output(self.env.command("rustup").arg("--version"))
+            .await
+            .wrap_err("failed to spawn rustup")?;
+        if !output.status.success() {
+            bail!(
+                "rustup --version failed: {:?}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let version = parse_rustup_version(&String::from_utf8_lossy(&output.stdout));
+        let previous = self.rustup_version.lock().unwrap().replace(version.clone());
+        if let Some(previous) = previous.filter(|previous| *previous != version) {
+            warn!(%previous, current = %version, "rustup changed while the service was running");
+        }
+        Ok(version)
+    }
+
+    /// Removes the least recently used toolchains that don't fit into the cache. Only call this
+    /// while holding the rustup lock, or before anything else can use the manager.
+    async fn evict(&self) -> Result<()> {
+        // Offline, nothing that is removed could be installed again.
+        if self.env.dist().is_offline() {
+            return Ok(());
+        }
+        let unused = self.usage.lock().unwrap().unused();
+        let mut sized = Vec::new();
+        for toolchain in unused {
+            let size = match self.cache.max_bytes {
+                Some(_) => toolchain_size(&self.env, &toolchain).await,
+                None => 0,
+            };
+            sized.push((toolchain, size));
+        }
+        for (toolchain, _) in &sized[..evictions(&sized, &self.cache)] {
+            uninstall_toolchain(&self.env, toolchain).await?;
+            self.usage.lock().unwrap().toolchains.remove(toolchain);
+        }
+        Ok(())
+    }
+}
+
+/// Whether the output of `rustup component list --installed` contains the component, which is
+/// listed with the host for components like miri that differ between hosts.
+fn has_component(installed: &str, component: &str, host: &str) -> bool {
+    let with_host = format!("{component}-{host}");
+    installed
+        .lines()
+        .any(|line| line.trim() == component || line.trim() == with_host)
+}
+
+/// The first line of `rustup --version`, newer versions also print which rustc is active.
+fn parse_rustup_version(output: &str) -> String {
+    output.lines().next().unwrap_or_default().trim().to_owned()
+}
+
+/// How many of the unused toolchains, least recently used first and with their size, have to be
+/// removed for the others to fit into the cache.
+fn evictions(unused: &[(Toolchain, u64)], cache: &ToolchainCacheConfig) -> usize {
+    let mut evict = unused.len().saturating_sub(cache.keep);
+    if let Some(max_bytes) = cache.max_bytes {
+        let mut kept = unused[evict..].iter().map(|(_, size)| size).sum::<u64>();
+        while kept > max_bytes {
+            kept -= unused[evict].1;
+            evict += 1;
+        }
+    }
+    evict
+}
+
+/// The dated nightlies in the output of `rustup toolchain list`. The default toolchain is left alone,
+/// even if it is one of them.
+fn parse_toolchain_list(output: &str, host: &str) -> Vec<Toolchain> {
+    output
+        .lines()
+        .filter(|line| !line.contains('('))
+        .filter_map(|line| {
+            let name = line.trim().strip_suffix(&format!("-{host}"))?;
+            let date = name
+                .strip_prefix("nightly-")
+                .or_else(|| name.strip_prefix("beta-"))?;
+            let is_date = date.len() == 10
+                && date.chars().enumerate().all(|(i, c)| {
+                    if i == 4 || i == 7 {
+                        c == '-'
+                    } else {
+                        c.is_ascii_digit()
+                    }
+                });
+            is_date.then(|| Toolchain(name.to_owned()))
+        })
+        .collect()
+}
+
+/// The disk space an installed toolchain takes up in bytes.
+async fn toolchain_size(env: &BuildEnv, toolchain: &Toolchain) -> u64 {
+    let dir = env
+        .rustup_home()
+        .join("toolchains")
+        .join(format!("{toolchain}-{}", env.host()));
+    match env.executor().remote_dir_size(&dir).await {
+        Ok(Some(size)) => return size,
+        Ok(None) => {}
+        Err(err) => {
+            warn!(?err, %toolchain, "Failed to get the size of the remote toolchain");
+            return 0;
+        }
+    }
+    tokio::task::spawn_blocking(move || dir_size(&dir))
+        .await
+        .unwrap_or_default()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// How often a download whose hash doesn't match the manifest is tried before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Runs rustup. The inner error is its output if it failed because something doesn't exist.
+/// Downloads that were corrupted on the way are tried again.
+async fn rustup(env: &BuildEnv, args: Vec<&str>) -> Result<Result<(), String>> {
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        let result = env
+            .executor()
+            .output(env.command("rustup").args(&args))
+            .await
+            .wrap_err("failed to spawn rustup")?;
+        if result.status.success() {
+            return Ok(Ok(()));
+        }
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        if is_unavailable(&stderr) {
+            return Ok(Err(stderr.trim().to_owned()));
+        }
+        if !is_checksum_failure(&stderr) || attempt == DOWNLOAD_ATTEMPTS {
+            bail!("rustup failed: {stderr:?}");
+        }
+        warn!(attempt, %stderr, "Download didn't match the hash in the manifest, trying again");
+    }
+    unreachable!("the last attempt returns")
+}
+
+/// Whether rustup rejected a download because its hash didn't match the one in the manifest.
+fn is_checksum_failure(rustup_stderr: &str) -> bool {
+    rustup_stderr.contains("checksum failed")
+}
+
+/// Whether rustup failed because the nightly or a component of it doesn't exist, like rust-src on
+/// very old nightlies or miri on days it didn't build, instead of a problem like a network error.
+fn is_unavailable(rustup_stderr: &str) -> bool {
+    [
+        "no release found",
+        "is unavailable for download",
+        "does not contain component",
+        "is not available",
+    ]
+    .iter()
+    .any(|message| rustup_stderr.contains(message))
+}
+
+#[tracing::instrument(skip(env))]
+async fn uninstall_toolchain(env: &BuildEnv, toolchain: &Toolchain) -> Result<()> {
+    info!(%toolchain, "Uninstalling toolchain");
+
+    let result = env
+        .executor()
+        .output(
+            env.command("rustup")
+                .arg("toolchain")
+                .arg("remove")
+                .arg(&toolchain.0),
+        )
+        .await
+        .wrap_err("failed to spawn rustup")?;
+    if !result.status.success() {
+        bail!(
+            "rustup toolchain remove failed: {:?}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Toolchain, Usage};
+    use crate::{config::ToolchainCacheConfig, db::BuildMode};
+
+    #[test]
+    fn usage() {
+        let old = Toolchain::from_nightly("2026-10-09");
+        let toolchain = Toolchain::from_nightly("2026-10-10");
+        let mut usage = Usage::default();
+
+        assert_eq!(usage.acquire(&old, BuildMode::Core), ["rust-src"]);
+        assert_eq!(usage.acquire(&toolchain, BuildMode::Core), ["rust-src"]);
+        usage.added(&toolchain, "rust-src");
+        // Only what the first user didn't need is added.
+        assert_eq!(usage.acquire(&toolchain, BuildMode::MiriStd), ["miri"]);
+        usage.added(&toolchain, "miri");
+        assert!(usage.acquire(&toolchain, BuildMode::MiriStd).is_empty());
+
+        usage.release(&toolchain);
+        usage.release(&toolchain);
+        assert!(usage.unused().is_empty());
+        usage.release(&toolchain);
+        usage.release(&old);
+        assert_eq!(usage.unused(), [toolchain.clone(), old]);
+        assert!(usage.acquire(&toolchain, BuildMode::MiriStd).is_empty());
+    }
+
+    #[test]
+    fn evictions() {
+        let unused = [
+            (Toolchain::from_nightly("2026-10-08"), 300),
+            (Toolchain::from_nightly("2026-10-09"), 200),
+            (Toolchain::from_nightly("2026-10-10"), 100),
+        ];
+        let cache = |keep, max_bytes| ToolchainCacheConfig { keep, max_bytes };
+        assert_eq!(super::evictions(&unused, &cache(3, None)), 0);
+        assert_eq!(super::evictions(&unused, &cache(1, None)), 2);
+        assert_eq!(super::evictions(&unused, &cache(5, Some(300))), 1);
+        assert_eq!(super::evictions(&unused, &cache(5, Some(50))), 3);
+        assert_eq!(super::evictions(&[], &cache(0, Some(0))), 0);
+    }
+
+    #[test]
+    fn rustup_version() {
+        assert_eq!(
+            super::parse_rustup_version("rustup 1.27.1 (54dd3d00f 2024-04-24)\n"),
+            "rustup 1.27.1 (54dd3d00f 2024-04-24)"
+        );
+    }
+
+    #[test]
+    fn has_component() {
+        let installed = "cargo-x86_64-unknown-linux-gnu\nmiri-x86_64-unknown-linux-gnu\nrust-src\nrust-std-x86_64-unknown-linux-gnu\nrustc-x86_64-unknown-linux-gnu\n";
+        let host = "x86_64-unknown-linux-gnu";
+        assert!(super::has_component(installed, "rust-src", host));
+        assert!(super::has_component(installed, "miri", host));
+        assert!(!super::has_component(
+            installed,
+            "miri",
+            "aarch64-apple-darwin"
+        ));
+        assert!(!super::has_component(installed, "rust", host));
+    }
+
+    #[test]
+    fn toolchain_list() {
+        let output = format!(
+            "stable-{host}\nbeta-2026-10-09-{host}\nnightly-{host} (default)\nnightly-2026-10-09-{host}\nnightly-2026-10-10-{host} (active)\nnightly-2026-10-08-aarch64-apple-darwin\n",
+            host = crate::HOST
+        );
+        assert_eq!(
+            super::parse_toolchain_list(&output, crate::HOST),
+            [
+                Toolchain::beta_of("2026-10-09"),
+                Toolchain::from_nightly("2026-10-09")
+            ]
+        );
+    }
+
+    #[test]
+    fn is_checksum_failure() {
+        assert!(super::is_checksum_failure(
+            "error: component download failed for rust-src: checksum failed for 'https://static.rust-lang.org/dist/2026-10-10/rust-src-nightly.tar.xz', expected: 'abc', calculated: 'def'"
+        ));
+        assert!(!super::is_checksum_failure(
+            "error: no release found for 'nightly-2015-01-01'"
+        ));
+    }
+
+    #[test]
+    fn is_unavailable() {
+        assert!(super::is_unavailable(
+            "error: component 'miri' for target 'x86_64-unknown-linux-gnu' is unavailable for download for channel 'nightly-2026-10-10'"
+        ));
+        assert!(super::is_unavailable(
+            "error: no release found for 'nightly-2015-01-01'"
+        ));
+        assert!(!super::is_unavailable(
+            "error: could not download file from 'https://static.rust-lang.org/dist/channel-rust-nightly.toml.sha256': error sending request"
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! The components of target triples, to group and filter targets by them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::wildcard_match;
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct TargetTriple {
+    pub arch: String,
+    pub vendor: String,
+    pub os: String,
+    /// Empty for targets without an environment, like `aarch64-apple-darwin`.
+    pub env: String,
+}
+
+impl TargetTriple {
+    /// Splits a target name like `x86_64-pc-windows-gnu` into its components.
+    /// Names with only three components are ambiguous, `x86_64-linux-android` has no vendor.
+    pub fn parse(target: &str) -> Self {
+        let parts = target.split('-').collect::<Vec<_>>();
+        let (arch, vendor, os, env) = match parts[..] {
+            [arch] => (arch, "unknown", "none", ""),
+            [arch, os] => (arch, "unknown", os, ""),
+            [arch, "linux", env] => (arch, "unknown", "linux", env),
+            [arch, vendor, os] => (arch, vendor, os, ""),
+            [arch, vendor, os, ..] => (
+                arch,
+                vendor,
+                os,
+                &target[arch.len() + vendor.len() + os.len() + 3..],
+            ),
+            [] => unreachable!("split always returns at least one part"),
+        };
+        Self {
+            arch: arch.to_owned(),
+            vendor: vendor.to_owned(),
+            os: os.to_owned(),
+            env: env.to_owned(),
+        }
+    }
+
+    /// Whether the name only has the characters of target names, like the ones from untrusted peers
+    /// must before they are shown anywhere.
+    pub fn is_plain(target: &str) -> bool {
+        !target.is_empty()
+            && target
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+    }
+}
+
+/// Selects targets by the components of their triple, `*` matches anything.
+#[derive(Debug, Default, Deserialize)]
+pub struct TripleFilter {
+    pub arch: Option<String>,
+    pub vendor: Option<String>,
+    pub os: Option<String>,
+    pub env: Option<String>,
+}
+
+impl TripleFilter {
+    pub fn is_empty(&self) -> bool {
+        self.arch.is_none() && self.vendor.is_none() && self.os.is_none() && self.env.is_none()
+    }
+
+    pub fn matches(&self, triple: &TargetTriple) -> bool {
+        let matches = |pattern: &Option<String>, value: &str| {
+            pattern
+                .as_deref()
+                .is_none_or(|pattern| wildcard_match(pattern, value))
+        };
+        matches(&self.arch, &triple.arch)
+            && matches(&self.vendor, &triple.vendor)
+            && matches(&self.os, &triple.os)
+            && matches(&self.env, &triple.env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TargetTriple;
+
+    #[test]
+    fn is_plain() {
+        assert!(TargetTriple::is_plain("x86_64-unknown-linux-gnu"));
+        assert!(TargetTriple::is_plain("armv7a-kmc-solid_asp3-eabi"));
+        assert!(!TargetTriple::is_plain(""));
+        assert!(!TargetTriple::is_plain("<script>alert(1)</script>"));
+        assert!(!TargetTriple::is_plain("x86_64 linux"));
+    }
+
+    #[test]
+    fn parse() {
+        let parse = |target| {
+            let triple = TargetTriple::parse(target);
+            (triple.arch, triple.vendor, triple.os, triple.env)
+        };
+        let owned = |arch: &str, vendor: &str, os: &str, env: &str| {
+            (
+                arch.to_owned(),
+                vendor.to_owned(),
+                os.to_owned(),
+                env.to_owned(),
+            )
+        };
+        assert_eq!(
+            parse("x86_64-pc-windows-gnu"),
+            owned("x86_64", "pc", "windows", "gnu")
+        );
+        assert_eq!(
+            parse("aarch64-apple-darwin"),
+            owned("aarch64", "apple", "darwin", "")
+        );
+        assert_eq!(
+            parse("x86_64-linux-android"),
+            owned("x86_64", "unknown", "linux", "android")
+        );
+        assert_eq!(
+            parse("wasm32-wasip1"),
+            owned("wasm32", "unknown", "wasip1", "")
+        );
+        assert_eq!(
+            parse("armv7-unknown-linux-gnueabihf"),
+            owned("armv7", "unknown", "linux", "gnueabihf")
+        );
+        assert_eq!(
+            parse("armv7a-kmc-solid_asp3-eabihf"),
+            owned("armv7a", "kmc", "solid_asp3", "eabihf")
+        );
+    }
+}
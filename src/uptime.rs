@@ -0,0 +1,57 @@
+//! The share of passing builds of a target over the last days, a single number for its health.
+
+use std::collections::HashMap;
+
+use color_eyre::Result;
+use serde::Serialize;
+
+use crate::{
+    db::{BuildMode, Db},
+    nightlies,
+};
+
+/// The number of days shown by default.
+pub const WINDOWS: [u32; 3] = [30, 90, 365];
+
+#[derive(Serialize)]
+pub struct Uptime {
+    pub mode: BuildMode,
+    pub days: u32,
+    pub passed: i64,
+    pub builds: i64,
+    pub percentage: f64,
+}
+
+/// The uptime of every target (or only `target`) per mode and window.
+/// Windows without builds are left out.
+pub async fn uptimes(
+    db: &Db,
+    windows: &[u32],
+    target: Option<&str>,
+    host: Option<&str>,
+) -> Result<HashMap<String, Vec<Uptime>>> {
+    let mut uptimes = HashMap::<_, Vec<_>>::new();
+    for &days in windows {
+        let since = nightlies::days_ago(days)?;
+        for (target, mode, passed, builds) in db.pass_totals(&since, target, host).await? {
+            uptimes.entry(target).or_default().push(Uptime {
+                mode,
+                days,
+                passed,
+                builds,
+                percentage: passed as f64 * 100.0 / builds as f64,
+            });
+        }
+    }
+    Ok(uptimes)
+}
+
+/// The color of a badge for the uptime, in the format of shields.io.
+pub fn badge_color(percentage: f64) -> &'static str {
+    match percentage {
+        99.0.. => "brightgreen",
+        90.0.. => "green",
+        75.0.. => "yellow",
+        _ => "red",
+    }
+}
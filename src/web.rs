@@ -1,35 +1,334 @@
+mod api;
+mod auth;
+mod grafana;
+mod index;
+mod miri;
+mod ndjson;
+mod nightly;
+mod target;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    body::Body,
+    extract::{ConnectInfo, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
 use color_eyre::{eyre::Context, Result};
+use does_it_build_api::HostFilter;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{error, info, info_span, Level};
 
-use crate::db::{BuildMode, Db};
+use crate::{
+    config::{AuthConfig, WebConfig},
+    db::{BuildHost, BuildInfo, BuildMode, BuildTiming, Db, Status},
+    log_offload::LogOffload,
+    target_spec::{self, CfgDiff, SpecDiff},
+    triple::TripleFilter,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Db,
+    /// The path prefix the app is served under, without a trailing slash.
+    /// Empty when served at the root.
+    pub base_path: String,
+    /// Whether to trust `X-Forwarded-For` and `X-Forwarded-Proto`.
+    /// Only enable this when running behind a reverse proxy that sets them.
+    pub trust_forwarded_headers: bool,
+    /// The configured URL of the app, without a trailing slash.
+    pub public_url: Option<String>,
+    /// Workers authenticate with this. The worker API is disabled without it.
+    pub worker_token: Option<String>,
+    /// External systems and maintainers authenticate with this to use the hooks.
+    pub auth: auth::Auth,
+    pub log_offload: LogOffload,
+    /// Maintainers of targets from the config, in addition to the ones from the docs.
+    pub extra_maintainers: Arc<BTreeMap<String, Vec<String>>>,
 }
 
-pub async fn webserver(db: Db) -> Result<()> {
+pub async fn webserver(
+    db: Db,
+    config: WebConfig,
+    auth_config: AuthConfig,
+    worker_token: Option<String>,
+    log_offload: LogOffload,
+    extra_maintainers: BTreeMap<String, Vec<String>>,
+) -> Result<()> {
+    let base_path = normalize_base_path(&config.base_path);
+
+    let state = AppState {
+        db,
+        base_path: base_path.clone(),
+        trust_forwarded_headers: config.trust_forwarded_headers,
+        public_url: config
+            .public_url
+            .map(|url| url.trim_end_matches('/').to_owned()),
+        worker_token,
+        auth: auth::Auth::new(&auth_config, config.hook_token)?,
+        log_offload,
+        extra_maintainers: Arc::new(extra_maintainers),
+    };
+
     let app = Router::new()
-        .route("/", get(root))
+        .route("/", get(index::root))
         .route("/build", get(build))
+        .route("/build/log", get(build_log))
+        .route("/never-passed", get(never_passed))
+        .route("/miri-support", get(miri::miri_support))
+        .route("/nightly/:nightly", get(nightly::nightly))
+        .route("/target/:target", get(target::target))
         .route("/index.css", get(index_css))
         .route("/index.js", get(index_js))
+        .route("/robots.txt", get(robots_txt))
+        .route("/sitemap.xml", get(sitemap_xml))
         .route("/target-state", get(target_state))
         .route("/trigger-build", post(trigger_build))
-        .with_state(AppState { db });
+        .nest("/api/v1", api::router(&state))
+        .nest("/api/v1/grafana", grafana::router())
+        // Nesting doesn't match the trailing slash, which Grafana appends to the datasource URL.
+        .route("/api/v1/grafana/", get(grafana::health));
+
+    let app = if base_path.is_empty() {
+        app
+    } else {
+        // Nesting only matches `/prefix` for the root route, but proxies usually forward `/prefix/`.
+        Router::new()
+            .nest(&base_path, app)
+            .route(&format!("{base_path}/"), get(index::root))
+    };
 
-    info!("Serving website on port 3000 (commit {})", crate::VERSION);
+    // Layers wrap everything added before them, so requests pass through these bottom to top.
+    let app = app
+        .layer(middleware::from_fn(request_id_in_errors))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), client_info))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.wrap_err("failed to serve")
+    info!(
+        "Serving website on {} under '{base_path}/' (commit {})",
+        config.bind,
+        crate::VERSION
+    );
+
+    let listener = tokio::net::TcpListener::bind(&config.bind)
+        .await
+        .wrap_err_with(|| format!("binding to {}", config.bind))?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .wrap_err("failed to serve")
+}
+
+/// Escapes text from the query to put it into HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Percent-encodes a value to put it into the query of a link.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Resolves `latest` to the newest nightly with builds and `latest-finished` to the newest one that
+/// finished building, so links and badges don't need to know the current date. Other nightlies are
+/// returned as they are. Responds with 404 if there is no such nightly yet.
+async fn resolve_nightly(
+    db: &Db,
+    nightly: String,
+    mode: Option<BuildMode>,
+    host: Option<&str>,
+) -> Result<String, Response> {
+    let finished = match nightly.as_str() {
+        "latest" => false,
+        "latest-finished" => true,
+        _ => return Ok(nightly),
+    };
+    match db.newest_nightly(finished, mode, host).await {
+        Ok(Some(nightly)) => Ok(nightly),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(err) => {
+            error!(?err, "Error resolving nightly");
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// The title of a page and the same as OpenGraph tags, with a description of what it shows, so that
+/// links pasted into chats unfurl with the actual status.
+fn page_head(title: &str, description: &str) -> String {
+    let title = escape(title);
+    let description = escape(description);
+    format!(
+        r#"<title>{title}</title>
+    <meta name="description" content="{description}" />
+    <meta property="og:site_name" content="Does it build?" />
+    <meta property="og:type" content="website" />
+    <meta property="og:title" content="{title}" />
+    <meta property="og:description" content="{description}" />"#
+    )
+}
+
+/// A `title` attribute with the error of a failed build, to show it when hovering over its cell.
+fn error_title(build: &BuildInfo) -> String {
+    build
+        .error_excerpt
+        .as_deref()
+        .map(|excerpt| format!(r#" title="{}""#, escape(excerpt)))
+        .unwrap_or_default()
+}
+
+/// The symbol of a build in the grids of builds.
+fn status_icon(status: Status) -> &'static str {
+    match status {
+        Status::Pass => "✅",
+        Status::Error => "❌",
+        Status::Skipped => "⏭",
+        Status::Unavailable => "➖",
+        Status::Timeout => "⏱",
+        Status::InfraError => "⚠",
+        Status::Unsupported => "🚫",
+    }
+}
+
+/// Turns a user-provided path prefix like `does-it-build/` into `/does-it-build`.
+fn normalize_base_path(path: &str) -> String {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
+}
+
+/// The client that sent a request, as seen through the reverse proxy if there is a trusted one.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub addr: IpAddr,
+    /// `http` or `https`.
+    pub proto: String,
+    /// The `Host` the client requested, if any.
+    pub host: Option<String>,
+}
+
+impl ClientInfo {
+    fn from_request(peer: SocketAddr, headers: &HeaderMap, trust_forwarded_headers: bool) -> Self {
+        let forwarded = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|_| trust_forwarded_headers)
+        };
+
+        let addr = forwarded("x-forwarded-for")
+            .and_then(client_addr_from_forwarded_for)
+            .unwrap_or(peer.ip());
+        let proto = forwarded("x-forwarded-proto")
+            .map(|proto| proto.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| "http".to_owned());
+        let host = headers
+            .get(axum::http::header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+
+        Self { addr, proto, host }
+    }
+
+    /// The absolute URL of the app as the client sees it, like `https://example.com/does-it-build`.
+    fn public_url(&self, base_path: &str) -> String {
+        let host = self.host.as_deref().unwrap_or("localhost");
+        format!("{}://{host}{base_path}", self.proto)
+    }
+}
+
+impl AppState {
+    /// The absolute URL of the app, as configured or else as the client sees it. The `Host` header
+    /// is up to the client, so the configured one is preferred.
+    fn public_url(&self, client: &ClientInfo) -> String {
+        match &self.public_url {
+            Some(url) => url.clone(),
+            None => client.public_url(&self.base_path),
+        }
+    }
+}
+
+/// Every proxy appends the address it received the request from, so the last entry
+/// is the one added by our proxy. Entries before it are controlled by the client.
+fn client_addr_from_forwarded_for(header: &str) -> Option<IpAddr> {
+    header.rsplit(',').next()?.trim().parse().ok()
+}
+
+async fn client_info(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let client = ClientInfo::from_request(peer, req.headers(), state.trust_forwarded_headers);
+    req.extensions_mut().insert(client);
+    next.run(req).await
+}
+
+fn request_id(req: &Request) -> &str {
+    req.extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or("unknown")
+}
+
+fn request_span(req: &Request) -> tracing::Span {
+    let client = req.extensions().get::<ClientInfo>();
+    info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id = %request_id(req),
+        client = client.map(|client| tracing::field::display(client.addr)),
+        proto = client.map(|client| tracing::field::display(&client.proto)),
+    )
+}
+
+/// Server errors have no body, so give the user something to put into a bug report.
+async fn request_id_in_errors(req: Request, next: Next) -> Response {
+    let request_id = request_id(&req).to_owned();
+    let mut response = next.run(req).await;
+    if response.status().is_server_error() {
+        *response.body_mut() =
+            Body::from(format!("{}\nrequest id: {request_id}\n", response.status()));
+    }
+    response
 }
 
 #[derive(Deserialize)]
@@ -37,26 +336,160 @@ struct BuildQuery {
     nightly: String,
     target: String,
     mode: Option<BuildMode>,
+    /// Without a host, the build of a host it failed on is shown, if there is one.
+    host: Option<String>,
+    /// Show the lines of the log before this character offset instead of the end of it.
+    log_end: Option<i64>,
 }
 
-async fn build(State(state): State<AppState>, Query(query): Query<BuildQuery>) -> Response {
-    match state
-        .db
-        .build_status_full(
-            &query.nightly,
-            &query.target,
-            query.mode.unwrap_or(BuildMode::Core),
-        )
-        .await
-    {
-        Ok(Some(build)) => {
+/// How much of a log is loaded for the build page, the lines to show are taken from this.
+const LOG_PAGE_CHARS: i64 = 256 * 1024;
+/// How many lines of the log are shown at once on the build page.
+const LOG_PAGE_LINES: usize = 500;
+
+async fn build(State(state): State<AppState>, Query(mut query): Query<BuildQuery>) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    query.nightly =
+        match resolve_nightly(&state.db, query.nightly, Some(mode), query.host.as_deref()).await {
+            Ok(nightly) => nightly,
+            Err(response) => return response,
+        };
+    let result = async {
+        let build = state
+            .db
+            .build_status_single(&query.nightly, &query.target, mode, query.host.as_deref())
+            .await?;
+        let Some(build) = build else {
+            return Ok(None);
+        };
+        let Some(log) = state
+            .db
+            .build_log_range(
+                &query.nightly,
+                &query.target,
+                mode,
+                &build.host.host,
+                query.log_end,
+                LOG_PAGE_CHARS,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        let same_stderr = state
+            .db
+            .targets_with_same_stderr(&query.nightly, &query.target, mode, &build.host.host)
+            .await?;
+        let spec_diff =
+            target_spec::diff_nightlies(&state.db, &query.target, None, &query.nightly).await?;
+        let cfg_diff =
+            target_spec::diff_cfg_nightlies(&state.db, &query.target, None, &query.nightly).await?;
+        let tier = state.db.target_tier(&query.target).await?;
+        color_eyre::Result::<_>::Ok(Some((build, log, same_stderr, spec_diff, cfg_diff, tier)))
+    }
+    .await;
+
+    match result {
+        Ok(Some((build, log, same_stderr, spec_diff, cfg_diff, tier))) => {
+            let build_url = format!(
+                "{}/build?nightly={}&target={}&mode={mode}&host={}",
+                state.base_path,
+                encode_query_value(&query.nightly),
+                encode_query_value(&query.target),
+                encode_query_value(&build.host.host)
+            );
+            let (start, lines) = last_lines(&log.content, log.start, LOG_PAGE_LINES);
+            let end = log.start + log.content.chars().count() as i64;
+            let mut log_nav = Vec::new();
+            if start > 0 {
+                log_nav.push(format!(
+                    r#"<a href="{build_url}&log_end={start}">Load earlier lines</a>"#
+                ));
+            }
+            if end < log.total {
+                log_nav.push(format!(r#"<a href="{build_url}">Jump to the end</a>"#));
+            }
+            match log.original_bytes {
+                Some(original_bytes) if log.offloaded => log_nav.push(format!(
+                    "The log is {original_bytes} bytes large, so only its start and end are shown here."
+                )),
+                Some(original_bytes) => log_nav.push(format!(
+                    "The log was {original_bytes} bytes large, so only its start and end are stored."
+                )),
+                None => {}
+            }
+            let log_nav = if log_nav.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "<p>Showing characters {start} to {end} of {}. {}</p>",
+                    log.total,
+                    log_nav.join(" ")
+                )
+            };
+
+            let same_stderr = if same_stderr.is_empty() {
+                String::new()
+            } else {
+                let links = same_stderr
+                    .iter()
+                    .map(|target| {
+                        format!(
+                            r#"<a href="{}/build?nightly={}&target={}&mode={mode}&host={}">{}</a>"#,
+                            state.base_path,
+                            encode_query_value(&query.nightly),
+                            encode_query_value(target),
+                            encode_query_value(&build.host.host),
+                            escape(target)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("<p>Identical output on this nightly: {links}</p>")
+            };
+
             let page = include_str!("../static/build.html")
+                .replace("{{base}}", &state.base_path)
                 .replace("{{nightly}}", &query.nightly)
                 .replace("{{target}}", &query.target)
-                .replace("{{stderr}}", &build.stderr)
+                .replace("{{details}}", &build_details(&build.timing, &build.host))
+                .replace(
+                    "{{tier}}",
+                    &tier
+                        .map(|tier| format!(r#"<span class="tier-badge">tier {tier}</span>"#))
+                        .unwrap_or_default(),
+                )
+                .replace("{{same_stderr}}", &same_stderr)
+                .replace(
+                    "{{spec_changes}}",
+                    &spec_changes(&state.base_path, spec_diff.as_ref()),
+                )
+                .replace("{{cfg_changes}}", &cfg_changes(cfg_diff.as_ref()))
+                .replace(
+                    "{{log_url}}",
+                    &format!(
+                        "{}/build/log?nightly={}&target={}&mode={mode}&host={}",
+                        state.base_path,
+                        encode_query_value(&query.nightly),
+                        encode_query_value(&query.target),
+                        encode_query_value(&build.host.host)
+                    ),
+                )
+                .replace("{{log_nav}}", &log_nav)
                 .replace("{{mode}}", &build.mode.to_string())
                 .replace("{{version}}", crate::VERSION)
-                .replace("{{status}}", &build.status.to_string());
+                .replace("{{status}}", &build.status.to_string())
+                .replace(
+                    "{{error_excerpt}}",
+                    &build
+                        .error_excerpt
+                        .as_deref()
+                        .map(|excerpt| format!("<pre>{}</pre>", escape(excerpt)))
+                        .unwrap_or_default(),
+                )
+                .replace("{{head}}", &build_head(&build))
+                // Last, so that the log is not searched for placeholders.
+                .replace("{{stderr}}", &escape(lines));
 
             Html(page).into_response()
         }
@@ -68,9 +501,232 @@ async fn build(State(state): State<AppState>, Query(query): Query<BuildQuery>) -
     }
 }
 
-async fn root() -> impl IntoResponse {
-    Html(include_str!("../static/index.html").replace("{{version}}", crate::VERSION))
+fn build_head(build: &BuildInfo) -> String {
+    let title = format!(
+        "{} on nightly-{} ({}): {}",
+        build.target, build.nightly, build.mode, build.status
+    );
+    let description = match (build.status, &build.error_excerpt) {
+        (Status::Pass, _) => "The build passed.".to_owned(),
+        (Status::Error, Some(excerpt)) => excerpt.clone(),
+        (Status::Error, None) => "The build failed.".to_owned(),
+        (Status::Skipped, _) => "The target was skipped.".to_owned(),
+        (Status::Unavailable, _) => "The toolchain isn't available for this nightly.".to_owned(),
+        (Status::Timeout, _) => "The build timed out.".to_owned(),
+        (Status::InfraError, _) => {
+            "The build couldn't run because of a problem of the builder.".to_owned()
+        }
+        (Status::Unsupported, _) => "Miri doesn't support the target.".to_owned(),
+    };
+    page_head(&title, &description)
+}
+
+/// The full log of a build as plain text, fetched from the log storage if it was offloaded.
+async fn build_log(State(state): State<AppState>, Query(mut query): Query<BuildQuery>) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    query.nightly =
+        match resolve_nightly(&state.db, query.nightly, Some(mode), query.host.as_deref()).await {
+            Ok(nightly) => nightly,
+            Err(response) => return response,
+        };
+    let result = async {
+        let Some(build) = state
+            .db
+            .build_status_single(&query.nightly, &query.target, mode, query.host.as_deref())
+            .await?
+        else {
+            return Ok(None);
+        };
+        let Some((log, offloaded_key)) = state
+            .db
+            .build_log(&query.nightly, &query.target, mode, &build.host.host)
+            .await?
+        else {
+            return Ok(None);
+        };
+        match offloaded_key {
+            Some(key) => state.log_offload.fetch(&key).await.map(Some),
+            None => color_eyre::Result::<_>::Ok(Some(log)),
+        }
+    }
+    .await;
+
+    match result {
+        Ok(Some(log)) => (
+            [(
+                axum::http::header::CONTENT_TYPE,
+                axum::http::HeaderValue::from_static("text/plain; charset=utf-8"),
+            )],
+            log,
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error loading build log");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
+
+/// The last `max_lines` lines of a part of a log starting at character `start`, and where they start.
+/// A line that is cut off at the start of the part is left out, unless the part is the start of the log.
+fn last_lines(content: &str, start: i64, max_lines: usize) -> (i64, &str) {
+    let without_newline = content.strip_suffix('\n').unwrap_or(content);
+    let mut cut = without_newline
+        .rmatch_indices('\n')
+        .nth(max_lines.saturating_sub(1))
+        .map(|(i, _)| i + 1);
+    if cut.is_none() && start > 0 {
+        cut = content.find('\n').map(|i| i + 1);
+    }
+    let cut = cut.unwrap_or(0);
+    (
+        start + content[..cut].chars().count() as i64,
+        &content[cut..],
+    )
+}
+
+/// The fields of the target spec that changed since the previous nightly.
+fn spec_changes(base_path: &str, diff: Option<&SpecDiff>) -> String {
+    let Some(diff) = diff.filter(|diff| !diff.changes.is_empty()) else {
+        return String::new();
+    };
+    let keys = diff
+        .changes
+        .iter()
+        .map(|change| format!("<code>{}</code>", change.key))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"<p>Target spec changed since {}: {keys} (<a href="{base_path}/api/v1/targets/{}/spec-diff?from={}&to={}">diff</a>)</p>"#,
+        diff.from, diff.target, diff.from, diff.to
+    )
+}
+
+/// The cfgs that were added or removed since the previous nightly.
+fn cfg_changes(diff: Option<&CfgDiff>) -> String {
+    let Some(diff) = diff.filter(|diff| !diff.added.is_empty() || !diff.removed.is_empty()) else {
+        return String::new();
+    };
+    let changes = diff
+        .added
+        .iter()
+        .map(|cfg| format!("<code>+{}</code>", cfg))
+        .chain(
+            diff.removed
+                .iter()
+                .map(|cfg| format!("<code>-{}</code>", cfg)),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("<p>cfg changed since {}: {changes}</p>", diff.from)
+}
+
+fn build_details(timing: &BuildTiming, host: &BuildHost) -> String {
+    let host = match &host.hostname {
+        Some(hostname) => format!("<p>Built on {} ({hostname})</p>", host.host),
+        None => format!("<p>Built on {}</p>", host.host),
+    };
+    let (Some(started_at), Some(finished_at)) = (timing.started_at, timing.finished_at) else {
+        return host;
+    };
+    let started = time::OffsetDateTime::from_unix_timestamp(started_at)
+        .ok()
+        .and_then(|started| {
+            started
+                .format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        })
+        .unwrap_or_else(|| started_at.to_string());
+    let exit = match timing.exit_code {
+        Some(code) => format!("exit code {code}"),
+        None => "killed by a signal".to_owned(),
+    };
+    let memory = match timing.peak_rss_kib {
+        Some(kib) => format!(", peak memory {} MiB", kib / 1024),
+        None => String::new(),
+    };
+    format!(
+        "<p>Built at {started} in {}s, {exit}{memory}</p>{host}",
+        finished_at - started_at
+    )
+}
+
+#[derive(Deserialize)]
+struct NeverPassedQuery {
+    /// Without a host, a target counts as passing if it passed on any host.
+    host: Option<String>,
+}
+
+async fn never_passed(
+    State(state): State<AppState>,
+    Query(query): Query<NeverPassedQuery>,
+) -> Response {
+    let targets = match state.db.never_passed(None, query.host.as_deref()).await {
+        Ok(targets) => targets,
+        Err(err) => {
+            error!(?err, "Error loading targets that never passed");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let mut modes = String::new();
+    for mode in [BuildMode::Core, BuildMode::MiriStd] {
+        let rows = targets
+            .iter()
+            .filter(|target| target.mode == mode)
+            .map(|target| {
+                let host = query
+                    .host
+                    .as_deref()
+                    .map(|host| format!("&host={}", encode_query_value(host)))
+                    .unwrap_or_default();
+                format!(
+                    r#"<tr><td><a href="{}/build?nightly={}&target={}&mode={mode}{host}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                    state.base_path,
+                    target.last_nightly,
+                    target.target,
+                    target.target,
+                    target.tier.map(|tier| tier.to_string()).unwrap_or_default(),
+                    target.builds,
+                    target.first_nightly,
+                )
+            })
+            .collect::<Vec<_>>();
+        modes.push_str(&format!("<h2>{mode}</h2>"));
+        if rows.is_empty() {
+            modes.push_str("<p>None.</p>");
+        } else {
+            modes.push_str(&format!(
+                "<table><tr><th>Target</th><th>Tier</th><th>Failed builds</th><th>Since</th></tr>{}</table>",
+                rows.join("")
+            ));
+        }
+    }
+
+    Html(
+        include_str!("../static/never-passed.html")
+            .replace(
+                "{{head}}",
+                &page_head(
+                    "Targets that never built",
+                    &format!(
+                        "{} targets have not passed a single build in a mode.",
+                        targets
+                            .iter()
+                            .map(|target| target.target.as_str())
+                            .collect::<HashSet<_>>()
+                            .len()
+                    ),
+                ),
+            )
+            .replace("{{base}}", &state.base_path)
+            .replace("{{modes}}", &modes)
+            .replace("{{version}}", crate::VERSION),
+    )
+    .into_response()
+}
+
 async fn index_css() -> impl IntoResponse {
     (
         [(
@@ -90,11 +746,139 @@ async fn index_js() -> impl IntoResponse {
     )
 }
 
-async fn target_state(State(state): State<AppState>) -> impl IntoResponse {
-    state.db.build_status().await.map(Json).map_err(|err| {
-        error!(?err, "Error loading target state");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })
+async fn robots_txt(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+) -> impl IntoResponse {
+    let base = &state.base_path;
+    // Build logs are huge and there are thousands of them, they are not useful to index.
+    format!(
+        "User-agent: *\nDisallow: {base}/build\nDisallow: {base}/target-state\n\nSitemap: {}/sitemap.xml\n",
+        state.public_url(&client)
+    )
+}
+
+async fn sitemap_xml(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+) -> Response {
+    let finished = match state.db.finished_nightlies(None).await {
+        Ok(finished) => finished,
+        Err(err) => {
+            error!(?err, "Error loading finished nightlies");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let targets = match state.db.targets().await {
+        Ok(targets) => targets,
+        Err(err) => {
+            error!(?err, "Error loading targets");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let latest = finished.iter().map(|finished| &finished.nightly).max();
+
+    let url = state.public_url(&client);
+    let mut entries = vec![(format!("{url}/"), latest)];
+    let nightlies = finished
+        .iter()
+        .map(|finished| &finished.nightly)
+        .collect::<BTreeSet<_>>();
+    entries.extend(
+        nightlies
+            .into_iter()
+            .map(|nightly| (format!("{url}/nightly/{nightly}"), Some(nightly))),
+    );
+    // The target pages change with every nightly.
+    entries.extend(
+        targets
+            .iter()
+            .map(|(target, _)| (format!("{url}/target/{target}"), latest)),
+    );
+
+    let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (loc, lastmod) in entries {
+        sitemap.push_str(&format!("  <url><loc>{}</loc>", escape(&loc)));
+        if let Some(lastmod) = lastmod {
+            sitemap.push_str(&format!("<lastmod>{lastmod}</lastmod>"));
+        }
+        sitemap.push_str("</url>\n");
+    }
+    sitemap.push_str("</urlset>\n");
+
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            axum::http::HeaderValue::from_static("application/xml"),
+        )],
+        sitemap,
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct TargetStateQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+    tier: Option<i32>,
+    #[serde(flatten)]
+    triple: TripleFilter,
+}
+
+/// Every build, which is a lot. `Accept: application/x-ndjson` streams them instead.
+async fn target_state(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<TargetStateQuery>,
+) -> Response {
+    let targets = if query.triple.is_empty() {
+        None
+    } else {
+        match state.db.targets().await {
+            Ok(targets) => Some(
+                targets
+                    .into_iter()
+                    .filter(|(_, triple)| query.triple.matches(triple))
+                    .map(|(target, _)| target)
+                    .collect::<HashSet<_>>(),
+            ),
+            Err(err) => {
+                error!(?err, "Error loading targets");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    };
+    let tier = query.tier;
+    let keep = move |build: &BuildInfo| {
+        let keep = targets
+            .as_ref()
+            .is_none_or(|targets| targets.contains(&build.target))
+            && tier.is_none_or(|tier| build.tier == Some(tier));
+        std::future::ready(keep)
+    };
+
+    if ndjson::wanted(&headers) {
+        let db = state.db.clone();
+        return ndjson::response(move |rows| async move {
+            let builds = db.build_status(query.host.as_deref()).try_filter(keep);
+            ndjson::send(rows, builds).await;
+        });
+    }
+    match state
+        .db
+        .build_status(query.host.as_deref())
+        .try_filter(keep)
+        .try_collect::<Vec<_>>()
+        .await
+    {
+        Ok(builds) => Json(builds).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target state");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -107,7 +891,7 @@ async fn trigger_build(
     State(_state): State<AppState>,
     _body: Json<TriggerBuildBody>,
 ) -> StatusCode {
-    return StatusCode::BAD_REQUEST;
+    StatusCode::BAD_REQUEST
     // tokio::spawn(async move {
     //     let result = build::build_every_target_for_toolchain(&state.db, &body.nightly).await;
     //     if let Err(err) = result {
@@ -117,3 +901,109 @@ async fn trigger_build(
     //
     // StatusCode::ACCEPTED
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use axum::{extract::State, response::IntoResponse, Extension};
+
+    use crate::{
+        config::{AuthConfig, LogsConfig},
+        db::{tests::temp_db, Db},
+        log_offload::LogOffload,
+    };
+
+    use super::{auth::Auth, AppState, ClientInfo};
+
+    /// The state of an app without a configured public URL whose hooks take the token `secret`.
+    pub(crate) fn state(db: Db) -> AppState {
+        AppState {
+            db,
+            base_path: String::new(),
+            trust_forwarded_headers: false,
+            public_url: None,
+            worker_token: None,
+            auth: Auth::new(&AuthConfig::default(), Some("secret".to_owned())).unwrap(),
+            log_offload: LogOffload::new(&LogsConfig::default()).unwrap(),
+            extra_maintainers: Arc::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sitemap() {
+        let (_dir, db) = temp_db().await;
+        let mut state = state(db);
+        let client = ClientInfo {
+            addr: "203.0.113.7".parse().unwrap(),
+            proto: "http".to_owned(),
+            host: Some("example.com/<x>&\"".to_owned()),
+        };
+        let sitemap = |state: AppState| async {
+            let response = super::sitemap_xml(State(state), Extension(client.clone()))
+                .await
+                .into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        assert!(sitemap(state.clone())
+            .await
+            .contains("<loc>http://example.com/&lt;x&gt;&amp;&quot;/</loc>"));
+        state.public_url = Some("https://does-it-build.example.com".to_owned());
+        assert!(sitemap(state)
+            .await
+            .contains("<loc>https://does-it-build.example.com/</loc>"));
+    }
+
+    #[test]
+    fn base_path() {
+        assert_eq!(super::normalize_base_path(""), "");
+        assert_eq!(super::normalize_base_path("/"), "");
+        assert_eq!(
+            super::normalize_base_path("does-it-build"),
+            "/does-it-build"
+        );
+        assert_eq!(
+            super::normalize_base_path("/does-it-build/"),
+            "/does-it-build"
+        );
+    }
+
+    #[test]
+    fn forwarded_for() {
+        assert_eq!(
+            super::client_addr_from_forwarded_for("203.0.113.7"),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(
+            super::client_addr_from_forwarded_for("198.51.100.1, 2001:db8::1"),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(super::client_addr_from_forwarded_for("garbage"), None);
+    }
+
+    #[test]
+    fn last_lines() {
+        let log = "a\nb\nc\nd\n";
+        assert_eq!(super::last_lines(log, 0, 2), (4, "c\nd\n"));
+        assert_eq!(super::last_lines(log, 0, 10), (0, log));
+        // The first line may be the end of a line before the part.
+        assert_eq!(super::last_lines("xa\nb\n", 5, 10), (8, "b\n"));
+        assert_eq!(super::last_lines("äa\nb", 5, 10), (8, "b"));
+    }
+
+    #[test]
+    fn encode_query_value() {
+        assert_eq!(
+            super::encode_query_value("x86_64-unknown-linux-gnu"),
+            "x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            super::encode_query_value("a&b=\"<c> d/ä"),
+            "a%26b%3D%22%3Cc%3E%20d%2F%C3%A4"
+        );
+    }
+}
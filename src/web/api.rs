@@ -0,0 +1,1825 @@
+//! The JSON API under `/api/v1`.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use does_it_build_api::{
+    BuildHistory, HostFilter, ModeHealth, ModeOverview, NightlyHealth, Overview, Regression,
+    Regressions, ServiceStatus, TierRegressions,
+};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    anomaly::{self, Anomaly},
+    coordinator::{self, WorkerCall},
+    db::{
+        AuditEntry, BuildMode, Db, ExpectedBreakage, StaleFailure, Status, StatusCount, TargetMeta,
+        ToolchainInfo,
+    },
+    federation, flaky, miri_support, nightlies,
+    prebuilt_std::{self, StdChange},
+    report::Report,
+    signoff::{self, SignoffReport},
+    target_spec,
+    triple::{TargetTriple, TripleFilter},
+    uptime::{self, Uptime},
+};
+
+use super::{
+    auth::{tokens_match, Actor},
+    ndjson, resolve_nightly, AppState, ClientInfo,
+};
+
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/overview", get(overview))
+        .route("/status", get(status))
+        .route("/latest", get(latest_builds))
+        .route("/federation/latest", get(federated_latest))
+        .route("/nightlies/:nightly", get(nightly_summary))
+        .route("/nightlies/:nightly/regressions", get(regressions))
+        .route("/nightlies/:nightly/spec-changes", get(spec_changes))
+        .route("/nightlies/:nightly/badge", get(nightly_badge))
+        .route("/health/:nightly", get(nightly_health))
+        .route("/builds/:nightly/:target", get(build_history))
+        .route("/targets", get(targets))
+        .route("/target-renames", get(target_renames))
+        .route("/never-passed", get(never_passed))
+        .route("/stale-failures", get(stale_failures))
+        .route("/flaky", get(flaky_targets))
+        .route("/expected-breakage", get(expected_breakages))
+        .route("/audit-log", get(audit_log))
+        .route("/compare-targets", get(compare_targets))
+        .route("/reports/weekly", get(weekly_report))
+        .route("/reports/signoff", get(signoff_report))
+        .route("/reports/anomalies", get(anomaly_report))
+        .route("/stats/pass-counts", get(pass_counts))
+        .route("/stats/streaks", get(streaks))
+        .route("/stats/regressions", get(regression_counts))
+        .route("/std-changes", get(std_changes))
+        .route("/miri-support", get(miri_support))
+        .route("/export/builds", get(export_builds))
+        .route("/export/nightlies", get(export_nightlies))
+        .route("/export/toolchains", get(export_toolchains))
+        .route("/export/target-meta", get(export_target_meta))
+        .route("/targets/:target/spec", get(target_spec))
+        .route("/targets/:target/spec-diff", get(target_spec_diff))
+        .route("/targets/:target/cfg", get(target_cfg))
+        .route("/targets/:target/cfg-diff", get(target_cfg_diff))
+        .route("/targets/:target/maintainers", get(target_maintainers))
+        .route("/targets/:target/latest", get(latest_build))
+        .route("/targets/:target/uptime", get(target_uptime))
+        .route("/targets/:target/calendar", get(target_calendar))
+        .route("/targets/:target/uptime/badge", get(target_uptime_badge))
+        .route("/hooks/rebuild", post(rebuild_hook))
+        .route("/hooks/expected-breakage", post(expected_breakage_hook))
+        // Workers send the full build logs, which can be tens of megabytes. Only workers may send
+        // that much, so the token is checked before the body is read.
+        .route(
+            "/worker",
+            post(worker)
+                .layer(DefaultBodyLimit::max(512 * 1024 * 1024))
+                .layer(middleware::from_fn_with_state(state.clone(), worker_auth)),
+        )
+}
+
+#[derive(Deserialize)]
+struct OverviewQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+/// The totals that the index page shows in its header.
+pub(super) async fn overview_of(
+    state: &AppState,
+    host: Option<&str>,
+) -> color_eyre::Result<Overview> {
+    let week_ago = nightlies::days_ago(7)?;
+    let mut modes = Vec::new();
+    for mode in [BuildMode::Core, BuildMode::MiriStd] {
+        let Some(nightly) = state.db.newest_nightly(false, Some(mode), host).await? else {
+            continue;
+        };
+        let counts = state
+            .db
+            .pass_counts(Some(&nightly), Some(mode), host)
+            .await?
+            .into_iter()
+            .find(|counts| counts.nightly == nightly);
+        let changes = state
+            .db
+            .regression_counts(Some(&week_ago), Some(mode), host)
+            .await?;
+        modes.push(ModeOverview {
+            mode,
+            pass: counts.as_ref().map_or(0, |counts| counts.pass),
+            error: counts.as_ref().map_or(0, |counts| counts.error),
+            regressions_this_week: changes.iter().map(|counts| counts.regressions).sum(),
+            fixes_this_week: changes.iter().map(|counts| counts.fixes).sum(),
+            progress: state.db.job_progress(&nightly, mode, host).await?,
+            nightly,
+        });
+    }
+    Ok(Overview {
+        targets: state.db.targets().await?.len() as i64,
+        latest_nightly: modes.iter().map(|mode| mode.nightly.clone()).max(),
+        modes,
+    })
+}
+
+async fn overview(State(state): State<AppState>, Query(query): Query<OverviewQuery>) -> Response {
+    match overview_of(&state, query.host.as_deref()).await {
+        Ok(overview) => Json(overview).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading overview");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// How many maintenance runs `/status` lists.
+const MAINTENANCE_RUNS: i64 = 10;
+
+async fn status(State(state): State<AppState>) -> Response {
+    match state.db.maintenance_runs(MAINTENANCE_RUNS).await {
+        Ok(maintenance) => Json(ServiceStatus {
+            version: crate::VERSION.to_owned(),
+            maintenance,
+        })
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error loading maintenance runs");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct NightlySummary {
+    nightly: String,
+    toolchain: Option<ToolchainInfo>,
+    /// The modes and hosts the nightly is finished for.
+    finished: Vec<FinishedMode>,
+    counts: Vec<StatusCount>,
+}
+
+#[derive(Serialize)]
+struct FinishedMode {
+    mode: BuildMode,
+    host: String,
+    broken: bool,
+    /// Why nothing was built, if rustup couldn't install the toolchain or a component of it.
+    toolchain_error: Option<String>,
+}
+
+async fn nightly_summary(State(state): State<AppState>, Path(nightly): Path<String>) -> Response {
+    let nightly = match resolve_nightly(&state.db, nightly, None, None).await {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    let result = async {
+        let toolchain = state.db.toolchain_info(&nightly).await?;
+        let counts = state.db.status_counts(&nightly).await?;
+        let finished = state
+            .db
+            .nightly_finish_states(&nightly)
+            .await?
+            .into_iter()
+            .map(|record| FinishedMode {
+                mode: record.mode,
+                host: record.host.unwrap_or_default(),
+                broken: record.broken,
+                toolchain_error: record.toolchain_error,
+            })
+            .collect();
+        color_eyre::Result::<_>::Ok(NightlySummary {
+            nightly: nightly.clone(),
+            toolchain,
+            finished,
+            counts,
+        })
+    }
+    .await;
+
+    match result {
+        Ok(summary)
+            if summary.toolchain.is_none()
+                && summary.counts.is_empty()
+                && summary.finished.is_empty() =>
+        {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Ok(summary) => Json(summary).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading nightly summary");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TargetInfo {
+    target: String,
+    tier: Option<i32>,
+    #[serde(flatten)]
+    triple: TargetTriple,
+    /// `None` until the target specs of a nightly with the target have been captured.
+    meta: Option<TargetMeta>,
+    /// The share of passing builds over the last 30, 90 and 365 days, merged over all hosts.
+    uptime: Vec<Uptime>,
+}
+
+#[derive(Deserialize)]
+struct TargetsQuery {
+    tier: Option<i32>,
+    #[serde(flatten)]
+    triple: TripleFilter,
+}
+
+/// Every target with the components of its triple and its platform support metadata.
+async fn targets(State(state): State<AppState>, Query(query): Query<TargetsQuery>) -> Response {
+    let result = async {
+        let mut metas = state
+            .db
+            .target_meta()
+            .await?
+            .into_iter()
+            .map(|meta| (meta.target.clone(), meta))
+            .collect::<HashMap<_, _>>();
+        let mut uptimes = uptime::uptimes(&state.db, &uptime::WINDOWS, None, None).await?;
+        let targets = state
+            .db
+            .targets()
+            .await?
+            .into_iter()
+            .filter(|(_, triple)| query.triple.matches(triple))
+            .map(|(target, triple)| {
+                let meta = metas.remove(&target);
+                TargetInfo {
+                    tier: meta.as_ref().and_then(|meta| meta.tier),
+                    meta,
+                    uptime: uptimes.remove(&target).unwrap_or_default(),
+                    target,
+                    triple,
+                }
+            })
+            .filter(|target| query.tier.is_none() || target.tier == query.tier)
+            .collect::<Vec<_>>();
+        color_eyre::Result::<_>::Ok(targets)
+    }
+    .await;
+
+    match result {
+        Ok(targets) => Json(targets).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target metadata");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn target_renames(State(state): State<AppState>) -> Response {
+    match state.db.target_renames().await {
+        Ok(renames) => Json(renames).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target renames");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StdChangesQuery {
+    target: Option<String>,
+    /// Only changes on this nightly or later.
+    since: Option<String>,
+}
+
+/// Targets gaining or losing a prebuilt std, oldest first.
+async fn std_changes(
+    State(state): State<AppState>,
+    Query(query): Query<StdChangesQuery>,
+) -> Response {
+    match state.db.prebuilt_std().await {
+        Ok(nightlies) => {
+            let changes = prebuilt_std::transitions(&nightlies)
+                .into_iter()
+                .filter(|change| {
+                    query
+                        .target
+                        .as_ref()
+                        .is_none_or(|target| &change.target == target)
+                })
+                .filter(|change| {
+                    query
+                        .since
+                        .as_ref()
+                        .is_none_or(|since| &change.nightly >= since)
+                })
+                .collect::<Vec<_>>();
+            Json(changes).into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error loading prebuilt std changes");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MiriSupportQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+    /// Only changes on this nightly or later.
+    since: Option<String>,
+}
+
+/// The targets Miri supports and when that changed.
+async fn miri_support(
+    State(state): State<AppState>,
+    Query(query): Query<MiriSupportQuery>,
+) -> Response {
+    match state.db.miri_setup_results(query.host.as_deref()).await {
+        Ok(builds) => {
+            let mut matrix = miri_support::matrix(&builds);
+            if let Some(since) = &query.since {
+                matrix.changes.retain(|change| &change.nightly >= since);
+            }
+            Json(matrix).into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error loading miri support");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SpecQuery {
+    /// The newest nightly by default.
+    nightly: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SpecResponse {
+    nightly: String,
+    spec: serde_json::Value,
+}
+
+async fn target_spec(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<SpecQuery>,
+) -> Response {
+    let result = async {
+        let Some(spec) = state
+            .db
+            .target_spec(&target, query.nightly.as_deref())
+            .await?
+        else {
+            return Ok(None);
+        };
+        color_eyre::Result::<_>::Ok(Some(SpecResponse {
+            nightly: spec.nightly,
+            spec: serde_json::from_str(&spec.spec)?,
+        }))
+    }
+    .await;
+
+    match result {
+        Ok(Some(spec)) => Json(spec).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target spec");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SpecDiffQuery {
+    /// The newest nightly before `to` that has a spec by default.
+    from: Option<String>,
+    to: String,
+}
+
+async fn target_spec_diff(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<SpecDiffQuery>,
+) -> Response {
+    match target_spec::diff_nightlies(&state.db, &target, query.from.as_deref(), &query.to).await {
+        Ok(Some(diff)) => Json(diff).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error diffing target specs");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CfgResponse {
+    nightly: String,
+    cfg: Vec<String>,
+}
+
+async fn target_cfg(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<SpecQuery>,
+) -> Response {
+    match state.db.target_cfg(&target, query.nightly.as_deref()).await {
+        Ok(Some(cfg)) => Json(CfgResponse {
+            nightly: cfg.nightly,
+            cfg: cfg.cfg.lines().map(ToOwned::to_owned).collect(),
+        })
+        .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target cfg");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(super) struct Maintainer {
+    pub github: String,
+    /// `docs` for the platform support docs, `config` for the ones added to the config.
+    pub source: &'static str,
+}
+
+/// The maintainers from the docs, followed by the ones from the config that aren't in the docs.
+pub(super) async fn maintainers(
+    state: &AppState,
+    target: &str,
+) -> color_eyre::Result<Vec<Maintainer>> {
+    let mut maintainers = state
+        .db
+        .target_maintainers(target)
+        .await?
+        .into_iter()
+        .map(|github| Maintainer {
+            github,
+            source: "docs",
+        })
+        .collect::<Vec<_>>();
+    for github in state.extra_maintainers.get(target).into_iter().flatten() {
+        let github = github.trim_start_matches('@');
+        if !maintainers
+            .iter()
+            .any(|maintainer| maintainer.github.eq_ignore_ascii_case(github))
+        {
+            maintainers.push(Maintainer {
+                github: github.to_owned(),
+                source: "config",
+            });
+        }
+    }
+    Ok(maintainers)
+}
+
+async fn target_maintainers(State(state): State<AppState>, Path(target): Path<String>) -> Response {
+    match maintainers(&state, &target).await {
+        Ok(maintainers) => Json(maintainers).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target maintainers");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UptimeQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+async fn target_uptime(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<UptimeQuery>,
+) -> Response {
+    match uptime::uptimes(
+        &state.db,
+        &uptime::WINDOWS,
+        Some(&target),
+        query.host.as_deref(),
+    )
+    .await
+    {
+        Ok(mut uptimes) => Json(uptimes.remove(&target).unwrap_or_default()).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target uptime");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UptimeBadgeQuery {
+    mode: Option<BuildMode>,
+    /// 30 by default.
+    days: Option<u32>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+/// The response format of the shields.io endpoint badge.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShieldsBadge {
+    schema_version: u32,
+    label: String,
+    message: String,
+    color: &'static str,
+}
+
+/// The uptime of the target for a badge from `https://img.shields.io/endpoint?url=<url of this>`.
+async fn target_uptime_badge(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<UptimeBadgeQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let days = query.days.unwrap_or(30);
+    match uptime::uptimes(&state.db, &[days], Some(&target), query.host.as_deref()).await {
+        Ok(mut uptimes) => {
+            let uptime = uptimes
+                .remove(&target)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|uptime| uptime.mode == mode);
+            let (message, color) = match uptime {
+                Some(uptime) => (
+                    format!("{:.1}%", uptime.percentage),
+                    uptime::badge_color(uptime.percentage),
+                ),
+                None => ("unknown".to_owned(), "lightgrey"),
+            };
+            Json(ShieldsBadge {
+                schema_version: 1,
+                label: format!("{mode} uptime ({days}d)"),
+                message,
+                color,
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error loading target uptime");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CalendarQuery {
+    /// 365 by default.
+    days: Option<u32>,
+    /// Without a mode, the builds of all modes are counted.
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+/// How many builds of the target passed and failed on every day, for a yearly heatmap.
+async fn target_calendar(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<CalendarQuery>,
+) -> Response {
+    let result = async {
+        let since = nightlies::days_ago(query.days.unwrap_or(365))?;
+        state
+            .db
+            .calendar(&target, &since, query.mode, query.host.as_deref())
+            .await
+    }
+    .await;
+    match result {
+        Ok(days) => Json(days).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading target calendar");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NightlyBadgeQuery {
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+/// How many targets of the nightly pass, for a badge like `target_uptime_badge`.
+async fn nightly_badge(
+    State(state): State<AppState>,
+    Path(nightly): Path<String>,
+    Query(query): Query<NightlyBadgeQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let nightly = match resolve_nightly(&state.db, nightly, Some(mode), query.host.as_deref()).await
+    {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    match state
+        .db
+        .pass_counts(Some(&nightly), Some(mode), query.host.as_deref())
+        .await
+    {
+        Ok(counts) => {
+            let counts = counts.into_iter().find(|count| count.nightly == nightly);
+            let (message, color) = match counts {
+                Some(counts) => {
+                    let total = counts.pass + counts.error;
+                    (
+                        format!("{}/{total} targets pass {mode}", counts.pass),
+                        uptime::badge_color(counts.pass as f64 / total as f64 * 100.0),
+                    )
+                }
+                None => (format!("not built for {mode}"), "lightgrey"),
+            };
+            Json(ShieldsBadge {
+                schema_version: 1,
+                label: nightly,
+                message,
+                color,
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error loading nightly pass counts");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn target_cfg_diff(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<SpecDiffQuery>,
+) -> Response {
+    match target_spec::diff_cfg_nightlies(&state.db, &target, query.from.as_deref(), &query.to)
+        .await
+    {
+        Ok(Some(diff)) => Json(diff).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error diffing target cfgs");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RegressionsQuery {
+    mode: Option<BuildMode>,
+    /// The host of the coordinator by default.
+    host: Option<String>,
+    tier: Option<i32>,
+}
+
+/// Targets that failed on the nightly but passed on the previous one.
+async fn regressions(
+    State(state): State<AppState>,
+    Path(nightly): Path<String>,
+    Query(query): Query<RegressionsQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let host = query.host.unwrap_or_else(|| crate::HOST.to_owned());
+    let nightly = match resolve_nightly(&state.db, nightly, Some(mode), Some(&host)).await {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    let result = async {
+        let (previous, mut regressions) =
+            find_regressions(&state.db, &nightly, mode, &host).await?;
+        regressions.retain(|regression| query.tier.is_none() || regression.tier == query.tier);
+        color_eyre::Result::<_>::Ok(Regressions {
+            nightly: nightly.clone(),
+            previous,
+            mode,
+            host,
+            regressions,
+        })
+    }
+    .await;
+
+    match result {
+        Ok(regressions) => Json(regressions).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading regressions");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SpecChanges {
+    nightly: String,
+    /// The nightly with specs before it, the specs are compared to it.
+    previous: Option<String>,
+    changes: Vec<target_spec::SemanticChange>,
+}
+
+/// Changes to important fields of target specs on the nightly, like the pointer width, which
+/// matter even if the targets still build.
+async fn spec_changes(State(state): State<AppState>, Path(nightly): Path<String>) -> Response {
+    let nightly = match resolve_nightly(&state.db, nightly, None, None).await {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    match target_spec::semantic_changes(&state.db, &nightly).await {
+        Ok(changes) => {
+            let (previous, changes) = changes.unzip();
+            Json(SpecChanges {
+                nightly,
+                previous,
+                changes: changes.unwrap_or_default(),
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error loading spec changes");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The previous nightly and the targets that passed on it, but fail on the nightly.
+async fn find_regressions(
+    db: &Db,
+    nightly: &str,
+    mode: BuildMode,
+    host: &str,
+) -> color_eyre::Result<(Option<String>, Vec<Regression>)> {
+    let previous = db.previous_nightly(nightly, mode, host).await?;
+    let targets = match &previous {
+        Some(previous) => db.regressions(nightly, previous, mode, host).await?,
+        None => Vec::new(),
+    };
+    let tiers = db
+        .target_meta()
+        .await?
+        .into_iter()
+        .map(|meta| (meta.target, meta.tier))
+        .collect::<HashMap<_, _>>();
+    let flaky = flaky::flaky_targets(db, mode, Some(host)).await?;
+    let beta = db
+        .beta_checks(nightly, mode, host)
+        .await?
+        .into_iter()
+        .map(|check| (check.target, check.status))
+        .collect::<HashMap<_, _>>();
+    let regressions = targets
+        .into_iter()
+        .map(|target| Regression {
+            tier: tiers.get(&target).copied().flatten(),
+            flaky: flaky.contains(&target),
+            beta_affected: beta_affected(beta.get(&target).copied()),
+            target,
+        })
+        .collect();
+    Ok((previous, regressions))
+}
+
+/// Whether the build with beta shows that the regression reached it. Builds without a verdict,
+/// like one that timed out, don't say.
+fn beta_affected(status: Option<Status>) -> Option<bool> {
+    match status? {
+        Status::Error => Some(true),
+        Status::Pass => Some(false),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct HealthQuery {
+    /// The host of the instance by default.
+    host: Option<String>,
+}
+
+/// Everything that speaks against promoting the nightly to beta, in one request.
+async fn nightly_health(
+    State(state): State<AppState>,
+    Path(nightly): Path<String>,
+    Query(query): Query<HealthQuery>,
+) -> Response {
+    let host = query.host.unwrap_or_else(|| crate::HOST.to_owned());
+    let nightly = match resolve_nightly(&state.db, nightly, None, Some(&host)).await {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    let result = async {
+        let counts = state.db.status_counts(&nightly).await?;
+        let finish_states = state.db.nightly_finish_states(&nightly).await?;
+        let mut modes = Vec::new();
+        for mode in [BuildMode::Core, BuildMode::MiriStd] {
+            let finish_state = finish_states
+                .iter()
+                .find(|state| state.mode == mode && state.host.as_deref() == Some(&host));
+            let built = counts
+                .iter()
+                .any(|count| count.mode == mode && count.host == host);
+            if !built && finish_state.is_none() {
+                continue;
+            }
+            let (previous, regressions) =
+                find_regressions(&state.db, &nightly, mode, &host).await?;
+            let mut regressions_by_tier = Vec::<TierRegressions>::new();
+            for regression in regressions {
+                match regressions_by_tier
+                    .iter_mut()
+                    .find(|tier| tier.tier == regression.tier)
+                {
+                    Some(tier) => tier.regressions.push(regression),
+                    None => regressions_by_tier.push(TierRegressions {
+                        tier: regression.tier,
+                        regressions: vec![regression],
+                    }),
+                }
+            }
+            regressions_by_tier.sort_by_key(|tier| tier.tier.unwrap_or(i32::MAX));
+            modes.push(ModeHealth {
+                mode,
+                finished: finish_state.is_some_and(|state| !state.broken),
+                unavailable: finish_state.and_then(|state| state.toolchain_error.clone()),
+                previous,
+                regressions_by_tier,
+                ices: state.db.nightly_ices(&nightly, mode, &host).await?,
+            });
+        }
+        let lost_prebuilt_std = prebuilt_std::transitions(&state.db.prebuilt_std().await?)
+            .into_iter()
+            .filter(|change| change.nightly == nightly && change.change == StdChange::Lost)
+            .map(|change| change.target)
+            .collect::<Vec<_>>();
+        let fit_to_promote = lost_prebuilt_std.is_empty()
+            && modes.iter().all(|mode| {
+                mode.finished
+                    && mode.unavailable.is_none()
+                    && mode.ices.is_empty()
+                    && mode
+                        .regressions_by_tier
+                        .iter()
+                        .flat_map(|tier| &tier.regressions)
+                        .all(|regression| {
+                            regression.flaky || !matches!(regression.tier, Some(1 | 2))
+                        })
+            });
+        color_eyre::Result::<_>::Ok(NightlyHealth {
+            nightly: nightly.clone(),
+            host: host.clone(),
+            fit_to_promote,
+            modes,
+            lost_prebuilt_std,
+        })
+    }
+    .await;
+
+    match result {
+        Ok(health) if health.modes.is_empty() => StatusCode::NOT_FOUND.into_response(),
+        Ok(health) => Json(health).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading nightly health");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NeverPassedQuery {
+    /// Without a mode, the targets of all modes are listed.
+    mode: Option<BuildMode>,
+    /// Without a host, a target counts as passing if it passed on any host.
+    host: Option<String>,
+}
+
+async fn never_passed(
+    State(state): State<AppState>,
+    Query(query): Query<NeverPassedQuery>,
+) -> Response {
+    match state
+        .db
+        .never_passed(query.mode, query.host.as_deref())
+        .await
+    {
+        Ok(targets) => Json(targets).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading targets that never passed");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StaleFailuresQuery {
+    /// How long the targets must have been failing, 30 days by default.
+    days: Option<u32>,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Serialize)]
+struct StaleFailures {
+    days: u32,
+    /// Targets failing since this nightly or earlier are listed.
+    cutoff: String,
+    failures: Vec<StaleFailure>,
+}
+
+async fn stale_failures(
+    State(state): State<AppState>,
+    Query(query): Query<StaleFailuresQuery>,
+) -> Response {
+    let days = query.days.unwrap_or(30);
+    let result = async {
+        let cutoff = nightlies::days_ago(days)?;
+        let failures = state
+            .db
+            .stale_failures(&cutoff, query.mode, query.host.as_deref())
+            .await?;
+        color_eyre::Result::<_>::Ok(StaleFailures {
+            days,
+            cutoff,
+            failures,
+        })
+    }
+    .await;
+
+    match result {
+        Ok(failures) => Json(failures).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading stale failures");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    /// Only nightlies from this one on.
+    since: Option<String>,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+async fn pass_counts(State(state): State<AppState>, Query(query): Query<StatsQuery>) -> Response {
+    match state
+        .db
+        .pass_counts(query.since.as_deref(), query.mode, query.host.as_deref())
+        .await
+    {
+        Ok(counts) => Json(counts).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading pass counts");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn regression_counts(
+    State(state): State<AppState>,
+    Query(query): Query<StatsQuery>,
+) -> Response {
+    match state
+        .db
+        .regression_counts(query.since.as_deref(), query.mode, query.host.as_deref())
+        .await
+    {
+        Ok(counts) => Json(counts).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading regression counts");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreaksQuery {
+    target: Option<String>,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+async fn streaks(State(state): State<AppState>, Query(query): Query<StreaksQuery>) -> Response {
+    match state
+        .db
+        .streaks(query.target.as_deref(), query.mode, query.host.as_deref())
+        .await
+    {
+        Ok(streaks) => Json(streaks).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading streaks");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FlakyQuery {
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Serialize)]
+struct FlakyTargets {
+    mode: BuildMode,
+    targets: Vec<String>,
+}
+
+async fn flaky_targets(State(state): State<AppState>, Query(query): Query<FlakyQuery>) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    match flaky::flaky_targets(&state.db, mode, query.host.as_deref()).await {
+        Ok(targets) => Json(FlakyTargets {
+            mode,
+            targets: targets.into_iter().collect(),
+        })
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error loading flaky targets");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AnomalyQuery {
+    /// The newest finished nightly if unset.
+    nightly: Option<String>,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Serialize)]
+struct AnomalyReport {
+    nightly: String,
+    mode: BuildMode,
+    anomalies: Vec<Anomaly>,
+}
+
+/// Builds of a nightly that took much longer or logged much more or less than usual for their target.
+async fn anomaly_report(
+    State(state): State<AppState>,
+    Query(query): Query<AnomalyQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let nightly = query
+        .nightly
+        .unwrap_or_else(|| "latest-finished".to_owned());
+    let nightly = match resolve_nightly(&state.db, nightly, Some(mode), query.host.as_deref()).await
+    {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    match anomaly::anomalies(&state.db, &nightly, mode, query.host.as_deref()).await {
+        Ok(anomalies) => Json(AnomalyReport {
+            nightly,
+            mode,
+            anomalies,
+        })
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error finding anomalies");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompareTargetsQuery {
+    a: String,
+    b: String,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Serialize)]
+struct TargetComparison {
+    a: String,
+    b: String,
+    mode: BuildMode,
+    /// How many nightlies both targets were built on.
+    compared: usize,
+    /// The nightlies where one target passed and the other one failed.
+    diverging: Vec<Divergence>,
+}
+
+#[derive(Serialize)]
+struct Divergence {
+    nightly: String,
+    a: Status,
+    b: Status,
+}
+
+async fn compare_targets(
+    State(state): State<AppState>,
+    Query(query): Query<CompareTargetsQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    match state
+        .db
+        .compare_targets(&query.a, &query.b, mode, query.host.as_deref())
+        .await
+    {
+        Ok(nightlies) => Json(TargetComparison {
+            compared: nightlies.len(),
+            diverging: nightlies
+                .into_iter()
+                .filter(|(_, a, b)| a != b)
+                .map(|(nightly, a, b)| Divergence { nightly, a, b })
+                .collect(),
+            a: query.a,
+            b: query.b,
+            mode,
+        })
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error comparing targets");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    format: Option<ReportFormat>,
+    /// 7 by default.
+    days: Option<u32>,
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+/// The changes of the last week, as Markdown by default.
+async fn weekly_report(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+    Query(query): Query<ReportQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let report = match Report::generate(
+        &state.db,
+        query.days.unwrap_or(7),
+        mode,
+        query.host.as_deref(),
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(err) => {
+            error!(?err, "Error generating report");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let url = state.public_url(&client);
+    match query.format.unwrap_or(ReportFormat::Markdown) {
+        ReportFormat::Markdown => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            report.markdown(&url),
+        )
+            .into_response(),
+        ReportFormat::Html => Html(report.html(&url)).into_response(),
+        ReportFormat::Json => Json(report).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SignoffQuery {
+    /// The date beta was branched on, like `2026-10-10`.
+    beta: String,
+    format: Option<SignoffFormat>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SignoffFormat {
+    Markdown,
+    Json,
+}
+
+/// The tier 1 and 2 targets that failed on the nightlies of a beta, as Markdown by default.
+async fn signoff_report(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+    Query(query): Query<SignoffQuery>,
+) -> Response {
+    if signoff::previous_branch(&query.beta).is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "beta must be a date like 2026-10-10",
+        )
+            .into_response();
+    }
+    let report = match SignoffReport::generate(&state.db, &query.beta, query.host.as_deref()).await
+    {
+        Ok(report) => report,
+        Err(err) => {
+            error!(?err, "Error generating signoff report");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    match query.format.unwrap_or(SignoffFormat::Markdown) {
+        SignoffFormat::Markdown => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            report.markdown(&state.public_url(&client)),
+        )
+            .into_response(),
+        SignoffFormat::Json => Json(report).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct BuildQuery {
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+#[derive(Deserialize)]
+struct BuildHistoryQuery {
+    mode: Option<BuildMode>,
+    #[serde(flatten)]
+    host: HostFilter,
+    /// A Unix timestamp in seconds to get the result as it was shown then, before later re-runs.
+    as_of: Option<i64>,
+}
+
+async fn build_history(
+    State(state): State<AppState>,
+    Path((nightly, target)): Path<(String, String)>,
+    Query(query): Query<BuildHistoryQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    let nightly = match resolve_nightly(&state.db, nightly, Some(mode), query.host.as_deref()).await
+    {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    let result = async {
+        let Some(current) = state
+            .db
+            .build_status_single(&nightly, &target, mode, query.host.as_deref())
+            .await?
+        else {
+            return Ok(None);
+        };
+        let superseded = state
+            .db
+            .build_attempts(&nightly, &target, mode, &current.host.host)
+            .await?;
+        let history = BuildHistory {
+            current,
+            superseded,
+        };
+        color_eyre::Result::<_>::Ok(match query.as_of {
+            Some(as_of) => history.as_of(as_of),
+            None => Some(history),
+        })
+    }
+    .await;
+
+    match result {
+        Ok(Some(history)) => Json(history).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error loading build history");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The build of the target on the newest nightly it was built on.
+async fn latest_build(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<BuildQuery>,
+) -> Response {
+    let mode = query.mode.unwrap_or(BuildMode::Core);
+    match state
+        .db
+        .latest_build(&target, mode, query.host.as_deref())
+        .await
+    {
+        Ok(Some(build)) => Json(build).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!(?err, "Error loading latest build");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportBuildsQuery {
+    /// Only optional with NDJSON, as the builds of every nightly don't fit into memory.
+    nightly: Option<String>,
+    /// Whether to include the build logs, true by default.
+    logs: Option<bool>,
+}
+
+/// The builds of a nightly including superseded attempts, like in `does-it-build export`.
+/// Mirrors sync from this.
+async fn export_builds(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ExportBuildsQuery>,
+) -> Response {
+    let logs = query.logs.unwrap_or(true);
+    if ndjson::wanted(&headers) {
+        let db = state.db.clone();
+        return ndjson::response(move |rows| async move {
+            ndjson::send(rows, db.build_records(logs, query.nightly.as_deref())).await;
+        });
+    }
+    let Some(nightly) = query.nightly else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "nightly is required unless the builds are streamed as application/x-ndjson",
+        )
+            .into_response();
+    };
+    match state
+        .db
+        .build_records(logs, Some(&nightly))
+        .try_collect::<Vec<_>>()
+        .await
+    {
+        Ok(records) => Json(records).into_response(),
+        Err(err) => {
+            error!(?err, "Error exporting builds");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn export_nightlies(State(state): State<AppState>) -> Response {
+    match state.db.nightly_records().await {
+        Ok(records) => Json(records).into_response(),
+        Err(err) => {
+            error!(?err, "Error exporting nightlies");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn export_toolchains(State(state): State<AppState>) -> Response {
+    match state.db.all_toolchain_info().await {
+        Ok(toolchains) => Json(toolchains).into_response(),
+        Err(err) => {
+            error!(?err, "Error exporting toolchains");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn export_target_meta(State(state): State<AppState>) -> Response {
+    match state.db.target_meta().await {
+        Ok(metas) => Json(metas).into_response(),
+        Err(err) => {
+            error!(?err, "Error exporting target metadata");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Responds with 404 if the worker API is disabled and 401 if the token is wrong.
+async fn worker_auth(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(token) = &state.worker_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !is_authorized(req.headers(), token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(req).await
+}
+
+async fn worker(State(state): State<AppState>, Json(call): Json<WorkerCall>) -> Response {
+    match coordinator::handle(&state.db, state.log_offload.clone(), call).await {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => {
+            error!(?err, "Error handling worker request");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| tokens_match(given, token))
+}
+
+#[derive(Serialize, Deserialize)]
+struct RebuildHook {
+    nightly: String,
+    /// Every target of the nightly is built again if empty.
+    #[serde(default)]
+    targets: Vec<String>,
+    /// Every mode the nightly was built in if unset.
+    mode: Option<BuildMode>,
+    host: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RebuildQueued {
+    nightly: String,
+    modes: Vec<BuildMode>,
+    targets: Vec<String>,
+}
+
+/// Lets external systems, like CI of a target after fixing it, have a nightly checked again.
+async fn rebuild_hook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(hook): Json<RebuildHook>,
+) -> Response {
+    let actor = match state.auth.check(&headers).await {
+        Ok(actor) => actor,
+        Err(status) => return status.into_response(),
+    };
+
+    let audit = match audit_entry(&actor, "rebuild", &hook) {
+        Ok(audit) => audit,
+        Err(status) => return status.into_response(),
+    };
+    let host = hook.host.as_deref().unwrap_or(crate::HOST);
+    match state
+        .db
+        .request_rebuild(&hook.nightly, hook.mode, host, &hook.targets, &audit)
+        .await
+    {
+        Ok(modes) if modes.is_empty() => (
+            StatusCode::NOT_FOUND,
+            format!("nightly {} has not finished building", hook.nightly),
+        )
+            .into_response(),
+        Ok(modes) => {
+            info!(%actor, nightly = %hook.nightly, ?modes, targets = ?hook.targets, "Rebuild requested");
+            (
+                StatusCode::ACCEPTED,
+                Json(RebuildQueued {
+                    nightly: hook.nightly,
+                    modes,
+                    targets: hook.targets,
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            error!(?err, "Error requesting rebuild");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExpectedBreakageHook {
+    target: String,
+    /// The first nightly the change is expected on. It may be in the future.
+    nightly: String,
+    /// The last nightly it may happen on, for changes that aren't in a nightly yet. `nightly` if unset.
+    until: Option<String>,
+    /// Every mode if unset.
+    mode: Option<BuildMode>,
+    reason: String,
+}
+
+/// Marks a status change of a target as expected, like the removal of a target, so its regression
+/// isn't reported and its history shows why it broke.
+async fn expected_breakage_hook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(hook): Json<ExpectedBreakageHook>,
+) -> Response {
+    let actor = match state.auth.check(&headers).await {
+        Ok(actor) => actor,
+        Err(status) => return status.into_response(),
+    };
+
+    let until = hook.until.clone().unwrap_or_else(|| hook.nightly.clone());
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    if time::Date::parse(&hook.nightly, format).is_err()
+        || time::Date::parse(&until, format).is_err()
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            "nightly and until must be dates like 2026-10-10",
+        )
+            .into_response();
+    }
+    if until < hook.nightly {
+        return (StatusCode::BAD_REQUEST, "until must not be before nightly").into_response();
+    }
+    if hook.reason.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "a reason is required").into_response();
+    }
+
+    let modes = match hook.mode {
+        Some(mode) => vec![mode],
+        None => vec![BuildMode::Core, BuildMode::MiriStd],
+    };
+    let created_at = time::OffsetDateTime::now_utc().unix_timestamp();
+    let breakages = modes
+        .into_iter()
+        .map(|mode| ExpectedBreakage {
+            target: hook.target.clone(),
+            mode,
+            first_nightly: hook.nightly.clone(),
+            last_nightly: until.clone(),
+            reason: hook.reason.clone(),
+            created_at,
+        })
+        .collect::<Vec<_>>();
+    let audit = match audit_entry(&actor, "expected_breakage", &hook) {
+        Ok(audit) => audit,
+        Err(status) => return status.into_response(),
+    };
+    if let Err(err) = state.db.insert_expected_breakages(&breakages, &audit).await {
+        error!(?err, "Error inserting expected breakage");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    info!(%actor, target = %hook.target, nightly = %hook.nightly, %until, reason = %hook.reason, "Breakage marked as expected");
+    (StatusCode::CREATED, Json(breakages)).into_response()
+}
+
+/// The record of a change made through an authenticated endpoint, which is stored together with it.
+fn audit_entry(
+    actor: &Actor,
+    action: &str,
+    payload: &impl Serialize,
+) -> Result<AuditEntry, StatusCode> {
+    Ok(AuditEntry {
+        created_at: time::OffsetDateTime::now_utc().unix_timestamp(),
+        actor: actor.0.clone(),
+        action: action.to_owned(),
+        payload: serde_json::to_string(payload).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    })
+}
+
+/// How many entries `/audit-log` returns at most.
+const AUDIT_LOG_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+struct AuditLogQuery {
+    actor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct AuditLogEntry {
+    created_at: i64,
+    actor: String,
+    action: String,
+    payload: serde_json::Value,
+}
+
+/// Who changed what through the hooks, newest first. Only for those who may use the hooks themselves.
+async fn audit_log(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditLogQuery>,
+) -> Response {
+    if let Err(status) = state.auth.check(&headers).await {
+        return status.into_response();
+    }
+    let limit = query.limit.unwrap_or(100).clamp(1, AUDIT_LOG_LIMIT);
+    match state.db.audit_log(query.actor.as_deref(), limit).await {
+        Ok(entries) => Json(
+            entries
+                .into_iter()
+                .map(|entry| AuditLogEntry {
+                    payload: serde_json::from_str(&entry.payload)
+                        .unwrap_or(serde_json::Value::String(entry.payload)),
+                    created_at: entry.created_at,
+                    actor: entry.actor,
+                    action: entry.action,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error loading audit log");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExpectedBreakageQuery {
+    target: Option<String>,
+}
+
+async fn expected_breakages(
+    State(state): State<AppState>,
+    Query(query): Query<ExpectedBreakageQuery>,
+) -> Response {
+    match state.db.expected_breakages(query.target.as_deref()).await {
+        Ok(breakages) => Json(breakages).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading expected breakages");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LatestQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+/// The newest build of every target and mode on every host of this instance. Peers pull this.
+async fn latest_builds(
+    State(state): State<AppState>,
+    Query(query): Query<LatestQuery>,
+) -> Response {
+    match state.db.latest_builds(None, query.host.as_deref()).await {
+        Ok(builds) => Json(builds).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading latest builds");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FederatedLatestQuery {
+    target: Option<String>,
+    mode: Option<BuildMode>,
+}
+
+/// The latest builds of this instance and of its peers, with the instance each one is from.
+async fn federated_latest(
+    State(state): State<AppState>,
+    Query(query): Query<FederatedLatestQuery>,
+) -> Response {
+    let result = async {
+        let local = state
+            .db
+            .latest_builds(query.target.as_deref(), None)
+            .await?;
+        let federated = state.db.federated_builds(query.target.as_deref()).await?;
+        color_eyre::Result::<_>::Ok(federation::merged(local, federated))
+    }
+    .await;
+    match result {
+        Ok(builds) => Json(
+            builds
+                .into_iter()
+                .filter(|build| query.mode.is_none_or(|mode| build.mode == mode))
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(err) => {
+            error!(?err, "Error loading federated builds");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::{Query, State},
+        http::{header, HeaderMap, StatusCode},
+        response::Response,
+        Json,
+    };
+
+    use crate::{
+        db::{tests::temp_db, AuditEntry, BuildMode},
+        web::tests::state,
+    };
+
+    use super::{AuditLogQuery, BuildHistoryQuery, ExpectedBreakageHook};
+
+    fn token(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        headers
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn audit_log() {
+        let (_dir, db) = temp_db().await;
+        let state = state(db);
+        state
+            .db
+            .insert_audit_entry(&AuditEntry {
+                created_at: 0,
+                actor: "github:alice".to_owned(),
+                action: "rebuild".to_owned(),
+                payload: "{}".to_owned(),
+            })
+            .await
+            .unwrap();
+        let response = super::expected_breakage_hook(
+            State(state.clone()),
+            token("secret"),
+            Json(ExpectedBreakageHook {
+                target: "i686-apple-darwin".to_owned(),
+                nightly: "2026-10-10".to_owned(),
+                until: None,
+                mode: Some(BuildMode::Core),
+                reason: "removed".to_owned(),
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let audit_log = |headers: HeaderMap, query: &str| {
+            let uri = format!("/audit-log{query}").parse().unwrap();
+            let query = Query::<AuditLogQuery>::try_from_uri(&uri).unwrap();
+            super::audit_log(State(state.clone()), headers, query)
+        };
+        let actors = |entries: serde_json::Value| {
+            entries
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["actor"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let entries = json(audit_log(token("secret"), "").await).await;
+        assert_eq!(actors(entries.clone()), ["token", "github:alice"]);
+        assert_eq!(entries[0]["action"], "expected_breakage");
+        assert_eq!(entries[0]["payload"]["target"], "i686-apple-darwin");
+
+        let entries = json(audit_log(token("secret"), "?actor=github:alice").await).await;
+        assert_eq!(actors(entries), ["github:alice"]);
+        let entries = json(audit_log(token("secret"), "?limit=1").await).await;
+        assert_eq!(actors(entries), ["token"]);
+        // At least one entry is returned.
+        let entries = json(audit_log(token("secret"), "?limit=0").await).await;
+        assert_eq!(actors(entries), ["token"]);
+
+        assert_eq!(
+            audit_log(token("wrong"), "").await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            audit_log(HeaderMap::new(), "").await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn host_filter() {
+        let query = |query: &str| {
+            let uri = format!("/builds/2026-10-10/wasm32-wasip1{query}")
+                .parse()
+                .unwrap();
+            Query::<BuildHistoryQuery>::try_from_uri(&uri).unwrap().0
+        };
+
+        let parsed = query("?mode=miri-std&host=aarch64-apple-darwin&as_of=1760000000");
+        assert_eq!(parsed.mode, Some(BuildMode::MiriStd));
+        assert_eq!(parsed.host.as_deref(), Some("aarch64-apple-darwin"));
+        assert_eq!(parsed.as_of, Some(1760000000));
+        // Forms submit empty fields.
+        assert_eq!(query("?host=").host.as_deref(), None);
+        assert_eq!(query("").host.as_deref(), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! Authenticating the bearer tokens of the hooks. Depending on `auth.provider`, they are compared
+//! with the static hook token, or are access tokens of GitHub or an OpenID Connect provider that
+//! are checked with the provider and must belong to one of the allowed users.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::http::{header, HeaderMap, StatusCode};
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use futures::{future::BoxFuture, FutureExt};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::error;
+
+use crate::config::{AuthConfig, AuthProvider};
+
+/// How long a token that was checked with the provider is trusted without checking it again.
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Who made a request, like `github:octocat`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actor(pub String);
+
+impl Display for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub trait Authenticator: Send + Sync {
+    /// The actor the token belongs to, or none if it doesn't belong to an allowed user.
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Option<Actor>>>;
+}
+
+/// `web.hook_token`, shared by everyone who uses the hooks.
+pub struct StaticToken(pub String);
+
+impl Authenticator for StaticToken {
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Option<Actor>>> {
+        let actor = tokens_match(token, &self.0).then(|| Actor("token".to_owned()));
+        async { Ok(actor) }.boxed()
+    }
+}
+
+/// Compares the hashes of the tokens, so the time it takes doesn't tell how much of the token is right.
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    let given = Sha256::digest(given.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    given
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// GitHub access tokens, of the users with the allowed logins.
+pub struct Github {
+    pub users: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubUser {
+    login: String,
+}
+
+impl Authenticator for Github {
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Option<Actor>>> {
+        async move {
+            let response = crate::http::send(
+                crate::http::client()
+                    .get("https://api.github.com/user")
+                    .bearer_auth(token),
+            )
+            .await
+            .wrap_err("fetching GitHub user")?;
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                return Ok(None);
+            }
+            let user = response
+                .error_for_status()
+                .wrap_err("fetching GitHub user")?
+                .json::<GithubUser>()
+                .await
+                .wrap_err("decoding GitHub user")?;
+            // Logins are case-insensitive on GitHub.
+            let allowed = self
+                .users
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&user.login));
+            Ok(allowed.then(|| Actor(format!("github:{}", user.login))))
+        }
+        .boxed()
+    }
+}
+
+/// Access tokens of an OpenID Connect provider, checked with its userinfo endpoint.
+pub struct Oidc {
+    pub issuer: String,
+    pub users: Vec<String>,
+    /// From the discovery document, which is only fetched once it is needed.
+    userinfo_endpoint: OnceCell<String>,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    sub: String,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+impl Oidc {
+    pub fn new(issuer: &str, users: Vec<String>) -> Self {
+        Self {
+            issuer: issuer.trim_end_matches('/').to_owned(),
+            users,
+            userinfo_endpoint: OnceCell::new(),
+        }
+    }
+
+    async fn userinfo_endpoint(&self) -> Result<&str> {
+        self.userinfo_endpoint
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer);
+                let discovery = crate::http::get(&url)
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .wrap_err_with(|| format!("fetching {url}"))?
+                    .json::<Discovery>()
+                    .await
+                    .wrap_err_with(|| format!("decoding {url}"))?;
+                Ok(discovery.userinfo_endpoint)
+            })
+            .await
+            .map(String::as_str)
+    }
+}
+
+impl Authenticator for Oidc {
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Option<Actor>>> {
+        async move {
+            let endpoint = self.userinfo_endpoint().await?;
+            let response =
+                crate::http::send(crate::http::client().get(endpoint).bearer_auth(token))
+                    .await
+                    .wrap_err("fetching OIDC userinfo")?;
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                return Ok(None);
+            }
+            let info = response
+                .error_for_status()
+                .wrap_err("fetching OIDC userinfo")?
+                .json::<UserInfo>()
+                .await
+                .wrap_err("decoding OIDC userinfo")?;
+            Ok(allowed_oidc_user(&info, &self.users).map(|name| Actor(format!("oidc:{name}"))))
+        }
+        .boxed()
+    }
+}
+
+/// The name of the user if its `sub` or verified email is allowed, preferring the email. Users can
+/// pick their `preferred_username` and unverified emails, so they can't be trusted.
+fn allowed_oidc_user(info: &UserInfo, users: &[String]) -> Option<String> {
+    let email = info.email.as_ref().filter(|_| info.email_verified);
+    let names = email.into_iter().chain([&info.sub]).collect::<Vec<_>>();
+    names
+        .iter()
+        .any(|name| users.contains(name))
+        .then(|| names[0].clone())
+}
+
+/// The configured authenticator, with the tokens it accepted recently.
+#[derive(Clone)]
+pub struct Auth {
+    /// The hooks are disabled without one.
+    authenticator: Option<Arc<dyn Authenticator>>,
+    /// By the hash of the token, so the tokens themselves aren't kept around.
+    accepted: Arc<Mutex<HashMap<String, (Actor, Instant)>>>,
+}
+
+impl Auth {
+    pub fn new(config: &AuthConfig, hook_token: Option<String>) -> Result<Self> {
+        let authenticator: Option<Arc<dyn Authenticator>> = match config.provider {
+            AuthProvider::Token => hook_token.map(|token| Arc::new(StaticToken(token)) as _),
+            AuthProvider::Github => Some(Arc::new(Github {
+                users: config.users.clone(),
+            })),
+            AuthProvider::Oidc => {
+                let Some(issuer) = &config.oidc_issuer else {
+                    bail!("the oidc auth provider needs an issuer");
+                };
+                Some(Arc::new(Oidc::new(issuer, config.users.clone())))
+            }
+        };
+        Ok(Self {
+            authenticator,
+            accepted: Default::default(),
+        })
+    }
+
+    /// The actor of the bearer token of the request. Responds with 404 if the hooks are disabled
+    /// and 401 if the token isn't accepted.
+    pub async fn check(&self, headers: &HeaderMap) -> Result<Actor, StatusCode> {
+        let Some(authenticator) = &self.authenticator else {
+            return Err(StatusCode::NOT_FOUND);
+        };
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        let hash = format!("{:x}", Sha256::digest(token.as_bytes()));
+        let mut accepted = self.accepted.lock().await;
+        accepted.retain(|_, (_, at)| at.elapsed() < CACHE_TTL);
+        if let Some((actor, _)) = accepted.get(&hash) {
+            return Ok(actor.clone());
+        }
+        drop(accepted);
+
+        match authenticator.authenticate(token).await {
+            Ok(Some(actor)) => {
+                self.accepted
+                    .lock()
+                    .await
+                    .insert(hash, (actor.clone(), Instant::now()));
+                Ok(actor)
+            }
+            Ok(None) => Err(StatusCode::UNAUTHORIZED),
+            Err(err) => {
+                error!(?err, "Error authenticating request");
+                Err(StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserInfo;
+
+    #[test]
+    fn allowed_oidc_user() {
+        let users = ["alice@example.com".to_owned(), "0815".to_owned()];
+        let info = |sub: &str, email: Option<&str>, email_verified: bool| UserInfo {
+            sub: sub.to_owned(),
+            email: email.map(ToOwned::to_owned),
+            email_verified,
+        };
+        assert_eq!(
+            super::allowed_oidc_user(&info("1234", Some("alice@example.com"), true), &users),
+            Some("alice@example.com".to_owned())
+        );
+        assert_eq!(
+            super::allowed_oidc_user(&info("0815", Some("bob@example.com"), false), &users),
+            Some("0815".to_owned())
+        );
+        assert_eq!(
+            super::allowed_oidc_user(&info("4711", Some("alice@example.com"), false), &users),
+            None
+        );
+        assert_eq!(
+            super::allowed_oidc_user(&info("alice", None, false), &["alice".to_owned()]),
+            Some("alice".to_owned())
+        );
+    }
+
+    #[test]
+    fn tokens_match() {
+        assert!(super::tokens_match("secret", "secret"));
+        assert!(!super::tokens_match("secreT", "secret"));
+        assert!(!super::tokens_match("secret1", "secret"));
+        assert!(!super::tokens_match("", "secret"));
+    }
+}
@@ -0,0 +1,238 @@
+//! The contract of the Grafana JSON datasources (simple-json and Infinity) under `/api/v1/grafana`,
+//! so dashboards can chart pass rates and regressions without going through a database.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::db::{BuildMode, PassCount, RegressionCount};
+
+use super::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(health))
+        .route("/search", post(search))
+        .route("/query", post(query))
+}
+
+/// Grafana checks that the datasource works with this.
+pub async fn health() -> StatusCode {
+    StatusCode::OK
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    /// Percentage of the targets that passed.
+    PassRate,
+    Passing,
+    Failing,
+    Regressions,
+    Fixes,
+}
+
+impl Metric {
+    const ALL: [(Metric, &'static str); 5] = [
+        (Metric::PassRate, "pass-rate"),
+        (Metric::Passing, "passing"),
+        (Metric::Failing, "failing"),
+        (Metric::Regressions, "regressions"),
+        (Metric::Fixes, "fixes"),
+    ];
+}
+
+/// Every series name, like `pass-rate.core`.
+fn series_names() -> Vec<String> {
+    Metric::ALL
+        .iter()
+        .flat_map(|(_, name)| {
+            [BuildMode::Core, BuildMode::MiriStd].map(|mode| format!("{name}.{mode}"))
+        })
+        .collect()
+}
+
+fn parse_series_name(series: &str) -> Option<(Metric, BuildMode)> {
+    let (name, mode) = series.split_once('.')?;
+    let (metric, _) = Metric::ALL.iter().find(|(_, known)| *known == name)?;
+    Some((*metric, mode.parse().ok()?))
+}
+
+#[derive(Deserialize)]
+struct SearchRequest {
+    /// What was typed into the query editor so far.
+    #[serde(default)]
+    target: String,
+}
+
+async fn search(Json(request): Json<SearchRequest>) -> Json<Vec<String>> {
+    Json(
+        series_names()
+            .into_iter()
+            .filter(|name| name.contains(&request.target))
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+struct QueryRequest {
+    range: Range,
+    targets: Vec<QueryTarget>,
+}
+
+/// RFC 3339 timestamps.
+#[derive(Deserialize)]
+struct Range {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct QueryTarget {
+    target: String,
+    #[serde(default)]
+    hide: bool,
+}
+
+#[derive(Serialize)]
+struct TimeSeries {
+    target: String,
+    /// The value and the time in milliseconds since the epoch, oldest first.
+    datapoints: Vec<(f64, i64)>,
+}
+
+async fn query(State(state): State<AppState>, Json(request): Json<QueryRequest>) -> Response {
+    let mut series = Vec::new();
+    for target in request.targets.iter().filter(|target| !target.hide) {
+        let Some((metric, mode)) = parse_series_name(&target.target) else {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("unknown series {}", target.target),
+            )
+                .into_response();
+        };
+        series.push((&target.target, metric, mode));
+    }
+
+    // The range is compared to the nightly dates, which only works on the date part.
+    let since = request.range.from.get(..10).unwrap_or_default();
+    let until = request.range.to.get(..10).unwrap_or_default();
+    let result = async {
+        let pass_counts = state.db.pass_counts(Some(since), None, None).await?;
+        let regression_counts = state.db.regression_counts(Some(since), None, None).await?;
+        series
+            .into_iter()
+            .map(|(target, metric, mode)| {
+                let datapoints = datapoints(metric, mode, &pass_counts, &regression_counts)
+                    .into_iter()
+                    .filter(|(nightly, _)| *nightly <= until)
+                    .map(|(nightly, value)| Ok((value, timestamp_millis(nightly)?)))
+                    .collect::<Result<_>>()?;
+                Ok(TimeSeries {
+                    target: target.clone(),
+                    datapoints,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+    }
+    .await;
+
+    match result {
+        Ok(series) => Json(series).into_response(),
+        Err(err) => {
+            error!(?err, "Error loading Grafana series");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The value of the metric for every nightly, oldest first.
+fn datapoints<'a>(
+    metric: Metric,
+    mode: BuildMode,
+    pass_counts: &'a [PassCount],
+    regression_counts: &'a [RegressionCount],
+) -> Vec<(&'a str, f64)> {
+    let passes = pass_counts.iter().filter(|count| count.mode == mode);
+    let regressions = regression_counts.iter().filter(|count| count.mode == mode);
+    match metric {
+        Metric::PassRate => passes
+            .map(|count| {
+                let total = (count.pass + count.error).max(1) as f64;
+                (count.nightly.as_str(), count.pass as f64 / total * 100.0)
+            })
+            .collect(),
+        Metric::Passing => passes
+            .map(|count| (count.nightly.as_str(), count.pass as f64))
+            .collect(),
+        Metric::Failing => passes
+            .map(|count| (count.nightly.as_str(), count.error as f64))
+            .collect(),
+        Metric::Regressions => regressions
+            .map(|count| (count.nightly.as_str(), count.regressions as f64))
+            .collect(),
+        Metric::Fixes => regressions
+            .map(|count| (count.nightly.as_str(), count.fixes as f64))
+            .collect(),
+    }
+}
+
+fn timestamp_millis(nightly: &str) -> Result<i64> {
+    let date = time::Date::parse(
+        nightly,
+        time::macros::format_description!("[year]-[month]-[day]"),
+    )
+    .wrap_err_with(|| format!("nightly {nightly} has an invalid date"))?;
+    Ok(date.midnight().assume_utc().unix_timestamp() * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metric;
+    use crate::db::{BuildMode, PassCount};
+
+    #[test]
+    fn parse_series_name() {
+        assert_eq!(
+            super::parse_series_name("pass-rate.miri-std"),
+            Some((Metric::PassRate, BuildMode::MiriStd))
+        );
+        assert_eq!(super::parse_series_name("pass-rate"), None);
+        assert_eq!(super::parse_series_name("uptime.core"), None);
+        for name in super::series_names() {
+            assert!(super::parse_series_name(&name).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn pass_rate() {
+        let counts = [
+            PassCount {
+                nightly: "2026-10-09".to_owned(),
+                mode: BuildMode::Core,
+                pass: 3,
+                error: 1,
+            },
+            PassCount {
+                nightly: "2026-10-09".to_owned(),
+                mode: BuildMode::MiriStd,
+                pass: 0,
+                error: 4,
+            },
+        ];
+        assert_eq!(
+            super::datapoints(Metric::PassRate, BuildMode::Core, &counts, &[]),
+            [("2026-10-09", 75.0)]
+        );
+        assert_eq!(
+            super::timestamp_millis("2026-10-09").unwrap(),
+            1_791_504_000_000
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! The index page, a grid of the recent builds of every target. It is rendered on the server with
+//! the filters from the query, so it works without JavaScript. The script only re-renders it in place.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+use tracing::error;
+
+use does_it_build_api::{HostFilter, Overview};
+
+use crate::db::{BuildInfo, BuildMode, Status};
+
+use super::{api, error_title, escape, page_head, status_icon, AppState};
+
+/// How many of the latest nightlies are shown by default.
+const DEFAULT_NIGHTLIES: i64 = 30;
+/// Showing more nightlies gets as slow as the full dump this page used to load.
+const MAX_NIGHTLIES: i64 = 365;
+
+/// The filters of the form on the page. Empty fields mean no filter, like the form submits them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct IndexQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+    /// Only show one of the modes.
+    mode: String,
+    /// `failed` only shows targets that failed on a nightly that is not completely broken.
+    status: String,
+    tier: String,
+    /// Matches the current and former names of targets.
+    search: String,
+    nightlies: String,
+}
+
+struct Filter<'a> {
+    search: &'a str,
+    failed: bool,
+    tier: Option<i32>,
+}
+
+pub async fn root(State(state): State<AppState>, Query(query): Query<IndexQuery>) -> Response {
+    let host = query.host.as_deref();
+    let mode = query.mode.parse::<BuildMode>().ok();
+    let nightlies = query
+        .nightlies
+        .parse::<i64>()
+        .map_or(DEFAULT_NIGHTLIES, |nightlies| {
+            nightlies.clamp(1, MAX_NIGHTLIES)
+        });
+    let filter = Filter {
+        search: query.search.trim(),
+        failed: query.status == "failed",
+        tier: query.tier.parse().ok(),
+    };
+
+    let result = async {
+        let renames = state
+            .db
+            .target_renames()
+            .await?
+            .into_iter()
+            .map(|rename| (rename.old_target, rename.new_target))
+            .collect::<HashMap<_, _>>();
+        let overview = api::overview_of(&state, host).await?;
+        let mut tables = Vec::new();
+        for table_mode in [BuildMode::Core, BuildMode::MiriStd] {
+            if mode.is_some_and(|mode| mode != table_mode) {
+                tables.push(String::new());
+                continue;
+            }
+            let builds = state
+                .db
+                .recent_build_status(table_mode, host, nightlies)
+                .await?;
+            let (nightlies, rows) = grid(&builds, &renames, &filter);
+            tables.push(render_table(&nightlies, &rows, &state.base_path));
+        }
+        color_eyre::Result::<_>::Ok((overview, tables))
+    }
+    .await;
+
+    let (overview, tables) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            error!(?err, "Error loading the index");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let hidden = |table_mode| {
+        if mode.is_some_and(|mode| mode != table_mode) {
+            "hidden"
+        } else {
+            ""
+        }
+    };
+    let tier_options = options(
+        &query.tier,
+        &[("", "all"), ("1", "1"), ("2", "2"), ("3", "3")],
+    );
+    let mode_options = options(
+        &query.mode,
+        &[("", "all"), ("core", "core"), ("miri-std", "miri-std")],
+    );
+    let host_input = host
+        .map(|host| {
+            format!(
+                r#"<input type="hidden" name="host" value="{}" />"#,
+                escape(host)
+            )
+        })
+        .unwrap_or_default();
+
+    Html(
+        include_str!("../../static/index.html")
+            .replace("{{base}}", &state.base_path)
+            .replace("{{failed}}", if filter.failed { "checked" } else { "" })
+            .replace("{{tier_options}}", &tier_options)
+            .replace("{{mode_options}}", &mode_options)
+            .replace("{{nightlies}}", &nightlies.to_string())
+            .replace(
+                "{{overview}}",
+                &render_overview(&overview, &state.base_path),
+            )
+            .replace(
+                "{{head}}",
+                &page_head("Does it build?", &describe(&overview)),
+            )
+            .replace("{{core_hidden}}", hidden(BuildMode::Core))
+            .replace("{{miri_hidden}}", hidden(BuildMode::MiriStd))
+            .replace("{{core}}", &tables[0])
+            .replace("{{miri}}", &tables[1])
+            .replace("{{version}}", crate::VERSION)
+            // Last, so that text from the query is not searched for placeholders.
+            .replace("{{host}}", &host_input)
+            .replace("{{search}}", &escape(filter.search)),
+    )
+    .into_response()
+}
+
+/// The numbers of the latest nightlies as plain text, for link previews.
+fn describe(overview: &Overview) -> String {
+    let mut description = format!("Tracking {} rustc targets.", overview.targets);
+    for mode in &overview.modes {
+        description.push_str(&format!(
+            " {}: {} of {} pass on {}.",
+            mode.mode,
+            mode.pass,
+            mode.pass + mode.error,
+            mode.nightly
+        ));
+    }
+    description
+}
+
+/// A line with the numbers of the latest nightly of every mode.
+fn render_overview(overview: &Overview, base_path: &str) -> String {
+    let mut html = format!("<p>Tracking {} targets.", overview.targets);
+    for mode in &overview.modes {
+        html.push_str(&format!(
+            r#" {} on <a href="{base_path}/nightly/{nightly}">{nightly}</a>: {} of {} pass, {} regressions and {} fixes this week."#,
+            mode.mode,
+            mode.pass,
+            mode.pass + mode.error,
+            mode.regressions_this_week,
+            mode.fixes_this_week,
+            nightly = mode.nightly,
+        ));
+        let left = mode.progress.pending + mode.progress.running;
+        if left > 0 {
+            html.push_str(&format!(" Still building {left} targets."));
+        }
+    }
+    html.push_str("</p>");
+    html
+}
+
+fn options(selected: &str, options: &[(&str, &str)]) -> String {
+    options
+        .iter()
+        .map(|(value, label)| {
+            let selected = if *value == selected { " selected" } else { "" };
+            format!(r#"<option value="{value}"{selected}>{label}</option>"#)
+        })
+        .collect()
+}
+
+/// Follows the renames of a target to its current name.
+fn current_name<'a>(renames: &'a HashMap<String, String>, mut target: &'a str) -> &'a str {
+    let mut seen = HashSet::new();
+    while let Some(new_target) = renames.get(target) {
+        if !seen.insert(target) {
+            break;
+        }
+        target = new_target;
+    }
+    target
+}
+
+#[derive(Debug, Default)]
+struct Row<'a> {
+    /// The old names of a renamed target.
+    former_names: BTreeSet<&'a str>,
+    /// The tier of the target, if it's known.
+    tier: Option<i32>,
+    builds: HashMap<&'a str, &'a BuildInfo>,
+    /// Whether the target failed on a nightly that built anything.
+    failed: bool,
+}
+
+/// The nightlies, newest first, and the rows of the targets that match the filter by their
+/// current name. The builds of renamed targets are shown in the row of the new name.
+fn grid<'a>(
+    builds: &'a [BuildInfo],
+    renames: &'a HashMap<String, String>,
+    filter: &Filter<'_>,
+) -> (Vec<&'a str>, BTreeMap<&'a str, Row<'a>>) {
+    // Nightlies where nothing passed are broken, their failures don't count for the failed filter.
+    let mut broken_nightlies = BTreeMap::new();
+    for build in builds {
+        let broken = broken_nightlies
+            .entry(build.nightly.as_str())
+            .or_insert(true);
+        *broken &= build.status != Status::Pass;
+    }
+
+    let mut rows = BTreeMap::<_, Row<'_>>::new();
+    for build in builds {
+        let name = current_name(renames, &build.target);
+        if !name.contains(filter.search) && !build.target.contains(filter.search) {
+            continue;
+        }
+        if filter.tier.is_some() && build.tier != filter.tier {
+            continue;
+        }
+
+        let row = rows.entry(name).or_default();
+        if name == build.target {
+            row.tier = build.tier;
+        } else {
+            row.former_names.insert(&build.target);
+        }
+        if build.status == Status::Error && !broken_nightlies[build.nightly.as_str()] {
+            row.failed = true;
+        }
+        row.builds.insert(&build.nightly, build);
+    }
+    if filter.failed {
+        rows.retain(|_, row| row.failed);
+    }
+
+    let nightlies = broken_nightlies.into_keys().rev().collect();
+    (nightlies, rows)
+}
+
+fn render_table(nightlies: &[&str], rows: &BTreeMap<&str, Row<'_>>, base_path: &str) -> String {
+    if rows.is_empty() {
+        return "<p>No targets match the filters.</p>".to_owned();
+    }
+
+    let mut html = String::from(r#"<table class="target-state-table"><tr><th>target</th>"#);
+    for nightly in nightlies {
+        html.push_str(&format!(
+            r#"<th class="target-header"><a href="{base_path}/nightly/{nightly}">{nightly}</a></th>"#
+        ));
+    }
+    html.push_str("</tr>");
+
+    for (target, row) in rows {
+        html.push_str(r#"<tr><td class="target-name-col">"#);
+        html.push_str(&format!(
+            r#"<a href="{base_path}/target/{target}">{target}</a>"#
+        ));
+        if !row.former_names.is_empty() {
+            let former_names = row.former_names.iter().copied().collect::<Vec<_>>();
+            html.push_str(&format!(" (formerly {})", former_names.join(", ")));
+        }
+        if let Some(tier) = row.tier {
+            html.push_str(&format!(r#" <span class="tier-badge">tier {tier}</span>"#));
+        }
+        html.push_str("</td>");
+
+        for nightly in nightlies {
+            let Some(build) = row.builds.get(nightly) else {
+                html.push_str(r#"<td class="missing"></td>"#);
+                continue;
+            };
+            html.push_str(&format!(
+                r#"<td class="build-cell {status}"{}><a class="build-info-a" href="{base_path}/build?nightly={nightly}&target={}&mode={}&host={}">{}</a></td>"#,
+                error_title(build),
+                build.target,
+                build.mode,
+                build.host.host,
+                status_icon(build.status),
+                status = build.status,
+            ));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::db::{BuildHost, BuildInfo, BuildMode, Status};
+
+    use super::Filter;
+
+    fn build(nightly: &str, target: &str, status: Status) -> BuildInfo {
+        BuildInfo {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            status,
+            mode: BuildMode::Core,
+            timing: Default::default(),
+            host: BuildHost {
+                host: "x86_64-unknown-linux-gnu".to_owned(),
+                hostname: None,
+            },
+            attempt: 1,
+            tier: Some(3),
+            error_excerpt: None,
+        }
+    }
+
+    #[test]
+    fn grid() {
+        let builds = [
+            build("2026-10-08", "old-none", Status::Error),
+            build("2026-10-08", "other-none", Status::Pass),
+            build("2026-10-09", "new-none", Status::Pass),
+            build("2026-10-09", "other-none", Status::Pass),
+            // Nothing passed on this nightly, so it doesn't count as failed.
+            build("2026-10-10", "other-none", Status::Error),
+        ];
+        let renames = HashMap::from([("old-none".to_owned(), "new-none".to_owned())]);
+        let filter = Filter {
+            search: "",
+            failed: false,
+            tier: None,
+        };
+
+        let (nightlies, rows) = super::grid(&builds, &renames, &filter);
+        assert_eq!(nightlies, ["2026-10-10", "2026-10-09", "2026-10-08"]);
+        assert_eq!(
+            rows.keys().copied().collect::<Vec<_>>(),
+            ["new-none", "other-none"]
+        );
+        let renamed = &rows["new-none"];
+        assert_eq!(
+            renamed.former_names.iter().copied().collect::<Vec<_>>(),
+            ["old-none"]
+        );
+        assert_eq!(renamed.builds.len(), 2);
+
+        let filter = Filter {
+            search: "old",
+            failed: true,
+            tier: Some(3),
+        };
+        let (_, rows) = super::grid(&builds, &renames, &filter);
+        assert_eq!(rows.keys().copied().collect::<Vec<_>>(), ["new-none"]);
+    }
+}
@@ -0,0 +1,104 @@
+//! The Miri support matrix, which targets Miri supports and when that changed.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use does_it_build_api::HostFilter;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::miri_support::{self, Support, SupportMatrix};
+
+use super::{escape, page_head, AppState};
+
+/// How many of the latest changes are shown.
+const CHANGES: usize = 50;
+
+#[derive(Deserialize)]
+pub struct MiriSupportQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+pub async fn miri_support(
+    State(state): State<AppState>,
+    Query(query): Query<MiriSupportQuery>,
+) -> Response {
+    let builds = match state.db.miri_setup_results(query.host.as_deref()).await {
+        Ok(builds) => builds,
+        Err(err) => {
+            error!(?err, "Error loading miri support");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let matrix = miri_support::matrix(&builds);
+    let supported = matrix
+        .targets
+        .iter()
+        .filter(|target| target.support == Support::Supported)
+        .count();
+
+    Html(
+        include_str!("../../static/miri-support.html")
+            .replace(
+                "{{head}}",
+                &page_head(
+                    "Miri target support",
+                    &format!(
+                        "Miri supports {supported} of {} targets.",
+                        matrix.targets.len()
+                    ),
+                ),
+            )
+            .replace("{{base}}", &state.base_path)
+            .replace("{{support}}", &render(&matrix, &state.base_path))
+            .replace("{{version}}", crate::VERSION),
+    )
+    .into_response()
+}
+
+fn support_cell(support: Support) -> &'static str {
+    match support {
+        Support::Supported => r#"<td class="build-cell pass">supported</td>"#,
+        Support::Unsupported => r#"<td class="build-cell unsupported">unsupported</td>"#,
+    }
+}
+
+fn render(matrix: &SupportMatrix, base_path: &str) -> String {
+    if matrix.targets.is_empty() {
+        return "<p>Nothing was built in the miri-std mode yet.</p>".to_owned();
+    }
+
+    let mut html = "<h2>Changes</h2>".to_owned();
+    if matrix.changes.is_empty() {
+        html.push_str("<p>None.</p>");
+    } else {
+        html.push_str("<table><tr><th>Nightly</th><th>Target</th><th>Now</th></tr>");
+        for change in matrix.changes.iter().rev().take(CHANGES) {
+            html.push_str(&format!(
+                r#"<tr><td><a href="{base_path}/nightly/{nightly}">{nightly}</a></td><td><a href="{base_path}/target/{target}">{target}</a></td>{}</tr>"#,
+                support_cell(change.support),
+                nightly = escape(&change.nightly),
+                target = escape(&change.target),
+            ));
+        }
+        html.push_str("</table>");
+    }
+
+    html.push_str(
+        "<h2>Targets</h2><table><tr><th>Target</th><th>Support</th><th>Since</th><th>Latest nightly</th></tr>",
+    );
+    for target in &matrix.targets {
+        html.push_str(&format!(
+            r#"<tr><td><a href="{base_path}/target/{name}">{name}</a></td>{}<td>{}</td><td>{}</td></tr>"#,
+            support_cell(target.support),
+            escape(&target.since),
+            escape(&target.latest_nightly),
+            name = escape(&target.target),
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
@@ -0,0 +1,67 @@
+//! Newline-delimited JSON for the endpoints that return everything. With `Accept: application/x-ndjson`,
+//! rows are written to the response as they are read from the database, so neither the server
+//! nor the client has to hold the whole result in memory.
+
+use std::future::Future;
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use serde::Serialize;
+use tracing::error;
+
+pub const CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// How many rows the database may be ahead of the client.
+const BUFFERED_ROWS: usize = 64;
+
+pub type Rows<T> = mpsc::Sender<color_eyre::Result<T>>;
+
+pub fn wanted(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains(CONTENT_TYPE))
+}
+
+/// Responds with the rows that `read` sends, one JSON object per line. It runs in a task of its own,
+/// because the rows borrow from the database, which the body can't. If reading fails, the response
+/// is cut off, as the status has already been sent.
+pub fn response<T, F>(read: impl FnOnce(Rows<T>) -> F) -> Response
+where
+    T: Serialize + Send + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(BUFFERED_ROWS);
+    tokio::spawn(read(sender));
+    let lines = receiver.map(|row| {
+        let line = row.and_then(|row| {
+            let mut line = serde_json::to_vec(&row)?;
+            line.push(b'\n');
+            Ok(line)
+        });
+        if let Err(err) = &line {
+            error!(?err, "Error streaming rows");
+        }
+        line.map_err(Box::<dyn std::error::Error + Send + Sync>::from)
+    });
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Sends the rows until they end, one fails or the client goes away.
+pub async fn send<T>(mut sender: Rows<T>, rows: impl Stream<Item = sqlx::Result<T>>) {
+    let mut rows = std::pin::pin!(rows);
+    while let Some(row) = rows.next().await {
+        let failed = row.is_err();
+        if sender.send(row.map_err(Into::into)).await.is_err() || failed {
+            break;
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! The page of a nightly, with the results of every target in every mode and what changed
+//! compared to the previous nightly.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use does_it_build_api::HostFilter;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::db::{BuildInfo, BuildMode, Status, ToolchainInfo};
+
+use super::{encode_query_value, escape, page_head, resolve_nightly, AppState};
+
+#[derive(Deserialize)]
+pub struct NightlyQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+struct ModeResults {
+    mode: BuildMode,
+    previous: Option<String>,
+    next: Option<String>,
+    builds: Vec<BuildInfo>,
+    previous_builds: Vec<BuildInfo>,
+    /// Why there are no builds, if rustup couldn't install the toolchain.
+    toolchain_error: Option<String>,
+    /// The targets whose status change is expected, with the reason.
+    expected: HashMap<String, String>,
+}
+
+pub async fn nightly(
+    State(state): State<AppState>,
+    Path(nightly): Path<String>,
+    Query(query): Query<NightlyQuery>,
+) -> Response {
+    let host = query.host.as_deref();
+    let nightly = match resolve_nightly(&state.db, nightly, None, host).await {
+        Ok(nightly) => nightly,
+        Err(response) => return response,
+    };
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    if time::Date::parse(&nightly, format).is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let result = async {
+        let toolchain = state.db.toolchain_info(&nightly).await?;
+        let finish_states = state.db.nightly_finish_states(&nightly).await?;
+        let expected = state.db.expected_breakages_on(&nightly).await?;
+        let mut results = Vec::new();
+        for mode in [BuildMode::Core, BuildMode::MiriStd] {
+            let builds = state.db.nightly_build_status(&nightly, mode, host).await?;
+            let (previous, next) = state.db.adjacent_nightlies(&nightly, mode, host).await?;
+            let previous_builds = match &previous {
+                Some(previous) => state.db.nightly_build_status(previous, mode, host).await?,
+                None => Vec::new(),
+            };
+            let toolchain_error = finish_states
+                .iter()
+                .filter(|state| state.mode == mode)
+                .filter(|state| host.is_none() || state.host.as_deref() == host)
+                .find_map(|state| state.toolchain_error.clone());
+            let expected = expected
+                .iter()
+                .filter(|breakage| breakage.mode == mode)
+                .map(|breakage| (breakage.target.clone(), breakage.reason.clone()))
+                .collect();
+            results.push(ModeResults {
+                mode,
+                previous,
+                next,
+                builds,
+                previous_builds,
+                toolchain_error,
+                expected,
+            });
+        }
+        color_eyre::Result::<_>::Ok((toolchain, results))
+    }
+    .await;
+
+    let (toolchain, results) = match result {
+        Ok(results) => results,
+        Err(err) => {
+            error!(?err, "Error loading nightly");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if results
+        .iter()
+        .all(|results| results.builds.is_empty() && results.toolchain_error.is_none())
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let host_query = host
+        .map(|host| format!("?host={}", encode_query_value(host)))
+        .unwrap_or_default();
+    let modes = results
+        .iter()
+        .map(|results| render_mode(results, &state.base_path, &host_query))
+        .collect::<String>();
+
+    Html(
+        include_str!("../../static/nightly.html")
+            .replace("{{base}}", &state.base_path)
+            .replace(
+                "{{head}}",
+                &page_head(
+                    &format!("Nightly {nightly} - Does it build?"),
+                    &describe(&results),
+                ),
+            )
+            .replace("{{nightly}}", &nightly)
+            .replace("{{toolchain}}", &render_toolchain(toolchain.as_ref()))
+            .replace("{{modes}}", &modes)
+            .replace("{{version}}", crate::VERSION),
+    )
+    .into_response()
+}
+
+/// How the status of a target changed compared to the previous nightly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Change {
+    Regressed,
+    /// A regression that was marked as expected.
+    Expected,
+    Fixed,
+    New,
+    Unchanged,
+}
+
+impl Change {
+    fn class(self) -> &'static str {
+        match self {
+            Self::Regressed => "regressed",
+            Self::Expected => "expected",
+            Self::Fixed => "fixed",
+            Self::New => "new",
+            Self::Unchanged => "",
+        }
+    }
+}
+
+/// The builds with their status on the previous nightly, regressions and fixes first.
+fn changes<'a>(
+    builds: &'a [BuildInfo],
+    previous_builds: &[BuildInfo],
+    expected: &HashMap<String, String>,
+) -> Vec<(&'a BuildInfo, Option<Status>, Change)> {
+    let previous = previous_builds
+        .iter()
+        .map(|build| (build.target.as_str(), build.status))
+        .collect::<HashMap<_, _>>();
+    let mut rows = builds
+        .iter()
+        .map(|build| {
+            let previous = previous.get(build.target.as_str()).copied();
+            let change = match (previous, build.status) {
+                (None, _) => Change::New,
+                (Some(Status::Pass), Status::Error) if expected.contains_key(&build.target) => {
+                    Change::Expected
+                }
+                (Some(Status::Pass), Status::Error) => Change::Regressed,
+                (Some(Status::Error), Status::Pass) => Change::Fixed,
+                (Some(_), _) => Change::Unchanged,
+            };
+            (build, previous, change)
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|(a, _, a_change), (b, _, b_change)| {
+        a_change.cmp(b_change).then_with(|| a.target.cmp(&b.target))
+    });
+    rows
+}
+
+/// How many targets pass and changed in every mode, for link previews.
+fn describe(results: &[ModeResults]) -> String {
+    results
+        .iter()
+        .filter(|results| !results.builds.is_empty())
+        .map(|results| {
+            let rows = changes(&results.builds, &results.previous_builds, &results.expected);
+            let count = |change| rows.iter().filter(|(_, _, c)| *c == change).count();
+            let passed = results
+                .builds
+                .iter()
+                .filter(|build| build.status == Status::Pass)
+                .count();
+            format!(
+                "{}: {passed} of {} targets pass, {} regressions and {} fixes.",
+                results.mode,
+                results.builds.len(),
+                count(Change::Regressed),
+                count(Change::Fixed),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The versions of the toolchain. Miri is developed in its own repository and synced into the
+/// compiler's every few weeks, so its regressions are filed there and need its version.
+fn render_toolchain(info: Option<&ToolchainInfo>) -> String {
+    let Some(info) = info else {
+        return String::new();
+    };
+    let commit = |path: &str| match &info.commit_hash {
+        Some(hash) => format!(
+            r#" (<a href="https://github.com/rust-lang/rust/tree/{hash}{path}">source</a>)"#,
+            hash = escape(hash)
+        ),
+        None => String::new(),
+    };
+    let mut html = format!(
+        "<p>Built with <code>{}</code>{}, <code>{}</code>",
+        escape(&info.rustc_version),
+        commit(""),
+        escape(&info.cargo_version)
+    );
+    if let Some(miri) = &info.miri_version {
+        html.push_str(&format!(
+            r#" and <code>{}</code>{}. Miri issues go to <a href="https://github.com/rust-lang/miri/issues">rust-lang/miri</a>"#,
+            escape(miri),
+            commit("/src/tools/miri"),
+        ));
+    }
+    html.push_str(".</p>");
+    html
+}
+
+fn render_mode(results: &ModeResults, base_path: &str, host_query: &str) -> String {
+    let mode = results.mode;
+    let mut html = format!("<h2>{mode}</h2>");
+    if results.builds.is_empty() {
+        match &results.toolchain_error {
+            Some(error) => html.push_str(&format!(
+                "<p>Not built, the toolchain could not be installed:</p><pre>{}</pre>",
+                escape(error)
+            )),
+            None => html.push_str("<p>Not built in this mode.</p>"),
+        }
+        return html;
+    }
+
+    let rows = changes(&results.builds, &results.previous_builds, &results.expected);
+    let passed = results
+        .builds
+        .iter()
+        .filter(|build| build.status == Status::Pass)
+        .count();
+    let count = |change| rows.iter().filter(|(_, _, c)| *c == change).count();
+    html.push_str(&format!(
+        "<p>{passed} of {} targets pass.",
+        results.builds.len()
+    ));
+    if let Some(previous) = &results.previous {
+        html.push_str(&format!(
+            r#" {} regressions and {} fixes since <a href="{base_path}/nightly/{previous}{host_query}">{previous}</a>."#,
+            count(Change::Regressed),
+            count(Change::Fixed),
+        ));
+    }
+    if let Some(next) = &results.next {
+        html.push_str(&format!(
+            r#" Next: <a href="{base_path}/nightly/{next}{host_query}">{next}</a>."#
+        ));
+    }
+    html.push_str("</p>");
+
+    html.push_str(
+        "<table><tr><th>Target</th><th>Tier</th><th>Status</th><th>Previous</th><th>Error</th><th>Log</th></tr>",
+    );
+    for (build, previous, change) in rows {
+        let query = format!(
+            "nightly={}&target={}&mode={mode}&host={}",
+            build.nightly, build.target, build.host.host
+        );
+        html.push_str(&format!(
+            r#"<tr class="{}"><td><a href="{base_path}/target/{target}">{target}</a></td><td>{}</td><td class="build-cell {status}"><a class="build-info-a" href="{base_path}/build?{query}">{status}</a></td><td>{}</td><td><code>{}</code>{}</td><td><a href="{base_path}/build/log?{query}">log</a></td></tr>"#,
+            change.class(),
+            build.tier.map(|tier| tier.to_string()).unwrap_or_default(),
+            previous.map(|status| status.to_string()).unwrap_or_default(),
+            escape(build.error_excerpt.as_deref().unwrap_or_default()),
+            match (change, results.expected.get(&build.target)) {
+                (Change::Expected, Some(reason)) => format!(" (expected: {})", escape(reason)),
+                _ => String::new(),
+            },
+            target = build.target,
+            status = build.status,
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::db::{BuildHost, BuildInfo, BuildMode, Status};
+
+    use super::Change;
+
+    fn build(nightly: &str, target: &str, status: Status) -> BuildInfo {
+        BuildInfo {
+            nightly: nightly.to_owned(),
+            target: target.to_owned(),
+            status,
+            mode: BuildMode::Core,
+            timing: Default::default(),
+            host: BuildHost {
+                host: "x86_64-unknown-linux-gnu".to_owned(),
+                hostname: None,
+            },
+            attempt: 1,
+            tier: None,
+            error_excerpt: None,
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let previous = [
+            build("2026-10-09", "a-none", Status::Pass),
+            build("2026-10-09", "b-none", Status::Pass),
+            build("2026-10-09", "c-none", Status::Error),
+            build("2026-10-09", "e-none", Status::Pass),
+        ];
+        let builds = [
+            build("2026-10-10", "a-none", Status::Pass),
+            build("2026-10-10", "b-none", Status::Error),
+            build("2026-10-10", "c-none", Status::Pass),
+            build("2026-10-10", "d-none", Status::Error),
+            build("2026-10-10", "e-none", Status::Error),
+        ];
+        let expected = HashMap::from([("e-none".to_owned(), "removed".to_owned())]);
+        let rows = super::changes(&builds, &previous, &expected)
+            .into_iter()
+            .map(|(build, previous, change)| (build.target.as_str(), previous, change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                ("b-none", Some(Status::Pass), Change::Regressed),
+                ("e-none", Some(Status::Pass), Change::Expected),
+                ("c-none", Some(Status::Error), Change::Fixed),
+                ("d-none", None, Change::New),
+                ("a-none", Some(Status::Pass), Change::Unchanged),
+            ]
+        );
+    }
+}
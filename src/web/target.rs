@@ -0,0 +1,569 @@
+//! The page of a target, with what is known about it, its current status in every mode
+//! and the history of its recent builds.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use does_it_build_api::HostFilter;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{
+    db::{
+        BuildInfo, BuildMode, CalendarDay, ExpectedBreakage, FederatedBuild, Status, Streak,
+        TargetMeta, TargetRename,
+    },
+    flaky, nightlies,
+    uptime::{self, Uptime},
+};
+
+use super::{api, encode_query_value, error_title, escape, page_head, status_icon, AppState};
+
+/// How many of the latest nightlies the target was built on are shown in the history.
+const HISTORY_NIGHTLIES: i64 = 60;
+/// How many days the calendar covers.
+const CALENDAR_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+pub struct TargetQuery {
+    #[serde(flatten)]
+    host: HostFilter,
+}
+
+struct ModeStatus {
+    mode: BuildMode,
+    current: Option<CurrentStatus>,
+    flaky: bool,
+}
+
+/// The status of the latest build of a target and since when it has had it.
+#[derive(Debug, PartialEq)]
+struct CurrentStatus {
+    status: Status,
+    latest_nightly: String,
+    /// The first nightly of the current streak of builds with this status.
+    since: String,
+    length: i64,
+    /// The last nightly the target passed on, if it is currently failing.
+    last_passed: Option<String>,
+}
+
+/// The current status from the streaks of the target in a mode, ordered by their first nightly.
+fn current_status(streaks: &[Streak]) -> Option<CurrentStatus> {
+    let (current, earlier) = streaks.split_last()?;
+    let last_passed = match current.status {
+        Status::Error => earlier
+            .iter()
+            .rev()
+            .find(|streak| streak.status == Status::Pass)
+            .map(|streak| streak.last_nightly.clone()),
+        _ => None,
+    };
+    Some(CurrentStatus {
+        status: current.status,
+        latest_nightly: current.last_nightly.clone(),
+        since: current.first_nightly.clone(),
+        length: current.length,
+        last_passed,
+    })
+}
+
+pub async fn target(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<TargetQuery>,
+) -> Response {
+    let host = query.host.as_deref();
+
+    let result = async {
+        let meta = state
+            .db
+            .target_meta()
+            .await?
+            .into_iter()
+            .find(|meta| meta.target == target);
+        let history = state
+            .db
+            .target_build_status(&target, host, HISTORY_NIGHTLIES)
+            .await?;
+        let calendar = state
+            .db
+            .calendar(
+                &target,
+                &nightlies::days_ago(CALENDAR_DAYS as u32 - 1)?,
+                None,
+                host,
+            )
+            .await?;
+        let mut statuses = Vec::new();
+        for mode in [BuildMode::Core, BuildMode::MiriStd] {
+            let streaks = state.db.streaks(Some(&target), Some(mode), host).await?;
+            statuses.push(ModeStatus {
+                mode,
+                current: current_status(&streaks),
+                flaky: flaky::flaky_targets(&state.db, mode, host)
+                    .await?
+                    .contains(&target),
+            });
+        }
+        let uptimes = uptime::uptimes(&state.db, &uptime::WINDOWS, Some(&target), host)
+            .await?
+            .remove(&target)
+            .unwrap_or_default();
+        let maintainers = api::maintainers(&state, &target).await?;
+        let renames = state
+            .db
+            .target_renames()
+            .await?
+            .into_iter()
+            .filter(|rename| rename.old_target == target || rename.new_target == target)
+            .collect::<Vec<_>>();
+        let expected = state.db.expected_breakages(Some(&target)).await?;
+        let federated = state.db.federated_builds(Some(&target)).await?;
+        color_eyre::Result::<_>::Ok((
+            meta,
+            history,
+            calendar,
+            statuses,
+            uptimes,
+            maintainers,
+            renames,
+            expected,
+            federated,
+        ))
+    }
+    .await;
+
+    let (meta, history, calendar, statuses, uptimes, maintainers, renames, expected, federated) =
+        match result {
+            Ok(result) => result,
+            Err(err) => {
+                error!(?err, "Error loading target");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+    if meta.is_none() && history.is_empty() && federated.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let host_query = host
+        .map(|host| format!("?host={}", encode_query_value(host)))
+        .unwrap_or_default();
+    let base = &state.base_path;
+
+    let maintainers = if maintainers.is_empty() {
+        "<p>No known maintainers.</p>".to_owned()
+    } else {
+        let links = maintainers
+            .iter()
+            .map(|maintainer| {
+                format!(
+                    r#"<a href="https://github.com/{github}">@{github}</a>"#,
+                    github = escape(&maintainer.github)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("<p>Maintained by {links}</p>")
+    };
+
+    Html(
+        include_str!("../../static/target.html")
+            .replace("{{base}}", base)
+            .replace("{{head}}", &target_head(&target, &statuses))
+            .replace("{{target}}", &escape(&target))
+            .replace("{{meta}}", &render_meta(meta.as_ref()))
+            .replace("{{renames}}", &render_renames(&renames, base, &host_query))
+            .replace("{{maintainers}}", &maintainers)
+            .replace(
+                "{{status}}",
+                &render_status(&statuses, &uptimes, base, &target, host),
+            )
+            .replace("{{federated}}", &render_federated(&federated))
+            .replace(
+                "{{calendar}}",
+                &render_calendar(&calendar, time::OffsetDateTime::now_utc().date()),
+            )
+            .replace("{{expected}}", &render_expected(&expected, base))
+            .replace("{{history}}", &render_history(&history, base))
+            .replace("{{version}}", crate::VERSION),
+    )
+    .into_response()
+}
+
+/// Like "failing since 2026-10-01".
+fn describe(current: &CurrentStatus) -> String {
+    match (current.status, &current.last_passed) {
+        (Status::Pass, _) => format!("passing since {}", current.since),
+        (Status::Error, Some(_)) => format!("failing since {}", current.since),
+        (Status::Error, None) => "never passed".to_owned(),
+        (status, _) => format!("{status} since {}", current.since),
+    }
+}
+
+/// The title shows the status of the first mode the target is built in, the description all of them.
+fn target_head(target: &str, statuses: &[ModeStatus]) -> String {
+    let built = statuses
+        .iter()
+        .filter_map(|status| Some((status.mode, status.current.as_ref()?)))
+        .collect::<Vec<_>>();
+    let title = match built.first() {
+        Some((mode, current)) => format!("{target} - {} ({mode})", describe(current)),
+        None => format!("{target} - Does it build?"),
+    };
+    let description = if built.is_empty() {
+        "Not built yet.".to_owned()
+    } else {
+        built
+            .iter()
+            .map(|(mode, current)| {
+                format!(
+                    "{mode}: {}, latest build on {}.",
+                    describe(current),
+                    current.latest_nightly
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    page_head(&title, &description)
+}
+
+fn render_meta(meta: Option<&TargetMeta>) -> String {
+    let Some(meta) = meta else {
+        return "<p>This target is not in the target list of the latest nightly.</p>".to_owned();
+    };
+    let mut html = String::new();
+    if let Some(description) = &meta.description {
+        html.push_str(&format!("<p>{}</p>", escape(description)));
+    }
+    let yes_no = |value: Option<bool>| match value {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    };
+    html.push_str(&format!(
+        "<p>Tier: {}, host tools: {}, std: {} (as of {})</p>",
+        meta.tier
+            .map(|tier| tier.to_string())
+            .unwrap_or_else(|| "unknown".to_owned()),
+        yes_no(meta.host_tools),
+        yes_no(meta.std),
+        meta.nightly,
+    ));
+    html
+}
+
+fn render_renames(renames: &[TargetRename], base: &str, host_query: &str) -> String {
+    renames
+        .iter()
+        .map(|rename| {
+            format!(
+                r#"<p><a href="{base}/target/{old}{host_query}">{old}</a> was renamed to <a href="{base}/target/{new}{host_query}">{new}</a> on {}.</p>"#,
+                rename.nightly,
+                old = rename.old_target,
+                new = rename.new_target,
+            )
+        })
+        .collect()
+}
+
+/// The status changes that were marked as expected, so failures in the history aren't a surprise.
+fn render_expected(expected: &[ExpectedBreakage], base: &str) -> String {
+    expected
+        .iter()
+        .map(|breakage| {
+            let nightlies = if breakage.first_nightly == breakage.last_nightly {
+                format!(
+                    r#"<a href="{base}/nightly/{first}">{first}</a>"#,
+                    first = breakage.first_nightly
+                )
+            } else {
+                format!(
+                    r#"<a href="{base}/nightly/{first}">{first}</a> to <a href="{base}/nightly/{last}">{last}</a>"#,
+                    first = breakage.first_nightly,
+                    last = breakage.last_nightly
+                )
+            };
+            format!(
+                "<p>Breakage in {} on {nightlies} is expected: {}</p>",
+                breakage.mode,
+                escape(&breakage.reason)
+            )
+        })
+        .collect()
+}
+
+/// The latest builds of the target on other instances. Everything in them comes from the peers,
+/// so it is escaped.
+fn render_federated(builds: &[FederatedBuild]) -> String {
+    if builds.is_empty() {
+        return String::new();
+    }
+    let mut html = String::from(
+        "<h2>Other instances</h2><table><tr><th>Instance</th><th>Host</th><th>Mode</th><th>Status</th><th>Nightly</th></tr>",
+    );
+    for build in builds {
+        let status = build.status;
+        let excerpt = build
+            .error_excerpt
+            .as_deref()
+            .map(|excerpt| format!(r#" title="{}""#, escape(excerpt)))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            r#"<tr><td><a href="{url}">{peer}</a></td><td>{host}</td><td>{mode}</td><td class="build-cell {status}"{excerpt}><a class="build-info-a" href="{url}/build?nightly={nightly}&target={target}&mode={mode}&host={host}">{status}</a></td><td>{nightly}</td></tr>"#,
+            url = escape(&build.peer_url),
+            peer = escape(&build.peer),
+            host = escape(&build.host),
+            mode = build.mode,
+            nightly = escape(&build.nightly),
+            target = escape(&build.target),
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn render_status(
+    statuses: &[ModeStatus],
+    uptimes: &[Uptime],
+    base: &str,
+    target: &str,
+    host: Option<&str>,
+) -> String {
+    let host_query = host
+        .map(|host| format!("?host={}", encode_query_value(host)))
+        .unwrap_or_default();
+    let host_param = host
+        .map(|host| format!("&host={}", encode_query_value(host)))
+        .unwrap_or_default();
+    let mut html = String::from("<table><tr><th>Mode</th><th>Status</th><th>Since</th>");
+    for days in uptime::WINDOWS {
+        html.push_str(&format!("<th>Uptime {days} days</th>"));
+    }
+    html.push_str("</tr>");
+
+    for status in statuses {
+        let mode = status.mode;
+        html.push_str(&format!("<tr><td>{mode}</td>"));
+        match &status.current {
+            Some(current) => {
+                let flaky = if status.flaky { " (flaky)" } else { "" };
+                let since = match (current.status, &current.last_passed) {
+                    (Status::Error, Some(last_passed)) => format!(
+                        r#"failing since <a href="{base}/nightly/{since}{host_query}">{since}</a>, last passed on {last_passed}"#,
+                        since = current.since,
+                    ),
+                    (Status::Error, None) => "never passed".to_owned(),
+                    (Status::Pass, _) => format!(
+                        r#"passing since <a href="{base}/nightly/{since}{host_query}">{since}</a>"#,
+                        since = current.since,
+                    ),
+                    (status, _) => format!(
+                        r#"{status} since <a href="{base}/nightly/{since}{host_query}">{since}</a>"#,
+                        since = current.since,
+                    ),
+                };
+                html.push_str(&format!(
+                    r#"<td class="build-cell {status}"><a class="build-info-a" href="{base}/build?nightly={}&target={}&mode={mode}{host_param}">{status}</a>{flaky}</td><td>{since} ({} build{})</td>"#,
+                    current.latest_nightly,
+                    encode_query_value(target),
+                    current.length,
+                    if current.length == 1 { "" } else { "s" },
+                    status = current.status,
+                ));
+            }
+            None => html.push_str(r#"<td class="missing">not built</td><td></td>"#),
+        }
+        for days in uptime::WINDOWS {
+            let uptime = uptimes
+                .iter()
+                .find(|uptime| uptime.mode == mode && uptime.days == days);
+            match uptime {
+                Some(uptime) => html.push_str(&format!(
+                    "<td>{:.1}% ({}/{})</td>",
+                    uptime.percentage, uptime.passed, uptime.builds
+                )),
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+/// The last year of builds in all modes as a grid of weeks, like the contribution calendar on GitHub.
+fn render_calendar(days: &[CalendarDay], today: time::Date) -> String {
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    let days = days
+        .iter()
+        .map(|day| (day.nightly.as_str(), day))
+        .collect::<HashMap<_, _>>();
+    let first = today - time::Duration::days(CALENDAR_DAYS - 1);
+    let start = first - time::Duration::days(first.weekday().number_days_from_monday().into());
+    let weeks = (today - start).whole_days() / 7 + 1;
+
+    let mut html = String::from(r#"<table class="calendar">"#);
+    for weekday in 0..7 {
+        html.push_str("<tr>");
+        for week in 0..weeks {
+            let date = start + time::Duration::days(week * 7 + weekday);
+            if date < first || date > today {
+                html.push_str("<td></td>");
+                continue;
+            }
+            let nightly = date.format(format).unwrap_or_default();
+            match days.get(nightly.as_str()) {
+                Some(day) => {
+                    let class = match (day.pass, day.error) {
+                        (_, 0) => "pass",
+                        (0, _) => "error",
+                        _ => "mixed",
+                    };
+                    html.push_str(&format!(
+                        r#"<td class="{class}" title="{nightly}: {} passed, {} failed"></td>"#,
+                        day.pass, day.error
+                    ));
+                }
+                None => html.push_str(&format!(r#"<td class="missing" title="{nightly}"></td>"#)),
+            }
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn render_history(history: &[BuildInfo], base: &str) -> String {
+    if history.is_empty() {
+        return "<p>No builds yet.</p>".to_owned();
+    }
+    let mut nightlies = BTreeMap::<_, HashMap<_, _>>::new();
+    for build in history {
+        nightlies
+            .entry(build.nightly.as_str())
+            .or_default()
+            .insert(build.mode, build);
+    }
+
+    let modes = [BuildMode::Core, BuildMode::MiriStd];
+    let mut html = String::from("<table><tr><th>Nightly</th>");
+    for mode in modes {
+        html.push_str(&format!("<th>{mode}</th>"));
+    }
+    html.push_str("</tr>");
+    for (nightly, builds) in nightlies.iter().rev() {
+        html.push_str(&format!(
+            r#"<tr><td><a href="{base}/nightly/{nightly}">{nightly}</a></td>"#
+        ));
+        for mode in modes {
+            match builds.get(&mode) {
+                Some(build) => html.push_str(&format!(
+                    r#"<td class="build-cell {status}"{}><a class="build-info-a" href="{base}/build?nightly={nightly}&target={}&mode={mode}&host={}">{}</a></td>"#,
+                    error_title(build),
+                    build.target,
+                    build.host.host,
+                    status_icon(build.status),
+                    status = build.status,
+                )),
+                None => html.push_str(r#"<td class="missing"></td>"#),
+            }
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{BuildMode, CalendarDay, Status, Streak};
+
+    use super::{CurrentStatus, ModeStatus, CALENDAR_DAYS};
+
+    fn streak(status: Status, first_nightly: &str, last_nightly: &str, length: i64) -> Streak {
+        Streak {
+            target: "x86_64-unknown-none".to_owned(),
+            mode: BuildMode::Core,
+            status,
+            first_nightly: first_nightly.to_owned(),
+            last_nightly: last_nightly.to_owned(),
+            length,
+        }
+    }
+
+    #[test]
+    fn current_status() {
+        assert_eq!(super::current_status(&[]), None);
+
+        let streaks = [
+            streak(Status::Error, "2026-10-01", "2026-10-02", 2),
+            streak(Status::Pass, "2026-10-03", "2026-10-05", 3),
+            streak(Status::Error, "2026-10-06", "2026-10-10", 5),
+        ];
+        assert_eq!(
+            super::current_status(&streaks),
+            Some(CurrentStatus {
+                status: Status::Error,
+                latest_nightly: "2026-10-10".to_owned(),
+                since: "2026-10-06".to_owned(),
+                length: 5,
+                last_passed: Some("2026-10-05".to_owned()),
+            })
+        );
+        assert_eq!(
+            super::current_status(&streaks[..1]).and_then(|current| current.last_passed),
+            None
+        );
+    }
+
+    #[test]
+    fn calendar() {
+        let day = |nightly: &str, pass, error| CalendarDay {
+            nightly: nightly.to_owned(),
+            pass,
+            error,
+        };
+        let days = [
+            day("2026-10-10", 2, 0),
+            day("2026-10-11", 1, 1),
+            day("2026-10-12", 0, 2),
+        ];
+        let html = super::render_calendar(&days, time::macros::date!(2026 - 10 - 12));
+        assert_eq!(html.matches("<tr>").count(), 7);
+        assert_eq!(html.matches("<td class=").count(), CALENDAR_DAYS as usize);
+        assert!(html.contains(r#"<td class="pass" title="2026-10-10: 2 passed, 0 failed"></td>"#));
+        assert!(html.contains(r#"<td class="mixed" title="2026-10-11: 1 passed, 1 failed"></td>"#));
+        assert!(html.contains(r#"<td class="error" title="2026-10-12: 0 passed, 2 failed"></td>"#));
+        assert!(html.contains(r#"<td class="missing" title="2026-10-09"></td>"#));
+    }
+
+    #[test]
+    fn head() {
+        let statuses = [
+            ModeStatus {
+                mode: BuildMode::Core,
+                current: super::current_status(&[
+                    streak(Status::Pass, "2025-06-01", "2025-06-30", 30),
+                    streak(Status::Error, "2025-07-01", "2025-07-10", 10),
+                ]),
+                flaky: false,
+            },
+            ModeStatus {
+                mode: BuildMode::MiriStd,
+                current: None,
+                flaky: false,
+            },
+        ];
+        let head = super::target_head("x86_64-pc-cygwin", &statuses);
+        assert!(head.contains("<title>x86_64-pc-cygwin - failing since 2025-07-01 (core)</title>"));
+        assert!(head.contains(
+            r#"<meta property="og:description" content="core: failing since 2025-07-01, latest build on 2025-07-10." />"#
+        ));
+    }
+}